@@ -7,15 +7,21 @@ const BUFFER_SIZE: cpal::FrameCount = 512;
 const RING_BUFFER_SIZE: usize = BUFFER_SIZE as usize * 16;
 const SAMPLE_RATE: i32 = 48000;
 
+// Sample formats both the core and cpal can handle, frontends pick the one
+// their output device prefers
+pub trait Sample: ceres_core::Sample + cpal::SizedSample + Send + 'static {}
+
+impl<S: ceres_core::Sample + cpal::SizedSample + Send + 'static> Sample for S {}
+
 // RingBuffer is a wrapper around a bounded ring buffer
 // that implements the AudioCallback trait
 #[derive(Clone)]
-pub struct RingBuffer {
-    buffer: Arc<Mutex<Bounded<[ceres_core::Sample; RING_BUFFER_SIZE]>>>,
+pub struct RingBuffer<S: Sample = f32> {
+    buffer: Arc<Mutex<Bounded<[S; RING_BUFFER_SIZE]>>>,
 }
 
-impl RingBuffer {
-    pub fn new(buffer: Arc<Mutex<Bounded<[ceres_core::Sample; RING_BUFFER_SIZE]>>>) -> Self {
+impl<S: Sample> RingBuffer<S> {
+    pub fn new(buffer: Arc<Mutex<Bounded<[S; RING_BUFFER_SIZE]>>>) -> Self {
         // FIll with silence
         if let Ok(mut buffer) = buffer.lock() {
             for _ in 0..buffer.max_len() {
@@ -27,19 +33,31 @@ impl RingBuffer {
     }
 }
 
-impl ceres_core::AudioCallback for RingBuffer {
-    fn audio_sample(&self, l: ceres_core::Sample, r: ceres_core::Sample) {
+impl<S: Sample> ceres_core::AudioCallback for RingBuffer<S> {
+    type Sample = S;
+
+    fn audio_sample(&self, l: S, r: S) {
         if let Ok(mut buffer) = self.buffer.lock() {
             buffer.push(l);
             buffer.push(r);
         }
     }
+
+    fn audio_batch(&self, samples: &[(S, S)]) {
+        if let Ok(mut buffer) = self.buffer.lock() {
+            for &(l, r) in samples {
+                buffer.push(l);
+                buffer.push(r);
+            }
+        }
+    }
 }
 
 pub struct State {
     _host: cpal::Host,
     device: cpal::Device,
     config: cpal::StreamConfig,
+    thread_priority: bool,
 }
 
 impl State {
@@ -59,9 +77,21 @@ impl State {
             _host: host,
             device,
             config,
+            thread_priority: true,
         })
     }
 
+    // Streams built afterwards raise the priority of their audio thread,
+    // realtime where the OS permits it. On by default.
+    pub fn set_thread_priority(&mut self, enabled: bool) {
+        self.thread_priority = enabled;
+    }
+
+    #[must_use]
+    pub fn thread_priority(&self) -> bool {
+        self.thread_priority
+    }
+
     #[must_use]
     pub fn device(&self) -> &cpal::Device {
         &self.device
@@ -71,16 +101,21 @@ impl State {
     pub fn config(&self) -> &cpal::StreamConfig {
         &self.config
     }
+
+    #[must_use]
+    pub const fn sample_rate() -> i32 {
+        SAMPLE_RATE
+    }
 }
 
 // Stream is not Send, so we can't use it directly in the renderer struct
-pub struct Stream {
+pub struct Stream<S: Sample = f32> {
     stream: cpal::Stream,
-    ring_buffer: RingBuffer,
+    ring_buffer: RingBuffer<S>,
     volume: Arc<Mutex<f32>>,
 }
 
-impl Stream {
+impl<S: Sample> Stream<S> {
     pub fn new(state: &State) -> Result<Self, Error> {
         let ring_buffer = Arc::new(Mutex::new(Bounded::from(
             [Default::default(); RING_BUFFER_SIZE],
@@ -88,7 +123,14 @@ impl Stream {
         let ring_buffer_clone = Arc::clone(&ring_buffer);
 
         let error_callback = |err| eprintln!("an AudioError occurred on stream: {err}");
-        let data_callback = move |buffer: &mut [ceres_core::Sample], _: &_| {
+        // cpal owns the audio thread, so raise its priority on the first callback
+        let mut raise_priority = state.thread_priority();
+        let data_callback = move |buffer: &mut [S], _: &_| {
+            if raise_priority {
+                raise_priority = false;
+                raise_audio_thread_priority();
+            }
+
             if let Ok(mut ring) = ring_buffer_clone.lock() {
                 if ring.len() < buffer.len() {
                     eprintln!("ring buffer underrun");
@@ -121,7 +163,7 @@ impl Stream {
     }
 
     #[must_use]
-    pub fn get_ring_buffer(&self) -> RingBuffer {
+    pub fn get_ring_buffer(&self) -> RingBuffer<S> {
         self.ring_buffer.clone()
     }
 
@@ -137,10 +179,33 @@ impl Stream {
     pub fn volume(&self) -> &Arc<Mutex<f32>> {
         &self.volume
     }
+}
 
-    #[must_use]
-    pub const fn sample_rate() -> i32 {
-        SAMPLE_RATE
+#[cfg(unix)]
+fn raise_audio_thread_priority() {
+    use thread_priority::{
+        set_thread_priority_and_policy, thread_native_id, RealtimeThreadSchedulePolicy,
+        ThreadPriority, ThreadSchedulePolicy,
+    };
+
+    // Realtime scheduling usually needs rtkit or CAP_SYS_NICE, fall back to
+    // the highest normal priority
+    if set_thread_priority_and_policy(
+        thread_native_id(),
+        ThreadPriority::Max,
+        ThreadSchedulePolicy::Realtime(RealtimeThreadSchedulePolicy::Fifo),
+    )
+    .is_err()
+        && thread_priority::set_current_thread_priority(ThreadPriority::Max).is_err()
+    {
+        eprintln!("couldn't raise the audio thread priority");
+    }
+}
+
+#[cfg(not(unix))]
+fn raise_audio_thread_priority() {
+    if thread_priority::set_current_thread_priority(thread_priority::ThreadPriority::Max).is_err() {
+        eprintln!("couldn't raise the audio thread priority");
     }
 }
 
@@ -0,0 +1,42 @@
+// Runs a ROM without a window or sound and prints what it sent through
+// the serial port, which is how test ROMs like Blargg's report results.
+//
+//     cargo run -p ceres-core --example headless -- rom.gb [frames]
+
+use ceres_core::{Cart, Gb, Model, NoAudio};
+use std::io::Write;
+
+fn main() -> Result<(), Box<dyn core::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().ok_or("usage: headless <rom> [frames]")?;
+    let frames: u32 = args.next().map_or(Ok(60 * 10), |frames| frames.parse())?;
+
+    let cart = Cart::new(std::fs::read(path)?.into_boxed_slice())?;
+    let mut gb = Gb::new(Model::from_cart(&cart), 48000, cart, NoAudio);
+
+    let mut serial = Vec::new();
+    for _ in 0..frames {
+        gb.run_frame();
+        serial.extend(gb.drain_serial_output());
+    }
+
+    // FNV-1a, enough to tell whether the last frame changed between runs
+    let screen_hash = gb
+        .pixel_data_rgb()
+        .iter()
+        .fold(0xCBF2_9CE4_8422_2325_u64, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+        });
+
+    let mut out = std::io::stdout().lock();
+    out.write_all(&serial)?;
+    writeln!(out)?;
+    let stats = gb.stats();
+    writeln!(
+        out,
+        "{} instructions, {} frames drawn, screen {screen_hash:016x}",
+        stats.instructions, stats.frames
+    )?;
+
+    Ok(())
+}
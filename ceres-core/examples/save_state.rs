@@ -0,0 +1,41 @@
+// Takes a save state, lets the game run on and loads it back, checking
+// the same frames are drawn again.
+//
+//     cargo run -p ceres-core --example save_state -- rom.gb
+
+use ceres_core::{Cart, Gb, Model, NoAudio, SaveStateManager};
+use std::io::Write;
+
+fn run(gb: &mut Gb<NoAudio>, frames: u32) -> Vec<u8> {
+    for _ in 0..frames {
+        gb.run_frame();
+    }
+
+    gb.pixel_data_rgb().to_vec()
+}
+
+fn main() -> Result<(), Box<dyn core::error::Error>> {
+    let path = std::env::args().nth(1).ok_or("usage: save_state <rom>")?;
+
+    let cart = Cart::new(std::fs::read(path)?.into_boxed_slice())?;
+    let mut gb = Gb::new(Model::Cgb, 48000, cart, NoAudio);
+    let mut states = SaveStateManager::new(1);
+
+    run(&mut gb, 60 * 5);
+    states.save(0, &gb, 0)?;
+    let expected = run(&mut gb, 60);
+
+    run(&mut gb, 60);
+    states.load(0, &mut gb)?;
+    let replayed = run(&mut gb, 60);
+
+    let info = states.info(0).ok_or("the slot is empty")?;
+    let mut out = std::io::stdout().lock();
+    writeln!(out, "state of {} at frame {}", info.title, info.frames)?;
+    if replayed != expected {
+        return Err("loading the state drew other frames".into());
+    }
+    writeln!(out, "replayed the same frames")?;
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+// Walks through the first instructions a ROM executes after the bootrom,
+// printing each opcode with the registers it ran with.
+//
+//     cargo run -p ceres-core --example trace -- rom.gb [instructions]
+
+use ceres_core::{Cart, Gb, Model, NoAudio, TC_PER_FRAME};
+use std::io::Write;
+
+fn main() -> Result<(), Box<dyn core::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().ok_or("usage: trace <rom> [instructions]")?;
+    let count: usize = args.next().map_or(Ok(64), |count| count.parse())?;
+
+    let cart = Cart::new(std::fs::read(path)?.into_boxed_slice())?;
+    let mut gb = Gb::new(Model::Dmg, 48000, cart, NoAudio);
+
+    // five seconds is plenty for the bootrom and for a halted CPU to wake
+    let limit = TC_PER_FRAME * 60 * 5;
+
+    // the bootrom hands over at 0x100
+    let mut dots = 0;
+    while gb.registers().pc != 0x100 && dots < limit {
+        dots += gb.run_dots(4);
+    }
+
+    gb.set_trace_capacity(count);
+    let mut entries = Vec::new();
+    dots = 0;
+    while entries.len() < count && dots < limit {
+        dots += gb.run_dots(4);
+        entries.extend(gb.drain_trace());
+    }
+
+    let mut out = std::io::stdout().lock();
+    for entry in entries.iter().take(count) {
+        let regs = entry.registers;
+        writeln!(
+            out,
+            "{:04x}: {:02x}  af={:04x} bc={:04x} de={:04x} hl={:04x} sp={:04x}",
+            regs.pc, entry.op, regs.af, regs.bc, regs.de, regs.hl, regs.sp
+        )?;
+    }
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 #[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum EnvelopeDirection {
     #[default]
     Decrease = 0,
@@ -19,7 +20,8 @@ impl EnvelopeDirection {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct Envelope {
     enabled: bool,
     direction: EnvelopeDirection,
@@ -6,7 +6,8 @@ pub(super) enum LengthTimerCalculationResult {
 }
 
 // LEN_MASK is the maximum length of the timer, 0x3F for all channels except wave, which is 0xFF
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct LengthTimer<const LENGTH_TIMER_MASK: u8> {
     enabled: bool,
     length: u8,
@@ -1,10 +1,12 @@
+pub use visualizer::AudioVisualizerFrame;
 use {
-    crate::TC_SEC,
+    crate::{wav::AudioRecorder, Stats, TC_SEC},
     length_timer::LengthTimer,
     noise::Noise,
     period_counter::PeriodCounter,
     square::Square,
     sweep::{Sweep, SweepTrait},
+    visualizer::Visualizer,
     wave::Wave,
 };
 
@@ -14,15 +16,58 @@ mod noise;
 mod period_counter;
 mod square;
 mod sweep;
+mod visualizer;
 mod wave;
 
-pub type Sample = f32;
+// Samples are handed to the callback in chunks of this many
+const BATCH_LEN: usize = 64;
+
+// Output sample format, mixing is always done in f32 in the [-1, 1] range
+pub trait Sample: Copy + Default {
+    fn from_f32(sample: f32) -> Self;
+}
+
+impl Sample for f32 {
+    #[inline]
+    fn from_f32(sample: f32) -> Self {
+        sample
+    }
+}
+
+impl Sample for i16 {
+    #[inline]
+    fn from_f32(sample: f32) -> Self {
+        (sample.clamp(-1.0, 1.0) * Self::MAX as f32) as Self
+    }
+}
 
 pub trait AudioCallback {
-    fn audio_sample(&self, l: Sample, r: Sample);
+    type Sample: Sample;
+
+    fn audio_sample(&self, l: Self::Sample, r: Self::Sample);
+
+    // Override to avoid paying the per call overhead for every sample
+    fn audio_batch(&self, samples: &[(Self::Sample, Self::Sample)]) {
+        for &(l, r) in samples {
+            self.audio_sample(l, r);
+        }
+    }
+}
+
+// Drops every sample, for headless runs and tests
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoAudio;
+
+impl AudioCallback for NoAudio {
+    type Sample = f32;
+
+    fn audio_sample(&self, _l: f32, _r: f32) {}
+
+    fn audio_batch(&self, _samples: &[(f32, f32)]) {}
 }
 
 #[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum PeriodHalf {
     #[default]
     First,
@@ -30,6 +75,12 @@ enum PeriodHalf {
 }
 
 // #[derive(Default)]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "", deserialize = "C: Default"))
+)]
 pub struct Apu<C: AudioCallback> {
     nr51: u8,
 
@@ -45,21 +96,54 @@ pub struct Apu<C: AudioCallback> {
     ch4: Noise,
 
     div_divider: u8,
+    // the last value written to every register and wave RAM byte, from NR10
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    last_writes: [u8; 0x30],
 
-    render_timer: i32,
+    // i64 since exact rates add cycles times the rate, which overflows an
+    // i32 for long steps like a general HDMA at high rates
+    render_timer: i64,
     ext_sample_period: i32,
-
+    sample_rate: i32,
+    exact_sample_rate: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    bypassed: bool,
+    // bit n set mutes channel n + 1 in the mix
+    #[cfg_attr(feature = "serde", serde(skip))]
+    muted: u8,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
     audio_callback: C,
+    #[cfg_attr(feature = "serde", serde(skip, default = "empty_batch"))]
+    batch: [(C::Sample, C::Sample); BATCH_LEN],
+    #[cfg_attr(feature = "serde", serde(skip))]
+    batch_len: usize,
 
     capacitor_l: f32,
     capacitor_r: f32,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    visualizer: Visualizer,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    recorder: Option<AudioRecorder>,
+}
+
+#[cfg(feature = "serde")]
+fn empty_batch<S: Sample>() -> [(S, S); BATCH_LEN] {
+    [Default::default(); BATCH_LEN]
 }
 
 impl<C: AudioCallback> Apu<C> {
     pub fn new(sample_rate: i32, audio_callback: C) -> Self {
         Self {
             ext_sample_period: Self::sample_period_from_rate(sample_rate),
+            sample_rate,
+            exact_sample_rate: false,
+            bypassed: false,
+            muted: 0,
             audio_callback,
+            batch: [Default::default(); BATCH_LEN],
+            batch_len: 0,
             nr51: 0,
             enabled: false,
             right_volume: 0,
@@ -71,33 +155,29 @@ impl<C: AudioCallback> Apu<C> {
             ch3: Wave::default(),
             ch4: Noise::default(),
             div_divider: 0,
+            last_writes: [0; 0x30],
             render_timer: 0,
             capacitor_l: 0.0,
             capacitor_r: 0.0,
+            visualizer: Visualizer::default(),
+            recorder: None,
         }
     }
 
-   const fn sample_period_from_rate(sample_rate: i32) -> i32 {
+    const fn sample_period_from_rate(sample_rate: i32) -> i32 {
         // FIXME:
         // This is mostly correct, the underrun errors are due to the timing issues in the run thread
         // maybe account for difference in frame rate and sample rate?
         TC_SEC / sample_rate
     }
 
-    pub fn run(&mut self, cycles: i32) {
-        fn mix_and_render<C1: AudioCallback>(apu: &Apu<C1>) -> (Sample, Sample) {
+    pub fn run(&mut self, cycles: i32, stats: &mut Stats) {
+        fn mix_and_render<C1: AudioCallback>(apu: &Apu<C1>) -> (f32, f32) {
             let mut l = 0;
             let mut r = 0;
 
-            for i in 0..4 {
-                let out = match i {
-                    0 => apu.ch1.output() * u8::from(apu.ch1.true_enabled()),
-                    1 => apu.ch2.output() * u8::from(apu.ch2.true_enabled()),
-                    2 => apu.ch3.output() * u8::from(apu.ch3.true_enabled()),
-                    3 => apu.ch4.output() * u8::from(apu.ch4.true_enabled()),
-                    _ => break,
-                };
-
+            for (i, out) in apu.channel_outputs().into_iter().enumerate() {
+                let out = if apu.muted & (1 << i) == 0 { out } else { 0 };
                 let right_on = u8::from(apu.nr51 & (1 << i) != 0);
                 let left_on = u8::from(apu.nr51 & (0x10 << i) != 0);
 
@@ -127,19 +207,138 @@ impl<C: AudioCallback> Apu<C> {
             self.ch4.step_sample(cycles);
         }
 
-        self.render_timer += cycles;
+        if self.bypassed {
+            return;
+        }
+
+        let period = if self.exact_sample_rate {
+            // Carries the fractional part of the period over, so on average
+            // sample_rate samples are rendered every TC_SEC cycles
+            self.render_timer += i64::from(cycles) * i64::from(self.sample_rate);
+            i64::from(TC_SEC)
+        } else {
+            self.render_timer += i64::from(cycles);
+            i64::from(self.ext_sample_period)
+        };
+
         #[allow(clippy::while_float)]
-        if self.render_timer >= self.ext_sample_period {
-            self.render_timer -= self.ext_sample_period;
+        if self.render_timer >= period {
+            self.render_timer -= period;
 
             let (l, r) = mix_and_render(self);
             let (l, r) = self.high_pass(l, r);
+            let channels = self.channel_outputs();
+            self.visualizer.push((l + r) / 2.0, channels);
+            if let Some(recorder) = &mut self.recorder {
+                recorder.push(l, r, channels);
+            }
+
+            self.batch[self.batch_len] = (C::Sample::from_f32(l), C::Sample::from_f32(r));
+            self.batch_len += 1;
+            stats.audio_samples += 1;
+
+            if self.batch_len == BATCH_LEN {
+                self.flush_samples();
+            }
+        }
+    }
+
+    const fn channel_outputs(&self) -> [u8; 4] {
+        [
+            self.ch1.output() * self.ch1.true_enabled() as u8,
+            self.ch2.output() * self.ch2.true_enabled() as u8,
+            self.ch3.output() * self.ch3.true_enabled() as u8,
+            self.ch4.output() * self.ch4.true_enabled() as u8,
+        ]
+    }
+
+    pub fn set_visualizer_enabled(&mut self, enabled: bool) {
+        self.visualizer.set_enabled(enabled);
+    }
+
+    pub fn take_visualizer_frame(&mut self) -> AudioVisualizerFrame {
+        self.visualizer.take_frame()
+    }
+
+    // Output goes on with the frontend's callback and settings, keeping the
+    // samples it hasn't received yet
+    pub const fn take_host_state(&mut self, other: &mut Self) {
+        core::mem::swap(&mut self.audio_callback, &mut other.audio_callback);
+        core::mem::swap(&mut self.batch, &mut other.batch);
+        core::mem::swap(&mut self.batch_len, &mut other.batch_len);
+        core::mem::swap(&mut self.visualizer, &mut other.visualizer);
+        core::mem::swap(&mut self.recorder, &mut other.recorder);
+        self.sample_rate = other.sample_rate;
+        self.ext_sample_period = other.ext_sample_period;
+        self.exact_sample_rate = other.exact_sample_rate;
+        self.bypassed = other.bypassed;
+        self.muted = other.muted;
+        self.render_timer = other.render_timer;
+    }
+
+    pub const fn sample_rate(&self) -> i32 {
+        self.sample_rate
+    }
+
+    pub const fn audio_callback(&self) -> &C {
+        &self.audio_callback
+    }
+
+    pub const fn set_exact_sample_rate(&mut self, exact: bool) {
+        self.exact_sample_rate = exact;
+        self.render_timer = 0;
+    }
+
+    // Muted channels still run and show in the visualizer's channel peaks,
+    // they're only left out of the mix
+    pub const fn set_channel_muted(&mut self, channel: usize, muted: bool) {
+        if muted {
+            self.muted |= 1 << channel;
+        } else {
+            self.muted &= !(1 << channel);
+        }
+    }
+
+    pub const fn channel_muted(&self, channel: usize) -> bool {
+        self.muted & (1 << channel) != 0
+    }
+
+    // addr is the low byte, 0x10..=0x3F
+    pub const fn note_write(&mut self, addr: u8, val: u8) {
+        self.last_writes[(addr - 0x10) as usize] = val;
+    }
+
+    pub const fn last_write(&self, addr: u8) -> u8 {
+        self.last_writes[(addr - 0x10) as usize]
+    }
+
+    // The recorder that was running
+    pub const fn set_recorder(&mut self, recorder: Option<AudioRecorder>) -> Option<AudioRecorder> {
+        core::mem::replace(&mut self.recorder, recorder)
+    }
+
+    pub const fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    // Channels keep running while bypassed, only mixing and output stop
+    pub fn set_bypassed(&mut self, bypassed: bool) {
+        if bypassed {
+            self.flush_samples();
+        }
+        self.bypassed = bypassed;
+        self.render_timer = 0;
+    }
 
-            self.audio_callback.audio_sample(l, r);
+    pub fn flush_samples(&mut self) {
+        if self.batch_len > 0 {
+            self.audio_callback
+                .audio_batch(&self.batch[..self.batch_len]);
+            self.batch_len = 0;
         }
     }
 
-    fn high_pass(&mut self, l: Sample, r: Sample) -> (Sample, Sample) {
+    fn high_pass(&mut self, l: f32, r: f32) -> (f32, f32) {
         let mut outl = 0.0;
         let mut outr = 0.0;
 
@@ -3,6 +3,8 @@ use {
     crate::apu::{LengthTimer, PeriodHalf},
 };
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct Noise {
     length_timer: LengthTimer<0x3F>,
     envelope: Envelope,
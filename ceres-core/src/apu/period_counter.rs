@@ -10,6 +10,8 @@ pub(super) enum PeriodStepResult {
     None,
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct PeriodCounter<const PERIOD_MULTIPLIER: u16, Sweep: SweepTrait> {
     timer: i32,
     period: u16, // 11 bit
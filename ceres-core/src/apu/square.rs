@@ -6,7 +6,8 @@ use {
     crate::apu::{period_counter::PeriodStepResult, LengthTimer, PeriodCounter, PeriodHalf},
 };
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct Square<Sweep: SweepTrait> {
     length_timer: LengthTimer<0x3F>,
     period_counter: PeriodCounter<4, Sweep>,
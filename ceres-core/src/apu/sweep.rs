@@ -8,6 +8,7 @@ pub(super) trait SweepTrait: Default {
 }
 
 #[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum SweepDirection {
     #[default]
     Add = 0,
@@ -36,6 +37,8 @@ pub(super) enum SweepCalculationResult {
     None,
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct Sweep {
     // TODO: check on behaviour
     enabled: bool,
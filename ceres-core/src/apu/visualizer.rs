@@ -0,0 +1,65 @@
+use alloc::{collections::VecDeque, vec::Vec};
+
+// Mixed output points kept for the oscilloscope
+const SCOPE_LEN: usize = 256;
+// Rendered samples per oscilloscope point
+const SCOPE_DECIMATION: u8 = 4;
+
+// What the APU played recently, for oscilloscopes and level meters
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AudioVisualizerFrame {
+    // Mono mix in [-1, 1], oldest first, every few rendered samples
+    pub scope: Vec<f32>,
+    // Peak level of each channel in [0, 1] since the previous frame
+    pub channels: [f32; 4],
+}
+
+// Only fed while enabled, the scope is allocated on first use
+#[derive(Clone, Default)]
+pub(super) struct Visualizer {
+    enabled: bool,
+    scope: VecDeque<f32>,
+    decimation: u8,
+    peaks: [u8; 4],
+}
+
+impl Visualizer {
+    #[inline]
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        *self = Self {
+            enabled,
+            ..Self::default()
+        };
+    }
+
+    #[inline]
+    pub(super) fn push(&mut self, mono: f32, outputs: [u8; 4]) {
+        if !self.enabled {
+            return;
+        }
+
+        for (peak, out) in self.peaks.iter_mut().zip(outputs) {
+            *peak = (*peak).max(out);
+        }
+
+        self.decimation += 1;
+        if self.decimation == SCOPE_DECIMATION {
+            self.decimation = 0;
+
+            if self.scope.len() == SCOPE_LEN {
+                self.scope.pop_front();
+            }
+            self.scope.push_back(mono);
+        }
+    }
+
+    pub(super) fn take_frame(&mut self) -> AudioVisualizerFrame {
+        let channels = self.peaks.map(|peak| f32::from(peak) / 15.0);
+        self.peaks = [0; 4];
+
+        AudioVisualizerFrame {
+            scope: self.scope.iter().copied().collect(),
+            channels,
+        }
+    }
+}
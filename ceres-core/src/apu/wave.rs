@@ -10,7 +10,8 @@ use {
 const RAM_LEN: u8 = 0x10;
 const SAMPLE_LEN: u8 = RAM_LEN * 2;
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct Wave {
     length_timer: LengthTimer<0xFF>,
     period_counter: PeriodCounter<2, ()>,
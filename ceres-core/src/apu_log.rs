@@ -0,0 +1,255 @@
+use {
+    crate::{AudioCallback, Gb, TC_PER_FRAME},
+    alloc::{vec, vec::Vec},
+};
+
+const NR10: u8 = 0x10;
+const NR30: u8 = 0x1A;
+const NR50: u8 = 0x24;
+const NR51: u8 = 0x25;
+const NR52: u8 = 0x26;
+const WAV_START: u8 = 0x30;
+const WAV_END: u8 = 0x3F;
+const DOTS_PER_FRAME: u64 = TC_PER_FRAME as u64;
+const REGISTER_NAMES: [&str; (WAV_START - NR10) as usize] = [
+    "NR10", "NR11", "NR12", "NR13", "NR14", "", "NR21", "NR22", "NR23", "NR24", "NR30", "NR31",
+    "NR32", "NR33", "NR34", "", "NR41", "NR42", "NR43", "NR44", "NR50", "NR51", "NR52", "", "", "",
+    "", "", "", "", "", "",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApuChannel {
+    Pulse1,
+    Pulse2,
+    Wave,
+    Noise,
+}
+
+impl ApuChannel {
+    pub const ALL: [Self; 4] = [Self::Pulse1, Self::Pulse2, Self::Wave, Self::Noise];
+
+    // Counting from 0, the bit of the channel in NR51 and NR52
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// A write to an APU register or wave RAM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApuWrite {
+    // dots since logging was enabled, unaffected by double speed
+    pub dots: u64,
+    // low byte of the address, 0x10..=0x3F
+    pub addr: u8,
+    pub val: u8,
+}
+
+impl ApuWrite {
+    // Frames of 70224 dots since logging was enabled, whether or not the LCD
+    // is on
+    #[must_use]
+    pub const fn frame(&self) -> u64 {
+        self.dots / DOTS_PER_FRAME
+    }
+
+    // None for the registers shared by all channels, NR50 to NR52
+    #[must_use]
+    pub const fn channel(&self) -> Option<ApuChannel> {
+        match self.addr {
+            0x10..=0x14 => Some(ApuChannel::Pulse1),
+            0x15..=0x19 => Some(ApuChannel::Pulse2),
+            0x1A..=0x1E | WAV_START.. => Some(ApuChannel::Wave),
+            0x1F..=0x23 => Some(ApuChannel::Noise),
+            _ => None,
+        }
+    }
+
+    // "NR10" to "NR52", "WAVE" for wave RAM and empty for unused addresses
+    #[must_use]
+    pub fn register_name(&self) -> &'static str {
+        if self.addr >= WAV_START {
+            return "WAVE";
+        }
+
+        REGISTER_NAMES
+            .get(usize::from(self.addr.wrapping_sub(NR10)))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+// A channel (re)started with a trigger write
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoteEvent {
+    pub frame: u64,
+    pub channel: ApuChannel,
+    // the 11 bit period of the tone channels, NR43 for noise
+    pub period: u16,
+    // initial volume from 0 to 15
+    pub volume: u8,
+}
+
+impl NoteEvent {
+    // Of the waveform for tone channels, of the LFSR clock for noise
+    #[must_use]
+    pub fn frequency(&self) -> f32 {
+        let divisor = f32::from(2048 - (self.period & 0x7FF));
+
+        match self.channel {
+            ApuChannel::Pulse1 | ApuChannel::Pulse2 => 131_072.0 / divisor,
+            ApuChannel::Wave => 65536.0 / divisor,
+            ApuChannel::Noise => {
+                let shift = self.period >> 4;
+                let divider = match self.period & 7 {
+                    0 => 0.5,
+                    r => f32::from(r),
+                };
+                262_144.0 / divider / f32::from(1_u16 << shift.min(15))
+            }
+        }
+    }
+}
+
+// Turns logged writes into notes, keeping the registers they were written
+// to. Feed it every write in order.
+#[derive(Clone, Default)]
+pub struct NoteTracker {
+    regs: [u8; (WAV_START - NR10) as usize],
+}
+
+impl NoteTracker {
+    pub fn push(&mut self, write: ApuWrite) -> Option<NoteEvent> {
+        let i = usize::from(write.addr.checked_sub(NR10)?);
+        *self.regs.get_mut(i)? = write.val;
+
+        // NRx4 with the trigger bit set, unless the channel's DAC is off
+        let (channel, base) = match write.addr {
+            0x14 => (ApuChannel::Pulse1, 0x10),
+            0x19 => (ApuChannel::Pulse2, 0x15),
+            0x1E => (ApuChannel::Wave, 0x1A),
+            0x23 => (ApuChannel::Noise, 0x1F),
+            _ => return None,
+        };
+        if write.val & 0x80 == 0 {
+            return None;
+        }
+
+        let reg = |addr: u8| self.regs[usize::from(addr - NR10)];
+        let (period, volume) = match channel {
+            ApuChannel::Wave => {
+                if reg(base) & 0x80 == 0 {
+                    return None;
+                }
+                // output level 100%, 50% or 25%
+                let volume = [0, 15, 7, 3][usize::from(reg(base + 2) >> 5 & 3)];
+                (u16::from_le_bytes([reg(base + 3), write.val & 7]), volume)
+            }
+            ApuChannel::Noise => (u16::from(reg(base + 3)), reg(base + 2) >> 4),
+            ApuChannel::Pulse1 | ApuChannel::Pulse2 => (
+                u16::from_le_bytes([reg(base + 3), write.val & 7]),
+                reg(base + 2) >> 4,
+            ),
+        };
+
+        let dac_off = channel != ApuChannel::Wave && reg(base + 2) & 0xF8 == 0;
+        (!dac_off).then_some(NoteEvent {
+            frame: write.frame(),
+            channel,
+            period,
+            volume,
+        })
+    }
+}
+
+// Only allocated while logging
+#[derive(Clone, Default)]
+pub struct ApuLog {
+    enabled: bool,
+    dots: u64,
+    writes: Vec<ApuWrite>,
+}
+
+impl ApuLog {
+    #[inline]
+    pub(crate) fn run(&mut self, dots: i32) {
+        if self.enabled {
+            self.dots += u64::from(dots.unsigned_abs());
+        }
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    #[inline]
+    pub(crate) fn log_apu_write(&mut self, addr: u8, val: u8) {
+        self.apu.note_write(addr, val);
+        if self.apu_log.enabled {
+            self.apu_log.writes.push(ApuWrite {
+                dots: self.apu_log.dots,
+                addr,
+                val,
+            });
+        }
+    }
+
+    // Record every APU register and wave RAM write, including those ignored
+    // while the APU is off. Enabling restarts the timestamps at 0,
+    // disabling frees the buffer.
+    #[inline]
+    pub fn set_apu_logging(&mut self, enabled: bool) {
+        self.apu_log = ApuLog {
+            enabled,
+            ..ApuLog::default()
+        };
+    }
+
+    // Writes since the last call, oldest first
+    #[inline]
+    pub fn drain_apu_writes(&mut self) -> impl Iterator<Item = ApuWrite> + '_ {
+        self.apu_log.writes.drain(..)
+    }
+
+    // Writes that bring a freshly powered on APU close to where this one is,
+    // stamped with the current apu_log_dots. A log started in the middle of a
+    // song misses the writes made before, so a VGM of it should start with
+    // these. Envelopes and sweeps restart, and playing channels are triggered
+    // again.
+    #[must_use]
+    pub fn apu_state_writes(&self) -> Vec<ApuWrite> {
+        let reg = |addr| self.apu.last_write(addr);
+        let write = |addr, val| ApuWrite {
+            dots: self.apu_log.dots,
+            addr,
+            val,
+        };
+        let playing = self.apu.read_nr52();
+
+        // wave RAM can only be written with the wave channel off
+        let mut writes = vec![write(NR52, 0x80), write(NR30, 0)];
+        writes.extend((WAV_START..=WAV_END).map(|addr| write(addr, reg(addr))));
+        writes.extend([write(NR50, reg(NR50)), write(NR51, reg(NR51))]);
+
+        for (channel, base) in ApuChannel::ALL.into_iter().zip((NR10..).step_by(5)) {
+            writes.extend((base..base + 4).map(|addr| write(addr, reg(addr))));
+            let trigger = if playing & (1 << channel.index()) == 0 {
+                0
+            } else {
+                0x80
+            };
+            writes.push(write(base + 4, reg(base + 4) & 0x7F | trigger));
+        }
+
+        if !self.apu.enabled() {
+            writes.push(write(NR52, 0));
+        }
+
+        writes
+    }
+
+    // Dots since logging was enabled, to end a recording after the last write
+    #[must_use]
+    #[inline]
+    pub const fn apu_log_dots(&self) -> u64 {
+        self.apu_log.dots
+    }
+}
@@ -0,0 +1,121 @@
+use {
+    crate::{AudioCallback, Gb},
+    alloc::{collections::VecDeque, vec::Vec},
+};
+
+// Switches kept in the history, older ones are dropped
+const HISTORY_LEN: usize = 1024;
+const ROM_BANK_SIZE: u32 = 0x4000;
+
+// The ROM banks mapped after an MBC write changed them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BankSwitch {
+    // dots since tracking was enabled, unaffected by double speed
+    pub dots: u64,
+    // mapped at 0x0000..=0x3FFF, only MBC1 carts change it
+    pub low: u16,
+    // mapped at 0x4000..=0x7FFF
+    pub high: u16,
+}
+
+// Only allocated while tracking
+#[derive(Clone, Default)]
+pub struct BankUsage {
+    enabled: bool,
+    dots: u64,
+    last_switch: u64,
+    high: u16,
+    // dots each bank spent mapped at 0x4000..=0x7FFF, indexed by bank
+    resident: Vec<u64>,
+    history: VecDeque<BankSwitch>,
+}
+
+impl BankUsage {
+    #[inline]
+    pub(crate) fn run(&mut self, dots: i32) {
+        if self.enabled {
+            self.dots += u64::from(dots.unsigned_abs());
+        }
+    }
+
+    fn settle(&mut self) {
+        let bank = usize::from(self.high);
+        if self.resident.len() <= bank {
+            self.resident.resize(bank + 1, 0);
+        }
+
+        self.resident[bank] += self.dots - self.last_switch;
+        self.last_switch = self.dots;
+    }
+
+    // Dots each ROM bank has been mapped at 0x4000..=0x7FFF, indexed by bank
+    #[must_use]
+    pub fn resident_dots(&self) -> Vec<u64> {
+        let mut resident = self.resident.clone();
+        let bank = usize::from(self.high);
+        if resident.len() <= bank {
+            resident.resize(bank + 1, 0);
+        }
+
+        resident[bank] += self.dots - self.last_switch;
+        resident
+    }
+
+    // Most recent switches, oldest first
+    #[inline]
+    pub fn history(&self) -> impl Iterator<Item = BankSwitch> + '_ {
+        self.history.iter().copied()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn dots(&self) -> u64 {
+        self.dots
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    #[inline]
+    pub(crate) fn track_bank_switch(&mut self) {
+        if !self.bank_usage.enabled {
+            return;
+        }
+
+        let (low, high) = self.mapped_rom_banks();
+        self.bank_usage.settle();
+        self.bank_usage.high = high;
+
+        if self.bank_usage.history.len() == HISTORY_LEN {
+            self.bank_usage.history.pop_front();
+        }
+        self.bank_usage.history.push_back(BankSwitch {
+            dots: self.bank_usage.dots,
+            low,
+            high,
+        });
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    const fn mapped_rom_banks(&self) -> (u16, u16) {
+        let (low, high, _) = self.cart.bank_offsets();
+        ((low / ROM_BANK_SIZE) as u16, (high / ROM_BANK_SIZE) as u16)
+    }
+
+    // Track how long each ROM bank stays mapped and keep a history of
+    // switches. Enabling restarts the counts from the banks mapped now,
+    // disabling frees the buffers.
+    #[inline]
+    pub fn set_bank_tracking(&mut self, enabled: bool) {
+        self.bank_usage = BankUsage {
+            enabled,
+            high: self.mapped_rom_banks().1,
+            ..BankUsage::default()
+        };
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn bank_usage(&self) -> &BankUsage {
+        &self.bank_usage
+    }
+}
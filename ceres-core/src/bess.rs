@@ -0,0 +1,475 @@
+// Best Effort Save State, the format SameBoy, Gambatte and others export.
+// The file ends with a footer pointing at a list of blocks, which hold the
+// registers, the memory mapped IO and where each memory buffer lies.
+// Timing inside the frame isn't part of it, the PPU starts the restored
+// frame from the top.
+
+use {
+    crate::{memory::HdmaState, AudioCallback, CgbMode, Gb, Model, Registers, PX_HEIGHT, PX_WIDTH},
+    alloc::{string::String, vec::Vec},
+    core::fmt::Display,
+};
+
+const MAGIC: &[u8; 4] = b"BESS";
+const FOOTER_LEN: usize = 8;
+const BLOCK_HEADER_LEN: usize = 8;
+const MAJOR_VERSION: u16 = 1;
+const MINOR_VERSION: u16 = 1;
+
+const INFO_LEN: usize = 0x12;
+const CORE_LEN: usize = 0xD0;
+const IO_LEN: usize = 0x80;
+const RTC_LEN: usize = 0x30;
+// Size of our own THMB block, which other emulators skip: half the screen in
+// each direction
+const THUMBNAIL_WIDTH: u16 = PX_WIDTH as u16 / 2;
+const THUMBNAIL_HEIGHT: u16 = PX_HEIGHT as u16 / 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BessError {
+    NotBess,
+    Corrupted,
+    UnsupportedVersion(u16),
+    DifferentRom { title: String },
+    // a CGB state on a DMG or the other way around
+    DifferentModel,
+}
+
+impl Display for BessError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotBess => write!(f, "not a BESS save state"),
+            Self::Corrupted => write!(f, "save state is truncated or corrupted"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "BESS version {version} isn't supported")
+            }
+            Self::DifferentRom { title } => write!(
+                f,
+                "save state belongs to \"{title}\", not the loaded cartridge"
+            ),
+            Self::DifferentModel => write!(
+                f,
+                "save state was made on another console family, change the model first"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for BessError {}
+
+// The blocks this core understands, borrowed from the file
+#[derive(Default)]
+struct Blocks<'a> {
+    core: Option<&'a [u8]>,
+    // cartridge title and global checksum
+    info: Option<&'a [u8]>,
+    // address and value pairs that map the saved banks
+    mbc: Option<&'a [u8]>,
+    rtc: Option<&'a [u8]>,
+    // width and height, then RGBA rows
+    thumbnail: Option<&'a [u8]>,
+}
+
+// A preview of the screen when the state was saved, for save slot pickers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BessThumbnail<'a> {
+    pub width: u16,
+    pub height: u16,
+    // four bytes per pixel, row by row
+    pub rgba: &'a [u8],
+}
+
+// Finds the thumbnail without loading the state. None if the state has no
+// thumbnail, like those made by other emulators.
+pub fn bess_thumbnail(data: &[u8]) -> Result<Option<BessThumbnail<'_>>, BessError> {
+    let Some(thumbnail) = blocks(data)?.thumbnail else {
+        return Ok(None);
+    };
+
+    let (width, height) = (u16_at(thumbnail, 0), u16_at(thumbnail, 2));
+    let rgba = &thumbnail[4..];
+    if rgba.len() != usize::from(width) * usize::from(height) * 4 {
+        return Err(BessError::Corrupted);
+    }
+
+    Ok(Some(BessThumbnail {
+        width,
+        height,
+        rgba,
+    }))
+}
+
+fn u16_at(data: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([data[i], data[i + 1]])
+}
+
+fn u32_at(data: &[u8], i: usize) -> usize {
+    u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize
+}
+
+fn blocks(data: &[u8]) -> Result<Blocks<'_>, BessError> {
+    let footer = data
+        .len()
+        .checked_sub(FOOTER_LEN)
+        .ok_or(BessError::NotBess)?;
+    if &data[footer + 4..] != MAGIC {
+        return Err(BessError::NotBess);
+    }
+
+    let mut blocks = Blocks::default();
+    let mut i = u32_at(data, footer);
+
+    loop {
+        let header = data
+            .get(i..i + BLOCK_HEADER_LEN)
+            .ok_or(BessError::Corrupted)?;
+        let len = u32_at(header, 4);
+        let start = i + BLOCK_HEADER_LEN;
+        let block = data
+            .get(start..start.checked_add(len).ok_or(BessError::Corrupted)?)
+            .ok_or(BessError::Corrupted)?;
+
+        match &header[..4] {
+            b"END " => break,
+            b"CORE" if len >= CORE_LEN => blocks.core = Some(block),
+            b"INFO" if len == INFO_LEN => blocks.info = Some(block),
+            b"MBC " if len.is_multiple_of(3) => blocks.mbc = Some(block),
+            b"RTC " if len == RTC_LEN => blocks.rtc = Some(block),
+            b"THMB" if len >= 4 => blocks.thumbnail = Some(block),
+            b"CORE" | b"INFO" | b"MBC " | b"RTC " | b"THMB" => return Err(BessError::Corrupted),
+            // NAME, SGB and whatever other emulators add
+            _ => (),
+        }
+
+        i = start + len;
+    }
+
+    Ok(blocks)
+}
+
+// Lays a state out like SameBoy does: the memory buffers first, then the
+// blocks, then the footer pointing at the first block
+#[derive(Default)]
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    // The size and offset pair CORE refers to the buffer with
+    fn buffer(&mut self, buffer: &[u8]) -> [u8; 8] {
+        let mut entry = [0; 8];
+        entry[..4].copy_from_slice(&(buffer.len() as u32).to_le_bytes());
+        entry[4..].copy_from_slice(&(self.data.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(buffer);
+        entry
+    }
+
+    fn block(&mut self, id: &[u8; 4], data: &[u8]) {
+        self.data.extend_from_slice(id);
+        self.data
+            .extend_from_slice(&(data.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(data);
+    }
+
+    fn finish(mut self, first_block: usize) -> Vec<u8> {
+        self.block(b"END ", &[]);
+        self.data
+            .extend_from_slice(&(first_block as u32).to_le_bytes());
+        self.data.extend_from_slice(MAGIC);
+        self.data
+    }
+}
+
+// Family, model and revision, spaces where they don't apply
+const fn model_id(model: Model) -> &'static [u8; 4] {
+    match model {
+        Model::Dmg0 => b"GD0 ",
+        Model::Dmg => b"GDB ",
+        Model::Mgb => b"GM  ",
+        Model::Sgb => b"SN  ",
+        Model::Sgb2 => b"S2  ",
+        Model::Cgb0 => b"CC0 ",
+        Model::Cgb => b"CC  ",
+        Model::CgbE => b"CCE ",
+        Model::Agb => b"CA  ",
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Exports the console in a state other emulators load, with every block
+    // SameBoy expects: NAME, INFO, CORE, MBC and RTC when the cartridge has
+    // them, and END. A thumbnail of the screen goes before END, read it back
+    // with bess_thumbnail. `now` is used like in Cart::rtc_footer.
+    #[must_use]
+    pub fn save_bess(&self, now: u64) -> Vec<u8> {
+        let dmg = matches!(self.cgb_mode, CgbMode::Dmg);
+        let (wram_len, vram_len) = if dmg {
+            (0x2000, 0x2000)
+        } else {
+            (0x8000, 0x4000)
+        };
+        let mut writer = Writer::default();
+
+        let mut buffers = Vec::with_capacity(CORE_LEN - 0x98);
+        for buffer in [
+            &self.wram[..wram_len],
+            &self.ppu.vram()[..vram_len],
+            self.cart.ram(),
+            &self.ppu.oam()[..0xA0],
+            &self.hram[..0x7F],
+        ] {
+            buffers.extend_from_slice(&writer.buffer(buffer));
+        }
+        for palette in [self.ppu.bcp(), self.ppu.ocp()] {
+            let colors = if dmg { &[][..] } else { &palette.bytes()[..] };
+            buffers.extend_from_slice(&writer.buffer(colors));
+        }
+
+        let first_block = writer.data.len();
+        writer.block(b"NAME", b"Ceres");
+
+        let mut info = [0; INFO_LEN];
+        info[..0x10].copy_from_slice(self.cart.raw_title());
+        info[0x10..].copy_from_slice(&self.cart.global_checksum().to_be_bytes());
+        writer.block(b"INFO", &info);
+
+        let mut core = Vec::with_capacity(CORE_LEN);
+        core.extend_from_slice(&MAJOR_VERSION.to_le_bytes());
+        core.extend_from_slice(&MINOR_VERSION.to_le_bytes());
+        core.extend_from_slice(model_id(self.model));
+        let regs = self.registers();
+        for reg in [regs.pc, regs.af, regs.bc, regs.de, regs.hl, regs.sp] {
+            core.extend_from_slice(&reg.to_le_bytes());
+        }
+        core.extend_from_slice(&[
+            u8::from(self.ints.enabled()),
+            self.ints.read_ie(),
+            u8::from(self.cpu_halted),
+            0,
+        ]);
+        core.extend_from_slice(&self.bess_io());
+        core.extend_from_slice(&buffers);
+        writer.block(b"CORE", &core);
+
+        let mbc = self.cart.bank_writes();
+        if !mbc.is_empty() {
+            let mbc = mbc
+                .into_iter()
+                .flat_map(|(addr, val)| {
+                    let [lo, hi] = addr.to_le_bytes();
+                    [lo, hi, val]
+                })
+                .collect::<Vec<_>>();
+            writer.block(b"MBC ", &mbc);
+        }
+        if let Some(rtc) = self.cart.rtc_footer(now) {
+            writer.block(b"RTC ", &rtc);
+        }
+        writer.block(b"THMB", &self.bess_thumbnail());
+
+        writer.finish(first_block)
+    }
+
+    // Every 2x2 square of the screen averaged into one pixel
+    fn bess_thumbnail(&self) -> Vec<u8> {
+        let screen = self.pixel_data_rgb();
+        let (width, height) = (usize::from(THUMBNAIL_WIDTH), usize::from(THUMBNAIL_HEIGHT));
+        let mut thumbnail = Vec::with_capacity(4 + width * height * 4);
+        thumbnail.extend_from_slice(&THUMBNAIL_WIDTH.to_le_bytes());
+        thumbnail.extend_from_slice(&THUMBNAIL_HEIGHT.to_le_bytes());
+
+        for y in 0..height {
+            for x in 0..width {
+                for channel in 0..3 {
+                    let sum = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                        .into_iter()
+                        .map(|(dx, dy)| {
+                            let px = (y * 2 + dy) * usize::from(PX_WIDTH) + x * 2 + dx;
+                            u16::from(screen[px * 3 + channel])
+                        })
+                        .sum::<u16>();
+                    thumbnail.push((sum / 4) as u8);
+                }
+                thumbnail.push(0xFF);
+            }
+        }
+
+        thumbnail
+    }
+
+    // IO as the CPU reads it, except what reads back differently from what
+    // load_bess expects
+    fn bess_io(&self) -> [u8; IO_LEN] {
+        let mut io = core::array::from_fn(|i| self.read_mem(0xFF00 | i as u16));
+
+        io[0x46] = self.dma;
+        io[0x50] = u8::from(self.bootrom.is_none());
+        if matches!(self.cgb_mode, CgbMode::Compat) {
+            io[0x4C] = 4;
+        }
+
+        io
+    }
+
+    // Loads a state exported by another emulator. Only states of the loaded
+    // cartridge on a model of the same family are accepted, nothing changes
+    // otherwise. `now` is used like in Cart::load_rtc_footer.
+    pub fn load_bess(&mut self, data: &[u8], now: u64) -> Result<(), BessError> {
+        let blocks = blocks(data)?;
+        let core = blocks.core.ok_or(BessError::Corrupted)?;
+
+        let version = u16_at(core, 0);
+        if version != MAJOR_VERSION {
+            return Err(BessError::UnsupportedVersion(version));
+        }
+
+        if let Some(info) = blocks.info {
+            let (title, checksum) = (&info[..0x10], u16::from_be_bytes([info[0x10], info[0x11]]));
+            if title != self.cart.raw_title() || checksum != self.cart.global_checksum() {
+                let end = title.iter().position(|&c| c == 0).unwrap_or(title.len());
+                return Err(BessError::DifferentRom {
+                    title: String::from_utf8_lossy(&title[..end]).into(),
+                });
+            }
+        }
+
+        // the first letter of the model is its family
+        let cgb_state = core[4] == b'C';
+        if cgb_state == matches!(self.cgb_mode, CgbMode::Dmg) {
+            return Err(BessError::DifferentModel);
+        }
+
+        // every buffer is checked before anything is touched
+        let buffers = core[0x98..CORE_LEN]
+            .chunks_exact(8)
+            .map(|b| {
+                let (len, offset) = (u32_at(b, 0), u32_at(b, 4));
+                data.get(offset..offset.checked_add(len)?)
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(BessError::Corrupted)?;
+        let [wram, vram, sram, oam, hram, bg_palettes, obj_palettes] = buffers[..] else {
+            return Err(BessError::Corrupted);
+        };
+
+        if let Some(mbc) = blocks.mbc {
+            for write in mbc.chunks_exact(3) {
+                let addr = u16_at(write, 0);
+                if addr < 0x8000 {
+                    self.write_mem(addr, write[2]);
+                }
+            }
+        }
+        if let Some(rtc) = blocks.rtc {
+            self.cart.load_rtc_footer(rtc, now);
+        }
+
+        self.set_registers(Registers {
+            pc: u16_at(core, 0x08),
+            af: u16_at(core, 0x0A),
+            bc: u16_at(core, 0x0C),
+            de: u16_at(core, 0x0E),
+            hl: u16_at(core, 0x10),
+            sp: u16_at(core, 0x12),
+        });
+        if core[0x14] == 0 {
+            self.ints.disable();
+        } else {
+            self.ints.enable();
+        }
+        self.ints.write_ie(core[0x15]);
+        self.cpu_halted = core[0x16] != 0;
+        self.ei_delay = false;
+        self.halt_bug = false;
+
+        copy(&mut self.wram[..], wram);
+        copy(self.ppu.vram_mut(), vram);
+        copy(self.cart.ram_mut(), sram);
+        copy(self.ppu.oam_mut(), oam);
+        copy(&mut self.hram, hram);
+
+        self.restore_io(&core[0x18..0x18 + IO_LEN], [bg_palettes, obj_palettes]);
+        self.track_bank_switch();
+
+        Ok(())
+    }
+
+    fn restore_io(&mut self, io: &[u8], palettes: [&[u8]; 2]) {
+        let write = |gb: &mut Self, addr: u8| {
+            gb.write_mem(0xFF00 | u16::from(addr), io[usize::from(addr)]);
+        };
+
+        // nothing is transferring after the load
+        self.dma_on = false;
+        self.dma_restarting = false;
+        self.dma = io[0x46];
+        self.hdma5 = 0xFF;
+        self.hdma_state = HdmaState::Sleep;
+
+        // the screen restarts from the top once LCDC is written back
+        self.ppu.write_lcdc(0, &mut self.ints);
+
+        if !matches!(self.cgb_mode, CgbMode::Dmg) {
+            // the bootrom writes 4 to KEY0 for DMG games
+            self.cgb_mode = if io[0x4C] == 4 {
+                CgbMode::Compat
+            } else {
+                CgbMode::Cgb
+            };
+
+            for (i, colors) in palettes.into_iter().enumerate() {
+                let palette = if i == 0 {
+                    self.ppu.bcp_mut()
+                } else {
+                    self.ppu.ocp_mut()
+                };
+                palette.set_spec(0x80);
+                for &color in colors.iter().take(0x40) {
+                    palette.set_data(color, false);
+                }
+            }
+            self.clock.restore_key1(io[0x4D]);
+            self.ppu.write_opri(io[0x6C]);
+        }
+        if io[0x50] & 1 != 0 {
+            self.bootrom = None;
+        }
+
+        // DIV holds the high byte of the system counter
+        self.div = u16::from(io[0x04]) << 8;
+        // a transfer in progress isn't restarted
+        self.write_mem(0xFF02, io[0x02] & 0x7F);
+
+        // NR52 first, the other sound registers are ignored while it's off.
+        // Triggers are left out so channels don't restart.
+        write(self, 0x26);
+        for addr in (0x10..=0x25_u8).filter(|addr| !matches!(addr, 0x15 | 0x1F)) {
+            let trigger = if matches!(addr, 0x14 | 0x19 | 0x1E | 0x23) {
+                0x80
+            } else {
+                0
+            };
+            self.write_mem(0xFF00 | u16::from(addr), io[usize::from(addr)] & !trigger);
+        }
+
+        for addr in [0x00, 0x01, 0x05, 0x06, 0x07, 0x0F]
+            .into_iter()
+            .chain(0x30..=0x3F)
+            .chain([0x41, 0x42, 0x43, 0x45, 0x47, 0x48, 0x49, 0x4A, 0x4B])
+        {
+            write(self, addr);
+        }
+        if matches!(self.cgb_mode, CgbMode::Cgb) {
+            for addr in [0x4F, 0x51, 0x52, 0x53, 0x54, 0x68, 0x6A, 0x70] {
+                write(self, addr);
+            }
+        }
+        // LCDC last, turning the screen back on
+        write(self, 0x40);
+    }
+}
+
+fn copy(to: &mut [u8], from: &[u8]) {
+    let len = to.len().min(from.len());
+    to[..len].copy_from_slice(&from[..len]);
+}
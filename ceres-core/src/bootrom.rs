@@ -0,0 +1,200 @@
+use {
+    crate::{AudioCallback, CgbMode, Gb, Model, Registers},
+    alloc::boxed::Box,
+    core::fmt::Display,
+};
+
+// DMG style bootroms are mapped over 0x00..0x100, CGB ones continue
+// after the cartridge header up to 0x900
+const SIZE: usize = 0x100;
+const SPLIT_SIZE: usize = 0x900;
+
+const LCDC: u16 = 0xFF40;
+const BGP: u16 = 0xFF47;
+const NR50: u16 = 0xFF24;
+const NR51: u16 = 0xFF25;
+const NR52: u16 = 0xFF26;
+// white to black, little endian RGB555, what a DMG game without an entry in
+// the CGB bootrom's table gets in compatibility mode
+const GREYS: [u8; 8] = [0xFF, 0x7F, 0xB5, 0x56, 0x4A, 0x29, 0x00, 0x00];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootromError {
+    WrongSize { expected: usize, found: usize },
+}
+
+impl Display for BootromError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::WrongSize { expected, found } => write!(
+                f,
+                "bootrom is {found} bytes long, this model expects {expected}"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for BootromError {}
+
+// The free bootroms built into the core, None when built without the
+// bundled-bootroms feature
+#[cfg(feature = "bundled-bootroms")]
+pub fn bundled(model: Model) -> Option<Box<[u8]>> {
+    const DMG_BOOTROM: &[u8] = include_bytes!("../../gb-bootroms/bin/dmg.bin");
+    const MGB_BOOTROM: &[u8] = include_bytes!("../../gb-bootroms/bin/mgb.bin");
+    const CGB_BOOTROM: &[u8] = include_bytes!("../../gb-bootroms/bin/cgb.bin");
+
+    Some(
+        match model {
+            // there's no free SGB bootrom, the DMG one hands over in
+            // nearly the same state. The first revisions run the later
+            // bootroms, their own leave other values in the registers and
+            // need a dump passed to set_bootrom.
+            Model::Dmg0 | Model::Dmg | Model::Sgb => DMG_BOOTROM,
+            Model::Mgb | Model::Sgb2 => MGB_BOOTROM,
+            // the AGB bootrom differs only in the quirks
+            Model::Cgb0 | Model::Cgb | Model::CgbE | Model::Agb => CGB_BOOTROM,
+        }
+        .into(),
+    )
+}
+
+#[cfg(not(feature = "bundled-bootroms"))]
+pub const fn bundled(_model: Model) -> Option<Box<[u8]>> {
+    None
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Starts with another bootrom, like a dump of the original one. Call it
+    // before running anything. Without a bundled or supplied bootrom the
+    // cartridge starts at 0x100 as if the bootrom had run, see hand_over.
+    pub fn set_bootrom(&mut self, bootrom: Box<[u8]>) -> Result<(), BootromError> {
+        let expected = if self.quirks.split_bootrom {
+            SPLIT_SIZE
+        } else {
+            SIZE
+        };
+
+        if bootrom.len() != expected {
+            return Err(BootromError::WrongSize {
+                expected,
+                found: bootrom.len(),
+            });
+        }
+
+        if self.bootrom.is_none() {
+            self.undo_hand_over();
+        }
+        self.bootrom = Some(bootrom);
+
+        Ok(())
+    }
+
+    // Leaves the registers and IO the way the bootrom does when handing over
+    // to the cartridge. The logo isn't drawn and the PPU, APU and DIV timing
+    // is off by what the bootrom takes, which games don't rely on.
+    pub(crate) fn hand_over(&mut self) {
+        // the bootrom compares the header checksum last
+        let flags = if self.cart.header_checksum() == 0 {
+            0x80
+        } else {
+            0xB0
+        };
+
+        let mut registers = match self.model {
+            // the first DMG bootrom doesn't depend on the header
+            Model::Dmg0 => Registers {
+                af: 0x0100,
+                bc: 0xFF13,
+                de: 0x00C1,
+                hl: 0x8403,
+                sp: 0xFFFE,
+                pc: 0x0100,
+            },
+            Model::Dmg => Registers {
+                af: 0x0100 | flags,
+                bc: 0x0013,
+                de: 0x00D8,
+                hl: 0x014D,
+                sp: 0xFFFE,
+                pc: 0x0100,
+            },
+            Model::Mgb => Registers {
+                af: 0xFF00 | flags,
+                bc: 0x0013,
+                de: 0x00D8,
+                hl: 0x014D,
+                sp: 0xFFFE,
+                pc: 0x0100,
+            },
+            Model::Sgb | Model::Sgb2 => Registers {
+                af: if matches!(self.model, Model::Sgb) {
+                    0x0100
+                } else {
+                    0xFF00
+                },
+                bc: 0x0014,
+                de: 0x0000,
+                hl: 0xC060,
+                sp: 0xFFFE,
+                pc: 0x0100,
+            },
+            // every CGB revision hands over alike, the AGB changes B below
+            Model::Cgb0 | Model::Cgb | Model::CgbE | Model::Agb => Registers {
+                af: 0x1180,
+                bc: 0x0000,
+                de: 0xFF56,
+                hl: 0x000D,
+                sp: 0xFFFE,
+                pc: 0x0100,
+            },
+        };
+
+        if self.model.is_cgb() && !self.cart.supports_cgb() {
+            self.cgb_mode = CgbMode::Compat;
+            self.ppu.write_opri(1);
+            registers.de = 0x0008;
+            registers.hl = 0x007C;
+
+            // one background palette, two object ones
+            for (bg, len) in [(true, 8), (false, 16)] {
+                let palette = if bg {
+                    self.ppu.bcp_mut()
+                } else {
+                    self.ppu.ocp_mut()
+                };
+                palette.set_spec(0x80);
+                for &byte in GREYS.iter().cycle().take(len) {
+                    palette.set_data(byte, false);
+                }
+            }
+        }
+
+        if self.quirks.agb_bootrom {
+            // inc b, clearing Z
+            registers.af &= 0xFF00;
+            registers.bc = registers.bc.wrapping_add(0x100);
+        }
+
+        self.set_registers(registers);
+        self.write_mem(LCDC, 0x91);
+        self.write_mem(BGP, 0xFC);
+        self.write_mem(NR52, 0x80);
+        self.write_mem(NR50, 0x77);
+        self.write_mem(NR51, 0xF3);
+    }
+
+    // Back to power on for a bootrom supplied after hand_over
+    pub(crate) fn undo_hand_over(&mut self) {
+        self.set_registers(Registers::default());
+        self.write_mem(LCDC, 0);
+        self.write_mem(BGP, 0);
+        // clears the other sound registers
+        self.write_mem(NR52, 0);
+
+        if self.model.is_cgb() {
+            self.cgb_mode = CgbMode::Cgb;
+            self.ppu.write_opri(0);
+        }
+    }
+}
@@ -1,14 +1,28 @@
 use {
-    alloc::boxed::Box,
-    core::{fmt::Display, num::NonZeroU8},
-    Mbc::{Mbc0, Mbc1, Mbc2, Mbc3, Mbc5},
+    alloc::{boxed::Box, vec::Vec},
+    core::{fmt::Display, num::NonZeroU8, str::Utf8Error},
+    Mbc::{Huc1, Mbc0, Mbc1, Mbc2, Mbc3, Mbc5, Mmm01},
 };
 
+// End of the cartridge header, exclusive
+const HEADER_END: usize = 0x150;
+// MMM01 multicarts keep their menu, and the header, in the last 32 KiB
+const MMM01_MENU_SIZE: usize = 0x8000;
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum Mbc {
     Mbc0,
     Mbc1 {
         // Alternative MBC1 wiring allows to address up to 2MB of ROM
         bank_mode: bool,
+        // MBC1M, 1MB collections of four games with 4 bit inner banks
+        multicart: bool,
     },
     Mbc2,
     Mbc3 {
@@ -16,19 +30,33 @@ enum Mbc {
         rtc: Option<Mbc3RTC>,
     },
     Mbc5,
+    Huc1 {
+        // A000-BFFF reads the infrared receiver instead of RAM
+        ir_mode: bool,
+    },
+    Mmm01 {
+        regs: Mmm01Regs,
+    },
 }
 
 impl Mbc {
-    fn mbc_and_battery(mbc_byte: u8, rom_size: ROMSize) -> Result<(Self, bool), Error> {
-        let bank_mode = matches!(
-            rom_size,
-            ROMSize::Mb1 | ROMSize::Mb2 | ROMSize::Mb4 | ROMSize::Mb8
-        );
-
+    fn mbc_and_battery(mbc_byte: u8) -> Result<(Self, bool), Error> {
         let res = match mbc_byte {
             0x00 => (Mbc0, false),
-            0x01 | 0x02 => (Mbc1 { bank_mode }, false),
-            0x03 => (Mbc1 { bank_mode }, true),
+            0x01 | 0x02 => (
+                Mbc1 {
+                    bank_mode: false,
+                    multicart: false,
+                },
+                false,
+            ),
+            0x03 => (
+                Mbc1 {
+                    bank_mode: false,
+                    multicart: false,
+                },
+                true,
+            ),
             0x05 => (Mbc2, false),
             0x06 => (Mbc2, true),
             0x0F | 0x10 => (
@@ -44,6 +72,19 @@ impl Mbc {
             // 0x1C | 0x1D => (Mbc5, false),
             // 0x1E => (Mbc5, true),
             0x1B => (Mbc5, true),
+            0x0B | 0x0C => (
+                Mmm01 {
+                    regs: Mmm01Regs::default(),
+                },
+                false,
+            ),
+            0x0D => (
+                Mmm01 {
+                    regs: Mmm01Regs::default(),
+                },
+                true,
+            ),
+            0xFF => (Huc1 { ir_mode: false }, true),
             _ => return Err(Error::UnsupportedMBC(mbc_byte)),
         };
 
@@ -51,7 +92,7 @@ impl Mbc {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Error {
     InvalidRomSize,
     InvalidRamSize,
@@ -59,6 +100,7 @@ pub enum Error {
     UnsupportedMBC(u8),
     RomSizeDifferentThanActual,
     RamSizeDifferentThanActual,
+    MissingHeader,
 }
 
 impl Display for Error {
@@ -84,12 +126,35 @@ impl Display for Error {
                 f,
                 "header RAM size is different from the size of the supplied file"
             ),
+            Self::MissingHeader => {
+                write!(f, "file is too small to contain a cartridge header")
+            }
+        }
+    }
+}
+
+impl Error {
+    // Stable numeric code for each error, for frontends that can't carry
+    // the enum across their boundary
+    #[must_use]
+    #[inline]
+    pub const fn code(&self) -> u8 {
+        match self {
+            Self::InvalidRomSize => 1,
+            Self::InvalidRamSize => 2,
+            Self::NonAsciiTitleString => 3,
+            Self::UnsupportedMBC(_) => 4,
+            Self::RomSizeDifferentThanActual => 5,
+            Self::RamSizeDifferentThanActual => 6,
+            Self::MissingHeader => 7,
         }
     }
 }
 
 impl core::error::Error for Error {}
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cart {
     mbc: Mbc,
 
@@ -105,6 +170,8 @@ pub struct Cart {
     ram_offset: u32,
 
     has_battery: bool,
+    // Where the header is in the ROM, only the MMM01 menu isn't at the start
+    header: usize,
 
     ram_size: RAMSize,
     rom_size: ROMSize,
@@ -114,7 +181,7 @@ impl Default for Cart {
     fn default() -> Self {
         let rom_size = ROMSize::new(0).unwrap();
         let ram_size = RAMSize::new(0).unwrap();
-        let (mbc, has_battery) = Mbc::mbc_and_battery(0, rom_size).unwrap();
+        let (mbc, has_battery) = Mbc::mbc_and_battery(0).unwrap();
 
         let rom = alloc::vec![0xFF; rom_size.size_bytes() as usize].into_boxed_slice();
         let ram = alloc::vec![0xFF; ram_size.size_bytes() as usize].into_boxed_slice();
@@ -132,15 +199,27 @@ impl Default for Cart {
             ram_bank: 0,
             ram_offset: 0,
             has_battery,
+            header: 0,
         }
     }
 }
 
 impl Cart {
     pub fn new(rom: Box<[u8]>) -> Result<Self, Error> {
-        let rom_size = ROMSize::new(rom[0x148])?;
-        let ram_size = RAMSize::new(rom[0x149])?;
-        let (mbc, has_battery) = Mbc::mbc_and_battery(rom[0x147], rom_size)?;
+        // Checked first since the header is parsed before the ROM size is known
+        if rom.len() < HEADER_END {
+            return Err(Error::MissingHeader);
+        }
+
+        let header = rom
+            .len()
+            .checked_sub(MMM01_MENU_SIZE)
+            .filter(|&menu| matches!(rom[menu + 0x147], 0x0B..=0x0D))
+            .unwrap_or(0);
+
+        let rom_size = ROMSize::new(rom[header + 0x148])?;
+        let ram_size = RAMSize::new(rom[header + 0x149])?;
+        let (mut mbc, has_battery) = Mbc::mbc_and_battery(rom[header + 0x147])?;
 
         if rom_size.size_bytes() as usize != rom.len() {
             return Err(Error::RomSizeDifferentThanActual);
@@ -148,7 +227,11 @@ impl Cart {
 
         let ram = alloc::vec![0xFF; ram_size.size_bytes() as usize].into_boxed_slice();
 
-        Ok(Self {
+        if let Mbc1 { multicart, .. } = &mut mbc {
+            *multicart = is_mbc1_multicart(&rom);
+        }
+
+        let mut cart = Self {
             mbc,
             rom,
             ram,
@@ -161,11 +244,19 @@ impl Cart {
             ram_bank: 0,
             ram_offset: 0,
             has_battery,
-        })
+            header,
+        };
+
+        // MMM01 starts with the menu mapped
+        if let Mmm01 { regs } = &cart.mbc {
+            (cart.rom_offsets, cart.ram_offset) = regs.offsets(rom_size, ram_size);
+        }
+
+        Ok(cart)
     }
 
     pub fn set_ram(&mut self, ram: Box<[u8]>) -> Result<(), Error> {
-        let ram_size = RAMSize::new(self.rom[0x149])?;
+        let ram_size = RAMSize::new(self.rom[self.header + 0x149])?;
 
         if ram_size.size_bytes() as usize != ram.len() {
             return Err(Error::RamSizeDifferentThanActual);
@@ -178,19 +269,19 @@ impl Cart {
 
     #[must_use]
     pub const fn is_old_licensee_code(&self) -> bool {
-        let code = self.rom[0x14B];
+        let code = self.rom[self.header + 0x14B];
         code != 0x33
     }
 
     #[must_use]
     pub fn ascii_title(&self) -> &[u8] {
-        let range = if self.is_old_licensee_code() {
-            0x134..0x144
+        let end = if self.is_old_licensee_code() {
+            0x144
         } else {
-            0x134..0x13F
+            0x13F
         };
 
-        let title = &self.rom[range];
+        let title = &self.rom[self.header + 0x134..self.header + end];
         let mut i = 0;
         while i < title.len() && title[i] != 0 {
             i += 1;
@@ -198,19 +289,53 @@ impl Cart {
         &title[..i]
     }
 
+    // All 16 bytes the title may take, as save states store it
+    #[must_use]
+    pub(crate) fn raw_title(&self) -> &[u8] {
+        &self.rom[self.header + 0x134..self.header + 0x144]
+    }
+
+    #[inline]
+    pub fn title_str(&self) -> Result<&str, Utf8Error> {
+        core::str::from_utf8(self.ascii_title())
+    }
+
     #[must_use]
     pub const fn header_checksum(&self) -> u8 {
-        self.rom[0x14D]
+        self.rom[self.header + 0x14D]
     }
 
     #[must_use]
     pub const fn global_checksum(&self) -> u16 {
-        u16::from_be_bytes([self.rom[0x14E], self.rom[0x14F]])
+        u16::from_be_bytes([self.rom[self.header + 0x14E], self.rom[self.header + 0x14F]])
     }
 
     #[must_use]
     pub const fn version(&self) -> u8 {
-        self.rom[0x14C]
+        self.rom[self.header + 0x14C]
+    }
+
+    // Set for both CGB enhanced and CGB only games
+    #[must_use]
+    pub const fn supports_cgb(&self) -> bool {
+        self.rom[self.header + 0x143] & 0x80 != 0
+    }
+
+    // Games that refuse to run on a DMG
+    #[must_use]
+    pub const fn requires_cgb(&self) -> bool {
+        self.rom[self.header + 0x143] & 0xC0 == 0xC0
+    }
+
+    #[must_use]
+    pub(crate) const fn cgb_flag(&self) -> u8 {
+        self.rom[self.header + 0x143]
+    }
+
+    // Only honored with the new licensee code
+    #[must_use]
+    pub const fn supports_sgb(&self) -> bool {
+        self.rom[self.header + 0x146] == 0x03 && !self.is_old_licensee_code()
     }
 
     #[must_use]
@@ -219,6 +344,18 @@ impl Cart {
         self.has_battery.then_some(&*self.ram)
     }
 
+    // Every bank, battery or not
+    #[must_use]
+    #[inline]
+    pub(crate) fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    #[inline]
+    pub(crate) fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
     #[must_use]
     #[inline]
     pub const fn clock(&self) -> Option<&[u8]> {
@@ -229,12 +366,132 @@ impl Cart {
         }
     }
 
+    // The footer BGB and VBA append to .sav files, now is in seconds since the
+    // UNIX epoch
+    #[must_use]
+    pub fn rtc_footer(&self, now: u64) -> Option<[u8; RTC_FOOTER_LEN]> {
+        let Mbc3 { rtc: Some(rtc) } = &self.mbc else {
+            return None;
+        };
+
+        // Latching isn't emulated, so the latched registers are the live ones
+        let regs = rtc.visible_regs();
+        let mut footer = [0; RTC_FOOTER_LEN];
+        for (i, reg) in regs.iter().chain(&regs).enumerate() {
+            footer[i * 4] = *reg;
+        }
+        footer[40..].copy_from_slice(&now.to_le_bytes());
+
+        Some(footer)
+    }
+
+    // Accepts both the 48 byte footer and the older 44 byte one with a 32 bit
+    // timestamp, the time passed since it was written is added to the clock.
+    // Returns false if the cartridge has no clock or the footer is malformed
+    pub fn load_rtc_footer(&mut self, footer: &[u8], now: u64) -> bool {
+        let Mbc3 { rtc: Some(rtc) } = &mut self.mbc else {
+            return false;
+        };
+
+        let saved_at = match footer.len() {
+            RTC_FOOTER_LEN => u64::from_le_bytes(footer[40..48].try_into().unwrap()),
+            44 => u64::from(u32::from_le_bytes(footer[40..44].try_into().unwrap())),
+            _ => return false,
+        };
+
+        let mut regs = [0; 5];
+        for (reg, value) in regs.iter_mut().zip(footer.chunks_exact(4)) {
+            *reg = value[0];
+        }
+        rtc.set_visible_regs(regs);
+        rtc.advance_secs(now.saturating_sub(saved_at));
+
+        true
+    }
+
     #[must_use]
     #[inline]
     pub const fn has_battery(&self) -> bool {
         self.has_battery
     }
 
+    #[inline]
+    pub(crate) fn same_rom(&self, other: &Self) -> bool {
+        self.rom == other.rom
+    }
+
+    // Changes whenever the mapped ROM or RAM banks do
+    #[inline]
+    pub(crate) const fn bank_offsets(&self) -> (u32, u32, u32) {
+        (self.rom_offsets.0, self.rom_offsets.1, self.ram_offset)
+    }
+
+    // Mapped at 0xA000, whether RAM is enabled or not
+    #[inline]
+    pub(crate) const fn ram_bank(&self) -> u8 {
+        (self.ram_offset / 0x2000) as u8
+    }
+
+    // Writes that map the current banks again on a cartridge that was just
+    // powered on, the MBC block of a BESS state
+    pub(crate) fn bank_writes(&self) -> Vec<(u16, u8)> {
+        let ram_enable = if self.ram_enabled { 0x0A } else { 0 };
+
+        match &self.mbc {
+            Mbc0 => Vec::new(),
+            Mbc1 { bank_mode, .. } => alloc::vec![
+                (0x0000, ram_enable),
+                (0x2000, self.rom_bank_lo),
+                (0x4000, self.rom_bank_hi),
+                (0x6000, u8::from(*bank_mode)),
+            ],
+            Mbc2 => alloc::vec![(0x0000, ram_enable), (0x0100, self.rom_bank_lo)],
+            Mbc3 { rtc } => alloc::vec![
+                (0x0000, ram_enable),
+                (0x2000, self.rom_bank_lo),
+                (
+                    0x4000,
+                    rtc.as_ref()
+                        .and_then(|rtc| rtc.mapped)
+                        .map_or(self.ram_bank, NonZeroU8::get),
+                ),
+            ],
+            Mbc5 => alloc::vec![
+                (0x0000, ram_enable),
+                (0x2000, self.rom_bank_lo),
+                (0x3000, self.rom_bank_hi),
+                (0x4000, self.ram_bank),
+            ],
+            Huc1 { ir_mode } => alloc::vec![
+                (0x0000, if *ir_mode { 0x0E } else { 0x0A }),
+                (0x2000, self.rom_bank_lo),
+                (0x4000, self.ram_bank),
+            ],
+            // the mode lock comes after the mode and the lock of everything
+            // else last
+            Mmm01 { regs } => alloc::vec![
+                (0x2000, regs.rom_bank_low | regs.rom_bank_mid << 5),
+                (
+                    0x6000,
+                    u8::from(regs.mbc1_mode)
+                        | regs.rom_bank_mask << 2
+                        | u8::from(regs.multiplex) << 6,
+                ),
+                (
+                    0x4000,
+                    regs.ram_bank_low
+                        | regs.ram_bank_high << 2
+                        | regs.rom_bank_high << 4
+                        | u8::from(regs.mbc1_mode_locked) << 6,
+                ),
+                (
+                    0x0000,
+                    ram_enable | regs.ram_bank_mask << 4 | u8::from(regs.locked) << 6,
+                ),
+            ],
+        }
+    }
+
     pub(crate) fn run_rtc(&mut self, cycles: i32) {
         if let Mbc3 { rtc: Some(rtc) } = &mut self.mbc {
             rtc.run_cycles(cycles);
@@ -268,23 +525,68 @@ impl Cart {
 
         match &self.mbc {
             Mbc0 => 0xFF,
-            Mbc1 { .. } | Mbc5 => mbc_read_ram(self, self.ram_enabled, addr),
+            Mbc1 { .. } | Mbc5 | Mmm01 { .. } => mbc_read_ram(self, self.ram_enabled, addr),
             Mbc2 => (mbc_read_ram(self, self.ram_enabled, addr) & 0xF) | 0xF0,
             Mbc3 { rtc } => rtc
                 .as_ref()
                 .and_then(|r| r.read(self.ram_enabled))
                 .unwrap_or_else(|| mbc_read_ram(self, self.ram_enabled, addr)),
+            // No light reaches the receiver, RAM doesn't need enabling
+            Huc1 { ir_mode: true } => 0xC0,
+            Huc1 { ir_mode: false } => mbc_read_ram(self, true, addr),
+        }
+    }
+
+    // What the mapped bank or clock register holds, even with RAM disabled or
+    // the HuC1 in IR mode
+    pub(crate) fn peek_ram(&self, addr: u16) -> u8 {
+        if let Mbc3 { rtc: Some(rtc) } = &self.mbc {
+            if let Some(val) = rtc.read(true) {
+                return val;
+            }
+        }
+
+        if matches!(self.mbc, Mbc0) || !self.ram_size.is_any() {
+            return 0xFF;
+        }
+
+        let val = self.ram[self.ram_addr(addr) as usize];
+        if matches!(self.mbc, Mbc2) {
+            val & 0xF | 0xF0
+        } else {
+            val
         }
     }
 
+    // Returns the selected ROM bank if it's out of the cartridge range, in
+    // which case it wraps around
     #[allow(clippy::too_many_lines)]
     #[inline]
-    pub(crate) fn write_rom(&mut self, addr: u16, val: u8) {
+    pub(crate) fn write_rom(&mut self, addr: u16, val: u8) -> Option<u16> {
+        let mut bank = None;
+
         match &mut self.mbc {
             Mbc0 => (),
-            Mbc1 { bank_mode } => {
-                const fn mbc1_rom_offsets(c: &Cart, bank_mode: bool) -> (u32, u32) {
-                    let (lo, hi) = (c.rom_bank_lo, c.rom_bank_hi << 5);
+            Mbc1 {
+                bank_mode,
+                multicart,
+            } => {
+                // Low bank and the high bits shifted to their place
+                const fn mbc1_banks(c: &Cart, multicart: bool) -> (u8, u8) {
+                    if multicart {
+                        // bit 4 of the low bank isn't wired
+                        (c.rom_bank_lo & 0xF, c.rom_bank_hi << 4)
+                    } else {
+                        (c.rom_bank_lo, c.rom_bank_hi << 5)
+                    }
+                }
+
+                const fn mbc1_rom_offsets(
+                    c: &Cart,
+                    bank_mode: bool,
+                    multicart: bool,
+                ) -> (u32, u32) {
+                    let (lo, hi) = mbc1_banks(c, multicart);
 
                     let lo_bank = if bank_mode {
                         hi as u16 & c.rom_size.mask()
@@ -301,13 +603,15 @@ impl Cart {
 
                 const fn mbc1_ram_offset(cart: &Cart, bank_mode: bool) -> u32 {
                     let bank = if bank_mode {
-                        cart.rom_bank_hi as u32
+                        (cart.rom_bank_hi & cart.ram_size.mask()) as u32
                     } else {
                         0
                     };
                     RAMSize::BANK_SIZE as u32 * bank
                 }
 
+                let multicart = *multicart;
+
                 match addr {
                     0x0000..=0x1FFF => {
                         self.ram_enabled = (val & 0xF) == 0xA;
@@ -315,21 +619,25 @@ impl Cart {
                     0x2000..=0x3FFF => {
                         let bank_mode = *bank_mode;
 
+                        // Only 5 bits are wired, zero is checked before masking to the ROM size
+                        let val = val & 0x1F;
                         self.rom_bank_lo = if val == 0 { 1 } else { val };
-                        self.rom_offsets = mbc1_rom_offsets(self, bank_mode);
+                        self.rom_offsets = mbc1_rom_offsets(self, bank_mode, multicart);
+                        let (lo, hi) = mbc1_banks(self, multicart);
+                        bank = Some(u16::from(hi | lo));
                     }
                     0x4000..=0x5FFF => {
                         let bank_mode = *bank_mode;
 
                         self.rom_bank_hi = val & 3;
-                        self.rom_offsets = mbc1_rom_offsets(self, bank_mode);
+                        self.rom_offsets = mbc1_rom_offsets(self, bank_mode, multicart);
                         self.ram_offset = mbc1_ram_offset(self, bank_mode);
                     }
                     0x6000..=0x7FFF => {
                         *bank_mode = val & 1 != 0;
                         let bank_mode = *bank_mode;
 
-                        self.rom_offsets = mbc1_rom_offsets(self, bank_mode);
+                        self.rom_offsets = mbc1_rom_offsets(self, bank_mode, multicart);
                         self.ram_offset = mbc1_ram_offset(self, bank_mode);
                     }
                     _ => (),
@@ -344,8 +652,10 @@ impl Cart {
                         self.rom_bank_lo = if val == 0 { 1 } else { val };
                         self.rom_offsets = (
                             0,
-                            u32::from(ROMSize::BANK_SIZE) * u32::from(self.rom_bank_lo),
+                            u32::from(ROMSize::BANK_SIZE)
+                                * u32::from(u16::from(self.rom_bank_lo) & self.rom_size.mask()),
                         );
+                        bank = Some(u16::from(self.rom_bank_lo));
                     }
                 }
             }
@@ -354,12 +664,12 @@ impl Cart {
                     self.ram_enabled = (val & 0x0F) == 0x0A;
                 }
                 0x2000..=0x3FFF => {
+                    // Zero is checked before masking to the ROM size
+                    let val = val & 0x7F;
+                    let val = if val == 0 { 1 } else { val };
+                    bank = Some(u16::from(val));
                     self.rom_bank_lo = val & (self.rom_size.mask() & 0x7F) as u8;
 
-                    if self.rom_bank_lo == 0 {
-                        self.rom_bank_lo = 1;
-                    };
-
                     self.rom_offsets = (
                         0,
                         u32::from(ROMSize::BANK_SIZE) * u32::from(self.rom_bank_lo),
@@ -401,6 +711,7 @@ impl Cart {
                     0x2000..=0x2FFF => {
                         self.rom_bank_lo = val;
                         self.rom_offsets = mbc5_rom_offsets(self);
+                        bank = Some(u16::from(self.rom_bank_hi) << 8 | u16::from(val));
                     }
                     0x3000..=0x3FFF => {
                         self.rom_bank_hi = val;
@@ -413,7 +724,37 @@ impl Cart {
                     _ => (),
                 }
             }
+            Huc1 { ir_mode } => match addr {
+                0x0000..=0x1FFF => {
+                    *ir_mode = val & 0xF == 0xE;
+                }
+                0x2000..=0x3FFF => {
+                    // Bank 0 can be mapped to 4000-7FFF
+                    let val = val & 0x3F;
+                    bank = Some(u16::from(val));
+                    self.rom_bank_lo = val & self.rom_size.mask() as u8;
+                    self.rom_offsets = (
+                        0,
+                        u32::from(ROMSize::BANK_SIZE) * u32::from(self.rom_bank_lo),
+                    );
+                }
+                0x4000..=0x5FFF => {
+                    self.ram_bank = val & 0x3 & self.ram_size.mask();
+                    self.ram_offset = u32::from(RAMSize::BANK_SIZE) * u32::from(self.ram_bank);
+                }
+                _ => (),
+            },
+            Mmm01 { regs } => {
+                if addr <= 0x1FFF {
+                    self.ram_enabled = val & 0xF == 0xA;
+                }
+
+                regs.write(addr, val);
+                (self.rom_offsets, self.ram_offset) = regs.offsets(self.rom_size, self.ram_size);
+            }
         }
+
+        bank.filter(|&bank| bank > self.rom_size.mask())
     }
 
     #[inline]
@@ -427,7 +768,7 @@ impl Cart {
 
         match &mut self.mbc {
             Mbc0 => (),
-            Mbc1 { .. } | Mbc2 | Mbc5 => {
+            Mbc1 { .. } | Mbc2 | Mbc5 | Mmm01 { .. } => {
                 mbc_write_ram(self, self.ram_enabled, addr, val);
             }
             Mbc3 { rtc } => rtc
@@ -436,6 +777,9 @@ impl Cart {
                 .unwrap_or_else(|| {
                     mbc_write_ram(self, self.ram_enabled, addr, val);
                 }),
+            // Writes turn the infrared LED on and off, nothing sees its light
+            Huc1 { ir_mode: true } => (),
+            Huc1 { ir_mode: false } => mbc_write_ram(self, true, addr, val),
         }
     }
 
@@ -446,7 +790,17 @@ impl Cart {
     }
 }
 
+// Every game of an MBC1M collection has its own header, the second one
+// starts at bank 0x10
+fn is_mbc1_multicart(rom: &[u8]) -> bool {
+    const SECOND_LOGO: usize = 0x10 * ROMSize::BANK_SIZE as usize + 0x104;
+
+    rom.len() == ROMSize::Mb1.size_bytes() as usize
+        && rom[SECOND_LOGO..SECOND_LOGO + NINTENDO_LOGO.len()] == NINTENDO_LOGO
+}
+
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum ROMSize {
     Kb32 = 0,
     Kb64 = 1,
@@ -497,6 +851,7 @@ impl ROMSize {
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum RAMSize {
     NoRAM,
     Kb8,
@@ -560,7 +915,108 @@ impl RAMSize {
     }
 }
 
-#[derive(Default)]
+// Registers are named after Pan Docs. Until the map enable bit is written
+// the last 32 KiB are mapped, then the outer bank bits lock so the game can
+// only switch banks inside its own part of the ROM.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Mmm01Regs {
+    locked: bool,
+    rom_bank_low: u8,
+    rom_bank_mid: u8,
+    rom_bank_high: u8,
+    // locks bits 1 to 4 of the low ROM bank
+    rom_bank_mask: u8,
+    ram_bank_low: u8,
+    ram_bank_high: u8,
+    // locks the low RAM bank bits
+    ram_bank_mask: u8,
+    mbc1_mode: bool,
+    mbc1_mode_locked: bool,
+    // swaps the mid ROM bank and the low RAM bank
+    multiplex: bool,
+}
+
+impl Mmm01Regs {
+    const fn write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF if !self.locked => {
+                self.ram_bank_mask = (val >> 4) & 3;
+                self.locked = val & 0x40 != 0;
+            }
+            0x2000..=0x3FFF => {
+                if !self.locked {
+                    self.rom_bank_mid = (val >> 5) & 3;
+                }
+
+                let fixed = self.rom_bank_mask << 1;
+                self.rom_bank_low = (self.rom_bank_low & fixed) | (val & !fixed & 0x1F);
+            }
+            0x4000..=0x5FFF => {
+                let fixed = self.ram_bank_mask;
+                self.ram_bank_low = (self.ram_bank_low & fixed) | (val & !fixed & 3);
+
+                if !self.locked {
+                    self.ram_bank_high = (val >> 2) & 3;
+                    self.rom_bank_high = (val >> 4) & 3;
+                    self.mbc1_mode_locked = val & 0x40 != 0;
+                }
+            }
+            0x6000..=0x7FFF => {
+                if !self.mbc1_mode_locked {
+                    self.mbc1_mode = val & 1 != 0;
+                }
+
+                if !self.locked {
+                    self.rom_bank_mask = (val >> 2) & 0xF;
+                    self.multiplex = val & 0x40 != 0;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // ROM offsets of both halves and the RAM offset
+    fn offsets(&self, rom_size: ROMSize, ram_size: RAMSize) -> ((u32, u32), u32) {
+        let (rom0_bank, rom_bank, ram_bank) = if self.locked {
+            let (mid, ram_low) = if self.multiplex {
+                (self.ram_bank_low, self.rom_bank_mid)
+            } else {
+                (self.rom_bank_mid, self.ram_bank_low)
+            };
+            let outer = u16::from(mid) << 5 | u16::from(self.rom_bank_high) << 7;
+            let fixed = self.rom_bank_mask << 1;
+
+            // Like MBC1, bank 0 of the game can't be mapped to 4000-7FFF
+            let low = if self.rom_bank_low & !fixed & 0x1F == 0 {
+                self.rom_bank_low | 1
+            } else {
+                self.rom_bank_low
+            };
+
+            (
+                outer | u16::from(self.rom_bank_low & fixed),
+                outer | u16::from(low),
+                ram_low | self.ram_bank_high << 2,
+            )
+        } else {
+            (0x1FE, 0x1FF, 0)
+        };
+
+        let rom_bank_offset =
+            |bank: u16| u32::from(ROMSize::BANK_SIZE) * u32::from(bank & rom_size.mask());
+
+        (
+            (rom_bank_offset(rom0_bank), rom_bank_offset(rom_bank)),
+            u32::from(RAMSize::BANK_SIZE) * u32::from(ram_bank & ram_size.mask()),
+        )
+    }
+}
+
+pub const RTC_FOOTER_LEN: usize = 48;
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Mbc3RTC {
     t_cycles: i32,
     regs: [u8; 5],
@@ -593,6 +1049,54 @@ impl Mbc3RTC {
         }
     }
 
+    // As the game reads them, with the halt and carry flags in the last one
+    const fn visible_regs(&self) -> [u8; 5] {
+        [
+            self.regs[0],
+            self.regs[1],
+            self.regs[2],
+            self.regs[3],
+            self.regs[4] & 1 | (self.halt as u8) << 6 | (self.carry as u8) << 7,
+        ]
+    }
+
+    const fn set_visible_regs(&mut self, regs: [u8; 5]) {
+        self.regs = [
+            regs[0] & 0x3F,
+            regs[1] & 0x3F,
+            regs[2] & 0x1F,
+            regs[3],
+            regs[4] & 1,
+        ];
+        self.halt = regs[4] & 0x40 != 0;
+        self.carry = regs[4] & 0x80 != 0;
+        self.t_cycles = 0;
+    }
+
+    // Skips ahead without ticking every second, used after long sleeps
+    fn advance_secs(&mut self, secs: u64) {
+        if self.halt || secs == 0 {
+            return;
+        }
+
+        let [s, m, h, lo, hi] = self.regs.map(u64::from);
+        let day = lo | (hi & 1) << 8;
+        let total = s + m * 60 + h * 3600 + day * 86400 + secs;
+
+        let days = total / 86400;
+        if days > 0x1FF {
+            self.carry = true;
+        }
+
+        self.regs = [
+            (total % 60) as u8,
+            (total / 60 % 60) as u8,
+            (total / 3600 % 24) as u8,
+            days as u8,
+            (days >> 8 & 1) as u8,
+        ];
+    }
+
     #[inline]
     fn update_secs(&mut self) {
         self.regs[0] = (self.regs[0] + 1) & 0x3F;
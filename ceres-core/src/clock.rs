@@ -0,0 +1,79 @@
+// The CPU, timers, serial and OAM DMA follow the CPU clock, which doubles
+// in double speed. The PPU, APU and cartridge keep running at the normal
+// rate, counted in dots. Cycles are spent in CPU T-cycles and converted
+// here, along with KEY1, which switches between the two speeds.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clock {
+    double_speed: bool,
+    switch_requested: bool,
+}
+
+impl Clock {
+    // Dots that pass in CPU T-cycles, which are always whole M-cycles
+    #[must_use]
+    #[inline]
+    pub const fn dots(&self, cycles: i32) -> i32 {
+        debug_assert!(cycles % 2 == 0);
+
+        if self.double_speed {
+            cycles / 2
+        } else {
+            cycles
+        }
+    }
+
+    // CPU T-cycles that take dots
+    #[must_use]
+    #[inline]
+    pub const fn cpu_cycles(&self, dots: i32) -> i32 {
+        if self.double_speed {
+            dots * 2
+        } else {
+            dots
+        }
+    }
+
+    // DIV bit whose falling edge steps the APU, at 512 Hz in both speeds
+    #[must_use]
+    #[inline]
+    pub const fn div_apu_bit(&self) -> u16 {
+        if self.double_speed {
+            0x2000
+        } else {
+            0x1000
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn read_key1(&self) -> u8 {
+        (self.double_speed as u8) << 7 | 0x7E | self.switch_requested as u8
+    }
+
+    #[inline]
+    pub const fn write_key1(&mut self, val: u8) {
+        self.switch_requested = val & 1 != 0;
+    }
+
+    // Both the speed and the pending switch, for loading states
+    #[inline]
+    pub const fn restore_key1(&mut self, val: u8) {
+        self.double_speed = val & 0x80 != 0;
+        self.switch_requested = val & 1 != 0;
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn switch_requested(&self) -> bool {
+        self.switch_requested
+    }
+
+    // Done by STOP when a switch was requested
+    #[inline]
+    pub const fn switch_speed(&mut self) {
+        debug_assert!(self.switch_requested);
+        self.double_speed = !self.double_speed;
+        self.switch_requested = false;
+    }
+}
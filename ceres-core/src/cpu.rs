@@ -1,4 +1,53 @@
-use crate::{AudioCallback, Gb};
+use crate::{AudioCallback, BreakpointId, Gb, Warning};
+
+// What to do when the CPU fetches one of the undefined opcodes
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidOpcodePolicy {
+    // Lock up like hardware does
+    #[default]
+    Hang,
+    // Stop running and report it, PC is left pointing to the opcode
+    Trap,
+    // Skip it
+    Nop,
+}
+
+// Why run_frame returned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    FrameDone,
+    InvalidOpcode {
+        pc: u16,
+        op: u8,
+    },
+    // ld b,b executed with the breakpoint enabled
+    Breakpoint {
+        pc: u16,
+    },
+    // Added with add_breakpoint, the instruction at PC hasn't executed yet
+    DebugBreakpoint {
+        id: BreakpointId,
+        pc: u16,
+    },
+    // Added with add_watchpoint, the accessing instruction has executed
+    Watchpoint {
+        id: BreakpointId,
+        addr: u16,
+        val: u8,
+        write: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub af: u16,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+    pub sp: u16,
+    pub pc: u16,
+}
 
 const ZF: u16 = 0x80;
 const NF: u16 = 0x40;
@@ -7,6 +56,10 @@ const CF: u16 = 0x10;
 
 impl<A: AudioCallback> Gb<A> {
     pub(crate) fn run_cpu(&mut self) {
+        if !self.cpu_halted && self.check_breakpoints() {
+            return;
+        }
+
         if self.ei_delay {
             self.ints.enable();
             self.ei_delay = false;
@@ -15,7 +68,9 @@ impl<A: AudioCallback> Gb<A> {
         if self.cpu_halted {
             self.tick_m_cycle();
         } else {
-            // println!("pc {:0x}", self.pc);
+            self.trace_instruction();
+            self.hook_execute();
+            self.stats.instructions += 1;
 
             let op = self.imm8();
             self.run_hdma();
@@ -46,14 +101,21 @@ impl<A: AudioCallback> Gb<A> {
     #[inline]
     fn cpu_write(&mut self, addr: u16, val: u8) {
         self.tick_m_cycle();
-        self.write_mem(addr, val);
+        if !self.ignores_write(addr) {
+            self.write_mem(addr, val);
+        }
+        self.hook_write(addr, val);
+        self.check_watchpoints(addr, val, true);
     }
 
     #[must_use]
     #[inline]
     fn read(&mut self, addr: u16) -> u8 {
         self.tick_m_cycle();
-        self.read_mem(addr)
+        let val = self.read_mem(addr);
+        self.hook_read(addr, val);
+        self.check_watchpoints(addr, val, false);
+        val
     }
 
     #[inline]
@@ -761,8 +823,8 @@ impl<A: AudioCallback> Gb<A> {
             self.imm8();
         }
 
-        if self.key1.requested() {
-            self.key1.change_speed();
+        if self.clock.switch_requested() {
+            self.clock.switch_speed();
             self.write_div();
 
             // TODO: div should not tick
@@ -800,12 +862,14 @@ impl<A: AudioCallback> Gb<A> {
     #[inline]
     const fn nop(&self) {}
 
-    // TODO: debugger breakpoint
-
+    // Used as a software breakpoint by test ROMs
     #[inline]
-    #[allow(clippy::needless_pass_by_ref_mut)]
     fn ld_b_b(&mut self) {
-        self.nop();
+        if self.ld_b_b_breakpoint {
+            self.stop_reason = Some(StopReason::Breakpoint {
+                pc: self.pc.wrapping_sub(1),
+            });
+        }
     }
 
     #[inline]
@@ -1085,9 +1149,21 @@ impl<A: AudioCallback> Gb<A> {
     }
 
     #[inline]
-    fn ill(&mut self, _op: u8) {
-        self.ints.ill();
-        self.cpu_halted = true;
+    fn ill(&mut self, op: u8) {
+        let pc = self.pc.wrapping_sub(1);
+        self.warn(Warning::InvalidOpcode { pc, op });
+
+        match self.invalid_opcode_policy {
+            InvalidOpcodePolicy::Hang => {
+                self.ints.ill();
+                self.cpu_halted = true;
+            }
+            InvalidOpcodePolicy::Trap => {
+                self.pc = pc;
+                self.stop_reason = Some(StopReason::InvalidOpcode { pc, op });
+            }
+            InvalidOpcodePolicy::Nop => (),
+        }
     }
 
     #[inline]
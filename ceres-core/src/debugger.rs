@@ -0,0 +1,264 @@
+use {
+    crate::{AudioCallback, Gb, StopReason, TC_PER_FRAME},
+    alloc::vec::Vec,
+};
+
+// Dots in a scanline, the same at double speed
+const SCANLINE_DOTS: i32 = 456;
+
+// Returned when adding a breakpoint or watchpoint, for removing it and
+// telling which one stopped emulation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BreakpointId(u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    A,
+    F,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    Af,
+    Bc,
+    De,
+    Hl,
+    Sp,
+}
+
+// Checked when the PC matches, before the instruction executes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Condition {
+    #[default]
+    Always,
+    Register(Register, u16),
+    Memory(u16, u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub pc: u16,
+    pub condition: Condition,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+// Stops after the instruction that accessed any address in start..=end
+// through the CPU, DMA transfers don't count
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub access: Access,
+}
+
+#[derive(Clone, Default)]
+pub struct Debugger {
+    breakpoints: Vec<(BreakpointId, Breakpoint)>,
+    watchpoints: Vec<(BreakpointId, Watchpoint)>,
+    next_id: u32,
+    // execution continues past the breakpoint it stopped at
+    resume_pc: Option<u16>,
+}
+
+impl Debugger {
+    const fn next_id(&mut self) -> BreakpointId {
+        let id = BreakpointId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        id
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> BreakpointId {
+        let id = self.debugger.next_id();
+        self.debugger.breakpoints.push((id, breakpoint));
+        id
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> BreakpointId {
+        let id = self.debugger.next_id();
+        self.debugger.watchpoints.push((id, watchpoint));
+        id
+    }
+
+    // Removes a breakpoint or watchpoint, returns false if it was already gone
+    pub fn remove_breakpoint(&mut self, id: BreakpointId) -> bool {
+        let len = self.debugger.breakpoints.len() + self.debugger.watchpoints.len();
+        self.debugger.breakpoints.retain(|(i, _)| *i != id);
+        self.debugger.watchpoints.retain(|(i, _)| *i != id);
+        len != self.debugger.breakpoints.len() + self.debugger.watchpoints.len()
+    }
+
+    #[inline]
+    pub fn clear_breakpoints(&mut self) {
+        self.debugger.breakpoints.clear();
+        self.debugger.watchpoints.clear();
+    }
+
+    #[inline]
+    pub fn breakpoints(&self) -> impl Iterator<Item = (BreakpointId, Breakpoint)> + '_ {
+        self.debugger.breakpoints.iter().copied()
+    }
+
+    #[inline]
+    pub fn watchpoints(&self) -> impl Iterator<Item = (BreakpointId, Watchpoint)> + '_ {
+        self.debugger.watchpoints.iter().copied()
+    }
+
+    // Runs until something stops emulation, never returns FrameDone. With no
+    // breakpoints or watchpoints this only returns on ld b,b or an invalid
+    // opcode, if those are enabled.
+    pub fn run_until_break(&mut self) -> StopReason {
+        self.dot_accumulator = 0;
+
+        loop {
+            self.run_cpu();
+
+            if self.dot_accumulator >= TC_PER_FRAME {
+                self.end_frame();
+            }
+
+            if let Some(reason) = self.stop_reason.take() {
+                self.apu.flush_samples();
+                return reason;
+            }
+        }
+    }
+
+    // Runs the instruction at PC, or one cycle while halted. A breakpoint at
+    // PC doesn't stop it, one or a watchpoint hit along the way is returned.
+    pub fn step_instruction(&mut self) -> Option<StopReason> {
+        self.debugger.resume_pc = Some(self.pc);
+        self.dot_accumulator = 0;
+        self.run_cpu();
+        self.apu.flush_samples();
+
+        self.stop_reason.take()
+    }
+
+    // Like step_instruction, but runs calls and rst until they return to the
+    // next instruction
+    pub fn step_over(&mut self) -> Option<StopReason> {
+        let op = self.read_mem(self.pc);
+        let len = match op {
+            // call, call cc
+            0xC4 | 0xCC | 0xCD | 0xD4 | 0xDC => 3,
+            // rst
+            0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => 1,
+            _ => return self.step_instruction(),
+        };
+
+        let ret = self.pc.wrapping_add(len);
+        let sp = self.sp;
+        if let Some(reason) = self.step_instruction() {
+            return Some(reason);
+        }
+
+        // deeper frames of a recursive call can pass through the same address
+        while self.pc != ret || self.sp < sp {
+            self.dot_accumulator = 0;
+            self.run_cpu();
+
+            if let Some(reason) = self.stop_reason.take() {
+                self.apu.flush_samples();
+                return Some(reason);
+            }
+        }
+
+        self.apu.flush_samples();
+        None
+    }
+
+    // Runs a scanline worth of dots, whether or not the LCD is on
+    pub fn step_scanline(&mut self) -> Option<StopReason> {
+        self.run_dots(SCANLINE_DOTS);
+        self.stop_reason.take()
+    }
+
+    // Sets the stop reason and returns true if execution should stop before
+    // the instruction at PC
+    #[inline]
+    pub(crate) fn check_breakpoints(&mut self) -> bool {
+        // taken before every instruction so a stale one can't skip a
+        // breakpoint added later
+        if self.debugger.resume_pc.take() == Some(self.pc) || self.debugger.breakpoints.is_empty() {
+            return false;
+        }
+
+        let hit = self
+            .debugger
+            .breakpoints
+            .iter()
+            .find(|(_, bp)| bp.pc == self.pc && self.condition_holds(bp.condition))
+            .map(|(id, _)| *id);
+
+        if let Some(id) = hit {
+            self.debugger.resume_pc = Some(self.pc);
+            self.stop_reason = Some(StopReason::DebugBreakpoint { id, pc: self.pc });
+        }
+
+        hit.is_some()
+    }
+
+    #[inline]
+    pub(crate) fn check_watchpoints(&mut self, addr: u16, val: u8, write: bool) {
+        if self.debugger.watchpoints.is_empty() || self.stop_reason.is_some() {
+            return;
+        }
+
+        let hit = self.debugger.watchpoints.iter().find(|(_, wp)| {
+            (wp.start..=wp.end).contains(&addr)
+                && match wp.access {
+                    Access::Read => !write,
+                    Access::Write => write,
+                    Access::ReadWrite => true,
+                }
+        });
+
+        if let Some((id, _)) = hit {
+            self.stop_reason = Some(StopReason::Watchpoint {
+                id: *id,
+                addr,
+                val,
+                write,
+            });
+        }
+    }
+
+    fn condition_holds(&self, condition: Condition) -> bool {
+        let [a, f] = self.af.to_be_bytes();
+        let [b, c] = self.bc.to_be_bytes();
+        let [d, e] = self.de.to_be_bytes();
+        let [h, l] = self.hl.to_be_bytes();
+
+        match condition {
+            Condition::Always => true,
+            Condition::Register(register, val) => {
+                val == match register {
+                    Register::A => a.into(),
+                    Register::F => f.into(),
+                    Register::B => b.into(),
+                    Register::C => c.into(),
+                    Register::D => d.into(),
+                    Register::E => e.into(),
+                    Register::H => h.into(),
+                    Register::L => l.into(),
+                    Register::Af => self.af,
+                    Register::Bc => self.bc,
+                    Register::De => self.de,
+                    Register::Hl => self.hl,
+                    Register::Sp => self.sp,
+                }
+            }
+            Condition::Memory(addr, val) => self.read_mem(addr) == val,
+        }
+    }
+}
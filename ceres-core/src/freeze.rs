@@ -0,0 +1,106 @@
+use {
+    crate::{AudioCallback, Gb},
+    alloc::vec::Vec,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FreezeMode {
+    // Writes go through, the frozen values come back when the frame ends
+    Revert,
+    // CPU writes are dropped, DMA still writes
+    IgnoreWrites,
+}
+
+// start..=end as the CPU addresses them, frozen with the values they hold
+// when added. Only memory can be frozen: VRAM, cartridge RAM, WRAM, OAM and
+// HRAM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Freeze {
+    pub start: u16,
+    pub end: u16,
+    pub mode: FreezeMode,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FreezeId(u32);
+
+#[derive(Clone, Default)]
+pub struct Freezes {
+    entries: Vec<(FreezeId, Freeze, Vec<u8>)>,
+    next_id: u32,
+}
+
+// Writing these back would switch banks, start transfers or acknowledge
+// interrupts
+const fn has_side_effects(addr: u16) -> bool {
+    matches!(addr, 0x0000..=0x7FFF | 0xFEA0..=0xFF7F | 0xFFFF)
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // None if the range reaches ROM, IO or IE
+    pub fn freeze(&mut self, freeze: Freeze) -> Option<FreezeId> {
+        if (freeze.start..=freeze.end).any(has_side_effects) {
+            return None;
+        }
+
+        let values = (freeze.start..=freeze.end)
+            .map(|addr| self.read_mem(addr))
+            .collect();
+
+        let id = FreezeId(self.freezes.next_id);
+        self.freezes.next_id = self.freezes.next_id.wrapping_add(1);
+        self.freezes.entries.push((id, freeze, values));
+        Some(id)
+    }
+
+    // Returns false if it was already gone
+    pub fn unfreeze(&mut self, id: FreezeId) -> bool {
+        let len = self.freezes.entries.len();
+        self.freezes.entries.retain(|(i, ..)| *i != id);
+        len != self.freezes.entries.len()
+    }
+
+    #[inline]
+    pub fn clear_freezes(&mut self) {
+        self.freezes.entries.clear();
+    }
+
+    #[inline]
+    pub fn freezes(&self) -> impl Iterator<Item = (FreezeId, Freeze)> + '_ {
+        self.freezes
+            .entries
+            .iter()
+            .map(|(id, freeze, _)| (*id, *freeze))
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) fn ignores_write(&self, addr: u16) -> bool {
+        self.freezes.entries.iter().any(|(_, freeze, _)| {
+            freeze.mode == FreezeMode::IgnoreWrites && (freeze.start..=freeze.end).contains(&addr)
+        })
+    }
+
+    // Called when a frame ends
+    pub(crate) fn revert_frozen(&mut self) {
+        if self.freezes.entries.is_empty() {
+            return;
+        }
+
+        let entries = core::mem::take(&mut self.freezes.entries);
+
+        for (_, freeze, values) in &entries {
+            if freeze.mode != FreezeMode::Revert {
+                continue;
+            }
+
+            for (addr, &val) in (freeze.start..=freeze.end).zip(values) {
+                if self.read_mem(addr) != val {
+                    self.write_mem(addr, val);
+                }
+            }
+        }
+
+        self.freezes.entries = entries;
+    }
+}
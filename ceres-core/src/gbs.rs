@@ -0,0 +1,360 @@
+use {
+    crate::{memory::Svbk, AudioCallback, Cart, Gb, Model, Registers},
+    alloc::{borrow::Cow, boxed::Box, string::String, vec},
+    core::fmt::Display,
+};
+
+const HEADER_SIZE: usize = 0x70;
+const VERSION: u8 = 1;
+// The code below the load address belongs to the player, as on the
+// cartridges the music was ripped from
+const MIN_LOAD_ADDR: u16 = 0x400;
+const MAX_ROM_SIZE: usize = 0x80_0000;
+const MIN_ROM_SIZE: usize = 0x8000;
+const DRIVER_ADDR: u16 = 0x150;
+
+// Header offsets
+const TRACK_COUNT: usize = 0x04;
+const FIRST_TRACK: usize = 0x05;
+const LOAD_ADDR: usize = 0x06;
+const INIT_ADDR: usize = 0x08;
+const PLAY_ADDR: usize = 0x0A;
+const STACK_POINTER: usize = 0x0C;
+const TMA: usize = 0x0E;
+const TAC: usize = 0x0F;
+const TITLE: usize = 0x10;
+const AUTHOR: usize = 0x30;
+const COPYRIGHT: usize = 0x50;
+const STRING_LEN: usize = 0x20;
+
+// TAC bits only GBS files give a meaning to
+const TAC_TIMER_INTERRUPT: u8 = 0x04;
+const TAC_DOUBLE_SPEED: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GbsError {
+    MissingHeader,
+    NotGbs,
+    UnsupportedVersion(u8),
+    NoTracks,
+    LoadAddressTooLow(u16),
+    TooLarge,
+}
+
+impl Display for GbsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MissingHeader => write!(f, "file is too short for a GBS header"),
+            Self::NotGbs => write!(f, "not a GBS file"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported GBS version {version}")
+            }
+            Self::NoTracks => write!(f, "GBS file has no tracks"),
+            Self::LoadAddressTooLow(addr) => {
+                write!(
+                    f,
+                    "GBS load address {addr:#06x} is below {MIN_LOAD_ADDR:#06x}"
+                )
+            }
+            Self::TooLarge => write!(f, "GBS data doesn't fit in 8 MiB of ROM"),
+        }
+    }
+}
+
+impl core::error::Error for GbsError {}
+
+// A GBS file, the sound code and data of a game with the addresses to call
+// to start a track and to play it a tick further
+#[derive(Clone)]
+pub struct Gbs {
+    file: Box<[u8]>,
+}
+
+impl Gbs {
+    pub fn new(file: Box<[u8]>) -> Result<Self, GbsError> {
+        if file.len() < HEADER_SIZE {
+            return Err(GbsError::MissingHeader);
+        }
+
+        if &file[..3] != b"GBS" {
+            return Err(GbsError::NotGbs);
+        }
+
+        if file[3] != VERSION {
+            return Err(GbsError::UnsupportedVersion(file[3]));
+        }
+
+        let gbs = Self { file };
+
+        if gbs.track_count() == 0 {
+            return Err(GbsError::NoTracks);
+        }
+
+        if gbs.load_addr() < MIN_LOAD_ADDR {
+            return Err(GbsError::LoadAddressTooLow(gbs.load_addr()));
+        }
+
+        if gbs.rom_size() > MAX_ROM_SIZE {
+            return Err(GbsError::TooLarge);
+        }
+
+        Ok(gbs)
+    }
+
+    #[must_use]
+    pub fn track_count(&self) -> u8 {
+        self.file[TRACK_COUNT]
+    }
+
+    // Counted from 0, the header counts from 1
+    #[must_use]
+    pub fn first_track(&self) -> u8 {
+        match self.file[FIRST_TRACK] {
+            track @ 1.. if track <= self.track_count() => track - 1,
+            _ => 0,
+        }
+    }
+
+    #[must_use]
+    pub fn title(&self) -> Cow<'_, str> {
+        self.string(TITLE)
+    }
+
+    #[must_use]
+    pub fn author(&self) -> Cow<'_, str> {
+        self.string(AUTHOR)
+    }
+
+    #[must_use]
+    pub fn copyright(&self) -> Cow<'_, str> {
+        self.string(COPYRIGHT)
+    }
+
+    // Padded with zeroes, and not always ASCII
+    fn string(&self, offset: usize) -> Cow<'_, str> {
+        let field = &self.file[offset..offset + STRING_LEN];
+        let len = field.iter().position(|&c| c == 0).unwrap_or(STRING_LEN);
+        String::from_utf8_lossy(&field[..len])
+    }
+
+    fn word(&self, offset: usize) -> u16 {
+        u16::from_le_bytes([self.file[offset], self.file[offset + 1]])
+    }
+
+    fn load_addr(&self) -> u16 {
+        self.word(LOAD_ADDR)
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.file[HEADER_SIZE..]
+    }
+
+    fn rom_size(&self) -> usize {
+        (usize::from(self.load_addr()) + self.data().len())
+            .next_power_of_two()
+            .max(MIN_ROM_SIZE)
+    }
+
+    // An MBC5 cartridge with the data at the load address and, below it, a
+    // driver starting the track in A at DRIVER_ADDR and calling the play
+    // routine on every VBlank or timer interrupt
+    fn rom(&self) -> Box<[u8]> {
+        let load = self.load_addr();
+        let [init_lo, init_hi] = self.word(INIT_ADDR).to_le_bytes();
+        let [play_lo, play_hi] = self.word(PLAY_ADDR).to_le_bytes();
+        let tma = self.file[TMA];
+        let tac = self.file[TAC];
+
+        let mut rom = vec![0; self.rom_size()];
+        rom[usize::from(load)..usize::from(load) + self.data().len()].copy_from_slice(self.data());
+
+        // rst vectors jump to the same offset from the load address
+        for rst in (0..0x40).step_by(8) {
+            let [lo, hi] = (load + rst).to_le_bytes();
+            rom[usize::from(rst)..usize::from(rst) + 3].copy_from_slice(&[0xC3, lo, hi]);
+        }
+
+        // VBlank and timer: call play, reti
+        for vector in [0x40, 0x50] {
+            rom[vector..vector + 4].copy_from_slice(&[0xCD, play_lo, play_hi, 0xD9]);
+        }
+
+        // nop, jp DRIVER_ADDR
+        let [driver_lo, driver_hi] = DRIVER_ADDR.to_le_bytes();
+        rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, driver_lo, driver_hi]);
+        rom[0x147] = 0x1A;
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            rom[0x148] = (self.rom_size() / MIN_ROM_SIZE).trailing_zeros() as u8;
+        }
+        // 8 KiB
+        rom[0x149] = 0x02;
+
+        let (interrupt, lcdc) = if tac & TAC_TIMER_INTERRUPT == 0 {
+            (0x01, 0x80)
+        } else {
+            (0x04, 0x00)
+        };
+
+        let mut driver = vec![
+            0xF3, // di
+            0x47, // ld b,a
+            0xAF, // xor a
+            0xE0,
+            0xFF, // ldh (IE),a
+            0xE0,
+            0x0F, // ldh (IF),a
+            // the APU starts over, with every channel on both sides
+            0xE0,
+            0x26, // ldh (NR52),a
+            0x3E,
+            0x80, // ld a,$80
+            0xE0,
+            0x26, // ldh (NR52),a
+            0x3E,
+            0x77, // ld a,$77
+            0xE0,
+            0x24, // ldh (NR50),a
+            0x3E,
+            0xFF, // ld a,$FF
+            0xE0,
+            0x25, // ldh (NR51),a
+            // RAM on and bank 1 mapped
+            0x3E,
+            0x0A, // ld a,$0A
+            0xEA,
+            0x00,
+            0x00, // ld ($0000),a
+            0x3E,
+            0x01, // ld a,1
+            0xEA,
+            0x00,
+            0x20, // ld ($2000),a
+            0x3E,
+            tma, // ld a,TMA
+            0xE0,
+            0x06, // ldh (TMA),a
+            0x3E,
+            tac & 0x07, // ld a,TAC
+            0xE0,
+            0x07, // ldh (TAC),a
+            0x3E,
+            lcdc, // ld a,LCDC
+            0xE0,
+            0x40, // ldh (LCDC),a
+        ];
+
+        if tac & TAC_DOUBLE_SPEED != 0 {
+            driver.extend_from_slice(&[
+                0xF0, 0x4D, // ldh a,(KEY1)
+                0x87, // add a,a
+                0x38, 0x06, // jr c,.fast, already or not a CGB
+                0x3E, 0x01, // ld a,1
+                0xE0, 0x4D, // ldh (KEY1),a
+                0x10, 0x00, // stop
+                      // .fast
+            ]);
+        }
+
+        driver.extend_from_slice(&[
+            0x3E, interrupt, // ld a,IE
+            0xE0, 0xFF, // ldh (IE),a
+            0x78, // ld a,b
+            0xCD, init_lo, init_hi, // call init
+            0xFB,    // ei
+            0x76,    // .loop: halt
+            0x18, 0xFD, // jr .loop
+        ]);
+
+        let driver_addr = usize::from(DRIVER_ADDR);
+        rom[driver_addr..driver_addr + driver.len()].copy_from_slice(&driver);
+
+        rom.into_boxed_slice()
+    }
+}
+
+// Plays the tracks of a GBS file. The console runs without a bootrom and
+// with the LCD on only when VBlank times the play routine.
+pub struct GbsPlayer<C: AudioCallback> {
+    gb: Gb<C>,
+    gbs: Gbs,
+    track: u8,
+}
+
+impl<C: AudioCallback> GbsPlayer<C> {
+    // Starts with the track the file asks for
+    #[must_use]
+    pub fn new(gbs: Gbs, model: Model, sample_rate: i32, audio_callback: C) -> Self {
+        // Gbs::new checks the size, the rest of the header is ours
+        let cart = Cart::new(gbs.rom()).unwrap_or_default();
+
+        let mut gb = Gb::new(model, sample_rate, cart, audio_callback);
+        // the driver sets up what it needs, a DMG header would leave a CGB in
+        // compatibility mode after the hand over
+        if gb.bootrom.take().is_none() {
+            gb.undo_hand_over();
+        }
+
+        let track = gbs.first_track();
+        let mut player = Self { gb, gbs, track };
+        player.play(track);
+        player
+    }
+
+    #[must_use]
+    pub const fn gbs(&self) -> &Gbs {
+        &self.gbs
+    }
+
+    // For muting channels, logging writes and the like
+    #[must_use]
+    pub const fn gb(&self) -> &Gb<C> {
+        &self.gb
+    }
+
+    pub const fn gb_mut(&mut self) -> &mut Gb<C> {
+        &mut self.gb
+    }
+
+    #[must_use]
+    pub const fn track(&self) -> u8 {
+        self.track
+    }
+
+    // Starts a track from the beginning, wrapping around past the last one.
+    // The memory is cleared first, as init routines expect.
+    pub fn play(&mut self, track: u8) {
+        self.track = track % self.gbs.track_count();
+
+        let gb = &mut self.gb;
+        gb.wram.fill(0);
+        gb.hram.fill(0);
+        gb.svbk = Svbk::default();
+        gb.cpu_halted = false;
+        gb.halt_bug = false;
+        gb.ei_delay = false;
+        gb.ints.disable();
+        gb.set_registers(Registers {
+            af: u16::from(self.track) << 8,
+            bc: 0,
+            de: 0,
+            hl: 0,
+            sp: self.gbs.word(STACK_POINTER),
+            pc: DRIVER_ADDR,
+        });
+    }
+
+    pub fn next_track(&mut self) {
+        self.play(self.track + 1);
+    }
+
+    pub fn previous_track(&mut self) {
+        let track = self.track.checked_sub(1);
+        self.play(track.unwrap_or_else(|| self.gbs.track_count() - 1));
+    }
+
+    #[inline]
+    pub fn run_frame(&mut self) {
+        self.gb.run_frame();
+    }
+}
@@ -4,7 +4,8 @@ const TIMER: u8 = 4;
 const SERIAL: u8 = 8;
 const P1: u8 = 16;
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Interrupts {
     ime: bool,
     ifr: u8,
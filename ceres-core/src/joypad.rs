@@ -1,6 +1,10 @@
-use crate::interrupts::Interrupts;
+use crate::{
+    interrupts::Interrupts,
+    sgb::{Command, SgbLink, MLT_REQ},
+};
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Button {
     Right = 0x01,
     Left = 0x02,
@@ -12,52 +16,119 @@ pub enum Button {
     Start = 0x80,
 }
 
-#[derive(Default)]
+// Up to four controllers through the Super Game Boy multitap, only the
+// first one is read unless the game asks for more with MLT_REQ
+pub const MAX_PLAYERS: usize = 4;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Joypad {
-    p1_btn: u8,
+    p1_btn: [u8; MAX_PLAYERS],
     p1_dirs: bool,
     p1_acts: bool,
+    sgb: SgbLink,
+    player: u8,
+    player_count: u8,
+    // first controller, for input displays
+    frame_btn: u8,
+    last_frame_btn: u8,
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self {
+            p1_btn: [0; MAX_PLAYERS],
+            p1_dirs: false,
+            p1_acts: false,
+            sgb: SgbLink::default(),
+            player: 0,
+            player_count: 1,
+            frame_btn: 0,
+            last_frame_btn: 0,
+        }
+    }
 }
 
 impl Joypad {
     #[inline]
-    pub(crate) fn press(&mut self, button: Button, ints: &mut Interrupts) {
+    pub(crate) fn press(&mut self, player: usize, button: Button, ints: &mut Interrupts) {
         let b = button as u8;
 
-        self.p1_btn |= b;
+        self.p1_btn[player] |= b;
+        if player == 0 {
+            self.frame_btn |= b;
+        }
 
-        if b & 0x0F != 0 && self.p1_dirs || b & 0xF0 != 0 && self.p1_acts {
+        let read = player == usize::from(self.player);
+        if read && (b & 0x0F != 0 && self.p1_dirs || b & 0xF0 != 0 && self.p1_acts) {
             ints.req_p1();
         }
     }
 
     #[inline]
-    pub(crate) fn release(&mut self, button: Button) {
-        self.p1_btn &= !(button as u8);
+    pub(crate) const fn release(&mut self, player: usize, button: Button) {
+        self.p1_btn[player] &= !(button as u8);
+    }
+
+    // Called when a frame ends, buttons still held carry over to the next
+    #[inline]
+    pub(crate) const fn end_frame(&mut self) {
+        self.last_frame_btn = self.frame_btn;
+        self.frame_btn = self.p1_btn[0];
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn held_in_last_frame(&self, button: Button) -> bool {
+        self.last_frame_btn & button as u8 != 0
     }
 
     #[must_use]
     #[inline]
     pub(crate) const fn read_p1(&self) -> u8 {
-        let act = if self.p1_acts {
-            self.p1_btn >> 4 | 1 << 5
-        } else {
-            0
-        };
+        let btn = self.p1_btn[self.player as usize];
 
-        let dir = if self.p1_dirs {
-            self.p1_btn & 0xF | 1 << 4
-        } else {
-            0
-        };
+        // with nothing selected the multitap answers with the player
+        // reading next, 0xF for the first
+        if !self.p1_acts && !self.p1_dirs && self.player_count > 1 {
+            return !self.player;
+        }
+
+        let act = if self.p1_acts { btn >> 4 | 1 << 5 } else { 0 };
+
+        let dir = if self.p1_dirs { btn & 0xF | 1 << 4 } else { 0 };
 
         // pressed on low
         !(act | dir)
     }
 
+    // Returns the SGB commands the joypad doesn't handle itself
     #[inline]
-    pub(crate) fn write_joy(&mut self, val: u8) {
+    pub(crate) fn write_joy(&mut self, val: u8, sgb: bool) -> Option<Command> {
+        let acts_were_selected = self.p1_acts;
         self.p1_acts = val & 0x20 == 0;
         self.p1_dirs = val & 0x10 == 0;
+
+        if !sgb {
+            return None;
+        }
+
+        // deselecting the action buttons moves on to the next controller
+        if acts_were_selected && !self.p1_acts && self.player_count > 1 {
+            self.player = (self.player + 1) % self.player_count;
+        }
+
+        let command = self.sgb.write(val)?;
+        if command[0] >> 3 != MLT_REQ {
+            return Some(command);
+        }
+
+        self.player_count = match command[1] & 3 {
+            0 => 1,
+            1 => 2,
+            _ => 4,
+        };
+        self.player = 0;
+        None
     }
 }
@@ -2,29 +2,87 @@
 
 use core::time::Duration;
 
+use alloc::boxed::Box;
+use apu_log::ApuLog;
+use clock::Clock;
+use debugger::Debugger;
+use freeze::Freezes;
 use interrupts::Interrupts;
 use joypad::Joypad;
-use memory::{Key1, Svbk};
+use memory::Svbk;
+use memory_hook::HookSlot;
+use rng::Rng;
 use serial::Serial;
+use sgb::Sgb;
+use trace::Trace;
+use warning::Warnings;
 use {apu::Apu, memory::HdmaState, ppu::Ppu, timing::TIMAState};
 pub use {
-    apu::{AudioCallback, Sample},
-    cart::{Cart, Error},
-    joypad::Button,
-    ppu::{PX_HEIGHT, PX_WIDTH},
+    apu::{AudioCallback, AudioVisualizerFrame, NoAudio, Sample},
+    apu_log::{ApuChannel, ApuWrite, NoteEvent, NoteTracker},
+    bank_usage::{BankSwitch, BankUsage},
+    bess::{bess_thumbnail, BessError, BessThumbnail},
+    bootrom::BootromError,
+    cart::{Cart, Error, RTC_FOOTER_LEN},
+    cpu::{InvalidOpcodePolicy, Registers, StopReason},
+    debugger::{Access, Breakpoint, BreakpointId, Condition, Register, Watchpoint},
+    freeze::{Freeze, FreezeId, FreezeMode},
+    gbs::{Gbs, GbsError, GbsPlayer},
+    joypad::{Button, MAX_PLAYERS},
+    link_port::{BarcodeBoy, DebugConsole, LinkPort, SerialDevice, Workboy, WorkboyClock},
+    memory_hook::MemoryHook,
+    memory_region::MemoryRegion,
+    ppu::{
+        DmgPalette, Layer, OutputFilter, ScanlineObjs, ScanlineScroll, VideoWrite, LUT_SIZE,
+        MAX_OBJS_PER_LINE, PX_HEIGHT, PX_WIDTH,
+    },
+    quirks::Quirks,
+    save_state::{SaveState, SaveStateError, SaveStateInfo, SaveStateManager},
+    sgb::{SGB_PX_HEIGHT, SGB_PX_WIDTH},
+    state_diff::{Mismatch, StateDiff, StateField},
+    stats::Stats,
+    trace::TraceEntry,
+    validate::{BuildError, BuildWarning},
+    vgm::VgmWriter,
+    warning::Warning,
+    wav::AudioRecorder,
 };
 
 extern crate alloc;
 
 mod apu;
+mod apu_log;
+mod bank_usage;
+mod bess;
+mod bootrom;
 mod cart;
+mod clock;
 mod cpu;
+mod debugger;
+mod freeze;
+mod gbs;
 mod interrupts;
 mod joypad;
+mod link_port;
 mod memory;
+mod memory_hook;
+mod memory_region;
 mod ppu;
+mod quirks;
+mod rng;
+mod save_state;
+#[cfg(feature = "serde")]
+mod serde_array;
 mod serial;
+mod sgb;
+mod state_diff;
+mod stats;
 mod timing;
+mod trace;
+mod validate;
+mod vgm;
+mod warning;
+mod wav;
 
 pub const FRAME_DURATION: Duration = Duration::new(0, 16742706);
 pub const TC_PER_FRAME: i32 = 70224; // t-cycles per frame
@@ -34,14 +92,25 @@ pub const TC_SEC: i32 = 0x40_0000; // 2^22
 pub const HRAM_SIZE: u8 = 0x80;
 pub const WRAM_SIZE: u16 = 0x2000 * 4;
 
+// With the serde feature the console state (de)serializes, leaving out what
+// SaveState leaves alone. A deserialized console gets a default audio
+// callback, load it through a SaveState to keep the running one's. Memories
+// and frame buffers are boxed so deserializing fits a thread's default stack.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "", deserialize = "C: Default"))
+)]
 pub struct Gb<C: AudioCallback> {
     model: Model,
+    quirks: Quirks,
     cgb_mode: CgbMode,
     dot_accumulator: i32,
 
     // cartridge
     cart: Cart,
-    bootrom: Option<&'static [u8]>,
+    bootrom: Option<Box<[u8]>>,
 
     // cpu
     af: u16,
@@ -54,12 +123,24 @@ pub struct Gb<C: AudioCallback> {
     ei_delay: bool,
     cpu_halted: bool,
     halt_bug: bool,
+    invalid_opcode_policy: InvalidOpcodePolicy,
+    ld_b_b_breakpoint: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stop_reason: Option<StopReason>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    debugger: Debugger,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    memory_hook: HookSlot,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    freezes: Freezes,
 
     // memory
-    wram: [u8; WRAM_SIZE as usize],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    wram: Box<[u8; WRAM_SIZE as usize]>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
     hram: [u8; HRAM_SIZE as usize],
     svbk: Svbk,
-    key1: Key1,
+    clock: Clock,
 
     // -- dma
     dma: u8,
@@ -88,34 +169,44 @@ pub struct Gb<C: AudioCallback> {
     serial: Serial,
     ints: Interrupts,
     joy: Joypad,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    warnings: Warnings,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    trace: Trace,
+    stats: Stats,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    apu_log: ApuLog,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    bank_usage: BankUsage,
+    // the SNES side, for SGB models
+    sgb: Option<Box<Sgb>>,
+    rng: Rng,
 }
 
 impl<C: AudioCallback> Gb<C> {
+    // sample_rate has to be between 1 and TC_SEC, Gb::try_new checks it
     #[must_use]
     pub fn new(model: Model, sample_rate: i32, cart: Cart, audio_callback: C) -> Self {
-        const DMG_BOOTROM: &[u8] = include_bytes!("../../gb-bootroms/bin/dmg.bin");
-        const MGB_BOOTROM: &[u8] = include_bytes!("../../gb-bootroms/bin/mgb.bin");
-        const CGB_BOOTROM: &[u8] = include_bytes!("../../gb-bootroms/bin/cgb.bin");
-
-        let cgb_mode = match model {
-            Model::Dmg | Model::Mgb => CgbMode::Dmg,
-            Model::Cgb => CgbMode::Cgb,
+        let cgb_mode = if model.is_cgb() {
+            CgbMode::Cgb
+        } else {
+            CgbMode::Dmg
         };
 
-        let bootrom = Some(match model {
-            Model::Dmg => DMG_BOOTROM,
-            Model::Mgb => MGB_BOOTROM,
-            Model::Cgb => CGB_BOOTROM,
-        });
+        let quirks = Quirks::for_model(model);
+        let mut ppu = Ppu::default();
+        ppu.set_agb_lcd(quirks.agb_lcd);
 
-        Self {
+        let mut gb = Self {
             model,
+            quirks,
             cgb_mode,
             cart,
-            bootrom,
+            bootrom: bootrom::bundled(model),
             apu: Apu::new(sample_rate, audio_callback),
 
-            wram: [0; WRAM_SIZE as usize],
+            wram: Box::new([0; WRAM_SIZE as usize]),
             hram: [0; HRAM_SIZE as usize],
             af: Default::default(),
             bc: Default::default(),
@@ -128,6 +219,12 @@ impl<C: AudioCallback> Gb<C> {
             dma: Default::default(),
             ei_delay: Default::default(),
             halt_bug: Default::default(),
+            invalid_opcode_policy: InvalidOpcodePolicy::default(),
+            ld_b_b_breakpoint: false,
+            stop_reason: None,
+            debugger: Debugger::default(),
+            memory_hook: HookSlot::default(),
+            freezes: Freezes::default(),
             hdma_dst: Default::default(),
             hdma_len: Default::default(),
             hdma_src: Default::default(),
@@ -136,9 +233,9 @@ impl<C: AudioCallback> Gb<C> {
             hl: Default::default(),
             ints: Interrupts::default(),
             joy: Joypad::default(),
-            key1: Key1::default(),
+            clock: Clock::default(),
             pc: Default::default(),
-            ppu: Ppu::default(),
+            ppu,
             serial: Serial::default(),
             sp: Default::default(),
             svbk: Svbk::default(),
@@ -148,18 +245,204 @@ impl<C: AudioCallback> Gb<C> {
             tma: Default::default(),
             div: Default::default(),
             dot_accumulator: Default::default(),
+            warnings: Warnings::default(),
+            trace: Trace::default(),
+            stats: Stats::default(),
+            apu_log: ApuLog::default(),
+            bank_usage: BankUsage::default(),
+            sgb: quirks.sgb_commands.then(Box::default),
+            rng: Rng::default(),
+        };
+
+        if gb.bootrom.is_none() {
+            gb.hand_over();
         }
+
+        gb
     }
 
     #[inline]
-    pub fn run_frame(&mut self) {
+    pub fn run_frame(&mut self) -> StopReason {
         self.dot_accumulator = 0;
 
         while self.dot_accumulator < TC_PER_FRAME {
             self.run_cpu();
+
+            if let Some(reason) = self.stop_reason.take() {
+                self.apu.flush_samples();
+                return reason;
+            }
         }
 
+        self.end_frame();
+
+        StopReason::FrameDone
+    }
+
+    // Bookkeeping once a frame worth of dots has run, shared by everything
+    // that runs across frames
+    pub(crate) fn end_frame(&mut self) {
         self.dot_accumulator -= TC_PER_FRAME;
+        self.apu.flush_samples();
+        self.joy.end_frame();
+        self.revert_frozen();
+    }
+
+    // Runs whole instructions until at least dots PPU dots (t-cycles at
+    // normal speed) have passed and returns how many did. It returns early
+    // when emulation stops, the reason is then available from
+    // take_stop_reason. Frames end along the way like with run_frame.
+    #[inline]
+    pub fn run_dots(&mut self, dots: i32) -> i32 {
+        let mut ran = 0;
+
+        while ran < dots && self.stop_reason.is_none() {
+            let before = self.dot_accumulator;
+            self.run_cpu();
+            ran += self.dot_accumulator - before;
+
+            if self.dot_accumulator >= TC_PER_FRAME {
+                self.end_frame();
+            }
+        }
+
+        self.apu.flush_samples();
+
+        ran
+    }
+
+    #[inline]
+    pub const fn take_stop_reason(&mut self) -> Option<StopReason> {
+        self.stop_reason.take()
+    }
+
+    #[inline]
+    pub const fn set_invalid_opcode_policy(&mut self, policy: InvalidOpcodePolicy) {
+        self.invalid_opcode_policy = policy;
+    }
+
+    #[inline]
+    pub const fn set_ld_b_b_breakpoint(&mut self, enabled: bool) {
+        self.ld_b_b_breakpoint = enabled;
+    }
+
+    // Renders exactly sample_rate / 59.7275 samples per frame on average,
+    // instead of rounding the period to whole cycles, which plays audio
+    // slightly fast and varies the samples per frame
+    #[inline]
+    pub const fn set_exact_sample_rate(&mut self, exact: bool) {
+        self.apu.set_exact_sample_rate(exact);
+    }
+
+    // Skips mixing and rendering samples altogether while off, to save time
+    // when the audio would be thrown away anyway, like in fast-forward. The
+    // channels, length timers and envelopes still run, so registers read the
+    // same as with audio on. On by default.
+    #[inline]
+    pub fn set_audio_enabled(&mut self, enabled: bool) {
+        self.apu.set_bypassed(!enabled);
+    }
+
+    // Leaves a channel out of the audio or puts it back, all are enabled by
+    // default. The game can't tell, the channel runs and reads the same.
+    #[inline]
+    pub const fn set_channel_enabled(&mut self, channel: ApuChannel, enabled: bool) {
+        self.apu.set_channel_muted(channel.index(), !enabled);
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn channel_enabled(&self, channel: ApuChannel) -> bool {
+        !self.apu.channel_muted(channel.index())
+    }
+
+    // Only this channel is heard
+    #[inline]
+    pub fn solo_channel(&mut self, channel: ApuChannel) {
+        for other in ApuChannel::ALL {
+            self.set_channel_enabled(other, other == channel);
+        }
+    }
+
+    // Start or stop collecting AudioVisualizerFrame data, off by default
+    #[inline]
+    pub fn set_audio_visualizer(&mut self, enabled: bool) {
+        self.apu.set_visualizer_enabled(enabled);
+    }
+
+    // Recent output and the channel peaks since the previous call, empty
+    // while the visualizer is off
+    #[inline]
+    pub fn audio_visualizer_frame(&mut self) -> AudioVisualizerFrame {
+        self.apu.take_visualizer_frame()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn registers(&self) -> Registers {
+        Registers {
+            af: self.af,
+            bc: self.bc,
+            de: self.de,
+            hl: self.hl,
+            sp: self.sp,
+            pc: self.pc,
+        }
+    }
+
+    // The low nibble of F doesn't exist and always reads 0
+    #[inline]
+    pub const fn set_registers(&mut self, registers: Registers) {
+        self.af = registers.af & 0xFFF0;
+        self.bc = registers.bc;
+        self.de = registers.de;
+        self.hl = registers.hl;
+        self.sp = registers.sp;
+        self.pc = registers.pc;
+    }
+
+    // Bytes sent through the serial port with the internal clock since the
+    // last call, test ROMs print their results this way
+    #[inline]
+    pub fn drain_serial_output(&mut self) -> impl Iterator<Item = u8> + '_ {
+        self.serial.drain_output()
+    }
+
+    // Plugs a device into the link port, returning the previous one
+    #[inline]
+    pub const fn set_link_port(&mut self, link_port: LinkPort) -> LinkPort {
+        self.serial.set_link_port(link_port)
+    }
+
+    // For feeding input to the plugged device
+    #[inline]
+    pub const fn link_port_mut(&mut self) -> &mut LinkPort {
+        self.serial.link_port_mut()
+    }
+
+    // Reads memory like the CPU would, without advancing time
+    #[must_use]
+    #[inline]
+    pub fn peek(&self, addr: u16) -> u8 {
+        self.read_mem(addr)
+    }
+
+    // For debuggers and disassemblers, never changes any state and sees VRAM,
+    // OAM and cartridge RAM even when the CPU would read 0xFF
+    #[must_use]
+    pub fn peek_mem(&self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0x9FFF => self.ppu.peek_vram(addr),
+            0xA000..=0xBFFF => self.cart.peek_ram(addr),
+            0xFE00..=0xFE9F => self.ppu.peek_oam(addr),
+            _ => self.read_mem(addr),
+        }
+    }
+
+    // Writes memory like the CPU would, without advancing time
+    #[inline]
+    pub fn poke(&mut self, addr: u16, val: u8) {
+        self.write_mem(addr, val);
     }
 
     #[must_use]
@@ -170,28 +453,145 @@ impl<C: AudioCallback> Gb<C> {
 
     #[must_use]
     #[inline]
-    pub const fn pixel_data_rgb(&self) -> &[u8] {
+    pub fn pixel_data_rgb(&self) -> &[u8] {
         self.ppu.pixel_data_rgb()
     }
 
+    // Changes only when the contents of pixel_data_rgb change, so frontends
+    // can skip redrawing while paused, with the LCD off or on static screens
+    #[must_use]
+    #[inline]
+    pub const fn frame_id(&self) -> u32 {
+        self.ppu.frame_id()
+    }
+
+    // The last frame as the Super Game Boy shows it, colored and inside its
+    // border, SGB_PX_WIDTH by SGB_PX_HEIGHT. None on other models.
+    #[must_use]
+    #[inline]
+    pub fn sgb_pixel_data_rgb(&self) -> Option<&[u8]> {
+        self.sgb.as_deref().map(Sgb::pixel_data_rgb)
+    }
+
+    // One entry per line of the last presented frame, for map viewers to
+    // follow raster splits
+    #[must_use]
+    #[inline]
+    pub const fn scanline_scroll(&self) -> &[ScanlineScroll] {
+        self.ppu.scanline_scroll()
+    }
+
+    // Objects every line of the last frame selected in OAM scan, whether or
+    // not objects are enabled or hidden, for sprite viewers and tests
+    #[must_use]
+    #[inline]
+    pub const fn scanline_objs(&self) -> &[ScanlineObjs] {
+        self.ppu.scanline_objs()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn layer_enabled(&self, layer: Layer) -> bool {
+        self.ppu.layer_enabled(layer)
+    }
+
+    // Hides a layer from the output from the next scanline on, for telling
+    // apart what each one draws. Emulation is unaffected.
+    #[inline]
+    pub const fn set_layer_enabled(&mut self, layer: Layer, enabled: bool) {
+        self.ppu.set_layer_enabled(layer, enabled);
+    }
+
+    // Takes effect from the next scanline on
+    #[inline]
+    pub fn set_output_filter(&mut self, filter: OutputFilter) {
+        self.ppu.set_output_filter(filter);
+    }
+
+    // Colors of the four shades on DMG models, lightest first. Takes effect
+    // from the next scanline on.
+    #[inline]
+    pub const fn set_dmg_palette(&mut self, colors: &[(u8, u8, u8); 4]) {
+        self.ppu.set_dmg_palette(colors);
+    }
+
     #[inline]
     pub fn press(&mut self, button: Button) {
-        self.joy.press(button, &mut self.ints);
+        self.joy.press(0, button, &mut self.ints);
+    }
+
+    #[inline]
+    pub const fn release(&mut self, button: Button) {
+        self.joy.release(0, button);
     }
 
+    // Controllers after the first are only read by games that enable Super
+    // Game Boy multiplayer. Players count from 0 up to MAX_PLAYERS.
     #[inline]
-    pub fn release(&mut self, button: Button) {
-        self.joy.release(button);
+    pub fn press_player(&mut self, player: usize, button: Button) {
+        if player < MAX_PLAYERS {
+            self.joy.press(player, button, &mut self.ints);
+        }
+    }
+
+    #[inline]
+    pub const fn release_player(&mut self, player: usize, button: Button) {
+        if player < MAX_PLAYERS {
+            self.joy.release(player, button);
+        }
+    }
+
+    // Whether the first controller held the button at any point of the last
+    // completed frame, so presses shorter than a frame still show
+    #[must_use]
+    #[inline]
+    pub const fn held_in_last_frame(&self, button: Button) -> bool {
+        self.joy.held_in_last_frame(button)
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Model {
+    // The first DMG revision, only sold in Japan
+    Dmg0,
     Dmg,
     Mgb,
+    // Super Game Boy, a DMG as far as the game runs, plus SGB commands
+    Sgb,
+    // Super Game Boy 2, hands over like the MGB
+    Sgb2,
+    // The first CGB revision, only sold in Japan
+    Cgb0,
     Cgb,
+    // The last CGB revision
+    CgbE,
+    // Game Boy Advance running CGB software
+    Agb,
+}
+
+impl Model {
+    // What the game was made for: CGB games run on a CGB, even when they
+    // support the SGB too
+    #[must_use]
+    pub const fn from_cart(cart: &Cart) -> Self {
+        if cart.supports_cgb() {
+            Self::Cgb
+        } else if cart.supports_sgb() {
+            Self::Sgb
+        } else {
+            Self::Dmg
+        }
+    }
+
+    #[must_use]
+    pub const fn is_cgb(self) -> bool {
+        matches!(self, Self::Cgb0 | Self::Cgb | Self::CgbE | Self::Agb)
+    }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum CgbMode {
     Dmg,
     Compat,
@@ -0,0 +1,68 @@
+use alloc::collections::VecDeque;
+
+// The game clocks these out and gets the reply byte by byte
+const HANDSHAKE: [u8; 4] = [0x10, 0x07, 0x10, 0x07];
+const HANDSHAKE_REPLY: [u8; 4] = [0xFF, 0xFF, 0x10, 0x07];
+// Framing of a scanned barcode
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+// JAN-13
+const DIGITS: usize = 13;
+
+// Namco's card scanner. Once a game completes the handshake, swiped
+// barcodes are sent with the scanner providing the clock.
+#[derive(Clone, Default)]
+pub struct BarcodeBoy {
+    handshake: usize,
+    pending: VecDeque<u8>,
+}
+
+impl BarcodeBoy {
+    // Swipes a card, returns false unless barcode is 13 ASCII digits.
+    // Swipes before the game is ready for them are dropped, like on
+    // hardware.
+    #[inline]
+    pub fn scan(&mut self, barcode: &str) -> bool {
+        if barcode.len() != DIGITS || !barcode.bytes().all(|b| b.is_ascii_digit()) {
+            return false;
+        }
+
+        if self.handshake == HANDSHAKE.len() {
+            self.pending.clear();
+            self.pending.push_back(STX);
+            self.pending.extend(barcode.bytes());
+            self.pending.push_back(ETX);
+        }
+
+        true
+    }
+
+    #[must_use]
+    #[inline]
+    pub(super) fn has_data(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub(super) fn exchange(&mut self, sent: u8) -> u8 {
+        if let Some(byte) = self.pending.pop_front() {
+            return byte;
+        }
+
+        // Games repeat the handshake when they return to the scanning screen
+        match HANDSHAKE.get(self.handshake) {
+            Some(&expected) if sent == expected => {
+                let reply = HANDSHAKE_REPLY[self.handshake];
+                self.handshake += 1;
+                reply
+            }
+            _ if sent == HANDSHAKE[0] => {
+                self.handshake = 1;
+                HANDSHAKE_REPLY[0]
+            }
+            _ => {
+                self.handshake = 0;
+                0xFF
+            }
+        }
+    }
+}
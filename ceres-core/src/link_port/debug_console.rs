@@ -0,0 +1,32 @@
+use alloc::{string::String, vec::Vec};
+
+// Not real hardware: every byte a game sends is text for the frontend to
+// show, a printf for homebrew without any setup. Escape sequences are kept
+// for terminals to interpret.
+#[derive(Clone, Default)]
+pub struct DebugConsole {
+    pending: Vec<u8>,
+}
+
+impl DebugConsole {
+    // What was sent since the last call, a character split between calls
+    // waits for the rest of its bytes. Invalid UTF-8 is replaced.
+    #[must_use]
+    pub fn take_text(&mut self) -> String {
+        let complete = match core::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // an incomplete sequence at the end
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+
+        let text = String::from_utf8_lossy(&self.pending[..complete]).into_owned();
+        self.pending.drain(..complete);
+        text
+    }
+
+    pub(super) fn exchange(&mut self, sent: u8) -> u8 {
+        self.pending.push(sent);
+        0xFF
+    }
+}
@@ -0,0 +1,111 @@
+pub use barcode_boy::BarcodeBoy;
+pub use debug_console::DebugConsole;
+pub use workboy::{Workboy, WorkboyClock};
+
+use alloc::boxed::Box;
+
+mod barcode_boy;
+mod debug_console;
+mod workboy;
+
+// A peripheral implemented outside the core: another console, a printer or
+// a network peer
+pub trait SerialDevice {
+    // The Game Boy clocked one bit out of SB, MSB first, returns the bit
+    // shifted in. Called at the transfer clock rate.
+    fn exchange_bit(&mut self, sent: bool) -> bool;
+
+    // A whole byte swapped at once, when either side clocked it while
+    // shifts_bits is false, or the device clocked it, see clocks_transfer
+    fn exchange_byte(&mut self, sent: u8) -> u8 {
+        (0..8).fold(0, |received, i| {
+            received << 1 | u8::from(self.exchange_bit(sent << i & 0x80 != 0))
+        })
+    }
+
+    // Polled while the Game Boy waits for an external clock, true when the
+    // device clocks a transfer
+    fn clocks_transfer(&mut self) -> bool {
+        false
+    }
+
+    // False for devices that can't keep up with the bit clock, like a network
+    // peer, they swap the byte as soon as the Game Boy starts the transfer
+    fn shifts_bits(&self) -> bool {
+        true
+    }
+}
+
+// What is plugged into the link port. Every transfer swaps the byte in SB
+// with one from the device, MSB first.
+#[derive(Default)]
+pub enum LinkPort {
+    // Reads as 0xFF, transfers waiting for an external clock never finish
+    #[default]
+    Disconnected,
+    BarcodeBoy(BarcodeBoy),
+    DebugConsole(DebugConsole),
+    Workboy(Workboy),
+    Device(Box<dyn SerialDevice + Send>),
+}
+
+// Devices belong to the frontend, so clones (and save states) start with
+// the cable unplugged
+impl Clone for LinkPort {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Disconnected | Self::Device(_) => Self::Disconnected,
+            Self::BarcodeBoy(bcb) => Self::BarcodeBoy(bcb.clone()),
+            Self::DebugConsole(console) => Self::DebugConsole(console.clone()),
+            Self::Workboy(workboy) => Self::Workboy(workboy.clone()),
+        }
+    }
+}
+
+impl LinkPort {
+    // A transfer started with sent in SB, returns the byte shifted in
+    #[inline]
+    pub(crate) fn exchange(&mut self, sent: u8) -> u8 {
+        match self {
+            Self::Disconnected => 0xFF,
+            Self::BarcodeBoy(bcb) => bcb.exchange(sent),
+            Self::DebugConsole(console) => console.exchange(sent),
+            Self::Workboy(workboy) => workboy.exchange(sent),
+            Self::Device(device) => device.exchange_byte(sent),
+        }
+    }
+
+    // Devices exchanging bit by bit are clocked through exchange_bit as the
+    // transfer runs, the rest answer a whole byte when it starts
+    #[must_use]
+    #[inline]
+    pub(crate) fn shifts_bits(&self) -> bool {
+        match self {
+            Self::Device(device) => device.shifts_bits(),
+            Self::Disconnected | Self::BarcodeBoy(_) | Self::DebugConsole(_) | Self::Workboy(_) => {
+                false
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn exchange_bit(&mut self, sent: bool) -> bool {
+        match self {
+            Self::Device(device) => device.exchange_bit(sent),
+            Self::Disconnected | Self::BarcodeBoy(_) | Self::DebugConsole(_) | Self::Workboy(_) => {
+                true
+            }
+        }
+    }
+
+    // Polled while the Game Boy waits for an external clock, true when the
+    // device clocks a transfer
+    #[inline]
+    pub(crate) fn clocks_transfer(&mut self) -> bool {
+        match self {
+            Self::Disconnected | Self::DebugConsole(_) | Self::Workboy(_) => false,
+            Self::BarcodeBoy(bcb) => bcb.has_data(),
+            Self::Device(device) => device.clocks_transfer(),
+        }
+    }
+}
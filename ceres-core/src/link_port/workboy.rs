@@ -0,0 +1,162 @@
+use alloc::collections::VecDeque;
+
+// Replies to the commands below
+const ACK: u8 = b'D';
+// Keyboard polls without a key pressed
+const NO_KEY: u8 = 0xFF;
+// The clock as the game reads and writes it
+const CLOCK_LEN: usize = 0x10;
+// Bytes the game sends after 'W' before the clock
+const WRITE_PREAMBLE: usize = 2;
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+// Date and time kept by the Workboy, in the host's time zone
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WorkboyClock {
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    // 1 to 31
+    pub day: u8,
+    // 1 to 12
+    pub month: u8,
+    // 1900 to 2155
+    pub year: u16,
+}
+
+impl WorkboyClock {
+    fn to_bytes(self) -> [u8; CLOCK_LEN] {
+        let mut bytes = [0; CLOCK_LEN];
+        // unknown, but the game expects it
+        bytes[0] = 4;
+        bytes[1] = to_bcd(self.minutes);
+        bytes[2] = to_bcd(self.seconds);
+        bytes[3] = to_bcd(self.hours);
+        bytes[4] = to_bcd(self.day);
+        bytes[5] = to_bcd(self.month);
+        // years since 1900, not BCD
+        bytes[0xF] = self.year.saturating_sub(1900).min(0xFF) as u8;
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; CLOCK_LEN]) -> Self {
+        Self {
+            seconds: from_bcd(bytes[2]),
+            minutes: from_bcd(bytes[1]),
+            hours: from_bcd(bytes[3]),
+            day: from_bcd(bytes[4]),
+            month: from_bcd(bytes[5]),
+            year: 1900 + u16::from(bytes[0xF]),
+        }
+    }
+}
+
+const fn to_bcd(value: u8) -> u8 {
+    ((value / 10 % 10) << 4) | (value % 10)
+}
+
+const fn from_bcd(bcd: u8) -> u8 {
+    (bcd >> 4) * 10 + (bcd & 0xF)
+}
+
+#[derive(Clone, Copy, Default)]
+enum Mode {
+    #[default]
+    Idle,
+    Keyboard,
+    ReadClock,
+    WriteClock,
+}
+
+// Bandai's keyboard with a clock, for its one unreleased game. Nothing about
+// it is documented, this follows SameBoy's emulation. The game sends a
+// command and reads the reply with the next transfer: 'O' polls the
+// keyboard, 'R' reads the clock as hex digits and 'W' sets it.
+#[derive(Clone, Default)]
+pub struct Workboy {
+    mode: Mode,
+    reply: u8,
+    clock: [u8; CLOCK_LEN],
+    // hex digits read or bytes written since the command
+    index: usize,
+    keys: VecDeque<u8>,
+    set_by_game: Option<WorkboyClock>,
+    now: WorkboyClock,
+}
+
+impl Workboy {
+    // Queues a key by its Workboy key code, shift included, it's up to the
+    // host to map its keyboard to them. One key is sent per poll.
+    #[inline]
+    pub fn press(&mut self, key: u8) {
+        self.keys.push_back(key);
+    }
+
+    // The Workboy doesn't keep time on its own, the host keeps it current,
+    // like once a frame
+    #[inline]
+    pub const fn set_clock(&mut self, now: WorkboyClock) {
+        self.now = now;
+    }
+
+    // What the game set the clock to, once, so the host can keep the
+    // difference to its own clock
+    #[must_use]
+    #[inline]
+    pub const fn take_clock_set_by_game(&mut self) -> Option<WorkboyClock> {
+        self.set_by_game.take()
+    }
+
+    // The reply to a byte goes out with the next transfer
+    pub(super) fn exchange(&mut self, sent: u8) -> u8 {
+        let reply = self.reply;
+        self.reply = self.receive(sent);
+        reply
+    }
+
+    fn receive(&mut self, sent: u8) -> u8 {
+        match (self.mode, sent) {
+            (Mode::WriteClock, _) => {
+                if self.index >= WRITE_PREAMBLE {
+                    self.clock[self.index - WRITE_PREAMBLE] = sent;
+                }
+                self.index += 1;
+
+                if self.index == WRITE_PREAMBLE + CLOCK_LEN {
+                    self.set_by_game = Some(WorkboyClock::from_bytes(&self.clock));
+                    self.mode = Mode::Keyboard;
+                }
+                ACK
+            }
+            (_, b'R') => {
+                self.mode = Mode::ReadClock;
+                self.clock = self.now.to_bytes();
+                // the first digit is the low one of the first byte
+                self.index = 1;
+                ACK
+            }
+            (_, b'W') => {
+                self.mode = Mode::WriteClock;
+                self.index = 0;
+                ACK
+            }
+            (_, b'O') | (Mode::Keyboard, _) => {
+                self.mode = Mode::Keyboard;
+                self.keys.pop_front().unwrap_or(NO_KEY)
+            }
+            (Mode::ReadClock, _) => {
+                let Some(&byte) = self.clock.get(self.index / 2) else {
+                    return 0;
+                };
+                let digit = if self.index.is_multiple_of(2) {
+                    byte >> 4
+                } else {
+                    byte & 0xF
+                };
+                self.index += 1;
+                HEX_DIGITS[usize::from(digit)]
+            }
+            (Mode::Idle, _) => 0,
+        }
+    }
+}
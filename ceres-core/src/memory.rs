@@ -1,7 +1,8 @@
-use crate::{ppu::Mode, CgbMode, Gb, Model::Cgb};
-use crate::{AudioCallback, Model};
+use crate::{ppu::Mode, CgbMode, Gb};
+use crate::{AudioCallback, Warning};
 
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HdmaState {
     #[default]
     Sleep,
@@ -124,13 +125,12 @@ impl<A: AudioCallback> Gb<A> {
 
     #[must_use]
     #[inline]
-    const fn read_boot_or_cart(&self, addr: u16) -> u8 {
-        if let Some(bootrom) = self.bootrom {
-            // TODO: as long as the bootrom is correct should be in bounds
-            bootrom[addr as usize]
-        } else {
-            self.cart.read_rom(addr)
-        }
+    fn read_boot_or_cart(&self, addr: u16) -> u8 {
+        // set_bootrom checks the size
+        self.bootrom.as_ref().map_or_else(
+            || self.cart.read_rom(addr),
+            |bootrom| bootrom[addr as usize],
+        )
     }
 
     // **************
@@ -142,7 +142,7 @@ impl<A: AudioCallback> Gb<A> {
         match addr {
             0x0000..=0x00FF => self.read_boot_or_cart(addr),
             0x0200..=0x08FF => {
-                if matches!(self.model, Model::Cgb) {
+                if self.quirks.split_bootrom {
                     self.read_boot_or_cart(addr)
                 } else {
                     self.cart.read_rom(addr)
@@ -163,7 +163,18 @@ impl<A: AudioCallback> Gb<A> {
     pub(crate) fn write_mem(&mut self, addr: u16, val: u8) {
         match addr {
             // FIXME: we assume bootrom doesn't write to rom
-            0x0000..=0x7FFF => self.cart.write_rom(addr, val),
+            0x0000..=0x7FFF => {
+                let offsets = self.cart.bank_offsets();
+
+                if let Some(bank) = self.cart.write_rom(addr, val) {
+                    self.warn(Warning::RomBankOutOfRange { bank });
+                }
+
+                if self.cart.bank_offsets() != offsets {
+                    self.stats.bank_switches += 1;
+                    self.track_bank_switch();
+                }
+            }
             0x8000..=0x9FFF => self.ppu.write_vram(addr, val),
             0xA000..=0xBFFF => self.cart.write_ram(addr, val),
             0xC000..=0xCFFF | 0xE000..=0xEFFF => self.write_wram_lo(addr, val),
@@ -215,13 +226,13 @@ impl<A: AudioCallback> Gb<A> {
             OBP1 => self.ppu.read_obp1(),
             WY => self.ppu.read_wy(),
             WX => self.ppu.read_wx(),
-            KEY1 if matches!(self.cgb_mode, CgbMode::Cgb) => self.key1.read(),
+            KEY1 if matches!(self.cgb_mode, CgbMode::Cgb) => self.clock.read_key1(),
             VBK if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.read_vbk(),
             HDMA5 if matches!(self.cgb_mode, CgbMode::Cgb) => self.read_hdma5(),
             BCPS if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.bcp().spec(),
-            BCPD if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.bcp().data(),
+            BCPD if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.read_bcpd(),
             OCPS if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.ocp().spec(),
-            OCPD if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.ocp().data(),
+            OCPD if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.read_ocpd(),
             OPRI if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.read_opri(),
             SVBK if matches!(self.cgb_mode, CgbMode::Cgb) => self.svbk.read(),
             PCM12 if matches!(self.cgb_mode, CgbMode::Cgb) => self.apu.pcm12(),
@@ -235,8 +246,18 @@ impl<A: AudioCallback> Gb<A> {
     #[allow(clippy::cognitive_complexity)]
     #[inline]
     fn write_high(&mut self, addr: u8, val: u8) {
+        if (NR10..=WAV_END).contains(&addr) {
+            self.log_apu_write(addr, val);
+        }
+
         match addr {
-            P1 => self.joy.write_joy(val),
+            P1 => {
+                if let Some(command) = self.joy.write_joy(val, self.quirks.sgb_commands) {
+                    if let Some(sgb) = &mut self.sgb {
+                        sgb.command(&command);
+                    }
+                }
+            }
             SB => self.serial.write_sb(val),
             SC => self.serial.write_sc(val, &mut self.ints, &self.cgb_mode),
             DIV => self.write_div(),
@@ -262,12 +283,16 @@ impl<A: AudioCallback> Gb<A> {
             NR42 if self.apu.enabled() => self.apu.write_nr42(val),
             NR43 if self.apu.enabled() => self.apu.write_nr43(val),
             NR44 if self.apu.enabled() => self.apu.write_nr44(val),
+            // ignored while the APU is off
+            NR10..=NR44 => (),
             NR50 => self.apu.write_nr50(val),
             NR51 => self.apu.write_nr51(val),
             NR52 => self.apu.write_nr52(val),
             WAV_BEG..=WAV_END => self.apu.write_wave_ram(addr, val),
             LCDC => self.ppu.write_lcdc(val, &mut self.ints),
-            STAT => self.ppu.write_stat(val),
+            STAT => self
+                .ppu
+                .write_stat(val, self.quirks.stat_write_bug, &mut self.ints),
             SCY => self.ppu.write_scy(val),
             SCX => self.ppu.write_scx(val),
             LYC => self.ppu.write_lyc(val),
@@ -277,15 +302,18 @@ impl<A: AudioCallback> Gb<A> {
             OBP1 => self.ppu.write_obp1(val),
             WY => self.ppu.write_wy(val),
             WX => self.ppu.write_wx(val),
-            KEY0 if matches!(self.model, Cgb) => {
+            KEY0 if self.quirks.cgb_bootrom_registers => {
                 if self.bootrom.is_some() && val == 4 {
                     self.cgb_mode = CgbMode::Compat;
                 }
             }
-            KEY1 if matches!(self.cgb_mode, CgbMode::Cgb) => self.key1.write(val),
+            KEY1 if matches!(self.cgb_mode, CgbMode::Cgb) => self.clock.write_key1(val),
             VBK if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.write_vbk(val),
             BANK => {
                 if val & 1 != 0 {
+                    if self.quirks.agb_bootrom && self.bootrom.is_some() {
+                        self.bc = self.bc.wrapping_add(0x100);
+                    }
                     self.bootrom = None;
                 }
             }
@@ -295,10 +323,10 @@ impl<A: AudioCallback> Gb<A> {
             HDMA4 if matches!(self.cgb_mode, CgbMode::Cgb) => self.write_hdma4(val),
             HDMA5 if matches!(self.cgb_mode, CgbMode::Cgb) => self.write_hdma5(val),
             BCPS if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.bcp_mut().set_spec(val),
-            BCPD if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.bcp_mut().set_data(val),
+            BCPD if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.write_bcpd(val),
             OCPS if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.ocp_mut().set_spec(val),
-            OCPD if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.ocp_mut().set_data(val),
-            OPRI if matches!(self.model, Model::Cgb) => {
+            OCPD if matches!(self.cgb_mode, CgbMode::Cgb) => self.ppu.write_ocpd(val),
+            OPRI if self.quirks.cgb_bootrom_registers => {
                 // FIXME: understand behaviour outside of bootrom
                 if self.bootrom.is_some() {
                     self.ppu.write_opri(val);
@@ -307,7 +335,10 @@ impl<A: AudioCallback> Gb<A> {
             SVBK if matches!(self.cgb_mode, CgbMode::Cgb) => self.svbk.write(val),
             HRAM_BEG..=HRAM_END => self.hram[(addr & 0x7F) as usize] = val,
             IE => self.ints.write_ie(val),
-            _ => (),
+            _ => self.warn(Warning::UnsupportedIoWrite {
+                addr: 0xFF00 | u16::from(addr),
+                val,
+            }),
         }
     }
 
@@ -325,6 +356,7 @@ impl<A: AudioCallback> Gb<A> {
         self.dma = val;
         self.dma_addr = u16::from(val) << 8;
         self.dma_on = true;
+        self.stats.dma_transfers += 1;
     }
 
     #[inline]
@@ -397,8 +429,11 @@ impl<A: AudioCallback> Gb<A> {
         self.hdma5 = val & 0x7F;
         self.hdma_len = (u16::from(self.hdma5) + 1) * 0x10;
         self.hdma_state = if val & 0x80 == 0 { General } else { WaitHBlank };
+        self.stats.dma_transfers += 1;
     }
 
+    // With the LCD off the PPU stays in mode 0, so an HBlank transfer copies
+    // one block right away and the rest once the LCD is on again
     #[inline]
     pub(crate) fn run_hdma(&mut self) {
         use HdmaState::{General, HBlankDone, Sleep, WaitHBlank};
@@ -444,15 +479,13 @@ impl<A: AudioCallback> Gb<A> {
         // etc..). If the PPU reads VRAM during an HDMA transfer it
         // should be glitchy anyways
         // FIXME: timings
-        if self.key1.enabled() {
-            self.advance_t_cycles(i32::from(len) * 2 * 2);
-        } else {
-            self.advance_t_cycles(i32::from(len) * 2);
-        }
+        // 2 dots per byte in both speeds
+        self.advance_t_cycles(self.clock.cpu_cycles(i32::from(len) * 2));
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Svbk {
     svbk: u8,
 }
@@ -469,46 +502,21 @@ impl Svbk {
         self.svbk = val & 7;
     }
 
+    // Selecting bank 0 maps bank 1
     #[must_use]
     #[inline]
-    pub const fn bank_offset(&self) -> u16 {
-        // Return value between 0x1000 and 0x7000
-        (if self.svbk == 0 { 1 } else { self.svbk } as u16) * 0x1000
-    }
-}
-
-#[derive(Default)]
-pub struct Key1 {
-    key1: u8,
-}
-
-impl Key1 {
-    #[must_use]
-    #[inline]
-    pub const fn read(&self) -> u8 {
-        self.key1 | 0x7E
-    }
-
-    #[inline]
-    pub fn write(&mut self, val: u8) {
-        self.key1 = self.key1 & 0x80 | val & 1;
-    }
-
-    #[must_use]
-    #[inline]
-    pub const fn enabled(&self) -> bool {
-        self.key1 & 0x80 != 0
+    pub const fn bank(&self) -> u8 {
+        if self.svbk == 0 {
+            1
+        } else {
+            self.svbk
+        }
     }
 
     #[must_use]
     #[inline]
-    pub const fn requested(&self) -> bool {
-        self.key1 & 1 != 0
-    }
-
-    #[inline]
-    pub fn change_speed(&mut self) {
-        debug_assert!(self.requested());
-        self.key1 = !self.key1;
+    pub const fn bank_offset(&self) -> u16 {
+        // Return value between 0x1000 and 0x7000
+        self.bank() as u16 * 0x1000
     }
 }
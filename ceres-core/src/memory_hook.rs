@@ -0,0 +1,56 @@
+use {
+    crate::{AudioCallback, Gb},
+    alloc::boxed::Box,
+};
+
+// Called on every CPU memory access, for tracers, cheat searchers and
+// coverage tools. DMA transfers and peek/poke don't go through it.
+pub trait MemoryHook {
+    fn on_read(&mut self, _addr: u16, _val: u8) {}
+    fn on_write(&mut self, _addr: u16, _val: u8) {}
+    // Before the instruction at pc is fetched
+    fn on_execute(&mut self, _pc: u16) {}
+}
+
+// A hook belongs to the frontend, so clones (and save states) start without
+// one
+#[derive(Default)]
+pub struct HookSlot(Option<Box<dyn MemoryHook + Send>>);
+
+impl Clone for HookSlot {
+    fn clone(&self) -> Self {
+        Self(None)
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Returns the previous hook, None removes it
+    #[inline]
+    pub fn set_memory_hook(
+        &mut self,
+        hook: Option<Box<dyn MemoryHook + Send>>,
+    ) -> Option<Box<dyn MemoryHook + Send>> {
+        core::mem::replace(&mut self.memory_hook.0, hook)
+    }
+
+    #[inline]
+    pub(crate) fn hook_read(&mut self, addr: u16, val: u8) {
+        if let Some(hook) = &mut self.memory_hook.0 {
+            hook.on_read(addr, val);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn hook_write(&mut self, addr: u16, val: u8) {
+        if let Some(hook) = &mut self.memory_hook.0 {
+            hook.on_write(addr, val);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn hook_execute(&mut self) {
+        if let Some(hook) = &mut self.memory_hook.0 {
+            hook.on_execute(self.pc);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{AudioCallback, CgbMode, Gb};
+
+const WRAM_BANK_SIZE: usize = 0x1000;
+const VRAM_BANK_SIZE: usize = 0x2000;
+const SRAM_BANK_SIZE: usize = 0x2000;
+
+// Memory as debuggers see it, any bank regardless of which is mapped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryRegion {
+    // bank 0 is always at 0xC000, 1 to 7 switch in at 0xD000
+    Wram(u8),
+    Hram,
+    // cartridge RAM
+    Sram(u8),
+    Vram(u8),
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Empty if the bank doesn't exist on this model or cartridge
+    #[must_use]
+    pub fn region_bytes(&self, region: MemoryRegion) -> &[u8] {
+        let (mem, bank_size, bank): (&[u8], _, _) = match region {
+            MemoryRegion::Wram(bank) => (self.wram_banks(), WRAM_BANK_SIZE, bank),
+            MemoryRegion::Hram => (&self.hram, self.hram.len(), 0),
+            MemoryRegion::Sram(bank) => (self.cart.ram(), SRAM_BANK_SIZE, bank),
+            MemoryRegion::Vram(bank) => (self.vram_banks(), VRAM_BANK_SIZE, bank),
+        };
+
+        // MBC2 has less than a bank
+        mem.chunks(bank_size)
+            .nth(usize::from(bank))
+            .unwrap_or_default()
+    }
+
+    // Bank mapped at 0xD000 and its echo at 0xF000, only SVBK switches it
+    // and SVBK is only writable in CGB mode
+    #[must_use]
+    #[inline]
+    pub const fn wram_bank(&self) -> u8 {
+        self.svbk.bank()
+    }
+
+    // Bank mapped at 0x8000
+    #[must_use]
+    #[inline]
+    pub const fn vram_bank(&self) -> u8 {
+        self.ppu.vram_bank()
+    }
+
+    // The region and offset the bus reaches at an address with the banks
+    // mapped now, echo RAM included. None for the ROM, OAM, IO registers and
+    // cartridge RAM the cartridge doesn't have.
+    #[must_use]
+    pub fn region_at(&self, addr: u16) -> Option<(MemoryRegion, usize)> {
+        let (region, offset) = match addr {
+            0x8000..=0x9FFF => (MemoryRegion::Vram(self.vram_bank()), addr & 0x1FFF),
+            0xA000..=0xBFFF => (MemoryRegion::Sram(self.cart.ram_bank()), addr & 0x1FFF),
+            0xC000..=0xCFFF | 0xE000..=0xEFFF => (MemoryRegion::Wram(0), addr & 0xFFF),
+            0xD000..=0xDFFF | 0xF000..=0xFDFF => {
+                (MemoryRegion::Wram(self.wram_bank()), addr & 0xFFF)
+            }
+            0xFF80..=0xFFFE => (MemoryRegion::Hram, addr - 0xFF80),
+            _ => return None,
+        };
+
+        // RAM smaller than the window repeats, like MBC2's
+        let len = self.region_bytes(region).len();
+        (len > 0).then(|| (region, usize::from(offset) % len))
+    }
+
+    #[must_use]
+    pub fn read_mem_byte(&self, region: MemoryRegion, offset: usize) -> Option<u8> {
+        self.region_bytes(region).get(offset).copied()
+    }
+
+    // Writes straight to memory, without the side effects or access
+    // restrictions of the bus. Returns false if the offset is out of the
+    // region.
+    pub fn write_mem_byte(&mut self, region: MemoryRegion, offset: usize, val: u8) -> bool {
+        let len = self.region_bytes(region).len();
+        if offset >= len {
+            return false;
+        }
+
+        let (mem, base): (&mut [u8], _) = match region {
+            MemoryRegion::Wram(bank) => (&mut self.wram[..], usize::from(bank) * WRAM_BANK_SIZE),
+            MemoryRegion::Hram => (&mut self.hram, 0),
+            MemoryRegion::Sram(bank) => (self.cart.ram_mut(), usize::from(bank) * SRAM_BANK_SIZE),
+            MemoryRegion::Vram(bank) => (self.ppu.vram_mut(), usize::from(bank) * VRAM_BANK_SIZE),
+        };
+        mem[base + offset] = val;
+
+        true
+    }
+
+    fn wram_banks(&self) -> &[u8] {
+        match self.cgb_mode {
+            CgbMode::Cgb => &self.wram[..],
+            CgbMode::Dmg | CgbMode::Compat => &self.wram[..2 * WRAM_BANK_SIZE],
+        }
+    }
+
+    fn vram_banks(&self) -> &[u8] {
+        match self.cgb_mode {
+            CgbMode::Cgb => self.ppu.vram(),
+            CgbMode::Dmg | CgbMode::Compat => &self.ppu.vram()[..VRAM_BANK_SIZE],
+        }
+    }
+}
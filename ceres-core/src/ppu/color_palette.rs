@@ -2,8 +2,11 @@
 const PAL_RAM_SIZE: u8 = 0x20;
 const PAL_RAM_SIZE_COLORS: u8 = PAL_RAM_SIZE * 3;
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ColorPalette {
     // Rgb color ram
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
     col: [u8; PAL_RAM_SIZE_COLORS as usize],
     spec: u8,
 }
@@ -43,9 +46,20 @@ impl ColorPalette {
 
     #[must_use]
     pub(crate) const fn data(&self) -> u8 {
-        let i = (self.index() as usize / 2) * 3;
+        self.byte(self.index())
+    }
+
+    // All of palette RAM as the game wrote it, two bytes per color
+    #[must_use]
+    pub(crate) fn bytes(&self) -> [u8; PAL_RAM_SIZE as usize * 2] {
+        core::array::from_fn(|i| self.byte(i as u8))
+    }
+
+    #[must_use]
+    const fn byte(&self, index: u8) -> u8 {
+        let i = (index as usize / 2) * 3;
 
-        if self.index() & 1 == 0 {
+        if index & 1 == 0 {
             // red and green
             let r = self.col[i];
             let g = self.col[i + 1] << 5;
@@ -58,21 +72,25 @@ impl ColorPalette {
         }
     }
 
-    pub(crate) fn set_data(&mut self, val: u8) {
+    // Writes while palette RAM is locked are dropped, but still increment
+    // the index
+    pub(crate) fn set_data(&mut self, val: u8, locked: bool) {
         let i = (self.index() as usize / 2) * 3;
 
-        if self.index() & 1 == 0 {
-            // red
-            self.col[i] = val & 0x1F;
-            // green
-            let tmp = (self.col[i + 1] & 3) << 3;
-            self.col[i + 1] = tmp | (val & 0xE0) >> 5;
-        } else {
-            // green
-            let tmp = self.col[i + 1] & 7;
-            self.col[i + 1] = tmp | (val & 3) << 3;
-            // blue
-            self.col[i + 2] = (val & 0x7C) >> 2;
+        if !locked {
+            if self.index() & 1 == 0 {
+                // red
+                self.col[i] = val & 0x1F;
+                // green, keeping the high bits from the other byte
+                let tmp = self.col[i + 1] & 0x18;
+                self.col[i + 1] = tmp | (val & 0xE0) >> 5;
+            } else {
+                // green
+                let tmp = self.col[i + 1] & 7;
+                self.col[i + 1] = tmp | (val & 3) << 3;
+                // blue
+                self.col[i + 2] = (val & 0x7C) >> 2;
+            }
         }
 
         if self.increment() {
@@ -0,0 +1,44 @@
+// Colors of the four DMG shades, lightest first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DmgPalette {
+    #[default]
+    Grayscale,
+    // The green tint of the original screen
+    Green,
+    // The MGB's gray-green
+    Pocket,
+    // Evenly spaced so every shade is easy to tell apart
+    HighContrast,
+}
+
+impl DmgPalette {
+    #[must_use]
+    pub const fn colors(self) -> [(u8, u8, u8); 4] {
+        match self {
+            Self::Grayscale => [
+                (0xFF, 0xFF, 0xFF),
+                (0xCC, 0xCC, 0xCC),
+                (0x77, 0x77, 0x77),
+                (0x00, 0x00, 0x00),
+            ],
+            Self::Green => [
+                (0x9B, 0xBC, 0x0F),
+                (0x8B, 0xAC, 0x0F),
+                (0x30, 0x62, 0x30),
+                (0x0F, 0x38, 0x0F),
+            ],
+            Self::Pocket => [
+                (0xC4, 0xCF, 0xA1),
+                (0x8B, 0x95, 0x6D),
+                (0x4D, 0x53, 0x3C),
+                (0x1F, 0x1F, 0x1F),
+            ],
+            Self::HighContrast => [
+                (0xFF, 0xFF, 0xFF),
+                (0xAA, 0xAA, 0xAA),
+                (0x55, 0x55, 0x55),
+                (0x00, 0x00, 0x00),
+            ],
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use {
     super::{
-        Ppu, LCDC_BG_AREA, LCDC_BG_B, LCDC_BG_SIGNED, LCDC_OBJL_B, LCDC_OBJ_B, LCDC_WIN_AREA,
-        LCDC_WIN_B, OAM_SIZE, VRAM_SIZE_GB,
+        Layer, Ppu, ScanlineObjs, ScanlineScroll, LCDC_BG_AREA, LCDC_BG_B, LCDC_BG_SIGNED,
+        LCDC_OBJL_B, LCDC_OBJ_B, LCDC_WIN_AREA, LCDC_WIN_B, MAX_OBJS_PER_LINE, OAM_SIZE,
+        VRAM_SIZE_GB,
     },
     crate::{CgbMode, PX_WIDTH},
 };
@@ -34,6 +35,8 @@ const fn shade_index(palette: u8, color: u8) -> u8 {
 
 #[derive(Default)]
 struct Obj {
+    // OAM entry
+    index: u8,
     x: u8,
     y: u8,
     tile_index: u8,
@@ -41,16 +44,8 @@ struct Obj {
 }
 
 impl Ppu {
-    const fn mono_rgb(index: u8) -> (u8, u8, u8) {
-        // DMG palette colors RGB
-        const GRAYSCALE_PALETTE: [(u8, u8, u8); 4] = [
-            (0xFF, 0xFF, 0xFF),
-            (0xCC, 0xCC, 0xCC),
-            (0x77, 0x77, 0x77),
-            (0x00, 0x00, 0x00),
-        ];
-
-        GRAYSCALE_PALETTE[index as usize]
+    const fn mono_rgb(&self, index: u8) -> (u8, u8, u8) {
+        self.dmg_palette[index as usize]
     }
 
     #[must_use]
@@ -140,6 +135,11 @@ impl Ppu {
         let mut bg_priority = [PxPrio::Normal; PX_WIDTH as usize];
         let base_idx = u32::from(PX_WIDTH) * u32::from(self.ly);
 
+        self.scroll[usize::from(self.ly)] = ScanlineScroll {
+            scx: self.scx,
+            scy: self.scy,
+        };
+
         self.draw_bg(&mut bg_priority, base_idx, cgb_mode);
         self.draw_win(&mut bg_priority, base_idx, cgb_mode);
         self.draw_obj(&bg_priority, base_idx, cgb_mode);
@@ -156,6 +156,7 @@ impl Ppu {
             return;
         }
 
+        let bg_shown = self.layer_enabled(Layer::Bg);
         let y = self.ly.wrapping_add(self.scy);
         let row = u16::from(y / 8) * 32;
         let line = u16::from((y & 7) * 2);
@@ -192,12 +193,20 @@ impl Ppu {
                 u8::from(hi & bit != 0) << 1 | u8::from(lo & bit != 0)
             };
 
+            // a hidden background is drawn with color 0, priorities still apply
+            let shown = if bg_shown { color } else { 0 };
+
             let rgb = match cgb_mode {
-                CgbMode::Dmg => Self::mono_rgb(shade_index(self.bgp, color)),
-                CgbMode::Compat => self.bcp.rgb(attr & BG_PAL_B, shade_index(self.bgp, color)),
-                CgbMode::Cgb => self.bcp.rgb(attr & BG_PAL_B, color),
+                CgbMode::Dmg => {
+                    let shade = shade_index(self.bgp, shown);
+                    self.shades[(base_idx + u32::from(i)) as usize] = shade;
+                    self.mono_rgb(shade)
+                }
+                CgbMode::Compat => self.bcp.rgb(attr & BG_PAL_B, shade_index(self.bgp, shown)),
+                CgbMode::Cgb => self.bcp.rgb(attr & BG_PAL_B, shown),
             };
 
+            let rgb = self.final_color(rgb);
             self.rgb_buf.set_px(base_idx + u32::from(i), rgb);
 
             bg_priority[i as usize] = if color == 0 {
@@ -225,6 +234,7 @@ impl Ppu {
             return;
         }
 
+        let win_shown = self.layer_enabled(Layer::Window);
         let wx = self.wx.saturating_sub(7);
         let y = (self.ly - self.wy).wrapping_sub(self.win_skipped);
         let row = u16::from(y / 8) * 32;
@@ -266,7 +276,7 @@ impl Ppu {
             };
 
             let rgb = match cgb_mode {
-                CgbMode::Dmg => Self::mono_rgb(shade_index(self.bgp, color)),
+                CgbMode::Dmg => self.mono_rgb(shade_index(self.bgp, color)),
                 CgbMode::Compat => self.bcp.rgb(attr & BG_PAL_B, shade_index(self.bgp, color)),
                 CgbMode::Cgb => self.bcp.rgb(attr & BG_PAL_B, color),
             };
@@ -279,21 +289,29 @@ impl Ppu {
                 PxPrio::Normal
             };
 
-            self.rgb_buf.set_px(base_idx + u32::from(i), rgb);
+            // a hidden window leaves the background visible
+            if win_shown {
+                if matches!(cgb_mode, CgbMode::Dmg) {
+                    self.shades[(base_idx + u32::from(i)) as usize] = shade_index(self.bgp, color);
+                }
+                let rgb = self.final_color(rgb);
+                self.rgb_buf.set_px(base_idx + u32::from(i), rgb);
+            }
         }
     }
 
     #[must_use]
     #[inline]
-    fn objs_in_ly(&self, height: u8, cgb_mode: &CgbMode) -> ([Obj; 10], u8) {
+    fn objs_in_ly(&self, height: u8, cgb_mode: &CgbMode) -> ([Obj; MAX_OBJS_PER_LINE], u8) {
         let mut len: u8 = 0;
-        let mut obj: [Obj; 10] = Default::default();
+        let mut obj: [Obj; MAX_OBJS_PER_LINE] = Default::default();
 
         for i in (0..OAM_SIZE as usize).step_by(4) {
             let y = self.oam[i].wrapping_sub(16);
 
             if self.ly.wrapping_sub(y) < height {
                 let attr = Obj {
+                    index: (i / 4) as u8,
                     y,
                     x: self.oam[i + 1].wrapping_sub(8),
                     tile_index: self.oam[i + 2],
@@ -303,7 +321,7 @@ impl Ppu {
                 obj[len as usize] = attr;
                 len += 1;
 
-                if len == 10 {
+                if usize::from(len) == MAX_OBJS_PER_LINE {
                     break;
                 }
             }
@@ -325,15 +343,29 @@ impl Ppu {
         base_idx: u32,
         cgb_mode: &CgbMode,
     ) {
-        if self.lcdc & LCDC_OBJ_B == 0 {
-            return;
-        }
-
         let large = self.lcdc & LCDC_OBJL_B != 0;
         let height = 8 * (u8::from(large) + 1);
 
         let (objs, len) = self.objs_in_ly(height, cgb_mode);
 
+        // drawn lowest priority first
+        let mut selected = ScanlineObjs {
+            len,
+            ..ScanlineObjs::default()
+        };
+        for (entry, obj) in selected
+            .entries
+            .iter_mut()
+            .zip(objs[..usize::from(len)].iter().rev())
+        {
+            *entry = obj.index;
+        }
+        self.objs[usize::from(self.ly)] = selected;
+
+        if self.lcdc & LCDC_OBJ_B == 0 || !self.layer_enabled(Layer::Obj) {
+            return;
+        }
+
         for obj in objs.iter().take(len as usize) {
             let tile_addr = {
                 let tile_number = if large {
@@ -386,7 +418,9 @@ impl Ppu {
                             self.obp1
                         };
 
-                        Self::mono_rgb(shade_index(palette, color))
+                        let shade = shade_index(palette, color);
+                        self.shades[(base_idx + u32::from(x)) as usize] = shade;
+                        self.mono_rgb(shade)
                     }
                     CgbMode::Compat => {
                         let palette = if obj.attr & SPR_PAL == 0 {
@@ -403,6 +437,7 @@ impl Ppu {
                     }
                 };
 
+                let rgb = self.final_color(rgb);
                 self.rgb_buf.set_px(base_idx + u32::from(x), rgb);
             }
         }
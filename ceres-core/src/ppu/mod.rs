@@ -1,14 +1,49 @@
-use crate::interrupts::Interrupts;
+use crate::{interrupts::Interrupts, Stats};
+use alloc::boxed::Box;
 
-use {self::color_palette::ColorPalette, crate::CgbMode, rgb_buf::RgbaBuf};
+pub use dmg_palette::DmgPalette;
+pub use output_filter::{OutputFilter, LUT_SIZE};
+pub use video_log::VideoWrite;
+use {self::color_palette::ColorPalette, crate::CgbMode, rgb_buf::RgbaBuf, video_log::VideoLog};
 
 mod color_palette;
+mod dmg_palette;
 mod draw;
+mod output_filter;
 mod rgb_buf;
+mod video_log;
 
 pub const PX_WIDTH: u8 = 160;
 pub const PX_HEIGHT: u8 = 144;
 
+// Scroll registers a scanline was drawn with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanlineScroll {
+    pub scx: u8,
+    pub scy: u8,
+}
+
+// Most objects the PPU draws on one scanline
+pub const MAX_OBJS_PER_LINE: usize = 10;
+
+// OAM entries (0 to 39) a scanline selected, highest priority first. The
+// DMG, and the CGB with OPRI set, order them by X, the CGB by OAM position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanlineObjs {
+    len: u8,
+    entries: [u8; MAX_OBJS_PER_LINE],
+}
+
+impl ScanlineObjs {
+    #[must_use]
+    #[inline]
+    pub fn entries(&self) -> &[u8] {
+        &self.entries[..usize::from(self.len)]
+    }
+}
+
 // Mode timings
 const OAM_SCAN_CYCLES: i32 = 80; // Constant
 const DRAWING_CYCLES: i32 = 172; // Variable, minimum ammount
@@ -39,6 +74,7 @@ const VRAM_SIZE_GB: u16 = 0x2000;
 const VRAM_SIZE_CGB: u16 = VRAM_SIZE_GB * 2;
 
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mode {
     #[default]
     HBlank = 0,
@@ -47,6 +83,25 @@ pub enum Mode {
     Drawing = 3,
 }
 
+// Layers that can be hidden from the output to tell them apart, emulation
+// is unaffected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Bg,
+    Window,
+    Obj,
+}
+
+impl Layer {
+    const fn mask(self) -> u8 {
+        match self {
+            Self::Bg => 0x1,
+            Self::Window => 0x2,
+            Self::Obj => 0x4,
+        }
+    }
+}
+
 impl Mode {
     pub(crate) fn cycles(self, scroll_x: u8) -> i32 {
         let scroll_adjust = i32::from(scroll_x & 7) * 4;
@@ -59,6 +114,8 @@ impl Mode {
     }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ppu {
     lcdc: u8,
     stat: u8,
@@ -76,20 +133,45 @@ pub struct Ppu {
     bcp: ColorPalette,
     ocp: ColorPalette,
 
-    vram: [u8; VRAM_SIZE_CGB as usize],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    vram: Box<[u8; VRAM_SIZE_CGB as usize]>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
     oam: [u8; OAM_SIZE as usize],
     rgb_buf: RgbaBuf,
     rgba_buf_present: RgbaBuf,
+    // DMG shade of every pixel being drawn, what the Super Game Boy reads
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    shades: Box<[u8; PX_WIDTH as usize * PX_HEIGHT as usize]>,
+    // changes when the presented frame contents change
+    frame_id: u32,
     cycles: i32,
     win_in_frame: bool,
     win_in_ly: bool,
     win_skipped: u8,
+    hidden_layers: u8,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    output_filter: OutputFilter,
+    // colors of the DMG shades, lightest first
+    dmg_palette: [(u8, u8, u8); 4],
+    #[cfg_attr(feature = "serde", serde(skip))]
+    video_log: VideoLog,
+    // the GBA screen's response, applied before the output filter
+    agb_lcd: bool,
+    // per line, presented with the frame
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    scroll: [ScanlineScroll; PX_HEIGHT as usize],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    scroll_present: [ScanlineScroll; PX_HEIGHT as usize],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    objs: [ScanlineObjs; PX_HEIGHT as usize],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    objs_present: [ScanlineObjs; PX_HEIGHT as usize],
 }
 
 impl Default for Ppu {
     fn default() -> Self {
         Self {
-            vram: [0; VRAM_SIZE_CGB as usize],
+            vram: Box::new([0; VRAM_SIZE_CGB as usize]),
             oam: [0; OAM_SIZE as usize],
             cycles: Mode::default().cycles(0),
             // Default
@@ -109,10 +191,21 @@ impl Default for Ppu {
             bcp: ColorPalette::default(),
             ocp: ColorPalette::default(),
             rgb_buf: RgbaBuf::default(),
+            shades: Box::new([0; PX_WIDTH as usize * PX_HEIGHT as usize]),
             rgba_buf_present: RgbaBuf::default(),
+            frame_id: Default::default(),
             win_in_frame: Default::default(),
             win_in_ly: Default::default(),
             win_skipped: Default::default(),
+            hidden_layers: Default::default(),
+            output_filter: OutputFilter::default(),
+            dmg_palette: DmgPalette::default().colors(),
+            video_log: VideoLog::default(),
+            agb_lcd: false,
+            scroll: [ScanlineScroll::default(); PX_HEIGHT as usize],
+            scroll_present: [ScanlineScroll::default(); PX_HEIGHT as usize],
+            objs: [ScanlineObjs::default(); PX_HEIGHT as usize],
+            objs_present: [ScanlineObjs::default(); PX_HEIGHT as usize],
         }
     }
 }
@@ -143,6 +236,39 @@ impl Ppu {
         &self.bcp
     }
 
+    // Like VRAM, palette RAM can't be accessed while drawing
+    #[must_use]
+    #[inline]
+    pub(crate) const fn read_bcpd(&self) -> u8 {
+        if matches!(self.mode(), Mode::Drawing) {
+            0xFF
+        } else {
+            self.bcp.data()
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn read_ocpd(&self) -> u8 {
+        if matches!(self.mode(), Mode::Drawing) {
+            0xFF
+        } else {
+            self.ocp.data()
+        }
+    }
+
+    #[inline]
+    pub(crate) fn write_bcpd(&mut self, val: u8) {
+        let locked = matches!(self.mode(), Mode::Drawing);
+        self.bcp.set_data(val, locked);
+    }
+
+    #[inline]
+    pub(crate) fn write_ocpd(&mut self, val: u8) {
+        let locked = matches!(self.mode(), Mode::Drawing);
+        self.ocp.set_data(val, locked);
+    }
+
     #[must_use]
     #[inline]
     pub(crate) const fn read_stat(&self) -> u8 {
@@ -171,6 +297,12 @@ impl Ppu {
         self.vbk = val & 1 != 0;
     }
 
+    #[must_use]
+    #[inline]
+    pub(crate) const fn vram_bank(&self) -> u8 {
+        self.vbk as u8
+    }
+
     #[must_use]
     #[inline]
     pub(crate) const fn read_vbk(&self) -> u8 {
@@ -285,6 +417,9 @@ impl Ppu {
             // );
 
             self.ly = 0;
+            // STAT reads mode 0 while off, which also lets a waiting HBlank
+            // HDMA copy one more block instead of waiting for the LCD
+            self.set_mode_stat(Mode::HBlank);
         }
 
         // turn on
@@ -301,15 +436,45 @@ impl Ppu {
     }
 
     #[inline]
-    pub(crate) fn write_stat(&mut self, val: u8) {
+    pub(crate) fn write_stat(&mut self, val: u8, write_bug: bool, ints: &mut Interrupts) {
         let ly_equals_lyc = self.stat & STAT_LYC_B;
         let mode: u8 = self.mode() as u8;
 
+        if write_bug
+            && self.lcdc & LCDC_ON_B != 0
+            && (ly_equals_lyc != 0 || matches!(self.mode(), Mode::HBlank | Mode::VBlank))
+        {
+            ints.req_lcd();
+        }
+
         self.stat = val;
         self.stat &= !(STAT_LYC_B | STAT_MODE_B);
         self.stat |= ly_equals_lyc | mode;
     }
 
+    // Both banks, ignoring the mode
+    #[must_use]
+    #[inline]
+    pub(crate) fn vram(&self) -> &[u8] {
+        &self.vram[..]
+    }
+
+    #[inline]
+    pub(crate) fn vram_mut(&mut self) -> &mut [u8] {
+        &mut self.vram[..]
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn oam(&self) -> &[u8] {
+        &self.oam
+    }
+
+    #[inline]
+    pub(crate) const fn oam_mut(&mut self) -> &mut [u8] {
+        &mut self.oam
+    }
+
     #[must_use]
     #[inline]
     pub(crate) const fn read_vram(&self, addr: u16) -> u8 {
@@ -322,11 +487,19 @@ impl Ppu {
         }
     }
 
+    // The selected bank whatever the mode
+    #[must_use]
+    pub(crate) const fn peek_vram(&self, addr: u16) -> u8 {
+        let bank = self.vbk as u16 * VRAM_SIZE_GB;
+        self.vram[((addr & 0x1FFF) + bank) as usize]
+    }
+
     pub(crate) fn write_vram(&mut self, addr: u16, val: u8) {
         if !matches!(self.mode(), Mode::Drawing) {
             let bank = u16::from(self.vbk) * VRAM_SIZE_GB;
             let i = (addr & 0x1FFF) + bank;
-            self.vram[i as usize] = val;
+            let old = core::mem::replace(&mut self.vram[i as usize], val);
+            self.log_vram_write(i, old, val);
         }
     }
 
@@ -339,25 +512,36 @@ impl Ppu {
         }
     }
 
+    #[must_use]
+    pub(crate) const fn peek_oam(&self, addr: u16) -> u8 {
+        self.oam[(addr & 0xFF) as usize]
+    }
+
     #[inline]
     pub(crate) fn write_oam(&mut self, addr: u16, val: u8, dma_active: bool) {
         match self.mode() {
-            Mode::HBlank | Mode::VBlank if !dma_active => {
-                self.oam[(addr & 0xFF) as usize] = val;
-            }
+            Mode::HBlank | Mode::VBlank if !dma_active => self.write_oam_by_dma(addr, val),
             _ => (),
         };
     }
 
     #[inline]
     pub(crate) fn write_oam_by_dma(&mut self, addr: u16, val: u8) {
-        self.oam[(addr & 0xFF) as usize] = val;
+        let i = (addr & 0xFF) as u8;
+        let old = core::mem::replace(&mut self.oam[usize::from(i)], val);
+        self.log_oam_write(i, old, val);
     }
 }
 
 // General
 impl Ppu {
-    pub(crate) fn run(&mut self, cycles: i32, ints: &mut Interrupts, cgb_mode: &CgbMode) {
+    pub(crate) fn run(
+        &mut self,
+        cycles: i32,
+        ints: &mut Interrupts,
+        cgb_mode: &CgbMode,
+        stats: &mut Stats,
+    ) {
         if self.lcdc & LCDC_ON_B == 0 {
             return;
         }
@@ -390,7 +574,8 @@ impl Ppu {
                     self.ly += 1;
                     if self.ly > 153 {
                         self.ly = 0;
-                        self.rgba_buf_present = self.rgb_buf.clone();
+                        self.present();
+                        stats.frames += 1;
                         self.enter_mode(Mode::OamScan, ints);
                     } else {
                         self.cycles += self.mode().cycles(self.scx);
@@ -464,9 +649,97 @@ impl Ppu {
         }
     }
 
+    fn present(&mut self) {
+        self.scroll_present = self.scroll;
+        self.objs_present = self.objs;
+
+        if self.rgba_buf_present.pixel_data() != self.rgb_buf.pixel_data() {
+            self.rgba_buf_present = self.rgb_buf.clone();
+            self.frame_id = self.frame_id.wrapping_add(1);
+        }
+    }
+
     #[must_use]
     #[inline]
-    pub(crate) const fn pixel_data_rgb(&self) -> &[u8] {
+    pub(crate) fn pixel_data_rgb(&self) -> &[u8] {
         self.rgba_buf_present.pixel_data()
     }
+
+    // Complete right as a frame ends
+    #[must_use]
+    #[inline]
+    pub(crate) fn shades(&self) -> &[u8] {
+        &self.shades[..]
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn scanline_scroll(&self) -> &[ScanlineScroll] {
+        &self.scroll_present
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn scanline_objs(&self) -> &[ScanlineObjs] {
+        &self.objs_present
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn layer_enabled(&self, layer: Layer) -> bool {
+        self.hidden_layers & layer.mask() == 0
+    }
+
+    #[inline]
+    pub(crate) const fn set_layer_enabled(&mut self, layer: Layer, enabled: bool) {
+        if enabled {
+            self.hidden_layers &= !layer.mask();
+        } else {
+            self.hidden_layers |= layer.mask();
+        }
+    }
+
+    #[inline]
+    pub(crate) fn set_output_filter(&mut self, filter: OutputFilter) {
+        self.output_filter = filter;
+    }
+
+    #[inline]
+    pub(crate) const fn set_dmg_palette(&mut self, colors: &[(u8, u8, u8); 4]) {
+        self.dmg_palette = *colors;
+    }
+
+    #[inline]
+    pub(crate) const fn set_agb_lcd(&mut self, enabled: bool) {
+        self.agb_lcd = enabled;
+    }
+
+    // Final color of a pixel leaving the palette
+    #[must_use]
+    #[inline]
+    fn final_color(&self, rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+        let rgb = if self.agb_lcd {
+            output_filter::agb_colors(rgb)
+        } else {
+            rgb
+        };
+
+        self.output_filter.apply(rgb)
+    }
+
+    #[inline]
+    pub(crate) const fn take_host_state(&mut self, other: &mut Self) {
+        self.hidden_layers = other.hidden_layers;
+        core::mem::swap(&mut self.output_filter, &mut other.output_filter);
+        self.dmg_palette = other.dmg_palette;
+        core::mem::swap(&mut self.video_log, &mut other.video_log);
+        // the restored frame is new to the frontend
+        self.frame_id = other.frame_id.wrapping_add(1);
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn frame_id(&self) -> u32 {
+        self.frame_id
+    }
 }
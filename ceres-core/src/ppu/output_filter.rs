@@ -0,0 +1,76 @@
+use alloc::boxed::Box;
+
+// Entries per channel of a LUT, 5 bits like CGB colors
+const LUT_DIM: usize = 32;
+pub const LUT_SIZE: usize = LUT_DIM * LUT_DIM * LUT_DIM * 3;
+
+// Applied to every pixel after the palette lookup, on top of whatever
+// correction the palette applies
+#[derive(Clone, Default)]
+pub enum OutputFilter {
+    #[default]
+    None,
+    Greyscale,
+    Sepia,
+    // Brighter and more saturated like a GBA screen, for CGB games made
+    // with its backlight in mind
+    Agb,
+    // LUT_SIZE bytes, the RGB output for every input color with the
+    // channels cut to 5 bits, indexed by r << 10 | g << 5 | b
+    Lut(Box<[u8]>),
+}
+
+impl OutputFilter {
+    // None if data isn't exactly LUT_SIZE bytes long
+    #[must_use]
+    pub fn lut(data: Box<[u8]>) -> Option<Self> {
+        (data.len() == LUT_SIZE).then_some(Self::Lut(data))
+    }
+
+    #[must_use]
+    #[inline]
+    pub(super) fn apply(&self, rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+        let (r, g, b) = (u32::from(rgb.0), u32::from(rgb.1), u32::from(rgb.2));
+
+        match self {
+            Self::None => rgb,
+            Self::Greyscale => {
+                // BT.601 luma
+                let y = ((77 * r + 150 * g + 29 * b) >> 8) as u8;
+                (y, y, y)
+            }
+            Self::Sepia => {
+                let channel =
+                    |rw: u32, gw: u32, bw: u32| ((rw * r + gw * g + bw * b) / 1000).min(0xFF) as u8;
+                (
+                    channel(393, 769, 189),
+                    channel(349, 686, 168),
+                    channel(272, 534, 131),
+                )
+            }
+            Self::Agb => agb_colors(rgb),
+            Self::Lut(lut) => {
+                let i = (((r >> 3) << 10 | (g >> 3) << 5 | b >> 3) * 3) as usize;
+                (lut[i], lut[i + 1], lut[i + 2])
+            }
+        }
+    }
+}
+
+// Lifts midtones, 0x80 becomes 0xA0, then pushes channels a quarter further
+// from the luma
+#[must_use]
+#[inline]
+pub(super) fn agb_colors(rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+    let lift = |c: u8| {
+        let c = i32::from(c);
+        c + (0xFF - c) * c / 0x1FF
+    };
+    let (r, g, b) = (lift(rgb.0), lift(rgb.1), lift(rgb.2));
+
+    // BT.601 luma
+    let y = (77 * r + 150 * g + 29 * b) >> 8;
+    let saturate = |c: i32| (y + (c - y) * 5 / 4).clamp(0, 0xFF) as u8;
+
+    (saturate(r), saturate(g), saturate(b))
+}
@@ -1,18 +1,23 @@
-use crate::{PX_HEIGHT, PX_WIDTH};
+use {
+    crate::{PX_HEIGHT, PX_WIDTH},
+    alloc::boxed::Box,
+};
 
 const BPP: u32 = 3; // bytes per pixel
 const PX_TOTAL: u16 = PX_WIDTH as u16 * PX_HEIGHT as u16;
 const RGB_BUF_SIZE: u32 = PX_TOTAL as u32 * BPP;
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct RgbaBuf {
-    data: [u8; RGB_BUF_SIZE as usize],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    data: Box<[u8; RGB_BUF_SIZE as usize]>,
 }
 
 impl Default for RgbaBuf {
     fn default() -> Self {
         Self {
-            data: [0xff; RGB_BUF_SIZE as usize],
+            data: Box::new([0xff; RGB_BUF_SIZE as usize]),
         }
     }
 }
@@ -28,7 +33,7 @@ impl RgbaBuf {
 
     #[must_use]
     #[inline]
-    pub(crate) const fn pixel_data(&self) -> &[u8] {
-        &self.data
+    pub(crate) fn pixel_data(&self) -> &[u8] {
+        &self.data[..]
     }
 }
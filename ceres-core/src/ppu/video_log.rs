@@ -0,0 +1,63 @@
+use {
+    super::Ppu,
+    crate::{AudioCallback, Gb},
+    alloc::vec::Vec,
+};
+
+// A byte of VRAM or OAM that changed, from the CPU, OAM DMA or HDMA
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoWrite {
+    // addr is in 0x8000..=0x9FFF, bank is 1 only for CGB bank 1
+    Vram { bank: u8, addr: u16, val: u8 },
+    // addr is in 0xFE00..=0xFE9F
+    Oam { addr: u16, val: u8 },
+}
+
+// Only allocated while logging
+#[derive(Clone, Default)]
+pub struct VideoLog {
+    enabled: bool,
+    writes: Vec<VideoWrite>,
+}
+
+impl Ppu {
+    #[inline]
+    pub(super) fn log_vram_write(&mut self, i: u16, old: u8, val: u8) {
+        if self.video_log.enabled && old != val {
+            self.video_log.writes.push(VideoWrite::Vram {
+                bank: u8::from(i >= super::VRAM_SIZE_GB),
+                addr: 0x8000 | (i & 0x1FFF),
+                val,
+            });
+        }
+    }
+
+    #[inline]
+    pub(super) fn log_oam_write(&mut self, i: u8, old: u8, val: u8) {
+        if self.video_log.enabled && old != val {
+            self.video_log.writes.push(VideoWrite::Oam {
+                addr: 0xFE00 | u16::from(i),
+                val,
+            });
+        }
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Record VRAM and OAM bytes as they change, so tile and sprite viewers
+    // can update incrementally. Drain every frame, disabling frees the
+    // buffer.
+    #[inline]
+    pub fn set_video_logging(&mut self, enabled: bool) {
+        self.ppu.video_log = VideoLog {
+            enabled,
+            ..VideoLog::default()
+        };
+    }
+
+    // Changes since the last call, oldest first
+    #[inline]
+    pub fn drain_video_writes(&mut self) -> impl Iterator<Item = VideoWrite> + '_ {
+        self.ppu.video_log.writes.drain(..)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{AudioCallback, Gb, Model};
+
+// Behaviour that differs between hardware revisions. Modules check these
+// flags instead of the model, so a new revision only needs a new entry in
+// for_model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quirks {
+    // The bootrom continues after the cartridge header, at 0x200..0x900
+    pub split_bootrom: bool,
+    // KEY0 and OPRI exist, written by the bootrom to set up compatibility
+    // mode
+    pub cgb_bootrom_registers: bool,
+    // Writing STAT briefly enables every STAT interrupt source, raising an
+    // interrupt in HBlank, VBlank or when LY equals LYC
+    pub stat_write_bug: bool,
+    // P1 writes carry Super Game Boy command packets, of which MLT_REQ
+    // switches between up to four controllers
+    pub sgb_commands: bool,
+    // The screen shows colors brighter and more saturated, like the GBA
+    pub agb_lcd: bool,
+    // The bootrom increments B right before handing over, games check it
+    // to tell they run on a GBA
+    pub agb_bootrom: bool,
+}
+
+impl Quirks {
+    #[must_use]
+    #[inline]
+    pub const fn for_model(model: Model) -> Self {
+        match model {
+            Model::Dmg0 | Model::Dmg | Model::Mgb => Self {
+                split_bootrom: false,
+                cgb_bootrom_registers: false,
+                stat_write_bug: true,
+                sgb_commands: false,
+                agb_lcd: false,
+                agb_bootrom: false,
+            },
+            Model::Sgb | Model::Sgb2 => Self {
+                split_bootrom: false,
+                cgb_bootrom_registers: false,
+                stat_write_bug: true,
+                sgb_commands: true,
+                agb_lcd: false,
+                agb_bootrom: false,
+            },
+            Model::Cgb0 | Model::Cgb | Model::CgbE | Model::Agb => Self {
+                split_bootrom: true,
+                cgb_bootrom_registers: true,
+                stat_write_bug: false,
+                sgb_commands: false,
+                agb_lcd: matches!(model, Model::Agb),
+                agb_bootrom: matches!(model, Model::Agb),
+            },
+        }
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    #[must_use]
+    #[inline]
+    pub const fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // Overrides the quirks of the emulated model, for games that rely on
+    // or break with a specific one
+    #[inline]
+    pub const fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        self.ppu.set_agb_lcd(quirks.agb_lcd);
+    }
+}
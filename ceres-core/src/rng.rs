@@ -0,0 +1,58 @@
+use crate::{AudioCallback, Gb};
+
+const DEFAULT_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// xorshift64*, small and the same everywhere, so a seed always replays
+// the same run. Part of the console, clones and save states carry it.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Rng {
+    // xorshift never leaves a zero state
+    const fn new(seed: u64) -> Self {
+        Self {
+            state: if seed == 0 { DEFAULT_SEED } else { seed },
+        }
+    }
+
+    const fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn fill(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            let random = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Everything random in the console comes from this seed, the same seed
+    // and inputs always give the same run
+    #[inline]
+    pub const fn seed_rng(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    // Fills WRAM and HRAM with noise like the hardware powers up with,
+    // instead of zeros. Call it before running anything.
+    pub fn randomize_ram(&mut self) {
+        self.rng.fill(&mut self.wram[..]);
+        self.rng.fill(&mut self.hram);
+    }
+}
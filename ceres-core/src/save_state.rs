@@ -0,0 +1,193 @@
+use {
+    crate::{AudioCallback, Gb, Model},
+    alloc::{boxed::Box, string::String, vec::Vec},
+    core::fmt::Display,
+};
+
+// Describes a save state for listing slots
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaveStateInfo {
+    // given by the frontend since the core has no clock, seconds since the
+    // Unix epoch unless the frontend says otherwise
+    pub timestamp: u64,
+    pub title: String,
+    // frames run since power on or the last reset_stats
+    pub frames: u64,
+}
+
+// The whole console at one point in time, including cartridge RAM. Restoring
+// it leaves frontend settings alone: the audio callback and sample rate,
+// output filter, hidden layers, link port device, warnings and logs.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "", deserialize = "C: Default"))
+)]
+pub struct SaveState<C: AudioCallback> {
+    gb: Box<Gb<C>>,
+    info: SaveStateInfo,
+}
+
+impl<C: AudioCallback> SaveState<C> {
+    #[must_use]
+    #[inline]
+    pub const fn info(&self) -> &SaveStateInfo {
+        &self.info
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveStateError {
+    NoSuchSlot,
+    EmptySlot,
+    DifferentCart,
+}
+
+impl Display for SaveStateError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoSuchSlot => write!(f, "there's no save state slot with that number"),
+            Self::EmptySlot => write!(f, "save state slot is empty"),
+            Self::DifferentCart => write!(f, "save state belongs to another cartridge"),
+        }
+    }
+}
+
+impl core::error::Error for SaveStateError {}
+
+impl<C: AudioCallback + Clone> Gb<C> {
+    #[must_use]
+    pub fn save_state(&self, timestamp: u64) -> SaveState<C> {
+        SaveState {
+            gb: Box::new(self.clone()),
+            info: SaveStateInfo {
+                timestamp,
+                title: self.cart.title_str().unwrap_or_default().into(),
+                frames: self.stats.frames,
+            },
+        }
+    }
+
+    // Only states taken with the same ROM are accepted
+    pub fn load_state(&mut self, state: &SaveState<C>) -> Result<(), SaveStateError> {
+        if !self.cart.same_rom(&state.gb.cart) {
+            return Err(SaveStateError::DifferentCart);
+        }
+
+        let mut gb = state.gb.clone();
+        gb.take_host_state(self);
+        *self = *gb;
+        // the restored MBC may map other banks
+        self.track_bank_switch();
+
+        Ok(())
+    }
+
+    // Boots the cartridge again as another model, keeping its RAM and the
+    // frontend's settings
+    pub fn change_model_and_soft_reset(&mut self, model: Model) {
+        let cart = core::mem::take(&mut self.cart);
+        let mut gb = Self::new(
+            model,
+            self.apu.sample_rate(),
+            cart,
+            self.apu.audio_callback().clone(),
+        );
+        gb.take_host_state(self);
+        *self = gb;
+        self.track_bank_switch();
+    }
+
+    const fn take_host_state(&mut self, other: &mut Self) {
+        self.apu.take_host_state(&mut other.apu);
+        self.ppu.take_host_state(&mut other.ppu);
+        self.serial.take_host_state(&mut other.serial);
+
+        self.invalid_opcode_policy = other.invalid_opcode_policy;
+        self.ld_b_b_breakpoint = other.ld_b_b_breakpoint;
+        self.stop_reason = None;
+        core::mem::swap(&mut self.debugger, &mut other.debugger);
+        core::mem::swap(&mut self.memory_hook, &mut other.memory_hook);
+        core::mem::swap(&mut self.freezes, &mut other.freezes);
+        self.stats = other.stats;
+        core::mem::swap(&mut self.warnings, &mut other.warnings);
+        core::mem::swap(&mut self.trace, &mut other.trace);
+        core::mem::swap(&mut self.apu_log, &mut other.apu_log);
+        core::mem::swap(&mut self.bank_usage, &mut other.bank_usage);
+    }
+}
+
+// Numbered slots plus a quick slot, kept in memory. Frontends persist or
+// list them as they see fit, and should clear them when the ROM changes.
+pub struct SaveStateManager<C: AudioCallback> {
+    slots: Vec<Option<SaveState<C>>>,
+    quick: Option<SaveState<C>>,
+}
+
+impl<C: AudioCallback + Clone> SaveStateManager<C> {
+    #[must_use]
+    pub fn new(slots: usize) -> Self {
+        Self {
+            slots: (0..slots).map(|_| None).collect(),
+            quick: None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn save(&mut self, slot: usize, gb: &Gb<C>, timestamp: u64) -> Result<(), SaveStateError> {
+        let slot = self.slots.get_mut(slot).ok_or(SaveStateError::NoSuchSlot)?;
+        *slot = Some(gb.save_state(timestamp));
+
+        Ok(())
+    }
+
+    pub fn load(&self, slot: usize, gb: &mut Gb<C>) -> Result<(), SaveStateError> {
+        let state = self
+            .slots
+            .get(slot)
+            .ok_or(SaveStateError::NoSuchSlot)?
+            .as_ref()
+            .ok_or(SaveStateError::EmptySlot)?;
+
+        gb.load_state(state)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn info(&self, slot: usize) -> Option<&SaveStateInfo> {
+        self.slots.get(slot)?.as_ref().map(SaveState::info)
+    }
+
+    #[inline]
+    pub fn clear(&mut self, slot: usize) {
+        if let Some(slot) = self.slots.get_mut(slot) {
+            *slot = None;
+        }
+    }
+
+    pub fn clear_all(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.quick = None;
+    }
+
+    pub fn quick_save(&mut self, gb: &Gb<C>, timestamp: u64) {
+        self.quick = Some(gb.save_state(timestamp));
+    }
+
+    pub fn quick_load(&self, gb: &mut Gb<C>) -> Result<(), SaveStateError> {
+        gb.load_state(self.quick.as_ref().ok_or(SaveStateError::EmptySlot)?)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn quick_info(&self) -> Option<&SaveStateInfo> {
+        self.quick.as_ref().map(SaveState::info)
+    }
+}
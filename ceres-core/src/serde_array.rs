@@ -0,0 +1,109 @@
+use {
+    alloc::boxed::Box,
+    core::{fmt, marker::PhantomData},
+    serde::{
+        de::{Error, IgnoredAny, SeqAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    },
+};
+
+// Serde stops at arrays of 32 elements, longer ones go through here as a
+// flat sequence, with #[serde(with = "crate::serde_array")]
+pub trait Elem: Copy + Default + Serialize + for<'de> Deserialize<'de> {}
+
+impl Elem for u8 {}
+impl Elem for u16 {}
+impl Elem for (u8, u8, u8) {}
+impl Elem for crate::ScanlineScroll {}
+impl Elem for crate::ScanlineObjs {}
+
+pub trait Array {
+    type Elem: Elem;
+
+    fn filled() -> Self;
+    fn elems(&self) -> &[Self::Elem];
+    fn elems_mut(&mut self) -> &mut [Self::Elem];
+}
+
+impl<T: Elem, const N: usize> Array for [T; N] {
+    type Elem = T;
+
+    fn filled() -> Self {
+        [T::default(); N]
+    }
+
+    fn elems(&self) -> &[T] {
+        self
+    }
+
+    fn elems_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T: Elem, const M: usize, const N: usize> Array for [[T; M]; N] {
+    type Elem = T;
+
+    fn filled() -> Self {
+        [[T::default(); M]; N]
+    }
+
+    fn elems(&self) -> &[T] {
+        self.as_flattened()
+    }
+
+    fn elems_mut(&mut self) -> &mut [T] {
+        self.as_flattened_mut()
+    }
+}
+
+impl<A: Array> Array for Box<A> {
+    type Elem = A::Elem;
+
+    fn filled() -> Self {
+        Self::new(A::filled())
+    }
+
+    fn elems(&self) -> &[A::Elem] {
+        (**self).elems()
+    }
+
+    fn elems_mut(&mut self) -> &mut [A::Elem] {
+        (**self).elems_mut()
+    }
+}
+
+pub fn serialize<A: Array, S: Serializer>(array: &A, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(array.elems())
+}
+
+pub fn deserialize<'de, A: Array, D: Deserializer<'de>>(deserializer: D) -> Result<A, D::Error> {
+    deserializer.deserialize_seq(ArrayVisitor(PhantomData))
+}
+
+struct ArrayVisitor<A>(PhantomData<A>);
+
+impl<'de, A: Array> Visitor<'de> for ArrayVisitor<A> {
+    type Value = A;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<A, S::Error> {
+        let mut array = A::filled();
+        let len = array.elems().len();
+
+        for (i, elem) in array.elems_mut().iter_mut().enumerate() {
+            *elem = seq
+                .next_element()?
+                .ok_or_else(|| Error::invalid_length(i, &self))?;
+        }
+
+        if seq.next_element::<IgnoredAny>()?.is_some() {
+            return Err(Error::invalid_length(len + 1, &self));
+        }
+
+        Ok(array)
+    }
+}
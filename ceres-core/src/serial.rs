@@ -1,36 +1,87 @@
-use crate::{interrupts::Interrupts, CgbMode};
+use {
+    crate::{interrupts::Interrupts, CgbMode, LinkPort},
+    alloc::collections::VecDeque,
+};
 
 const START: u8 = 0x80;
 const SPEED: u8 = 0x2;
 const SHIFT: u8 = 0x1;
+const INTERNAL_TRANSFER: u8 = START | SHIFT;
+// Oldest bytes are dropped if nobody drains the output
+const MAX_OUTPUT: usize = 0x100;
 
-// TODO: always off
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Serial {
     sc: u8,
     sb: u8,
+    // byte being shifted in from the link port
+    incoming: u8,
     count: u8,
     div_mask: u8,
     master_clock: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    output: VecDeque<u8>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    link_port: LinkPort,
 }
 
 impl Serial {
     pub(crate) fn run_master(&mut self, ints: &mut Interrupts) {
         self.master_clock ^= true;
 
-        if !self.master_clock && (self.sc & (START | SHIFT) == (START | SHIFT)) {
-            self.count += 1;
-            if self.count > 7 {
-                self.count = 0;
+        if self.master_clock {
+            return;
+        }
+
+        match self.sc & (START | SHIFT) {
+            INTERNAL_TRANSFER => {
+                self.count += 1;
+                if self.count > 7 {
+                    self.count = 0;
+                    ints.req_serial();
+                    self.sc &= !START;
+                }
+
+                let received = if self.link_port.shifts_bits() {
+                    self.link_port.exchange_bit(self.sb & 0x80 != 0)
+                } else {
+                    self.incoming & 0x80 != 0
+                };
+
+                self.sb = self.sb << 1 | u8::from(received);
+                self.incoming <<= 1;
+            }
+            START if self.link_port.clocks_transfer() => {
+                // the device shifts the whole byte at once
+                self.sb = self.link_port.exchange(self.sb);
                 ints.req_serial();
                 self.sc &= !START;
             }
+            _ => (),
+        }
+    }
 
-            self.sb <<= 1;
+    // The plugged device and the collected output aren't part of the console
+    #[inline]
+    pub(crate) const fn take_host_state(&mut self, other: &mut Self) {
+        core::mem::swap(&mut self.link_port, &mut other.link_port);
+        core::mem::swap(&mut self.output, &mut other.output);
+    }
 
-            // TODO: always off
-            self.sb |= 1;
-        }
+    #[inline]
+    pub(crate) const fn link_port_mut(&mut self) -> &mut LinkPort {
+        &mut self.link_port
+    }
+
+    #[inline]
+    pub(crate) const fn set_link_port(&mut self, link_port: LinkPort) -> LinkPort {
+        core::mem::replace(&mut self.link_port, link_port)
+    }
+
+    #[inline]
+    pub(crate) fn drain_output(&mut self) -> impl Iterator<Item = u8> + '_ {
+        self.output.drain(..)
     }
 
     #[must_use]
@@ -64,6 +115,16 @@ impl Serial {
             val |= 2;
         }
 
+        if val & (START | SHIFT) == (START | SHIFT) {
+            if self.output.len() == MAX_OUTPUT {
+                self.output.pop_front();
+            }
+            self.output.push_back(self.sb);
+            if !self.link_port.shifts_bits() {
+                self.incoming = self.link_port.exchange(self.sb);
+            }
+        }
+
         self.sc = val | !(START | SPEED | SHIFT);
         self.div_mask = if matches!(cgb_mode, CgbMode::Cgb) && val & SPEED != 0 {
             4
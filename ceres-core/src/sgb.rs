@@ -0,0 +1,549 @@
+// Super Game Boy command packets, sent bit by bit through P1, and the SNES
+// side acting on them: palettes, the attribute map, the border and the
+// controller multiplexing. SGB sound commands aren't emulated.
+
+use {
+    crate::{PX_HEIGHT, PX_WIDTH},
+    alloc::{boxed::Box, vec},
+};
+
+const PACKET_BITS: u8 = 128;
+const PACKET_LEN: usize = 16;
+const MAX_PACKETS: usize = 7;
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0A;
+const PAL_TRN: u8 = 0x0B;
+pub const MLT_REQ: u8 = 0x11;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+const ATTR_TRN: u8 = 0x15;
+const ATTR_SET: u8 = 0x16;
+const MASK_EN: u8 = 0x17;
+
+pub const SGB_PX_WIDTH: u16 = 256;
+pub const SGB_PX_HEIGHT: u16 = 224;
+// where the Game Boy screen sits inside the border
+const SCREEN_X: usize = 48;
+const SCREEN_Y: usize = 40;
+
+// in tiles
+const MAP_WIDTH: usize = PX_WIDTH as usize / 8;
+const MAP_HEIGHT: usize = PX_HEIGHT as usize / 8;
+const MAP_SIZE: usize = MAP_WIDTH * MAP_HEIGHT;
+const BORDER_MAP_WIDTH: usize = 32;
+const BORDER_MAP_HEIGHT: usize = 28;
+
+// A VRAM transfer is the screen read back as 256 tiles, 20 per row
+const TRANSFER_LEN: usize = 0x1000;
+const BORDER_TILES: usize = 256;
+// 4 bits per pixel, SNES planar
+const BORDER_TILE_LEN: usize = 32;
+const SYSTEM_PALETTES: usize = 512;
+const ATTR_FILES: usize = 45;
+const ATTR_FILE_LEN: usize = MAP_SIZE / 4;
+
+// What the SNES shows until the game sends its own palette
+const DEFAULT_PALETTE: [u16; 4] = [0x67BF, 0x265B, 0x10B5, 0x2866];
+
+// Completed command, the first byte holds the command and packet count
+pub type Command = [u8; PACKET_LEN * MAX_PACKETS];
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SgbLink {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    command: Command,
+    // packets of the current command received so far
+    packets: usize,
+    bits: u8,
+    receiving: bool,
+    // P14 and P15 went high since the last bit, which ends a pulse
+    released: bool,
+}
+
+impl Default for SgbLink {
+    fn default() -> Self {
+        Self {
+            command: [0; PACKET_LEN * MAX_PACKETS],
+            packets: 0,
+            bits: 0,
+            receiving: false,
+            released: false,
+        }
+    }
+}
+
+impl SgbLink {
+    // Returns the command once its last packet arrives
+    pub(crate) fn write(&mut self, val: u8) -> Option<Command> {
+        match (val >> 4) & 3 {
+            // both low, reset pulse starting a packet
+            0 => {
+                self.receiving = true;
+                self.released = false;
+                self.bits = 0;
+                let start = self.packets * PACKET_LEN;
+                self.command[start..start + PACKET_LEN].fill(0);
+                None
+            }
+            3 => {
+                self.released = true;
+                None
+            }
+            // P15 low sends a 1, P14 low a 0
+            pulse => {
+                if !self.receiving || !self.released {
+                    return None;
+                }
+
+                self.released = false;
+                let one = pulse == 1;
+
+                if self.bits < PACKET_BITS {
+                    let i = self.packets * PACKET_LEN + usize::from(self.bits / 8);
+                    self.command[i] |= u8::from(one) << (self.bits % 8);
+                    self.bits += 1;
+                    return None;
+                }
+
+                // a 0 stop bit ends the packet, a 1 corrupts it
+                self.receiving = false;
+                if one {
+                    return None;
+                }
+
+                self.packet_done()
+            }
+        }
+    }
+
+    fn packet_done(&mut self) -> Option<Command> {
+        self.packets += 1;
+
+        let total = usize::from(self.command[0] & 7).max(1);
+        if self.packets < total {
+            return None;
+        }
+
+        self.packets = 0;
+        Some(self.command)
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Mask {
+    #[default]
+    Cancel,
+    // the last frame stays on screen
+    Freeze,
+    Black,
+    Color0,
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Transfer {
+    Palettes,
+    // the first or last 128 border tiles
+    Tiles(usize),
+    Border,
+    AttrFiles,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sgb {
+    // RGB555, color 0 is shared by all four
+    palettes: [[u16; 4]; 4],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    system_palettes: Box<[[u16; 4]; SYSTEM_PALETTES]>,
+    // palette of every tile on screen
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    attr_map: [u8; MAP_SIZE],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    attr_files: Box<[[u8; ATTR_FILE_LEN]; ATTR_FILES]>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    border_tiles: Box<[u8; BORDER_TILES * BORDER_TILE_LEN]>,
+    // tile, palette and flips of every border tile, 32 per row
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    border_map: Box<[u16; BORDER_MAP_WIDTH * BORDER_MAP_HEIGHT]>,
+    // palettes 4 to 7, 16 colors each
+    border_palettes: [[u16; 16]; 4],
+    mask: Mask,
+    // waits for a whole frame drawn after the command
+    transfer: Option<(Transfer, u8)>,
+    frame: Box<[u8]>,
+}
+
+impl Default for Sgb {
+    fn default() -> Self {
+        Self {
+            palettes: [DEFAULT_PALETTE; 4],
+            system_palettes: Box::new([[0; 4]; SYSTEM_PALETTES]),
+            attr_map: [0; MAP_SIZE],
+            attr_files: Box::new([[0; ATTR_FILE_LEN]; ATTR_FILES]),
+            border_tiles: Box::new([0; BORDER_TILES * BORDER_TILE_LEN]),
+            border_map: Box::new([0; BORDER_MAP_WIDTH * BORDER_MAP_HEIGHT]),
+            border_palettes: [[0; 16]; 4],
+            mask: Mask::default(),
+            transfer: None,
+            frame: vec![0; usize::from(SGB_PX_WIDTH) * usize::from(SGB_PX_HEIGHT) * 3]
+                .into_boxed_slice(),
+        }
+    }
+}
+
+impl Sgb {
+    pub(crate) fn command(&mut self, command: &Command) {
+        let data = &command[1..];
+
+        match command[0] >> 3 {
+            PAL01 => self.set_palettes(0, 1, data),
+            PAL23 => self.set_palettes(2, 3, data),
+            PAL03 => self.set_palettes(0, 3, data),
+            PAL12 => self.set_palettes(1, 2, data),
+            ATTR_BLK => self.attr_blk(data),
+            ATTR_LIN => self.attr_lin(data),
+            ATTR_DIV => self.attr_div(data),
+            ATTR_CHR => self.attr_chr(data),
+            PAL_SET => self.pal_set(data),
+            PAL_TRN => self.start_transfer(Transfer::Palettes),
+            CHR_TRN => self.start_transfer(Transfer::Tiles(usize::from(data[0] & 1))),
+            PCT_TRN => self.start_transfer(Transfer::Border),
+            ATTR_TRN => self.start_transfer(Transfer::AttrFiles),
+            ATTR_SET => self.attr_set(data[0]),
+            MASK_EN => {
+                self.mask = match data[0] & 3 {
+                    0 => Mask::Cancel,
+                    1 => Mask::Freeze,
+                    2 => Mask::Black,
+                    _ => Mask::Color0,
+                };
+            }
+            // MLT_REQ is handled by the joypad, sound and SNES programs are
+            // ignored
+            _ => (),
+        }
+    }
+
+    // Call with the shades of every frame as it ends
+    pub(crate) fn frame_done(&mut self, shades: &[u8]) {
+        if let Some((transfer, frames)) = self.transfer {
+            if frames > 1 {
+                self.transfer = Some((transfer, frames - 1));
+            } else {
+                self.transfer = None;
+                self.receive(transfer, &vram_transfer(shades));
+            }
+        }
+
+        self.compose(shades);
+    }
+
+    #[must_use]
+    pub(crate) fn pixel_data_rgb(&self) -> &[u8] {
+        &self.frame
+    }
+
+    fn set_palettes(&mut self, a: usize, b: usize, data: &[u8]) {
+        let color = |i: usize| u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
+
+        for palette in &mut self.palettes {
+            palette[0] = color(0);
+        }
+        for c in 1..4 {
+            self.palettes[a][c] = color(c);
+            self.palettes[b][c] = color(c + 3);
+        }
+    }
+
+    fn attr_blk(&mut self, data: &[u8]) {
+        let sets = usize::from(data[0] & 0x1F);
+
+        for set in data[1..].chunks_exact(6).take(sets) {
+            let [ctrl, pals] = [set[0], set[1]];
+            let [x1, y1, x2, y2] = [set[2], set[3], set[4], set[5]].map(usize::from);
+            let (inside, line, outside) = (ctrl & 1 != 0, ctrl & 2 != 0, ctrl & 4 != 0);
+            let pal_inside = pals & 3;
+            let pal_outside = pals >> 4 & 3;
+            // the surrounding line follows the only area being changed
+            let pal_line = match (inside, line, outside) {
+                (true, false, false) => Some(pal_inside),
+                (false, false, true) => Some(pal_outside),
+                (_, true, _) => Some(pals >> 2 & 3),
+                _ => None,
+            };
+
+            for (i, attr) in self.attr_map.iter_mut().enumerate() {
+                let (x, y) = (i % MAP_WIDTH, i / MAP_WIDTH);
+                let in_block = (x1..=x2).contains(&x) && (y1..=y2).contains(&y);
+                let on_line = in_block && (x == x1 || x == x2 || y == y1 || y == y2);
+
+                let pal = if on_line {
+                    pal_line
+                } else if in_block {
+                    inside.then_some(pal_inside)
+                } else {
+                    outside.then_some(pal_outside)
+                };
+
+                if let Some(pal) = pal {
+                    *attr = pal;
+                }
+            }
+        }
+    }
+
+    fn attr_lin(&mut self, data: &[u8]) {
+        let lines = usize::from(data[0]);
+
+        for &line in data[1..].iter().take(lines) {
+            let n = usize::from(line & 0x1F);
+            let pal = line >> 5 & 3;
+
+            for (i, attr) in self.attr_map.iter_mut().enumerate() {
+                // bit 7 picks a row instead of a column
+                let pos = if line & 0x80 == 0 {
+                    i % MAP_WIDTH
+                } else {
+                    i / MAP_WIDTH
+                };
+
+                if pos == n {
+                    *attr = pal;
+                }
+            }
+        }
+    }
+
+    fn attr_div(&mut self, data: &[u8]) {
+        let (ctrl, at) = (data[0], usize::from(data[1]));
+        let after = ctrl & 3;
+        let before = ctrl >> 2 & 3;
+        let on = ctrl >> 4 & 3;
+        let horizontal = ctrl & 0x40 != 0;
+
+        for (i, attr) in self.attr_map.iter_mut().enumerate() {
+            let (x, y) = (i % MAP_WIDTH, i / MAP_WIDTH);
+            let pos = if horizontal { y } else { x };
+
+            *attr = match pos.cmp(&at) {
+                core::cmp::Ordering::Less => before,
+                core::cmp::Ordering::Equal => on,
+                core::cmp::Ordering::Greater => after,
+            };
+        }
+    }
+
+    fn attr_chr(&mut self, data: &[u8]) {
+        let (mut x, mut y) = (usize::from(data[0]), usize::from(data[1]));
+        let count = usize::from(u16::from_le_bytes([data[2], data[3]])).min(MAP_SIZE);
+        let vertical = data[4] & 1 != 0;
+
+        for i in 0..count {
+            let Some(&byte) = data.get(5 + i / 4) else {
+                break;
+            };
+            if x >= MAP_WIDTH || y >= MAP_HEIGHT {
+                break;
+            }
+
+            self.attr_map[y * MAP_WIDTH + x] = byte >> (6 - 2 * (i % 4)) & 3;
+
+            if vertical {
+                y += 1;
+                if y == MAP_HEIGHT {
+                    y = 0;
+                    x += 1;
+                }
+            } else {
+                x += 1;
+                if x == MAP_WIDTH {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+    }
+
+    fn pal_set(&mut self, data: &[u8]) {
+        for (i, palette) in self.palettes.iter_mut().enumerate() {
+            let n = usize::from(u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]) & 0x1FF);
+            *palette = self.system_palettes[n];
+        }
+        // color 0 of the first palette is shared
+        let color0 = self.palettes[0][0];
+        for palette in &mut self.palettes {
+            palette[0] = color0;
+        }
+
+        let flags = data[8];
+        if flags & 0x80 != 0 {
+            self.attr_set(flags & 0x3F);
+        }
+        if flags & 0x40 != 0 {
+            self.mask = Mask::Cancel;
+        }
+    }
+
+    fn attr_set(&mut self, val: u8) {
+        if let Some(file) = self.attr_files.get(usize::from(val & 0x3F)) {
+            for (i, attr) in self.attr_map.iter_mut().enumerate() {
+                *attr = file[i / 4] >> (6 - 2 * (i % 4)) & 3;
+            }
+        }
+
+        if val & 0x40 != 0 {
+            self.mask = Mask::Cancel;
+        }
+    }
+
+    const fn start_transfer(&mut self, transfer: Transfer) {
+        // the frame being drawn may still show what was there before
+        self.transfer = Some((transfer, 2));
+    }
+
+    fn receive(&mut self, transfer: Transfer, data: &[u8; TRANSFER_LEN]) {
+        let words = |bytes: &[u8]| {
+            bytes
+                .chunks_exact(2)
+                .map(|w| u16::from_le_bytes([w[0], w[1]]))
+                .collect::<alloc::vec::Vec<_>>()
+        };
+
+        match transfer {
+            Transfer::Palettes => {
+                for (palette, colors) in self.system_palettes.iter_mut().zip(data.chunks_exact(8)) {
+                    palette.copy_from_slice(&words(colors));
+                }
+            }
+            Transfer::Tiles(half) => {
+                let start = half * TRANSFER_LEN;
+                self.border_tiles[start..start + TRANSFER_LEN].copy_from_slice(data);
+            }
+            Transfer::Border => {
+                let map_len = self.border_map.len() * 2;
+                self.border_map.copy_from_slice(&words(&data[..map_len]));
+
+                // the map has room for 32 rows, the palettes come after them
+                let palettes = &data[BORDER_MAP_WIDTH * 32 * 2..];
+                for (palette, colors) in self.border_palettes.iter_mut().zip(palettes.chunks(32)) {
+                    palette.copy_from_slice(&words(colors));
+                }
+            }
+            Transfer::AttrFiles => {
+                for (file, bytes) in self
+                    .attr_files
+                    .iter_mut()
+                    .zip(data.chunks_exact(ATTR_FILE_LEN))
+                {
+                    file.copy_from_slice(bytes);
+                }
+            }
+        }
+    }
+
+    fn compose(&mut self, shades: &[u8]) {
+        let backdrop = self.palettes[0][0];
+
+        if self.mask != Mask::Freeze {
+            for y in 0..usize::from(PX_HEIGHT) {
+                for x in 0..usize::from(PX_WIDTH) {
+                    let color = match self.mask {
+                        Mask::Black => 0,
+                        Mask::Color0 => backdrop,
+                        Mask::Cancel | Mask::Freeze => {
+                            let palette = self.attr_map[y / 8 * MAP_WIDTH + x / 8];
+                            let shade = shades[y * usize::from(PX_WIDTH) + x];
+                            self.palettes[usize::from(palette)][usize::from(shade)]
+                        }
+                    };
+                    set_px(&mut self.frame, SCREEN_X + x, SCREEN_Y + y, color);
+                }
+            }
+        }
+
+        // the border covers the screen, its transparent pixels show the
+        // backdrop outside of it
+        for (i, &entry) in self.border_map.iter().enumerate() {
+            let (tile_x, tile_y) = (i % BORDER_MAP_WIDTH * 8, i / BORDER_MAP_WIDTH * 8);
+            let tile = usize::from(entry & 0xFF) * BORDER_TILE_LEN;
+            let palette = usize::from(entry >> 10 & 7);
+            let (flip_x, flip_y) = (entry & 0x4000 != 0, entry & 0x8000 != 0);
+
+            for row in 0..8 {
+                let r = if flip_y { 7 - row } else { row };
+                let planes = [
+                    self.border_tiles[tile + r * 2],
+                    self.border_tiles[tile + r * 2 + 1],
+                    self.border_tiles[tile + 16 + r * 2],
+                    self.border_tiles[tile + 16 + r * 2 + 1],
+                ];
+
+                for col in 0..8 {
+                    let bit = if flip_x { col } else { 7 - col };
+                    let index = planes
+                        .iter()
+                        .enumerate()
+                        .fold(0, |index, (p, plane)| index | (plane >> bit & 1) << p);
+
+                    let (x, y) = (tile_x + col, tile_y + row);
+                    let on_screen = (SCREEN_X..SCREEN_X + usize::from(PX_WIDTH)).contains(&x)
+                        && (SCREEN_Y..SCREEN_Y + usize::from(PX_HEIGHT)).contains(&y);
+
+                    let color = if index != 0 && palette >= 4 {
+                        Some(self.border_palettes[palette - 4][usize::from(index)])
+                    } else {
+                        (!on_screen).then_some(backdrop)
+                    };
+
+                    if let Some(color) = color {
+                        set_px(&mut self.frame, x, y, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Writes an RGB555 color as RGB888
+fn set_px(frame: &mut [u8], x: usize, y: usize, color: u16) {
+    const fn scale_channel(c: u16) -> u8 {
+        let c = (c & 0x1F) as u8;
+        (c << 3) | (c >> 2)
+    }
+
+    let i = (y * usize::from(SGB_PX_WIDTH) + x) * 3;
+    frame[i] = scale_channel(color);
+    frame[i + 1] = scale_channel(color >> 5);
+    frame[i + 2] = scale_channel(color >> 10);
+}
+
+// The SNES reads the screen as 2 bit per pixel tiles, left to right and top
+// to bottom
+fn vram_transfer(shades: &[u8]) -> [u8; TRANSFER_LEN] {
+    let mut data = [0; TRANSFER_LEN];
+
+    for (tile, bytes) in data.chunks_exact_mut(16).enumerate() {
+        let (x, y) = (tile % MAP_WIDTH * 8, tile / MAP_WIDTH * 8);
+
+        for row in 0..8 {
+            let line = &shades[(y + row) * usize::from(PX_WIDTH) + x..][..8];
+            for (col, &shade) in line.iter().enumerate() {
+                bytes[row * 2] |= (shade & 1) << (7 - col);
+                bytes[row * 2 + 1] |= (shade >> 1 & 1) << (7 - col);
+            }
+        }
+    }
+
+    data
+}
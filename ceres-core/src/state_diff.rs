@@ -0,0 +1,153 @@
+use {
+    crate::{AudioCallback, Gb},
+    alloc::vec::Vec,
+    core::fmt::Display,
+};
+
+// Lines printed before the rest are summarized
+const DISPLAY_LIMIT: usize = 16;
+
+// Where two consoles disagree, memory offsets are into the whole region
+// regardless of the selected bank
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateField {
+    // "af", "bc", "de", "hl", "sp", "pc", "ime" or "halted"
+    Cpu(&'static str),
+    // 0xFF00..=0xFF7F and 0xFFFF, as the CPU reads them
+    Io(u16),
+    Wram(usize),
+    Hram(usize),
+    Vram(usize),
+    Oam(usize),
+    CartRam(usize),
+}
+
+impl Display for StateField {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Cpu(name) => write!(f, "{name}"),
+            Self::Io(addr) => write!(f, "io ${addr:04X}"),
+            Self::Wram(i) => write!(f, "wram[${i:04X}]"),
+            Self::Hram(i) => write!(f, "hram[${i:02X}]"),
+            Self::Vram(i) => write!(f, "vram[${i:04X}]"),
+            Self::Oam(i) => write!(f, "oam[${i:02X}]"),
+            Self::CartRam(i) => write!(f, "cart ram[${i:05X}]"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub field: StateField,
+    pub ours: u16,
+    pub theirs: u16,
+}
+
+// Mismatches in a fixed order: CPU, IO, then memory by address. The first
+// one is usually closest to the cause of a desync.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    mismatches: Vec<Mismatch>,
+}
+
+impl StateDiff {
+    #[must_use]
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn first(&self) -> Option<&Mismatch> {
+        self.mismatches.first()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+
+    fn compare(&mut self, field: StateField, ours: u16, theirs: u16) {
+        if ours != theirs {
+            self.mismatches.push(Mismatch {
+                field,
+                ours,
+                theirs,
+            });
+        }
+    }
+
+    fn compare_bytes(&mut self, field: fn(usize) -> StateField, ours: &[u8], theirs: &[u8]) {
+        for (i, (&a, &b)) in ours.iter().zip(theirs).enumerate() {
+            self.compare(field(i), a.into(), b.into());
+        }
+    }
+}
+
+impl Display for StateDiff {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "states match");
+        }
+
+        for m in self.mismatches.iter().take(DISPLAY_LIMIT) {
+            writeln!(f, "{}: ${:02X} != ${:02X}", m.field, m.ours, m.theirs)?;
+        }
+
+        let rest = self.mismatches.len().saturating_sub(DISPLAY_LIMIT);
+        if rest > 0 {
+            writeln!(f, "and {rest} more")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Compares everything a running game can observe, leaving out frontend
+    // settings and timing counters that don't feed back into emulation
+    #[must_use]
+    pub fn diff_state<D: AudioCallback>(&self, other: &Gb<D>) -> StateDiff {
+        let mut diff = StateDiff::default();
+
+        let (ours, theirs) = (self.registers(), other.registers());
+        for (name, a, b) in [
+            ("af", ours.af, theirs.af),
+            ("bc", ours.bc, theirs.bc),
+            ("de", ours.de, theirs.de),
+            ("hl", ours.hl, theirs.hl),
+            ("sp", ours.sp, theirs.sp),
+            ("pc", ours.pc, theirs.pc),
+        ] {
+            diff.compare(StateField::Cpu(name), a, b);
+        }
+        diff.compare(
+            StateField::Cpu("ime"),
+            self.ints.enabled().into(),
+            other.ints.enabled().into(),
+        );
+        diff.compare(
+            StateField::Cpu("halted"),
+            self.cpu_halted.into(),
+            other.cpu_halted.into(),
+        );
+
+        for addr in (0xFF00..=0xFF7F).chain([0xFFFF]) {
+            diff.compare(
+                StateField::Io(addr),
+                self.read_mem(addr).into(),
+                other.read_mem(addr).into(),
+            );
+        }
+
+        diff.compare_bytes(StateField::Wram, &self.wram[..], &other.wram[..]);
+        diff.compare_bytes(StateField::Hram, &self.hram, &other.hram);
+        diff.compare_bytes(StateField::Vram, self.ppu.vram(), other.ppu.vram());
+        diff.compare_bytes(StateField::Oam, self.ppu.oam(), other.ppu.oam());
+        diff.compare_bytes(StateField::CartRam, self.cart.ram(), other.cart.ram());
+
+        diff
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{AudioCallback, Gb};
+
+// Running totals since power on or the last reset, cheap enough to be
+// always on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    pub instructions: u64,
+    pub frames: u64,
+    // OAM DMA, general purpose and HBlank DMA transfers started
+    pub dma_transfers: u64,
+    // MBC writes that changed the mapped ROM or RAM banks
+    pub bank_switches: u64,
+    pub audio_samples: u64,
+}
+
+impl<C: AudioCallback> Gb<C> {
+    #[must_use]
+    #[inline]
+    pub const fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    #[inline]
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{AudioCallback, Gb};
 
 #[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TIMAState {
     Reloading,
     Reloaded,
@@ -9,24 +10,31 @@ pub enum TIMAState {
 }
 
 impl<A: AudioCallback> Gb<A> {
-    pub(crate) fn advance_t_cycles(&mut self, mut cycles: i32) {
-        // affected by speed boost
+    // cycles are CPU T-cycles
+    pub(crate) fn advance_t_cycles(&mut self, cycles: i32) {
+        // CPU clock
         self.run_timers(cycles);
         self.dma_cycles += cycles;
 
-        // not affected by speed boost
-        if self.key1.enabled() {
-            cycles >>= 1;
-        }
+        let dots = self.clock.dots(cycles);
 
         // TODO: is this order right?
-        self.ppu.run(cycles, &mut self.ints, &self.cgb_mode);
+        let frames = self.stats.frames;
+        self.ppu
+            .run(dots, &mut self.ints, &self.cgb_mode, &mut self.stats);
+        if self.stats.frames != frames {
+            if let Some(sgb) = &mut self.sgb {
+                sgb.frame_done(self.ppu.shades());
+            }
+        }
         self.run_dma();
 
-        self.apu.run(cycles);
-        self.cart.run_rtc(cycles);
+        self.apu.run(dots, &mut self.stats);
+        self.apu_log.run(dots);
+        self.bank_usage.run(dots);
+        self.cart.run_rtc(dots);
 
-        self.dot_accumulator += cycles;
+        self.dot_accumulator += dots;
     }
 
     #[inline]
@@ -68,7 +76,7 @@ impl<A: AudioCallback> Gb<A> {
         }
 
         let triggers = self.div & !val;
-        let apu_bit = if self.key1.enabled() { 0x2000 } else { 0x1000 };
+        let apu_bit = self.clock.div_apu_bit();
 
         // increase TIMA on falling edge of TAC mux
         if self.tac_enabled() && (triggers & sys_clk_tac_mux(self.tac) != 0) {
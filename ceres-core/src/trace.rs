@@ -0,0 +1,57 @@
+use {
+    crate::{AudioCallback, Gb, Registers},
+    alloc::collections::VecDeque,
+};
+
+// CPU state right before an instruction executes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub op: u8,
+    pub registers: Registers,
+}
+
+// Keeps the most recent instructions, only allocated while tracing
+#[derive(Clone, Default)]
+pub struct Trace {
+    entries: VecDeque<TraceEntry>,
+    capacity: usize,
+}
+
+impl Trace {
+    #[inline]
+    fn push(&mut self, entry: TraceEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(entry);
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    #[inline]
+    pub(crate) fn trace_instruction(&mut self) {
+        if self.trace.capacity > 0 {
+            let entry = TraceEntry {
+                op: self.read_mem(self.pc),
+                registers: self.registers(),
+            };
+            self.trace.push(entry);
+        }
+    }
+
+    // Record up to capacity of the most recent instructions, 0 disables
+    // tracing and frees the buffer
+    #[inline]
+    pub fn set_trace_capacity(&mut self, capacity: usize) {
+        self.trace.capacity = capacity;
+        self.trace.entries.truncate(capacity);
+        self.trace.entries.shrink_to(capacity);
+    }
+
+    // Instructions executed since the last call, oldest first
+    #[inline]
+    pub fn drain_trace(&mut self) -> impl Iterator<Item = TraceEntry> + '_ {
+        self.trace.entries.drain(..)
+    }
+}
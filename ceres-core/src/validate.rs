@@ -0,0 +1,85 @@
+use {
+    crate::{AudioCallback, Cart, Gb, Model, TC_SEC},
+    alloc::{vec, vec::Vec},
+    core::fmt::Display,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    SampleRate(i32),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::SampleRate(rate) => {
+                write!(f, "sample rate {rate} isn't between 1 and {TC_SEC} Hz")
+            }
+        }
+    }
+}
+
+impl core::error::Error for BuildError {}
+
+// A model and cartridge that work together, but likely not the way the user
+// expects
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildWarning {
+    // The game only shows a message asking for a CGB
+    CgbOnlyOnDmg,
+    // Bit 7 with other bits than bit 6 is a PGB mode on hardware, which isn't
+    // emulated, the game runs as a CGB game
+    UnusualCgbFlag(u8),
+}
+
+impl Display for BuildWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::CgbOnlyOnDmg => write!(f, "this game needs a Game Boy Color"),
+            Self::UnusualCgbFlag(flag) => write!(
+                f,
+                "CGB flag {flag:#04x} in the cartridge header asks for a mode that isn't emulated"
+            ),
+        }
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Gb::new checking its arguments first, with what the frontend may want
+    // to tell the user about the model chosen for the cartridge
+    pub fn try_new(
+        model: Model,
+        sample_rate: i32,
+        cart: Cart,
+        audio_callback: C,
+    ) -> Result<(Self, Vec<BuildWarning>), BuildError> {
+        if !(1..=TC_SEC).contains(&sample_rate) {
+            return Err(BuildError::SampleRate(sample_rate));
+        }
+
+        let warnings = build_warnings(model, &cart);
+
+        Ok((
+            Self::new(model, sample_rate, cart, audio_callback),
+            warnings,
+        ))
+    }
+}
+
+fn build_warnings(model: Model, cart: &Cart) -> Vec<BuildWarning> {
+    let flag = cart.cgb_flag();
+
+    if !model.is_cgb() {
+        return if cart.requires_cgb() {
+            vec![BuildWarning::CgbOnlyOnDmg]
+        } else {
+            Vec::new()
+        };
+    }
+
+    if flag & 0x80 != 0 && flag & 0x3F != 0 {
+        vec![BuildWarning::UnusualCgbFlag(flag)]
+    } else {
+        Vec::new()
+    }
+}
@@ -0,0 +1,89 @@
+use {
+    crate::{ApuWrite, TC_SEC},
+    alloc::{vec, vec::Vec},
+};
+
+// VGM 1.61, the first version with the Game Boy APU
+const VERSION: u32 = 0x161;
+const HEADER_SIZE: usize = 0x100;
+const SAMPLE_RATE: u64 = 44100;
+
+// Header offsets
+const EOF_OFFSET: usize = 0x04;
+const VERSION_OFFSET: usize = 0x08;
+const TOTAL_SAMPLES: usize = 0x18;
+const DATA_OFFSET: usize = 0x34;
+const DMG_CLOCK: usize = 0x80;
+
+// Commands
+const CMD_DMG_WRITE: u8 = 0xB3;
+const CMD_WAIT: u8 = 0x61;
+const CMD_END: u8 = 0x66;
+
+// Builds a VGM file from logged APU writes, waits are rounded to 44100 Hz
+// samples without drifting over long recordings
+pub struct VgmWriter {
+    data: Vec<u8>,
+    samples: u64,
+}
+
+impl Default for VgmWriter {
+    fn default() -> Self {
+        let mut data = vec![0; HEADER_SIZE];
+        data[..4].copy_from_slice(b"Vgm ");
+
+        Self { data, samples: 0 }
+    }
+}
+
+impl VgmWriter {
+    #[inline]
+    pub fn push(&mut self, write: ApuWrite) {
+        self.wait_until(write.dots);
+        // registers are numbered from NR10
+        self.data
+            .extend_from_slice(&[CMD_DMG_WRITE, write.addr - 0x10, write.val]);
+    }
+
+    // dots is when the recording stops, usually Gb::apu_log_dots
+    #[must_use]
+    pub fn finish(mut self, dots: u64) -> Vec<u8> {
+        self.wait_until(dots);
+        self.data.push(CMD_END);
+
+        #[allow(clippy::cast_possible_truncation)]
+        let eof = (self.data.len() - EOF_OFFSET) as u32;
+        #[allow(clippy::cast_possible_truncation)]
+        let samples = self.samples as u32;
+        #[allow(clippy::cast_possible_truncation)]
+        let data_offset = (HEADER_SIZE - DATA_OFFSET) as u32;
+        #[allow(clippy::cast_sign_loss)]
+        let clock = TC_SEC as u32;
+
+        for (offset, val) in [
+            (EOF_OFFSET, eof),
+            (VERSION_OFFSET, VERSION),
+            (TOTAL_SAMPLES, samples),
+            (DATA_OFFSET, data_offset),
+            (DMG_CLOCK, clock),
+        ] {
+            self.data[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+        }
+
+        self.data
+    }
+
+    fn wait_until(&mut self, dots: u64) {
+        #[allow(clippy::cast_sign_loss)]
+        let target = dots * SAMPLE_RATE / TC_SEC as u64;
+
+        while self.samples < target {
+            let wait = (target - self.samples).min(u64::from(u16::MAX));
+            self.samples += wait;
+
+            #[allow(clippy::cast_possible_truncation)]
+            let [lo, hi] = (wait as u16).to_le_bytes();
+            self.data.extend_from_slice(&[CMD_WAIT, lo, hi]);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use {
+    crate::{AudioCallback, Gb},
+    alloc::collections::VecDeque,
+    core::fmt::Display,
+};
+
+// Oldest warnings are dropped once the frontend falls this far behind
+const MAX_QUEUED: usize = 64;
+
+// Odd behaviour from the running program, reported so users can include it
+// in compatibility bug reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Warning {
+    UnsupportedIoWrite { addr: u16, val: u8 },
+    RomBankOutOfRange { bank: u16 },
+    InvalidOpcode { pc: u16, op: u8 },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedIoWrite { addr, val } => {
+                write!(f, "unsupported IO write of {val:#04x} to {addr:#06x}")
+            }
+            Self::RomBankOutOfRange { bank } => {
+                write!(
+                    f,
+                    "selected ROM bank {bank:#x} is out of the cartridge range"
+                )
+            }
+            Self::InvalidOpcode { pc, op } => {
+                write!(f, "invalid opcode {op:#04x} at {pc:#06x}")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Warnings {
+    queue: VecDeque<Warning>,
+}
+
+impl Warnings {
+    #[inline]
+    pub(crate) fn push(&mut self, warning: Warning) {
+        if self.queue.len() == MAX_QUEUED {
+            self.queue.pop_front();
+        }
+
+        self.queue.push_back(warning);
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    #[inline]
+    pub(crate) fn warn(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    // Warnings raised since the last call, oldest first
+    #[inline]
+    pub fn drain_warnings(&mut self) -> impl Iterator<Item = Warning> + '_ {
+        self.warnings.queue.drain(..)
+    }
+}
@@ -0,0 +1,106 @@
+use {
+    crate::{AudioCallback, Gb},
+    alloc::{vec, vec::Vec},
+};
+
+const HEADER_SIZE: usize = 44;
+const BITS_PER_SAMPLE: u16 = 16;
+const PCM: u16 = 1;
+
+// Builds a 16-bit PCM WAV file from the samples the APU renders, at its
+// sample rate. With raw channels each frame also has the four channel DACs
+// before mixing and panning, for isolating a part.
+#[derive(Clone)]
+pub struct AudioRecorder {
+    data: Vec<u8>,
+    sample_rate: u32,
+    raw_channels: bool,
+}
+
+impl AudioRecorder {
+    #[must_use]
+    pub fn new(sample_rate: u32, raw_channels: bool) -> Self {
+        Self {
+            data: vec![0; HEADER_SIZE],
+            sample_rate,
+            raw_channels,
+        }
+    }
+
+    // l and r between -1 and 1, channels between 0 and 15
+    #[inline]
+    pub fn push(&mut self, l: f32, r: f32, channels: [u8; 4]) {
+        #[allow(clippy::cast_possible_truncation)]
+        for sample in [l, r] {
+            let sample = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+            self.data.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        if self.raw_channels {
+            for out in channels {
+                // centered like the DACs, -15 to 15 in steps of 2
+                let sample = (i16::from(out) * 2 - 15) * 0x800;
+                self.data.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+    }
+
+    const fn channels(&self) -> u16 {
+        if self.raw_channels {
+            6
+        } else {
+            2
+        }
+    }
+
+    #[must_use]
+    pub fn finish(mut self) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation)]
+        let data_len = (self.data.len() - HEADER_SIZE) as u32;
+        let block_align = self.channels() * BITS_PER_SAMPLE / 8;
+        let byte_rate = self.sample_rate * u32::from(block_align);
+
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(data_len + HEADER_SIZE as u32 - 8).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16_u32.to_le_bytes());
+        header.extend_from_slice(&PCM.to_le_bytes());
+        header.extend_from_slice(&self.channels().to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&byte_rate.to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_len.to_le_bytes());
+
+        self.data[..HEADER_SIZE].copy_from_slice(&header);
+        self.data
+    }
+}
+
+impl<C: AudioCallback> Gb<C> {
+    // Records what the audio callback receives until finished, restarting
+    // the recording if one was running. Nothing is recorded while audio is
+    // disabled with set_audio_enabled.
+    #[inline]
+    pub fn start_audio_recording(&mut self, raw_channels: bool) {
+        #[allow(clippy::cast_sign_loss)]
+        let sample_rate = self.apu.sample_rate() as u32;
+        self.apu
+            .set_recorder(Some(AudioRecorder::new(sample_rate, raw_channels)));
+    }
+
+    // The WAV file, None if nothing was being recorded
+    #[must_use]
+    #[inline]
+    pub fn finish_audio_recording(&mut self) -> Option<Vec<u8>> {
+        self.apu.set_recorder(None).map(AudioRecorder::finish)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_recording_audio(&self) -> bool {
+        self.apu.is_recording()
+    }
+}
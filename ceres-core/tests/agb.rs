@@ -0,0 +1,32 @@
+// A GBA in CGB mode differs from a CGB in the register B left by the bootrom.
+// The test bootroms never unmap themselves so the program does it.
+#![cfg(feature = "bundled-bootroms")]
+
+mod common;
+
+use ceres_core::{Breakpoint, Condition, Model};
+
+const HANDOVER: [u8; 6] = [
+    0x3E, 0x01, // ld a,1
+    0xE0, 0x50, // ldh (BANK),a
+    0x18, 0xFE, // .done: jr @
+];
+
+fn b_after_handover(model: Model) -> u16 {
+    let mut gb = common::gb(model, &HANDOVER);
+    gb.add_breakpoint(Breakpoint {
+        pc: 0x154,
+        condition: Condition::Always,
+    });
+    gb.run_until_break();
+
+    gb.registers().bc >> 8
+}
+
+#[test]
+fn bootrom_increments_b() {
+    assert_eq!(
+        b_after_handover(Model::Agb),
+        b_after_handover(Model::Cgb) + 1
+    );
+}
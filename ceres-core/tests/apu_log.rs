@@ -0,0 +1,70 @@
+// APU writes decoded into registers and notes
+
+mod common;
+
+use ceres_core::{ApuChannel, Gb, Model, NoAudio, NoteTracker};
+
+fn gb() -> Gb<NoAudio> {
+    let mut gb = common::gb_with_rom(Model::Dmg, vec![0; 0x8000]);
+    gb.set_apu_logging(true);
+    gb
+}
+
+#[test]
+fn triggers_become_notes() {
+    let mut gb = gb();
+    gb.poke(0xFF26, 0x80);
+    // A4 on pulse 1 at full volume
+    gb.poke(0xFF12, 0xF0);
+    gb.poke(0xFF13, 0xD6);
+    gb.poke(0xFF14, 0x86);
+    // pulse 2 triggered with its DAC off
+    gb.poke(0xFF17, 0x00);
+    gb.poke(0xFF19, 0x80);
+
+    let writes = gb.drain_apu_writes().collect::<Vec<_>>();
+    assert_eq!(writes[3].register_name(), "NR14");
+    assert_eq!(writes[3].channel(), Some(ApuChannel::Pulse1));
+    assert_eq!(writes[0].channel(), None);
+    assert_eq!(writes[0].frame(), 0);
+
+    let mut tracker = NoteTracker::default();
+    let notes = writes
+        .into_iter()
+        .filter_map(|write| tracker.push(write))
+        .collect::<Vec<_>>();
+
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].channel, ApuChannel::Pulse1);
+    assert_eq!(notes[0].volume, 15);
+    assert!((notes[0].frequency() - 440.0).abs() < 1.0);
+}
+
+#[test]
+fn state_writes_restore_the_registers() {
+    let mut gb = gb();
+    gb.poke(0xFF26, 0x80);
+    gb.poke(0xFF24, 0x35);
+    gb.poke(0xFF25, 0xD2);
+    gb.poke(0xFF11, 0x80);
+    gb.poke(0xFF12, 0xF3);
+    gb.poke(0xFF13, 0xD6);
+    gb.poke(0xFF14, 0x86);
+    for addr in 0xFF30..=0xFF3F {
+        gb.poke(addr, addr as u8);
+    }
+    gb.poke(0xFF1A, 0x80);
+    gb.poke(0xFF1C, 0x20);
+    for _ in 0..3 {
+        gb.run_frame();
+    }
+
+    let mut replay = common::gb_with_rom(Model::Dmg, vec![0; 0x8000]);
+    for write in gb.apu_state_writes() {
+        replay.poke(0xFF00 | u16::from(write.addr), write.val);
+    }
+
+    for addr in (0xFF10..=0xFF26).chain(0xFF30..=0xFF3F) {
+        assert_eq!(gb.peek(addr), replay.peek(addr), "{addr:04X}");
+    }
+}
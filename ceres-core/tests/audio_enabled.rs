@@ -0,0 +1,72 @@
+// Turning audio off stops the samples but not the sound hardware
+
+use {
+    ceres_core::{AudioCallback, Cart, Gb, Model},
+    std::sync::mpsc::{channel, Receiver, Sender},
+};
+
+struct Counter(Sender<()>);
+
+impl AudioCallback for Counter {
+    type Sample = f32;
+
+    fn audio_sample(&self, _l: f32, _r: f32) {
+        self.0.send(()).unwrap();
+    }
+}
+
+fn gb() -> (Gb<Counter>, Receiver<()>) {
+    let mut rom = vec![0; 0x8000];
+    // nop, jp $0150
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    // jr @
+    rom[0x150..0x152].copy_from_slice(&[0x18, 0xFE]);
+
+    let cart = Cart::new(rom.into_boxed_slice()).unwrap();
+    let (tx, rx) = channel();
+    (Gb::new(Model::Dmg, 48000, cart, Counter(tx)), rx)
+}
+
+// Pulse 1 with a length of 32 steps of 1/256 s, about eight frames
+fn play_short_note(gb: &mut Gb<Counter>) {
+    gb.poke(0xFF26, 0x80);
+    gb.poke(0xFF11, 0x20);
+    gb.poke(0xFF12, 0xF0);
+    gb.poke(0xFF14, 0xC0);
+}
+
+#[test]
+fn no_samples_while_off() {
+    let (mut gb, samples) = gb();
+    gb.run_frame();
+    assert!(samples.try_iter().count() > 0);
+
+    gb.set_audio_enabled(false);
+    for _ in 0..10 {
+        gb.run_frame();
+    }
+    assert_eq!(samples.try_iter().count(), 0);
+
+    gb.set_audio_enabled(true);
+    gb.run_frame();
+    assert!(samples.try_iter().count() > 0);
+}
+
+#[test]
+fn channels_keep_running_while_off() {
+    let (mut on, _on_samples) = gb();
+    let (mut off, _off_samples) = gb();
+    off.set_audio_enabled(false);
+    play_short_note(&mut on);
+    play_short_note(&mut off);
+
+    assert_eq!(off.peek(0xFF26) & 1, 1);
+    for _ in 0..20 {
+        on.run_frame();
+        off.run_frame();
+        assert_eq!(on.peek(0xFF26), off.peek(0xFF26));
+    }
+    // the length timer ran out
+    assert_eq!(off.peek(0xFF26) & 1, 0);
+    assert!(on.diff_state(&off).is_empty());
+}
@@ -0,0 +1,38 @@
+// Bank tracking follows MBC writes that change the mapped ROM banks
+
+mod common;
+
+use ceres_core::Model;
+
+// Maps bank 2, then bank 3 and waits there
+const SWITCH: [u8; 11] = [
+    0x3E, 0x02, // ld a,2
+    0xEA, 0x00, 0x20, // ld ($2000),a
+    0x3C, // inc a
+    0xEA, 0x00, 0x20, // ld ($2000),a
+    0x18, 0xFE, // jr @
+];
+
+#[test]
+fn switches_and_residency() {
+    // MBC1, 128 KiB
+    let mut rom = vec![0; 0x2_0000];
+    rom[0x147] = 0x01;
+    rom[0x148] = 0x02;
+
+    // nop, jp $0150
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    rom[0x150..0x150 + SWITCH.len()].copy_from_slice(&SWITCH);
+
+    let mut gb = common::gb_with_rom(Model::Dmg, rom);
+    gb.set_bank_tracking(true);
+    gb.run_frame();
+
+    let usage = gb.bank_usage();
+    let banks = usage.history().map(|s| s.high).collect::<Vec<_>>();
+    assert_eq!(banks, [2, 3]);
+
+    let resident = usage.resident_dots();
+    assert_eq!(resident.iter().sum::<u64>(), usage.dots());
+    assert!(resident[3] > resident[2]);
+}
@@ -0,0 +1,255 @@
+// States exported by other emulators in the BESS format
+
+mod common;
+
+use ceres_core::{bess_thumbnail, BessError, Gb, Model, NoAudio, Registers, StateField, PX_WIDTH};
+
+const TITLE: &[u8] = b"BESS TEST";
+
+fn gb(model: Model) -> Gb<NoAudio> {
+    let mut rom = vec![0; 0x8000];
+    // jr -2
+    rom[0x100..0x102].copy_from_slice(&[0x18, 0xFE]);
+    rom[0x134..0x134 + TITLE.len()].copy_from_slice(TITLE);
+
+    common::gb_with_rom(model, rom)
+}
+
+fn block(state: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    state.extend_from_slice(id);
+    state.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+    state.extend_from_slice(data);
+}
+
+// A DMG state with the given title, WRAM filled with 0x42 and PC at 0x1234
+fn state(title: &[u8]) -> Vec<u8> {
+    state_with_blocks(title, &[])
+}
+
+// Like state, with more blocks between CORE and END
+fn state_with_blocks(title: &[u8], extra: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    let wram = vec![0x42; 0x2000];
+    let vram = vec![0; 0x2000];
+    let oam = vec![0; 0xA0];
+    let hram = vec![0x24; 0x7F];
+
+    // buffers first, like SameBoy does
+    let mut state = Vec::new();
+    let mut buffers = Vec::new();
+    for buffer in [&wram, &vram, &vec![], &oam, &hram, &vec![], &vec![]] {
+        buffers.extend_from_slice(&u32::try_from(buffer.len()).unwrap().to_le_bytes());
+        buffers.extend_from_slice(&u32::try_from(state.len()).unwrap().to_le_bytes());
+        state.extend_from_slice(buffer);
+    }
+    let first_block = u32::try_from(state.len()).unwrap();
+
+    let mut info = [0; 0x12];
+    info[..title.len()].copy_from_slice(title);
+    block(&mut state, b"NAME", b"Other emulator");
+    block(&mut state, b"INFO", &info);
+
+    let mut core = vec![0; 0x18];
+    core[..2].copy_from_slice(&1_u16.to_le_bytes());
+    core[2..4].copy_from_slice(&1_u16.to_le_bytes());
+    core[4..8].copy_from_slice(b"GD  ");
+    for (i, reg) in [0x1234_u16, 0x01B0, 0x0013, 0x00D8, 0x014D, 0xFFFE]
+        .into_iter()
+        .enumerate()
+    {
+        core[8 + i * 2..10 + i * 2].copy_from_slice(&reg.to_le_bytes());
+    }
+    let mut io = [0; 0x80];
+    // boot done, LCD off
+    io[0x50] = 1;
+    core.extend_from_slice(&io);
+    core.extend_from_slice(&buffers);
+    block(&mut state, b"CORE", &core);
+    for (id, data) in extra {
+        block(&mut state, id, data);
+    }
+    block(&mut state, b"END ", &[]);
+
+    state.extend_from_slice(&first_block.to_le_bytes());
+    state.extend_from_slice(b"BESS");
+    state
+}
+
+#[test]
+fn restores_registers_and_memory() {
+    let mut gb = gb(Model::Dmg);
+    gb.run_frame();
+
+    gb.load_bess(&state(TITLE), 0).unwrap();
+
+    assert_eq!(
+        gb.registers(),
+        Registers {
+            af: 0x01B0,
+            bc: 0x0013,
+            de: 0x00D8,
+            hl: 0x014D,
+            sp: 0xFFFE,
+            pc: 0x1234,
+        }
+    );
+    assert_eq!(gb.peek(0xC000), 0x42);
+    assert_eq!(gb.peek(0xDFFF), 0x42);
+    assert_eq!(gb.peek(0xFF80), 0x24);
+}
+
+#[test]
+fn rejects_states_of_other_cartridges() {
+    let mut gb = gb(Model::Dmg);
+    let before = gb.registers();
+
+    assert_eq!(
+        gb.load_bess(&state(b"OTHER GAME"), 0),
+        Err(BessError::DifferentRom {
+            title: "OTHER GAME".into()
+        })
+    );
+    assert_eq!(gb.registers(), before);
+}
+
+#[test]
+fn rejects_states_of_other_families() {
+    let mut gb = gb(Model::Cgb);
+
+    assert_eq!(
+        gb.load_bess(&state(TITLE), 0),
+        Err(BessError::DifferentModel)
+    );
+}
+
+#[test]
+fn rejects_other_files() {
+    let mut gb = gb(Model::Dmg);
+    let mut state = state(TITLE);
+
+    assert_eq!(gb.load_bess(&[0; 0x100], 0), Err(BessError::NotBess));
+
+    // footer pointing past the end
+    let footer = state.len() - 8;
+    state[footer..footer + 4].copy_from_slice(&0xFFFF_u32.to_le_bytes());
+    assert_eq!(gb.load_bess(&state, 0), Err(BessError::Corrupted));
+}
+
+#[test]
+fn skips_blocks_it_doesnt_know() {
+    let mut gb = gb(Model::Dmg);
+
+    // what SameBoy adds for SGB and CGB consoles, and a block from the future
+    let state = state_with_blocks(
+        TITLE,
+        &[
+            (b"XOAM", &[0; 0x60]),
+            (b"SGB ", &[0; 0x39]),
+            (b"NEW!", b"anything"),
+        ],
+    );
+    gb.load_bess(&state, 0).unwrap();
+
+    assert_eq!(gb.registers().pc, 0x1234);
+    assert_eq!(gb.peek(0xC000), 0x42);
+}
+
+// The ids of every block in order, following the footer like a reader would
+fn block_ids(state: &[u8]) -> Vec<[u8; 4]> {
+    let footer = state.len() - 8;
+    assert_eq!(&state[footer + 4..], b"BESS");
+
+    let mut i = u32::from_le_bytes(state[footer..footer + 4].try_into().unwrap()) as usize;
+    let mut ids = Vec::new();
+    loop {
+        let id: [u8; 4] = state[i..i + 4].try_into().unwrap();
+        let len = u32::from_le_bytes(state[i + 4..i + 8].try_into().unwrap()) as usize;
+        ids.push(id);
+        if &id == b"END " {
+            return ids;
+        }
+        i += 8 + len;
+    }
+}
+
+#[test]
+fn exports_the_blocks_other_emulators_expect() {
+    assert_eq!(
+        block_ids(&gb(Model::Dmg).save_bess(0)),
+        [*b"NAME", *b"INFO", *b"CORE", *b"THMB", *b"END "]
+    );
+
+    // MBC3 with a clock
+    let mut rom = vec![0; 0x8000];
+    rom[0x100..0x102].copy_from_slice(&[0x18, 0xFE]);
+    rom[0x147] = 0x0F;
+    let gb = common::gb_with_rom(Model::Dmg, rom);
+
+    assert_eq!(
+        block_ids(&gb.save_bess(0)),
+        [*b"NAME", *b"INFO", *b"CORE", *b"MBC ", *b"RTC ", *b"THMB", *b"END "]
+    );
+}
+
+#[test]
+fn exported_states_load_back() {
+    for model in [Model::Dmg, Model::Cgb] {
+        let mut gb = gb(model);
+        for _ in 0..10 {
+            gb.run_frame();
+        }
+        gb.poke(0xC123, 0x12);
+        gb.poke(0xFF90, 0x34);
+        // background palette 1, color 2
+        gb.poke(0xFF68, 0x8C);
+        gb.poke(0xFF69, 0x56);
+        // SC reads its unused bits as set only once written, as loading does
+        gb.poke(0xFF02, 0);
+        let state = gb.save_bess(0);
+
+        let mut loaded = self::gb(model);
+        loaded.load_bess(&state, 0).unwrap();
+
+        assert_eq!(loaded.registers(), gb.registers());
+        assert_eq!(loaded.peek(0xC123), 0x12);
+        assert_eq!(loaded.peek(0xFF90), 0x34);
+        // timing inside the frame isn't saved, LY and STAT start over, and
+        // HDMA5 reads like after a finished transfer
+        assert!(gb
+            .diff_state(&loaded)
+            .mismatches()
+            .iter()
+            .all(|m| matches!(m.field, StateField::Io(0xFF41 | 0xFF44 | 0xFF55))));
+    }
+}
+
+#[test]
+fn exported_states_carry_a_thumbnail() {
+    let mut gb = gb(Model::Cgb);
+    for _ in 0..300 {
+        gb.run_frame();
+    }
+    let state = gb.save_bess(0);
+    let thumbnail = bess_thumbnail(&state).unwrap().unwrap();
+
+    assert_eq!((thumbnail.width, thumbnail.height), (80, 72));
+    assert_eq!(thumbnail.rgba.len(), 80 * 72 * 4);
+    assert!(thumbnail.rgba.chunks(4).all(|px| px[3] == 0xFF));
+
+    // the top left pixel averages the first two pixels of the first two rows
+    let screen = gb.pixel_data_rgb();
+    let row = usize::from(PX_WIDTH) * 3;
+    let sum: u16 = [0, 3, row, row + 3]
+        .into_iter()
+        .map(|i| u16::from(screen[i]))
+        .sum();
+    assert_eq!(u16::from(thumbnail.rgba[0]), sum / 4);
+}
+
+#[test]
+fn states_from_other_emulators_have_no_thumbnail() {
+    assert_eq!(bess_thumbnail(&state_with_blocks(TITLE, &[])), Ok(None));
+
+    // one pixel announced, none there
+    let state = state_with_blocks(TITLE, &[(b"THMB", &[1, 0, 1, 0])]);
+    assert_eq!(bess_thumbnail(&state), Err(BessError::Corrupted));
+}
@@ -0,0 +1,119 @@
+// Bootroms supplied at runtime instead of the bundled ones
+
+mod common;
+
+use ceres_core::{BootromError, Gb, Model, NoAudio};
+
+fn gb(model: Model) -> Gb<NoAudio> {
+    let mut rom = vec![0; 0x8000];
+    // jr @
+    rom[0x100..0x102].copy_from_slice(&[0x18, 0xFE]);
+
+    common::gb_with_rom(model, rom)
+}
+
+// Sets C and hands over at 0x100 like the real ones
+fn custom_bootrom() -> Box<[u8]> {
+    let mut bootrom = vec![0; 0x100];
+    // ld c,$42
+    bootrom[..2].copy_from_slice(&[0x0E, 0x42]);
+    // ld a,$01; ldh (BANK),a
+    bootrom[0xFC..].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x50]);
+    bootrom.into_boxed_slice()
+}
+
+#[test]
+fn supplied_bootrom_runs_first() {
+    let mut gb = gb(Model::Dmg);
+    gb.set_bootrom(custom_bootrom()).unwrap();
+    gb.run_frame();
+
+    let registers = gb.registers();
+    assert_eq!(registers.bc & 0xFF, 0x42);
+    assert_eq!(registers.pc, 0x100);
+    // the cartridge shows through once it's unmapped
+    assert_eq!(gb.peek(0x0000), 0x00);
+}
+
+#[test]
+fn size_depends_on_the_model() {
+    let mut gb = gb(Model::Cgb);
+
+    assert_eq!(
+        gb.set_bootrom(custom_bootrom()),
+        Err(BootromError::WrongSize {
+            expected: 0x900,
+            found: 0x100
+        })
+    );
+    assert_eq!(gb.set_bootrom(vec![0; 0x900].into_boxed_slice()), Ok(()));
+}
+
+// Without a bootrom the cartridge starts where the bootrom would hand over
+#[cfg(not(feature = "bundled-bootroms"))]
+#[test]
+fn starts_at_the_cartridge_without_bootroms() {
+    const LCDC: u16 = 0xFF40;
+
+    for model in [Model::Dmg, Model::Sgb, Model::Cgb, Model::Agb] {
+        let mut gb = gb(model);
+        gb.run_frame();
+
+        let registers = gb.registers();
+        assert_eq!(registers.pc, 0x100, "{model:?}");
+        assert_eq!(registers.sp, 0xFFFE, "{model:?}");
+        assert_eq!(gb.peek(LCDC), 0x91, "{model:?}");
+    }
+
+    // a DMG game on a CGB, with B telling the GBA apart
+    assert_eq!(gb(Model::Cgb).registers().af >> 8, 0x11);
+    assert_eq!(gb(Model::Cgb).registers().bc >> 8, 0x00);
+    assert_eq!(gb(Model::Agb).registers().bc >> 8, 0x01);
+}
+
+// The registers each bootrom leaves for a CGB enhanced cartridge with a
+// header checksum that isn't zero
+#[cfg(not(feature = "bundled-bootroms"))]
+#[test]
+fn registers_depend_on_the_model() {
+    use ceres_core::Registers;
+
+    let mut rom = vec![0; 0x8000];
+    rom[0x143] = 0x80;
+    rom[0x14D] = 0x01;
+
+    for (model, af, bc, de, hl) in [
+        (Model::Dmg0, 0x0100, 0xFF13, 0x00C1, 0x8403),
+        (Model::Dmg, 0x01B0, 0x0013, 0x00D8, 0x014D),
+        (Model::Mgb, 0xFFB0, 0x0013, 0x00D8, 0x014D),
+        (Model::Sgb, 0x0100, 0x0014, 0x0000, 0xC060),
+        (Model::Sgb2, 0xFF00, 0x0014, 0x0000, 0xC060),
+        (Model::Cgb0, 0x1180, 0x0000, 0xFF56, 0x000D),
+        (Model::Cgb, 0x1180, 0x0000, 0xFF56, 0x000D),
+        (Model::CgbE, 0x1180, 0x0000, 0xFF56, 0x000D),
+        (Model::Agb, 0x1100, 0x0100, 0xFF56, 0x000D),
+    ] {
+        assert_eq!(
+            common::gb_with_rom(model, rom.clone()).registers(),
+            Registers {
+                af,
+                bc,
+                de,
+                hl,
+                sp: 0xFFFE,
+                pc: 0x0100,
+            },
+            "{model:?}"
+        );
+    }
+}
+
+#[cfg(not(feature = "bundled-bootroms"))]
+#[test]
+fn supplied_bootrom_starts_from_power_on() {
+    let mut gb = gb(Model::Dmg);
+    gb.set_bootrom(custom_bootrom()).unwrap();
+
+    assert_eq!(gb.registers().pc, 0x0000);
+    assert_eq!(gb.peek(0xFF40), 0x00);
+}
@@ -0,0 +1,82 @@
+// Channels left out of the mix by the frontend, unknown to the game
+
+use {
+    ceres_core::{ApuChannel, AudioCallback, Cart, Gb, Model},
+    std::sync::mpsc::{channel, Receiver, Sender},
+};
+
+struct Recorder(Sender<(f32, f32)>);
+
+impl AudioCallback for Recorder {
+    type Sample = f32;
+
+    fn audio_sample(&self, l: f32, r: f32) {
+        self.0.send((l, r)).unwrap();
+    }
+}
+
+fn gb() -> (Gb<Recorder>, Receiver<(f32, f32)>) {
+    let mut rom = vec![0; 0x8000];
+    // nop, jp $0150
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    // jr @
+    rom[0x150..0x152].copy_from_slice(&[0x18, 0xFE]);
+
+    let cart = Cart::new(rom.into_boxed_slice()).unwrap();
+    let (tx, rx) = channel();
+    (Gb::new(Model::Dmg, 48000, cart, Recorder(tx)), rx)
+}
+
+// Every channel to both sides at full volume
+fn apu_on(gb: &mut Gb<Recorder>) {
+    gb.poke(0xFF26, 0x80);
+    gb.poke(0xFF24, 0x77);
+    gb.poke(0xFF25, 0xFF);
+}
+
+// Pulse 1 held, at volume 0 with its DAC still on
+fn play_pulse_1(gb: &mut Gb<Recorder>, volume: u8) {
+    apu_on(gb);
+    gb.poke(0xFF12, volume << 4 | 0x08);
+    gb.poke(0xFF13, 0xD6);
+    gb.poke(0xFF14, 0x86);
+}
+
+fn samples(gb: &mut Gb<Recorder>, rx: &Receiver<(f32, f32)>) -> Vec<(f32, f32)> {
+    for _ in 0..5 {
+        gb.run_frame();
+    }
+    rx.try_iter().collect()
+}
+
+#[test]
+fn muted_channels_are_silent_but_run() {
+    let (mut muted, muted_rx) = gb();
+    let (mut silent, silent_rx) = gb();
+    let (mut heard, heard_rx) = gb();
+
+    muted.set_channel_enabled(ApuChannel::Pulse1, false);
+    assert!(!muted.channel_enabled(ApuChannel::Pulse1));
+    play_pulse_1(&mut muted, 15);
+    play_pulse_1(&mut silent, 0);
+    play_pulse_1(&mut heard, 15);
+
+    let muted_samples = samples(&mut muted, &muted_rx);
+    assert_eq!(muted_samples, samples(&mut silent, &silent_rx));
+    assert_ne!(muted_samples, samples(&mut heard, &heard_rx));
+    // still playing as far as the game knows
+    assert_eq!(muted.peek(0xFF26) & 1, 1);
+}
+
+#[test]
+fn solo_mutes_the_rest() {
+    let (mut gb, _rx) = gb();
+    gb.solo_channel(ApuChannel::Wave);
+
+    for channel in ApuChannel::ALL {
+        assert_eq!(gb.channel_enabled(channel), channel == ApuChannel::Wave);
+    }
+
+    gb.set_channel_enabled(ApuChannel::Pulse2, true);
+    assert!(gb.channel_enabled(ApuChannel::Pulse2));
+}
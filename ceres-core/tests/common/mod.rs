@@ -0,0 +1,23 @@
+// Helpers shared by the integration tests, each test only uses some of them
+#![allow(dead_code)]
+
+use ceres_core::{Cart, Gb, Model, NoAudio};
+
+// A 32 KiB ROM that skips the header and runs program from $0150
+pub fn rom(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+
+    // nop, jp $0150
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    rom[0x150..0x150 + program.len()].copy_from_slice(program);
+    rom
+}
+
+pub fn gb_with_rom(model: Model, rom: Vec<u8>) -> Gb<NoAudio> {
+    let cart = Cart::new(rom.into_boxed_slice()).unwrap();
+    Gb::new(model, 48000, cart, NoAudio)
+}
+
+pub fn gb(model: Model, program: &[u8]) -> Gb<NoAudio> {
+    gb_with_rom(model, rom(program))
+}
@@ -0,0 +1,165 @@
+// Breakpoints stop before the instruction at their PC, watchpoints right
+// after the instruction touching the watched memory
+
+mod common;
+
+use ceres_core::{
+    Access, Breakpoint, Button, Condition, Gb, Model, NoAudio, Register, StopReason, Watchpoint,
+};
+
+const COUNTER: u16 = 0xC000;
+const LOOP: u16 = 0x150;
+const STORE: u16 = 0x152;
+
+// Counts up in A forever, storing it
+const COUNT: [u8; 7] = [
+    0x3C, // .loop: inc a
+    0x00, // nop
+    0xEA, 0x00, 0xC0, // ld (COUNTER),a
+    0x18, 0xF9, // jr .loop
+];
+
+// Calls a subroutine that calls itself 3 times
+const RECURSE: [u8; 12] = [
+    0x06, 0x03, // ld b,3
+    0xCD, 0x57, 0x01, // call .sub
+    0x18, 0xFE, // jr @
+    0x05, // .sub: dec b
+    0xC4, 0x57, 0x01, // call nz,.sub
+    0xC9, // ret
+];
+
+// Spins for a bit over 3 frames, then hits ld b,b
+const DELAY: [u8; 11] = [
+    0x01, 0x00, 0x20, // ld bc,$2000
+    0x0B, // .loop: dec bc
+    0x78, // ld a,b
+    0xB1, // or c
+    0x20, 0xFB, // jr nz,.loop
+    0x40, // ld b,b
+    0x18, 0xFE, // jr @
+];
+
+fn gb(program: &[u8]) -> Gb<NoAudio> {
+    common::gb(Model::Dmg, program)
+}
+
+#[test]
+fn conditional_breakpoint() {
+    let mut gb = gb(&COUNT);
+    let id = gb.add_breakpoint(Breakpoint {
+        pc: STORE,
+        condition: Condition::Register(Register::A, 0x42),
+    });
+
+    assert_eq!(
+        gb.run_until_break(),
+        StopReason::DebugBreakpoint { id, pc: STORE }
+    );
+    assert_eq!(gb.registers().af >> 8, 0x42);
+    assert_eq!(gb.peek(COUNTER), 0x41);
+
+    // resuming runs the instruction it stopped at
+    let step = gb.add_breakpoint(Breakpoint {
+        pc: LOOP,
+        condition: Condition::Always,
+    });
+    assert_eq!(
+        gb.run_until_break(),
+        StopReason::DebugBreakpoint { id: step, pc: LOOP }
+    );
+    assert_eq!(gb.peek(COUNTER), 0x42);
+
+    assert!(gb.remove_breakpoint(id));
+    assert!(!gb.remove_breakpoint(id));
+}
+
+#[test]
+fn write_watchpoint() {
+    let mut gb = gb(&COUNT);
+    let id = gb.add_watchpoint(Watchpoint {
+        start: COUNTER,
+        end: COUNTER,
+        access: Access::Write,
+    });
+
+    let reason = gb.run_until_break();
+    // A is where the bootrom left it, plus one
+    let stored = (gb.registers().af >> 8) as u8;
+    assert_eq!(
+        reason,
+        StopReason::Watchpoint {
+            id,
+            addr: COUNTER,
+            val: stored,
+            write: true,
+        }
+    );
+    assert_eq!(gb.registers().pc, STORE + 3);
+
+    gb.clear_breakpoints();
+    assert_eq!(gb.watchpoints().count(), 0);
+}
+
+#[test]
+fn stepping() {
+    let mut gb = gb(&RECURSE);
+    let start = gb.add_breakpoint(Breakpoint {
+        pc: LOOP,
+        condition: Condition::Always,
+    });
+    gb.run_until_break();
+    gb.remove_breakpoint(start);
+
+    assert_eq!(gb.step_instruction(), None);
+    assert_eq!(gb.registers().pc, LOOP + 2);
+
+    // the whole recursion runs as one step
+    assert_eq!(gb.step_over(), None);
+    assert_eq!(gb.registers().pc, LOOP + 5);
+    assert_eq!(gb.registers().bc >> 8, 0);
+
+    assert_eq!(gb.step_instruction(), None);
+    assert_eq!(gb.registers().pc, LOOP + 5);
+}
+
+#[test]
+fn resuming_only_skips_the_next_instruction() {
+    let mut gb = gb(&COUNT);
+    let first = gb.add_breakpoint(Breakpoint {
+        pc: LOOP,
+        condition: Condition::Always,
+    });
+    gb.run_until_break();
+    gb.remove_breakpoint(first);
+
+    // runs without breakpoints until LOOP is next again
+    gb.run_dots(1);
+    while gb.registers().pc != LOOP {
+        gb.run_dots(1);
+    }
+
+    let a = gb.registers().af >> 8;
+    let id = gb.add_breakpoint(Breakpoint {
+        pc: LOOP,
+        condition: Condition::Always,
+    });
+    assert_eq!(
+        gb.run_until_break(),
+        StopReason::DebugBreakpoint { id, pc: LOOP }
+    );
+    assert_eq!(gb.registers().af >> 8, a);
+}
+
+#[test]
+fn frames_end_while_running_until_break() {
+    let mut gb = gb(&DELAY);
+    gb.set_ld_b_b_breakpoint(true);
+    gb.press(Button::A);
+
+    assert!(matches!(
+        gb.run_until_break(),
+        StopReason::Breakpoint { .. }
+    ));
+    assert!(gb.held_in_last_frame(Button::A));
+}
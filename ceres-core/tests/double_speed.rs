@@ -0,0 +1,75 @@
+// The CPU clock doubles in double speed, the APU keeps its pace
+
+mod common;
+
+use ceres_core::{Gb, Model, NoAudio};
+
+const DIV: u16 = 0xFF04;
+const NR52: u16 = 0xFF26;
+
+// Optionally switches speed, then plays pulse 1 for 64 length steps
+fn gb(double_speed: bool) -> Gb<NoAudio> {
+    let switch: [u8; 6] = if double_speed {
+        [
+            0x3E, 0x01, // ld a,1
+            0xE0, 0x4D, // ldh (KEY1),a
+            0x10, 0x00, // stop
+        ]
+    } else {
+        [0; 6]
+    };
+    let play = [
+        0x3E, 0x80, // ld a,$80
+        0xE0, 0x26, // ldh (NR52),a
+        0x3E, 0xF0, // ld a,$F0
+        0xE0, 0x12, // ldh (NR12),a
+        0x3E, 0x00, // ld a,0
+        0xE0, 0x11, // ldh (NR11),a
+        0x3E, 0xC0, // ld a,$C0
+        0xE0, 0x14, // ldh (NR14),a
+        0x18, 0xFE, // jr @
+    ];
+
+    let mut rom = common::rom(&[switch.as_slice(), &play].concat());
+    // double speed is only there for CGB games
+    rom[0x143] = 0x80;
+
+    common::gb_with_rom(Model::Cgb, rom)
+}
+
+fn div_ticks(gb: &mut Gb<NoAudio>, dots: i32) -> u8 {
+    let start = gb.peek(DIV);
+    gb.run_dots(dots);
+    gb.peek(DIV).wrapping_sub(start)
+}
+
+#[test]
+fn div_counts_cpu_cycles() {
+    // 64 DIV ticks at normal speed
+    let dots = 0x4000;
+
+    let mut normal = gb(false);
+    normal.run_frame();
+    assert!((63..=65).contains(&div_ticks(&mut normal, dots)));
+
+    let mut double = gb(true);
+    double.run_frame();
+    assert!((127..=129).contains(&div_ticks(&mut double, dots)));
+}
+
+#[test]
+fn length_counter_keeps_its_pace() {
+    // 64 steps at 256 Hz are a quarter of a second, a bit less than 15 frames
+    for double_speed in [false, true] {
+        let mut gb = gb(double_speed);
+        for _ in 0..14 {
+            gb.run_frame();
+        }
+        assert_eq!(gb.peek(NR52) & 1, 1, "double speed: {double_speed}");
+
+        for _ in 0..2 {
+            gb.run_frame();
+        }
+        assert_eq!(gb.peek(NR52) & 1, 0, "double speed: {double_speed}");
+    }
+}
@@ -0,0 +1,119 @@
+// Frozen memory against a program counting up in WRAM
+
+mod common;
+
+use ceres_core::{Freeze, FreezeMode, Gb, Model, NoAudio, TC_PER_FRAME};
+
+const COUNTER: u16 = 0xC000;
+
+// Counts up in A forever, storing it
+const COUNT: [u8; 6] = [
+    0x3C, // .loop: inc a
+    0xEA, 0x00, 0xC0, // ld (COUNTER),a
+    0x18, 0xFA, // jr .loop
+];
+
+fn gb() -> Gb<NoAudio> {
+    let mut gb = common::gb(Model::Dmg, &COUNT);
+    gb.poke(COUNTER, 0x63);
+    gb
+}
+
+#[test]
+fn ignored_writes_never_land() {
+    let mut gb = gb();
+    gb.freeze(Freeze {
+        start: COUNTER,
+        end: COUNTER,
+        mode: FreezeMode::IgnoreWrites,
+    })
+    .unwrap();
+
+    gb.run_dots(0x1000);
+    assert_eq!(gb.peek(COUNTER), 0x63);
+}
+
+#[test]
+fn reverted_at_the_end_of_the_frame() {
+    let mut gb = gb();
+    let id = gb
+        .freeze(Freeze {
+            start: COUNTER,
+            end: COUNTER,
+            mode: FreezeMode::Revert,
+        })
+        .unwrap();
+
+    gb.run_dots(0x1000);
+    assert_ne!(gb.peek(COUNTER), 0x63);
+    gb.run_frame();
+    assert_eq!(gb.peek(COUNTER), 0x63);
+
+    assert!(gb.unfreeze(id));
+    assert_eq!(gb.freezes().count(), 0);
+    gb.run_frame();
+    assert_ne!(gb.peek(COUNTER), 0x63);
+}
+
+#[test]
+fn reverted_when_run_dots_crosses_a_frame() {
+    // ld a,$12; ld (COUNTER),a; jr @
+    let mut gb = common::gb(Model::Dmg, &[0x3E, 0x12, 0xEA, 0x00, 0xC0, 0x18, 0xFE]);
+    gb.poke(COUNTER, 0x63);
+    gb.freeze(Freeze {
+        start: COUNTER,
+        end: COUNTER,
+        mode: FreezeMode::Revert,
+    })
+    .unwrap();
+
+    // after the bootrom, if there is one
+    let written = (0..1000).any(|_| {
+        gb.run_dots(0x100);
+        gb.peek(COUNTER) == 0x12
+    });
+    assert!(written, "the program didn't write");
+    // the program doesn't write it again
+    gb.run_dots(TC_PER_FRAME);
+    assert_eq!(gb.peek(COUNTER), 0x63);
+}
+
+#[test]
+fn only_memory_can_be_frozen() {
+    let mut gb = gb();
+    let freeze = |start, end| Freeze {
+        start,
+        end,
+        mode: FreezeMode::Revert,
+    };
+
+    // ROM, where writes switch banks, the unusable area, IO and IE
+    for (start, end) in [
+        (0x2000, 0x2000),
+        (0x7FFF, 0x8000),
+        (0xFEA0, 0xFEA0),
+        (0xFF40, 0xFF40),
+        (0xFE00, 0xFF80),
+        (0xFFFF, 0xFFFF),
+    ] {
+        assert_eq!(
+            gb.freeze(freeze(start, end)),
+            None,
+            "{start:04X}..={end:04X}"
+        );
+    }
+    assert_eq!(gb.freezes().count(), 0);
+
+    for (start, end) in [
+        (0x8000, 0x9FFF),
+        (0xA000, 0xA0FF),
+        (0xC000, 0xFDFF),
+        (0xFE00, 0xFE9F),
+        (0xFF80, 0xFFFE),
+    ] {
+        assert!(
+            gb.freeze(freeze(start, end)).is_some(),
+            "{start:04X}..={end:04X}"
+        );
+    }
+}
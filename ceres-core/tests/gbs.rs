@@ -0,0 +1,104 @@
+// GBS files play through a driver calling their init and play routines
+
+use ceres_core::{Gbs, GbsError, GbsPlayer, Model, NoAudio};
+
+const TRACK: u16 = 0xC000;
+const TICKS: u16 = 0xC001;
+
+// Three tracks starting at the second one, init stores the track and play
+// counts its calls
+fn gbs(tma: u8, tac: u8) -> Vec<u8> {
+    let mut file = vec![0; 0x70];
+    file[..4].copy_from_slice(b"GBS\x01");
+    file[4] = 3;
+    file[5] = 2;
+    // load, init, play, sp
+    file[6..14].copy_from_slice(&[0x00, 0x04, 0x00, 0x04, 0x08, 0x04, 0xFE, 0xFF]);
+    file[14] = tma;
+    file[15] = tac;
+    file[0x10..0x15].copy_from_slice(b"Tunes");
+
+    file.extend_from_slice(&[
+        0xEA, 0x00, 0xC0, // ld (TRACK),a
+        0xC9, // ret
+        0x00, 0x00, 0x00, 0x00, // padding
+        0x21, 0x01, 0xC0, // ld hl,TICKS
+        0x34, // inc (hl)
+        0xC9, // ret
+    ]);
+    file
+}
+
+fn player(model: Model, tma: u8, tac: u8) -> GbsPlayer<NoAudio> {
+    let gbs = Gbs::new(gbs(tma, tac).into_boxed_slice()).unwrap();
+    GbsPlayer::new(gbs, model, 48000, NoAudio)
+}
+
+fn run(player: &mut GbsPlayer<NoAudio>, frames: usize) {
+    (0..frames).for_each(|_| player.run_frame());
+}
+
+#[test]
+fn plays_on_vblank() {
+    let mut player = player(Model::Dmg, 0, 0);
+    assert_eq!(player.gbs().title(), "Tunes");
+    assert_eq!(player.track(), 1);
+
+    run(&mut player, 60);
+    assert_eq!(player.gb().peek(TRACK), 1);
+    assert!((59..=61).contains(&player.gb().peek(TICKS)));
+}
+
+#[test]
+fn plays_on_the_timer() {
+    // 4096 Hz over 256 counts
+    let mut player = player(Model::Dmg, 0, 0x04);
+
+    run(&mut player, 60);
+    assert!((15..=17).contains(&player.gb().peek(TICKS)));
+}
+
+#[test]
+fn double_speed_doubles_the_timer() {
+    let mut player = player(Model::Cgb, 0, 0x84);
+    player.next_track();
+
+    run(&mut player, 60);
+    assert!((31..=33).contains(&player.gb().peek(TICKS)));
+}
+
+#[test]
+fn tracks_wrap_around() {
+    let mut player = player(Model::Dmg, 0, 0);
+    run(&mut player, 10);
+
+    player.next_track();
+    assert_eq!(player.track(), 2);
+    run(&mut player, 1);
+    assert_eq!(player.gb().peek(TRACK), 2);
+    assert!(player.gb().peek(TICKS) <= 1);
+
+    player.next_track();
+    assert_eq!(player.track(), 0);
+    player.previous_track();
+    assert_eq!(player.track(), 2);
+}
+
+#[test]
+fn bad_files_are_rejected() {
+    let error = |file: Vec<u8>| Gbs::new(file.into_boxed_slice()).err();
+
+    assert_eq!(error(vec![0; 0x10]), Some(GbsError::MissingHeader));
+
+    let mut not_gbs = gbs(0, 0);
+    not_gbs[0] = b'X';
+    assert_eq!(error(not_gbs), Some(GbsError::NotGbs));
+
+    let mut no_tracks = gbs(0, 0);
+    no_tracks[4] = 0;
+    assert_eq!(error(no_tracks), Some(GbsError::NoTracks));
+
+    let mut low_load = gbs(0, 0);
+    low_load[7] = 0x01;
+    assert_eq!(error(low_load), Some(GbsError::LoadAddressTooLow(0x100)));
+}
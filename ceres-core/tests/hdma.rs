@@ -0,0 +1,125 @@
+// CGB HDMA, mostly HBlank transfers around the LCD being turned off and on
+
+mod common;
+
+use ceres_core::{Gb, MemoryRegion, Model, NoAudio};
+
+const LCDC: u16 = 0xFF40;
+const STAT: u16 = 0xFF41;
+const HDMA1: u16 = 0xFF51;
+const HDMA5: u16 = 0xFF55;
+const SRC: u16 = 0xC000;
+
+fn gb() -> Gb<NoAudio> {
+    // jr @
+    let mut rom = common::rom(&[0x18, 0xFE]);
+    rom[0x143] = 0x80;
+
+    let mut gb = common::gb_with_rom(Model::Cgb, rom);
+    // past the bootrom
+    for _ in 0..300 {
+        gb.run_frame();
+    }
+
+    for i in 0..0x40 {
+        gb.poke(SRC + i, i as u8 + 1);
+    }
+    gb.poke(LCDC, 0x00);
+    for i in 0..0x40 {
+        gb.write_mem_byte(MemoryRegion::Vram(0), i, 0);
+    }
+
+    gb
+}
+
+// From SRC to the start of VRAM
+fn start_hblank_dma(gb: &mut Gb<NoAudio>, blocks: u8) {
+    for (i, val) in [0xC0, 0x00, 0x00, 0x00].into_iter().enumerate() {
+        gb.poke(HDMA1 + i as u16, val);
+    }
+    gb.poke(HDMA5, 0x80 | (blocks - 1));
+}
+
+fn blocks_copied(gb: &Gb<NoAudio>) -> usize {
+    gb.region_bytes(MemoryRegion::Vram(0))[..0x40]
+        .chunks(0x10)
+        .take_while(|block| block.iter().all(|&b| b != 0))
+        .count()
+}
+
+#[test]
+fn one_block_right_away_with_the_lcd_off() {
+    let mut gb = gb();
+    start_hblank_dma(&mut gb, 4);
+    gb.step_instruction();
+
+    assert_eq!(blocks_copied(&gb), 1);
+    // three blocks left, still active
+    assert_eq!(gb.peek(HDMA5), 0x02);
+
+    // the rest waits for the LCD
+    gb.run_frame();
+    assert_eq!(blocks_copied(&gb), 1);
+
+    gb.poke(LCDC, 0x91);
+    gb.run_frame();
+    assert_eq!(blocks_copied(&gb), 4);
+    assert_eq!(gb.peek(HDMA5), 0xFF);
+}
+
+#[test]
+fn turning_the_lcd_off_mid_transfer() {
+    let mut gb = gb();
+    gb.poke(LCDC, 0x91);
+    // wait for a line with the LCD drawing, before HBlank
+    while gb.peek(STAT) & 3 != 3 {
+        gb.step_instruction();
+    }
+    start_hblank_dma(&mut gb, 4);
+    gb.poke(LCDC, 0x11);
+
+    assert_eq!(gb.peek(STAT) & 3, 0);
+    gb.step_instruction();
+    assert_eq!(blocks_copied(&gb), 1);
+    gb.run_frame();
+    assert_eq!(blocks_copied(&gb), 1);
+
+    // picks up where it left off
+    gb.poke(LCDC, 0x91);
+    gb.run_frame();
+    assert_eq!(blocks_copied(&gb), 4);
+    assert_eq!(gb.peek(HDMA5), 0xFF);
+}
+
+#[test]
+fn stopping_while_the_lcd_is_off() {
+    let mut gb = gb();
+    start_hblank_dma(&mut gb, 4);
+    gb.step_instruction();
+    gb.poke(HDMA5, 0x00);
+
+    gb.poke(LCDC, 0x91);
+    gb.run_frame();
+    assert_eq!(blocks_copied(&gb), 1);
+    // inactive, with the blocks that were left
+    assert_eq!(gb.peek(HDMA5), 0x82);
+}
+
+#[test]
+fn general_dma_at_the_highest_sample_rate() {
+    // jr @
+    let mut rom = common::rom(&[0x18, 0xFE]);
+    rom[0x143] = 0x80;
+    let cart = ceres_core::Cart::new(rom.into_boxed_slice()).unwrap();
+    let (mut gb, _) = Gb::try_new(Model::Cgb, ceres_core::TC_SEC, cart, NoAudio).unwrap();
+    gb.set_exact_sample_rate(true);
+
+    // all 128 blocks in one go, thousands of dots the APU steps at once
+    for (i, val) in [0xC0, 0x00, 0x00, 0x00].into_iter().enumerate() {
+        gb.poke(HDMA1 + i as u16, val);
+    }
+    gb.poke(HDMA5, 0x7F);
+    gb.step_instruction();
+
+    assert_eq!(gb.peek(HDMA5), 0xFF);
+}
@@ -0,0 +1,28 @@
+// Buttons held during a frame, as input displays show them
+
+mod common;
+
+use ceres_core::{Button, Model};
+
+#[test]
+fn presses_shorter_than_a_frame_show() {
+    let mut gb = common::gb_with_rom(Model::Dmg, vec![0; 0x8000]);
+
+    gb.press(Button::A);
+    gb.release(Button::A);
+    gb.press(Button::Left);
+    gb.run_frame();
+    assert!(gb.held_in_last_frame(Button::A));
+    assert!(gb.held_in_last_frame(Button::Left));
+
+    // still held
+    gb.run_frame();
+    assert!(!gb.held_in_last_frame(Button::A));
+    assert!(gb.held_in_last_frame(Button::Left));
+
+    gb.release(Button::Left);
+    gb.run_frame();
+    assert!(gb.held_in_last_frame(Button::Left));
+    gb.run_frame();
+    assert!(!gb.held_in_last_frame(Button::Left));
+}
@@ -0,0 +1,264 @@
+// Devices plugged into the link port, driven by a program through SB/SC
+
+mod common;
+
+use ceres_core::{
+    BarcodeBoy, DebugConsole, Gb, LinkPort, Model, NoAudio, SerialDevice, StopReason, Workboy,
+    WorkboyClock,
+};
+use std::sync::mpsc::{channel, Sender};
+
+const RECEIVED: u16 = 0xC000;
+const HANDSHAKE_DONE: u16 = 0xC010;
+
+// Clocks out the Barcode Boy handshake keeping the replies, then waits for
+// 15 bytes clocked by the scanner and stops at ld b,b
+const SCAN: [u8; 52] = [
+    0x21, 0x00, 0xC0, // ld hl,RECEIVED
+    0x11, 0x90, 0x01, // ld de,handshake
+    0x06, 0x04, // ld b,4
+    0x1A, // .send: ld a,(de)
+    0x13, // inc de
+    0xE0, 0x01, // ldh (SB),a
+    0x3E, 0x81, // ld a,$81
+    0xE0, 0x02, // ldh (SC),a
+    0xF0, 0x02, // .wait_send: ldh a,(SC)
+    0x87, // add a,a
+    0x38, 0xFB, // jr c,.wait_send
+    0xF0, 0x01, // ldh a,(SB)
+    0x22, // ld (hl+),a
+    0x05, // dec b
+    0x20, 0xED, // jr nz,.send
+    0x3E, 0x01, // ld a,1
+    0xEA, 0x10, 0xC0, // ld (HANDSHAKE_DONE),a
+    0x06, 0x0F, // ld b,15
+    0x3E, 0x80, // .receive: ld a,$80
+    0xE0, 0x02, // ldh (SC),a
+    0xF0, 0x02, // .wait_receive: ldh a,(SC)
+    0x87, // add a,a
+    0x38, 0xFB, // jr c,.wait_receive
+    0xF0, 0x01, // ldh a,(SB)
+    0x22, // ld (hl+),a
+    0x05, // dec b
+    0x20, 0xF1, // jr nz,.receive
+    0x40, // ld b,b
+    0x18, 0xFE, // jr @
+];
+const HANDSHAKE: [u8; 4] = [0x10, 0x07, 0x10, 0x07];
+
+// Clocks out the bytes at $0190 keeping the replies, then stops at ld b,b.
+// How many goes at $0157.
+const EXCHANGE: [u8; 30] = [
+    0x21, 0x00, 0xC0, // ld hl,RECEIVED
+    0x11, 0x90, 0x01, // ld de,bytes
+    0x0E, 0x00, // ld c,count
+    0x1A, // .send: ld a,(de)
+    0x13, // inc de
+    0xE0, 0x01, // ldh (SB),a
+    0x3E, 0x81, // ld a,$81
+    0xE0, 0x02, // ldh (SC),a
+    0xF0, 0x02, // .wait: ldh a,(SC)
+    0x87, // add a,a
+    0x38, 0xFB, // jr c,.wait
+    0xF0, 0x01, // ldh a,(SB)
+    0x22, // ld (hl+),a
+    0x0D, // dec c
+    0x20, 0xED, // jr nz,.send
+    0x40, // ld b,b
+    0x18, 0xFE, // jr @
+];
+
+fn gb() -> Gb<NoAudio> {
+    gb_sending(HANDSHAKE)
+}
+
+// SCAN clocking out other bytes than the handshake
+fn gb_sending(sent: [u8; 4]) -> Gb<NoAudio> {
+    let mut rom = common::rom(&SCAN);
+    rom[0x190..0x194].copy_from_slice(&sent);
+
+    let mut gb = common::gb_with_rom(Model::Dmg, rom);
+    gb.set_ld_b_b_breakpoint(true);
+    gb
+}
+
+fn swipe(gb: &mut Gb<NoAudio>, barcode: &str) -> bool {
+    if let LinkPort::BarcodeBoy(bcb) = gb.link_port_mut() {
+        bcb.scan(barcode)
+    } else {
+        false
+    }
+}
+
+fn console_text(gb: &mut Gb<NoAudio>) -> Option<String> {
+    if let LinkPort::DebugConsole(console) = gb.link_port_mut() {
+        Some(console.take_text())
+    } else {
+        None
+    }
+}
+
+#[test]
+fn barcode_boy_sends_scanned_code() {
+    let mut gb = gb();
+    gb.set_link_port(LinkPort::BarcodeBoy(BarcodeBoy::default()));
+
+    let ready = (0..10).any(|_| {
+        gb.run_frame();
+        gb.peek(HANDSHAKE_DONE) == 1
+    });
+    assert!(ready, "handshake didn't finish");
+
+    let replies: Vec<u8> = (0..4).map(|i| gb.peek(RECEIVED + i)).collect();
+    assert_eq!(replies, [0xFF, 0xFF, 0x10, 0x07]);
+
+    assert!(!swipe(&mut gb, "12345"));
+    assert!(swipe(&mut gb, "4902370503616"));
+
+    let stopped = (0..10).any(|_| matches!(gb.run_frame(), StopReason::Breakpoint { .. }));
+    assert!(stopped, "barcode wasn't received");
+
+    let code: Vec<u8> = (4..19).map(|i| gb.peek(RECEIVED + i)).collect();
+    assert_eq!(code, b"\x024902370503616\x03");
+}
+
+#[test]
+fn disconnected_port_reads_ones_and_never_clocks() {
+    let mut gb = gb();
+
+    (0..10).for_each(|_| {
+        gb.run_frame();
+    });
+
+    let replies: Vec<u8> = (0..4).map(|i| gb.peek(RECEIVED + i)).collect();
+    assert_eq!(replies, [0xFF; 4]);
+    assert_eq!(gb.peek(RECEIVED + 4), 0, "external clock transfer finished");
+}
+
+// Answers every bit with its complement, reporting the bits it was sent
+struct Inverter(Sender<bool>);
+
+impl SerialDevice for Inverter {
+    fn exchange_bit(&mut self, sent: bool) -> bool {
+        self.0.send(sent).unwrap();
+        !sent
+    }
+}
+
+#[test]
+fn device_exchanges_bit_by_bit() {
+    let mut gb = gb();
+    let (tx, rx) = channel();
+    gb.set_link_port(LinkPort::Device(Box::new(Inverter(tx))));
+
+    let ready = (0..10).any(|_| {
+        gb.run_frame();
+        gb.peek(HANDSHAKE_DONE) == 1
+    });
+    assert!(ready, "handshake didn't finish");
+
+    let replies: Vec<u8> = (0..4).map(|i| gb.peek(RECEIVED + i)).collect();
+    assert_eq!(replies, HANDSHAKE.map(|b| !b));
+
+    let sent: Vec<u8> = rx
+        .try_iter()
+        .collect::<Vec<_>>()
+        .chunks(8)
+        .map(|bits| bits.iter().fold(0, |byte, &bit| byte << 1 | u8::from(bit)))
+        .collect();
+    assert_eq!(sent, HANDSHAKE);
+}
+
+#[test]
+fn debug_console_collects_sent_text() {
+    let mut gb = gb_sending(*b"h\xC3\xA9!");
+    gb.set_link_port(LinkPort::DebugConsole(DebugConsole::default()));
+
+    let done = (0..10).any(|_| {
+        gb.run_frame();
+        gb.peek(HANDSHAKE_DONE) == 1
+    });
+    assert!(done, "text wasn't sent");
+
+    let replies: Vec<u8> = (0..4).map(|i| gb.peek(RECEIVED + i)).collect();
+    assert_eq!(replies, [0xFF; 4]);
+
+    assert_eq!(console_text(&mut gb).as_deref(), Some("hé!"));
+    assert_eq!(console_text(&mut gb).as_deref(), Some(""));
+}
+
+// Runs EXCHANGE with the Workboy, returns what it replied to sent
+fn workboy_replies(workboy: Workboy, sent: &[u8]) -> (Vec<u8>, Workboy) {
+    let mut rom = common::rom(&EXCHANGE);
+    rom[0x157] = u8::try_from(sent.len()).unwrap();
+    rom[0x190..0x190 + sent.len()].copy_from_slice(sent);
+
+    let mut gb = common::gb_with_rom(Model::Dmg, rom);
+    gb.set_ld_b_b_breakpoint(true);
+    gb.set_link_port(LinkPort::Workboy(workboy));
+
+    let stopped = (0..10).any(|_| matches!(gb.run_frame(), StopReason::Breakpoint { .. }));
+    assert!(stopped, "bytes weren't sent");
+
+    let replies = (0..sent.len())
+        .map(|i| gb.peek(RECEIVED + u16::try_from(i).unwrap()))
+        .collect();
+    let LinkPort::Workboy(unplugged) = gb.set_link_port(LinkPort::Disconnected) else {
+        unreachable!()
+    };
+    (replies, unplugged)
+}
+
+#[test]
+fn workboy_sends_pressed_keys() {
+    let mut workboy = Workboy::default();
+    workboy.press(0x11);
+    workboy.press(0x12);
+
+    // every reply comes with the next transfer
+    let (replies, _) = workboy_replies(workboy, b"OOOO");
+    assert_eq!(replies, [0x00, 0x11, 0x12, 0xFF]);
+}
+
+#[test]
+fn workboy_reads_its_clock_as_hex_digits() {
+    let mut workboy = Workboy::default();
+    workboy.set_clock(WorkboyClock {
+        seconds: 9,
+        minutes: 45,
+        hours: 13,
+        day: 17,
+        month: 10,
+        year: 2026,
+    });
+
+    let (replies, _) = workboy_replies(workboy, b"R\0\0\0\0\0\0\0\0");
+    // acknowledged, then 4 and minutes, seconds and hours in BCD
+    assert_eq!(replies, b"\0D4450913");
+}
+
+#[test]
+fn workboy_clock_is_set_by_the_game() {
+    let mut sent = b"W\0\0".to_vec();
+    let mut clock = [0; 0x10];
+    clock[..6].copy_from_slice(&[0x04, 0x30, 0x15, 0x08, 0x24, 0x12]);
+    // years since 1900
+    clock[0xF] = 126;
+    sent.extend_from_slice(&clock);
+
+    let (replies, mut workboy) = workboy_replies(Workboy::default(), &sent);
+    assert!(replies[1..].iter().all(|&reply| reply == b'D'));
+
+    assert_eq!(
+        workboy.take_clock_set_by_game(),
+        Some(WorkboyClock {
+            seconds: 15,
+            minutes: 30,
+            hours: 8,
+            day: 24,
+            month: 12,
+            year: 2026,
+        })
+    );
+    assert_eq!(workboy.take_clock_set_by_game(), None);
+}
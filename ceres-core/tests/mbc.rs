@@ -0,0 +1,301 @@
+// Random MBC register writes checked against a naive model of each mapper.
+// Every ROM and RAM bank is tagged with its own number, so reading the
+// switchable areas tells which bank the emulator mapped.
+
+mod common;
+
+use ceres_core::{Cart, Gb, Model, NoAudio};
+use proptest::prelude::*;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+// Away from the bootrom, which stays mapped over the low ROM bank
+const ROM_TAG: u16 = 0x3000;
+
+#[derive(Clone, Copy, Debug)]
+enum Mapper {
+    Mbc1,
+    Mbc2,
+    Mbc3,
+    Mbc5,
+    Huc1,
+}
+
+impl Mapper {
+    const fn header_byte(self) -> u8 {
+        match self {
+            Self::Mbc1 => 0x03,
+            Self::Mbc2 => 0x06,
+            Self::Mbc3 => 0x13,
+            Self::Mbc5 => 0x1B,
+            Self::Huc1 => 0xFF,
+        }
+    }
+
+    const fn max_rom_size_byte(self) -> u8 {
+        match self {
+            Self::Mbc2 => 3,
+            Self::Huc1 => 5,
+            Self::Mbc1 | Self::Mbc3 | Self::Mbc5 => 6,
+        }
+    }
+}
+
+// Hardware registers, named after Pan Docs
+#[derive(Default)]
+struct Reference {
+    ram_enabled: bool,
+    bank1: u16,
+    bank2: u16,
+    mode: bool,
+    ram_bank: u8,
+    ir_mode: bool,
+}
+
+impl Reference {
+    fn new() -> Self {
+        Self {
+            bank1: 1,
+            ..Self::default()
+        }
+    }
+
+    fn write(&mut self, mapper: Mapper, addr: u16, val: u8) {
+        match (mapper, addr) {
+            (Mapper::Mbc1 | Mapper::Mbc3 | Mapper::Mbc5, 0x0000..=0x1FFF) => {
+                self.ram_enabled = val & 0xF == 0xA;
+            }
+            (Mapper::Mbc1, 0x2000..=0x3FFF) => self.bank1 = u16::from(val & 0x1F).max(1),
+            (Mapper::Mbc1, 0x4000..=0x5FFF) => self.bank2 = u16::from(val & 3),
+            (Mapper::Mbc1, 0x6000..=0x7FFF) => self.mode = val & 1 != 0,
+            (Mapper::Mbc2, 0x0000..=0x3FFF) => {
+                if addr & 0x100 == 0 {
+                    self.ram_enabled = val & 0xF == 0xA;
+                } else {
+                    self.bank1 = u16::from(val & 0xF).max(1);
+                }
+            }
+            (Mapper::Mbc3, 0x2000..=0x3FFF) => self.bank1 = u16::from(val & 0x7F).max(1),
+            (Mapper::Mbc3, 0x4000..=0x5FFF) if !(0x8..=0xC).contains(&val) => {
+                self.ram_bank = val & 7;
+            }
+            (Mapper::Mbc5, 0x2000..=0x2FFF) => self.bank1 = self.bank1 & 0x100 | u16::from(val),
+            (Mapper::Mbc5, 0x3000..=0x3FFF) => {
+                self.bank1 = self.bank1 & 0xFF | u16::from(val & 1) << 8;
+            }
+            (Mapper::Mbc5, 0x4000..=0x5FFF) => self.ram_bank = val & 0xF,
+            (Mapper::Huc1, 0x0000..=0x1FFF) => self.ir_mode = val & 0xF == 0xE,
+            (Mapper::Huc1, 0x2000..=0x3FFF) => self.bank1 = u16::from(val & 0x3F),
+            (Mapper::Huc1, 0x4000..=0x5FFF) => self.ram_bank = val & 3,
+            _ => (),
+        }
+    }
+
+    const fn low_rom_bank(&self, mapper: Mapper, rom_banks: u16) -> u16 {
+        match mapper {
+            Mapper::Mbc1 if self.mode => (self.bank2 << 5) & (rom_banks - 1),
+            _ => 0,
+        }
+    }
+
+    const fn high_rom_bank(&self, mapper: Mapper, rom_banks: u16) -> u16 {
+        let bank = match mapper {
+            Mapper::Mbc1 => self.bank2 << 5 | self.bank1,
+            Mapper::Mbc2 | Mapper::Mbc3 | Mapper::Mbc5 | Mapper::Huc1 => self.bank1,
+        };
+        bank & (rom_banks - 1)
+    }
+
+    const fn ram_bank(&self, mapper: Mapper, ram_banks: u8) -> u8 {
+        let bank = match mapper {
+            Mapper::Mbc1 if self.mode => self.bank2 as u8,
+            Mapper::Mbc1 | Mapper::Mbc2 => 0,
+            Mapper::Mbc3 | Mapper::Mbc5 | Mapper::Huc1 => self.ram_bank,
+        };
+        bank & (ram_banks - 1)
+    }
+}
+
+const fn ram_banks(ram_size_byte: u8) -> u8 {
+    match ram_size_byte {
+        2 => 1,
+        3 => 4,
+        4 => 16,
+        5 => 8,
+        _ => 0,
+    }
+}
+
+fn tagged_gb(mapper: Mapper, rom_size_byte: u8, ram_size_byte: u8) -> Gb<NoAudio> {
+    let rom_banks = 2_usize << rom_size_byte;
+    let mut rom = vec![0; rom_banks * ROM_BANK_SIZE];
+
+    for (bank, data) in rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+        data[ROM_TAG as usize..][..2].copy_from_slice(&(bank as u16).to_le_bytes());
+    }
+
+    rom[0x147] = mapper.header_byte();
+    rom[0x148] = rom_size_byte;
+    rom[0x149] = ram_size_byte;
+
+    let mut cart = Cart::new(rom.into_boxed_slice()).unwrap();
+
+    let ram_banks = usize::from(ram_banks(ram_size_byte));
+    if ram_banks > 0 {
+        let mut ram = vec![0; ram_banks * RAM_BANK_SIZE];
+        for (bank, data) in ram.chunks_mut(RAM_BANK_SIZE).enumerate() {
+            data[0] = bank as u8;
+        }
+        cart.set_ram(ram.into_boxed_slice()).unwrap();
+    }
+
+    Gb::new(Model::Cgb, 48000, cart, NoAudio)
+}
+
+fn rom_tag(gb: &Gb<NoAudio>, addr: u16) -> u16 {
+    u16::from_le_bytes([gb.peek(addr), gb.peek(addr + 1)])
+}
+
+fn mapper() -> impl Strategy<Value = Mapper> {
+    prop_oneof![
+        Just(Mapper::Mbc1),
+        Just(Mapper::Mbc2),
+        Just(Mapper::Mbc3),
+        Just(Mapper::Mbc5),
+        Just(Mapper::Huc1),
+    ]
+}
+
+fn case() -> impl Strategy<Value = (Mapper, u8, u8, Vec<(u16, u8)>)> {
+    mapper().prop_flat_map(|mapper| {
+        let ram_size = match mapper {
+            Mapper::Mbc2 => Just(0).boxed(),
+            // Two RAM bank bits
+            Mapper::Huc1 => prop_oneof![Just(0), Just(2), Just(3)].boxed(),
+            _ => prop_oneof![Just(0), Just(2), Just(3), Just(4), Just(5)].boxed(),
+        };
+
+        (
+            Just(mapper),
+            0..=mapper.max_rom_size_byte(),
+            ram_size,
+            prop::collection::vec((0x0000..0x8000_u16, any::<u8>()), 0..64),
+        )
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn banks_match_reference((mapper, rom_size, ram_size, writes) in case()) {
+        let mut gb = tagged_gb(mapper, rom_size, ram_size);
+        let mut reference = Reference::new();
+
+        let rom_banks = 2_u16 << rom_size;
+        let ram_banks = ram_banks(ram_size);
+
+        for (addr, val) in writes {
+            gb.poke(addr, val);
+            reference.write(mapper, addr, val);
+
+            prop_assert_eq!(
+                rom_tag(&gb, ROM_TAG),
+                reference.low_rom_bank(mapper, rom_banks),
+                "low ROM bank after writing {:#04x} to {:#06x}", val, addr
+            );
+            prop_assert_eq!(
+                rom_tag(&gb, 0x4000 | ROM_TAG),
+                reference.high_rom_bank(mapper, rom_banks),
+                "high ROM bank after writing {:#04x} to {:#06x}", val, addr
+            );
+
+            if ram_banks > 0 {
+                let expected = if matches!(mapper, Mapper::Huc1) {
+                    if reference.ir_mode {
+                        0xC0
+                    } else {
+                        reference.ram_bank(mapper, ram_banks)
+                    }
+                } else if reference.ram_enabled {
+                    reference.ram_bank(mapper, ram_banks)
+                } else {
+                    0xFF
+                };
+                prop_assert_eq!(
+                    gb.peek(0xA000),
+                    expected,
+                    "RAM bank after writing {:#04x} to {:#06x}", val, addr
+                );
+            }
+        }
+    }
+}
+
+// Seven 32 KiB games and a menu in the last 32 KiB
+#[test]
+fn mmm01_maps_the_menu_then_a_game() {
+    let mut rom = vec![0; 16 * ROM_BANK_SIZE];
+
+    for (bank, data) in rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+        data[ROM_TAG as usize..][..2].copy_from_slice(&(bank as u16).to_le_bytes());
+    }
+
+    let menu = rom.len() - 2 * ROM_BANK_SIZE;
+    rom[menu + 0x147] = 0x0B;
+    rom[menu + 0x148] = 3;
+
+    let mut gb = common::gb_with_rom(Model::Cgb, rom);
+    assert_eq!(rom_tag(&gb, ROM_TAG), 14);
+    assert_eq!(rom_tag(&gb, 0x4000 | ROM_TAG), 15);
+
+    // the menu picks the game in banks 2 and 3 and locks the outer bits
+    gb.poke(0x2000, 0x02);
+    gb.poke(0x6000, 0x0F << 2);
+    gb.poke(0x0000, 0x40);
+    assert_eq!(rom_tag(&gb, ROM_TAG), 2);
+    assert_eq!(rom_tag(&gb, 0x4000 | ROM_TAG), 3);
+
+    // the game can't leave its banks
+    gb.poke(0x2000, 0x1F);
+    gb.poke(0x6000, 0);
+    gb.poke(0x0000, 0x00);
+    assert_eq!(rom_tag(&gb, ROM_TAG), 2);
+    assert_eq!(rom_tag(&gb, 0x4000 | ROM_TAG), 3);
+}
+
+// MBC1M, the second game's header at bank 0x10 gives the wiring away
+#[test]
+fn mbc1_multicart_uses_4_bit_banks() {
+    const LOGO: [u8; 48] = [
+        0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00,
+        0x0D, 0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD,
+        0xD9, 0x99, 0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB,
+        0xB9, 0x33, 0x3E,
+    ];
+
+    let mut rom = vec![0; 0x40 * ROM_BANK_SIZE];
+
+    for (bank, data) in rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+        data[ROM_TAG as usize..][..2].copy_from_slice(&(bank as u16).to_le_bytes());
+        if bank % 0x10 == 0 {
+            data[0x104..0x134].copy_from_slice(&LOGO);
+        }
+    }
+
+    rom[0x147] = Mapper::Mbc1.header_byte();
+    rom[0x148] = 5;
+
+    let mut gb = common::gb_with_rom(Model::Cgb, rom);
+
+    // the third game, in mode 1 its first bank is mapped low
+    gb.poke(0x4000, 2);
+    gb.poke(0x2000, 0x03);
+    gb.poke(0x6000, 1);
+    assert_eq!(rom_tag(&gb, ROM_TAG), 0x20);
+    assert_eq!(rom_tag(&gb, 0x4000 | ROM_TAG), 0x23);
+
+    // bit 4 of the low bank is ignored, 0x10 isn't turned into 0x11
+    gb.poke(0x2000, 0x10);
+    assert_eq!(rom_tag(&gb, 0x4000 | ROM_TAG), 0x20);
+}
@@ -0,0 +1,64 @@
+// Hooks see CPU accesses in program order
+
+mod common;
+
+use ceres_core::{MemoryHook, Model};
+use std::sync::mpsc::{channel, Sender};
+
+const PROGRAM: [u8; 6] = [
+    0xFA, 0x00, 0xC0, // ld a,($C000)
+    0xEA, 0x01, 0xC0, // ld ($C001),a
+];
+
+#[derive(Debug, PartialEq, Eq)]
+enum Access {
+    Read(u16),
+    Write(u16),
+    Execute(u16),
+}
+
+struct Recorder(Sender<Access>);
+
+impl MemoryHook for Recorder {
+    fn on_read(&mut self, addr: u16, _val: u8) {
+        self.0.send(Access::Read(addr)).unwrap();
+    }
+
+    fn on_write(&mut self, addr: u16, _val: u8) {
+        self.0.send(Access::Write(addr)).unwrap();
+    }
+
+    fn on_execute(&mut self, pc: u16) {
+        self.0.send(Access::Execute(pc)).unwrap();
+    }
+}
+
+#[test]
+fn hook_sees_fetches_reads_and_writes() {
+    let mut gb = common::gb(Model::Dmg, &PROGRAM);
+    while gb.registers().pc != 0x150 {
+        gb.step_instruction();
+    }
+
+    let (tx, rx) = channel();
+    assert!(gb.set_memory_hook(Some(Box::new(Recorder(tx)))).is_none());
+    gb.step_instruction();
+    gb.step_instruction();
+    assert!(gb.set_memory_hook(None).is_some());
+
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        [
+            Access::Execute(0x150),
+            Access::Read(0x150),
+            Access::Read(0x151),
+            Access::Read(0x152),
+            Access::Read(0xC000),
+            Access::Execute(0x153),
+            Access::Read(0x153),
+            Access::Read(0x154),
+            Access::Read(0x155),
+            Access::Write(0xC001),
+        ]
+    );
+}
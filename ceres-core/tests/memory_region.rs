@@ -0,0 +1,119 @@
+// Debugger memory access by region and bank, bypassing the bus
+
+mod common;
+
+use ceres_core::{Gb, MemoryRegion, Model, NoAudio};
+
+fn gb(model: Model) -> Gb<NoAudio> {
+    let mut rom = vec![0; 0x8000];
+    // CGB game with an MBC1 and 32 KiB of RAM
+    rom[0x143] = 0x80;
+    rom[0x147] = 0x03;
+    rom[0x149] = 0x03;
+
+    common::gb_with_rom(model, rom)
+}
+
+#[test]
+fn writes_show_on_the_bus() {
+    let mut gb = gb(Model::Dmg);
+
+    assert!(gb.write_mem_byte(MemoryRegion::Wram(1), 0x10, 0x42));
+    assert_eq!(gb.peek(0xD010), 0x42);
+
+    assert!(gb.write_mem_byte(MemoryRegion::Hram, 0x7E, 0x24));
+    assert_eq!(gb.peek(0xFFFE), 0x24);
+
+    // the LCD is off so VRAM is accessible
+    assert!(gb.write_mem_byte(MemoryRegion::Vram(0), 0x1FFF, 0x99));
+    assert_eq!(gb.peek(0x9FFF), 0x99);
+}
+
+#[test]
+fn any_bank_is_reachable() {
+    let mut gb = gb(Model::Cgb);
+
+    assert!(gb.write_mem_byte(MemoryRegion::Sram(3), 0, 0x33));
+    assert_eq!(gb.read_mem_byte(MemoryRegion::Sram(3), 0), Some(0x33));
+    assert_eq!(gb.region_bytes(MemoryRegion::Sram(3)).len(), 0x2000);
+    assert!(gb.region_bytes(MemoryRegion::Sram(4)).is_empty());
+
+    // mapped through the bus only after enabling RAM and selecting the bank
+    gb.poke(0x0000, 0x0A);
+    gb.poke(0x6000, 0x01);
+    gb.poke(0x4000, 0x03);
+    assert_eq!(gb.peek(0xA000), 0x33);
+}
+
+#[test]
+fn banks_depend_on_the_model() {
+    let mut dmg = gb(Model::Dmg);
+    assert!(dmg.region_bytes(MemoryRegion::Wram(2)).is_empty());
+    assert!(dmg.region_bytes(MemoryRegion::Vram(1)).is_empty());
+
+    assert!(!dmg.write_mem_byte(MemoryRegion::Wram(2), 0, 0));
+    assert!(!dmg.write_mem_byte(MemoryRegion::Hram, 0x80, 0));
+}
+
+#[test]
+fn svbk_zero_maps_bank_one() {
+    let mut gb = gb(Model::Cgb);
+    assert_eq!(gb.wram_bank(), 1);
+
+    gb.poke(0xFF70, 3);
+    assert_eq!(gb.wram_bank(), 3);
+    gb.poke(0xD000, 0x33);
+    assert_eq!(gb.read_mem_byte(MemoryRegion::Wram(3), 0), Some(0x33));
+
+    gb.poke(0xFF70, 0);
+    assert_eq!(gb.wram_bank(), 1);
+    gb.poke(0xD000, 0x11);
+    assert_eq!(gb.read_mem_byte(MemoryRegion::Wram(1), 0), Some(0x11));
+    // only the low three bits select the bank
+    gb.poke(0xFF70, 0xFB);
+    assert_eq!(gb.wram_bank(), 3);
+    assert_eq!(gb.peek(0xFF70), 0xFB);
+}
+
+#[test]
+fn echo_ram_mirrors_wram() {
+    let mut gb = gb(Model::Cgb);
+    gb.poke(0xFF70, 5);
+
+    gb.poke(0xE123, 0x42);
+    assert_eq!(gb.peek(0xC123), 0x42);
+    gb.poke(0xD456, 0x24);
+    assert_eq!(gb.peek(0xF456), 0x24);
+    assert_eq!(gb.read_mem_byte(MemoryRegion::Wram(5), 0x456), Some(0x24));
+
+    assert_eq!(gb.region_at(0xE123), Some((MemoryRegion::Wram(0), 0x123)));
+    assert_eq!(gb.region_at(0xF456), Some((MemoryRegion::Wram(5), 0x456)));
+    // OAM and the unusable area past echo RAM
+    assert_eq!(gb.region_at(0xFE00), None);
+}
+
+#[test]
+fn dmg_has_no_svbk() {
+    let mut gb = gb(Model::Dmg);
+    gb.poke(0xFF70, 3);
+
+    assert_eq!(gb.wram_bank(), 1);
+    assert_eq!(gb.peek(0xFF70), 0xFF);
+    gb.poke(0xD000, 0x11);
+    assert_eq!(gb.read_mem_byte(MemoryRegion::Wram(1), 0), Some(0x11));
+}
+
+#[test]
+fn addresses_are_classified_with_the_mapped_banks() {
+    let mut gb = gb(Model::Cgb);
+    gb.poke(0xFF4F, 1);
+    // MBC1 switches RAM banks in its second banking mode
+    gb.poke(0x6000, 0x01);
+    gb.poke(0x4000, 2);
+
+    assert_eq!(gb.region_at(0x0150), None);
+    assert_eq!(gb.region_at(0x9800), Some((MemoryRegion::Vram(1), 0x1800)));
+    assert_eq!(gb.region_at(0xA010), Some((MemoryRegion::Sram(2), 0x10)));
+    assert_eq!(gb.region_at(0xFF90), Some((MemoryRegion::Hram, 0x10)));
+    assert_eq!(gb.region_at(0xFF44), None);
+}
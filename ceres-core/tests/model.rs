@@ -0,0 +1,41 @@
+// Picking the model a cartridge was made for from its header
+
+use ceres_core::{Cart, Model, Quirks};
+
+fn cart(cgb_flag: u8, sgb_flag: u8, licensee: u8) -> Cart {
+    let mut rom = vec![0; 0x8000];
+    rom[0x143] = cgb_flag;
+    rom[0x146] = sgb_flag;
+    rom[0x14B] = licensee;
+    Cart::new(rom.into_boxed_slice()).unwrap()
+}
+
+#[test]
+fn header_flags_pick_the_model() {
+    assert_eq!(Model::from_cart(&cart(0x00, 0x00, 0x33)), Model::Dmg);
+    assert_eq!(Model::from_cart(&cart(0x00, 0x03, 0x33)), Model::Sgb);
+    // CGB enhanced and CGB only
+    assert_eq!(Model::from_cart(&cart(0x80, 0x03, 0x33)), Model::Cgb);
+    assert_eq!(Model::from_cart(&cart(0xC0, 0x00, 0x33)), Model::Cgb);
+}
+
+#[test]
+fn sgb_flag_needs_the_new_licensee_code() {
+    assert_eq!(Model::from_cart(&cart(0x00, 0x03, 0x01)), Model::Dmg);
+}
+
+#[test]
+fn revisions_behave_like_their_family() {
+    assert_eq!(
+        Quirks::for_model(Model::Dmg0),
+        Quirks::for_model(Model::Dmg)
+    );
+    assert_eq!(
+        Quirks::for_model(Model::Cgb0),
+        Quirks::for_model(Model::Cgb)
+    );
+    assert_eq!(
+        Quirks::for_model(Model::CgbE),
+        Quirks::for_model(Model::Cgb)
+    );
+}
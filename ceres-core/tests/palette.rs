@@ -0,0 +1,162 @@
+// CGB palette RAM access through BCPS/BCPD and OCPS/OCPD, and the colors
+// given to the DMG shades
+
+mod common;
+
+use ceres_core::{DmgPalette, Gb, Model, NoAudio, StopReason};
+
+const BCPS: u16 = 0xFF68;
+const BCPD: u16 = 0xFF69;
+const OCPS: u16 = 0xFF6A;
+const OCPD: u16 = 0xFF6B;
+const LCDC: u16 = 0xFF40;
+const BGP: u16 = 0xFF47;
+
+const LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+// Writes to BCPD while the PPU is drawing, then stops at ld b,b once it
+// isn't, with the value read back while drawing in C
+const LOCKED_WRITE: [u8; 34] = [
+    0x3E, 0x91, // ld a,$91
+    0xE0, 0x40, // ldh (LCDC),a
+    0x3E, 0x80, // ld a,$80
+    0xE0, 0x68, // ldh (BCPS),a
+    0xF0, 0x41, // .drawing: ldh a,(STAT)
+    0xE6, 0x03, // and 3
+    0xFE, 0x03, // cp 3
+    0x20, 0xF8, // jr nz,.drawing
+    0xF0, 0x69, // ldh a,(BCPD)
+    0x4F, // ld c,a
+    0x3E, 0x12, // ld a,$12
+    0xE0, 0x69, // ldh (BCPD),a
+    0xF0, 0x41, // .done: ldh a,(STAT)
+    0xE6, 0x03, // and 3
+    0xFE, 0x03, // cp 3
+    0x28, 0xF8, // jr z,.done
+    0x40, // ld b,b
+    0x18, 0xFE, // jr @
+];
+
+fn cgb(program: &[u8]) -> Gb<NoAudio> {
+    gb(Model::Cgb, program)
+}
+
+fn gb(model: Model, program: &[u8]) -> Gb<NoAudio> {
+    let mut rom = common::rom(program);
+    rom[0x104..0x134].copy_from_slice(&LOGO);
+    if matches!(model, Model::Cgb) {
+        rom[0x143] = 0x80;
+    }
+    rom[0x14D] = rom[0x134..0x14D]
+        .iter()
+        .fold(0_u8, |sum, b| sum.wrapping_sub(*b).wrapping_sub(1));
+
+    common::gb_with_rom(model, rom)
+}
+
+#[test]
+fn index_register_unused_bit_reads_set() {
+    let mut gb = cgb(&[]);
+
+    for (spec, data) in [(BCPS, BCPD), (OCPS, OCPD)] {
+        gb.poke(spec, 0x3F);
+        assert_eq!(gb.peek(spec), 0x7F);
+
+        gb.poke(spec, 0x80);
+        assert_eq!(gb.peek(spec), 0xC0);
+
+        // Data writes keep the increment flag
+        gb.poke(data, 0);
+        assert_eq!(gb.peek(spec), 0xC1);
+    }
+}
+
+#[test]
+fn auto_increment_wraps_and_can_be_disabled() {
+    let mut gb = cgb(&[]);
+
+    for (spec, data) in [(BCPS, BCPD), (OCPS, OCPD)] {
+        gb.poke(spec, 0xBF);
+        gb.poke(data, 0);
+        assert_eq!(gb.peek(spec), 0xC0);
+
+        gb.poke(spec, 0x05);
+        gb.poke(data, 0);
+        assert_eq!(gb.peek(spec), 0x45);
+    }
+}
+
+#[test]
+fn color_bytes_survive_any_write_order() {
+    let mut gb = cgb(&[]);
+
+    for (spec, data) in [(BCPS, BCPD), (OCPS, OCPD)] {
+        // High byte first, the green bits are split between both bytes
+        gb.poke(spec, 0x03);
+        gb.poke(data, 0x7F);
+        gb.poke(spec, 0x02);
+        gb.poke(data, 0xFF);
+
+        gb.poke(spec, 0x02);
+        assert_eq!(gb.peek(data), 0xFF);
+        gb.poke(spec, 0x03);
+        assert_eq!(gb.peek(data), 0x7F);
+
+        gb.poke(spec, 0x04);
+        gb.poke(data, 0xE0);
+        gb.poke(spec, 0x05);
+        gb.poke(data, 0x03);
+
+        gb.poke(spec, 0x04);
+        assert_eq!(gb.peek(data), 0xE0);
+        gb.poke(spec, 0x05);
+        assert_eq!(gb.peek(data), 0x03);
+    }
+}
+
+#[test]
+fn locked_while_drawing_but_index_increments() {
+    let mut gb = cgb(&LOCKED_WRITE);
+    gb.set_ld_b_b_breakpoint(true);
+
+    let stopped = (0..60).any(|_| matches!(gb.run_frame(), StopReason::Breakpoint { .. }));
+    assert!(stopped, "test program didn't finish");
+
+    assert_eq!(gb.registers().bc & 0xFF, 0xFF, "read while drawing");
+    assert_eq!(gb.peek(BCPS), 0xC1);
+
+    gb.poke(BCPS, 0);
+    assert_ne!(gb.peek(BCPD), 0x12, "write while drawing was stored");
+}
+
+#[test]
+fn dmg_palette_colors_the_shades() {
+    // jr @
+    let mut gb = gb(Model::Dmg, &[0x18, 0xFE]);
+    let colors = DmgPalette::Green.colors();
+    gb.set_dmg_palette(&colors);
+
+    // the top left tile in color 3, the rest in color 0
+    for addr in 0x8010..0x8020 {
+        gb.poke(addr, 0xFF);
+    }
+    gb.poke(0x9800, 1);
+    gb.poke(BGP, 0xE4);
+    gb.poke(LCDC, 0x91);
+    (0..2).for_each(|_| {
+        gb.run_frame();
+    });
+
+    let pixels: Vec<_> = gb
+        .pixel_data_rgb()
+        .chunks_exact(3)
+        .map(|px| (px[0], px[1], px[2]))
+        .collect();
+    assert_eq!(pixels[0], colors[3]);
+    assert_eq!(pixels[8], colors[0]);
+    assert!(pixels.iter().all(|px| colors.contains(px)));
+}
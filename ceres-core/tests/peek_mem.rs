@@ -0,0 +1,62 @@
+// peek reads like the CPU, peek_mem sees through the PPU and cartridge gates
+
+mod common;
+
+use ceres_core::{Gb, Model, NoAudio};
+
+const LCDC: u16 = 0xFF40;
+const STAT: u16 = 0xFF41;
+
+// MBC1+RAM, spinning at $0150
+fn gb() -> Gb<NoAudio> {
+    // jr @
+    let mut rom = common::rom(&[0x18, 0xFE]);
+    rom[0x147] = 0x02;
+    rom[0x149] = 0x02;
+
+    common::gb_with_rom(Model::Dmg, rom)
+}
+
+#[test]
+fn sees_vram_and_oam_while_drawing() {
+    let mut gb = gb();
+
+    gb.poke(LCDC, 0x00);
+    gb.poke(0x8010, 0x42);
+    gb.poke(0xFE04, 0x24);
+    gb.poke(LCDC, 0x91);
+
+    while gb.peek(STAT) & 3 != 3 {
+        gb.run_dots(4);
+    }
+
+    assert_eq!(gb.peek(0x8010), 0xFF);
+    assert_eq!(gb.peek(0xFE04), 0xFF);
+    assert_eq!(gb.peek_mem(0x8010), 0x42);
+    assert_eq!(gb.peek_mem(0xFE04), 0x24);
+}
+
+#[test]
+fn sees_disabled_cartridge_ram() {
+    let mut gb = gb();
+
+    gb.poke(0x0000, 0x0A);
+    gb.poke(0xA123, 0x5A);
+    gb.poke(0x0000, 0x00);
+
+    assert_eq!(gb.peek(0xA123), 0xFF);
+    assert_eq!(gb.peek_mem(0xA123), 0x5A);
+}
+
+#[test]
+fn matches_peek_elsewhere() {
+    let mut gb = gb();
+    gb.run_dots(0x1000);
+
+    for addr in (0x0000..0x8000)
+        .chain(0xC000..=0xFDFF)
+        .chain(0xFF00..=0xFFFF)
+    {
+        assert_eq!(gb.peek_mem(addr), gb.peek(addr), "at {addr:04x}");
+    }
+}
@@ -0,0 +1,52 @@
+// Randomness comes from a seed and travels with save states, so runs
+// replay exactly
+
+mod common;
+
+use ceres_core::{Gb, Model, NoAudio, SaveStateManager};
+
+fn gb(seed: u64) -> Gb<NoAudio> {
+    let mut gb = common::gb_with_rom(Model::Dmg, vec![0; 0x8000]);
+    gb.seed_rng(seed);
+    gb
+}
+
+fn ram(gb: &Gb<NoAudio>) -> Vec<u8> {
+    (0xC000..=0xDFFF)
+        .chain(0xFF80..=0xFFFE)
+        .map(|addr| gb.peek(addr))
+        .collect()
+}
+
+#[test]
+fn seed_decides_the_ram_contents() {
+    let mut gb1 = gb(1);
+    let mut gb2 = gb(1);
+    let mut other = gb(2);
+    assert!(ram(&gb1).iter().all(|&byte| byte == 0));
+
+    gb1.randomize_ram();
+    gb2.randomize_ram();
+    other.randomize_ram();
+
+    assert_eq!(ram(&gb1), ram(&gb2));
+    assert_ne!(ram(&gb1), ram(&other));
+    assert!(ram(&gb1).iter().any(|&byte| byte != 0));
+}
+
+#[test]
+fn save_states_carry_the_rng() {
+    let mut gb = gb(7);
+    let mut states = SaveStateManager::new(1);
+    states.save(0, &gb, 0).unwrap();
+
+    gb.randomize_ram();
+    let expected = ram(&gb);
+    // moves the rng on
+    gb.randomize_ram();
+    assert_ne!(ram(&gb), expected);
+
+    states.load(0, &mut gb).unwrap();
+    gb.randomize_ram();
+    assert_eq!(ram(&gb), expected);
+}
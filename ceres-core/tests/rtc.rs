@@ -0,0 +1,82 @@
+// The MBC3 clock is stored after the RAM in .sav files, in the footer BGB and
+// VBA write
+
+use ceres_core::{Cart, RTC_FOOTER_LEN};
+
+const SAVED_AT: u64 = 1_700_000_000;
+
+// MBC3+TIMER+RAM+BATTERY with 8 KiB of RAM
+fn cart() -> Cart {
+    let mut rom = vec![0; 0x8000];
+    rom[0x147] = 0x10;
+    rom[0x149] = 0x02;
+
+    Cart::new(rom.into_boxed_slice()).unwrap()
+}
+
+// Seconds, minutes, hours, day low and day high with the flags, twice
+fn footer(regs: [u8; 5], saved_at: u64) -> [u8; RTC_FOOTER_LEN] {
+    let mut footer = [0; RTC_FOOTER_LEN];
+    for (i, reg) in regs.iter().chain(&regs).enumerate() {
+        footer[i * 4] = *reg;
+    }
+    footer[40..].copy_from_slice(&saved_at.to_le_bytes());
+    footer
+}
+
+#[test]
+fn round_trips() {
+    let mut cart = cart();
+    let saved = footer([12, 34, 5, 0x2A, 0x01], SAVED_AT);
+
+    assert!(cart.load_rtc_footer(&saved, SAVED_AT));
+    assert_eq!(cart.clock(), Some([12, 34, 5, 0x2A, 0x01].as_slice()));
+    assert_eq!(cart.rtc_footer(SAVED_AT), Some(saved));
+}
+
+#[test]
+fn catches_up_elapsed_time() {
+    let mut cart = cart();
+    let saved = footer([59, 59, 23, 0xFF, 0x00], SAVED_AT);
+
+    // a second to roll over to day 256, then a day, an hour and a minute
+    assert!(cart.load_rtc_footer(&saved, SAVED_AT + 1 + 86400 + 3600 + 60));
+    assert_eq!(cart.clock(), Some([0, 1, 1, 0x01, 0x01].as_slice()));
+}
+
+#[test]
+fn day_overflow_sets_carry() {
+    let mut cart = cart();
+    let saved = footer([0, 0, 0, 0xFF, 0x01], SAVED_AT);
+
+    assert!(cart.load_rtc_footer(&saved, SAVED_AT + 86400));
+    let footer = cart.rtc_footer(SAVED_AT).unwrap();
+    assert_eq!(footer[12], 0x00);
+    assert_eq!(footer[16], 0x80);
+}
+
+#[test]
+fn halted_clock_stays_put() {
+    let mut cart = cart();
+    let saved = footer([1, 2, 3, 4, 0x40], SAVED_AT);
+
+    assert!(cart.load_rtc_footer(&saved, SAVED_AT + 86400));
+    assert_eq!(cart.rtc_footer(SAVED_AT), Some(saved));
+}
+
+#[test]
+fn reads_the_32_bit_timestamp_footer() {
+    let mut cart = cart();
+    let saved = footer([0, 0, 0, 0, 0], SAVED_AT);
+
+    assert!(cart.load_rtc_footer(&saved[..44], SAVED_AT + 90));
+    assert_eq!(cart.clock(), Some([30, 1, 0, 0, 0].as_slice()));
+}
+
+#[test]
+fn carts_without_a_clock_have_no_footer() {
+    let mut cart = Cart::new(vec![0; 0x8000].into_boxed_slice()).unwrap();
+
+    assert_eq!(cart.rtc_footer(SAVED_AT), None);
+    assert!(!cart.load_rtc_footer(&footer([0; 5], SAVED_AT), SAVED_AT));
+}
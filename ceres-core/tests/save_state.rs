@@ -0,0 +1,117 @@
+// Save states restore the console exactly, and only for the ROM they were
+// taken with
+
+mod common;
+
+use ceres_core::{Gb, Model, NoAudio, SaveStateError, SaveStateManager, StateField};
+
+const COUNTER: u16 = 0xC000;
+
+// Counts up in BC forever, storing the high byte
+const COUNT: [u8; 9] = [
+    0x03, // .loop: inc bc
+    0x78, // ld a,b
+    0xEA, 0x00, 0xC0, // ld (COUNTER),a
+    0x18, 0xF9, // jr .loop
+    0x00, 0x00,
+];
+
+fn gb(title: &[u8]) -> Gb<NoAudio> {
+    gb_as(Model::Dmg, title)
+}
+
+fn gb_as(model: Model, title: &[u8]) -> Gb<NoAudio> {
+    let mut rom = common::rom(&COUNT);
+    rom[0x134..0x134 + title.len()].copy_from_slice(title);
+
+    common::gb_with_rom(model, rom)
+}
+
+fn run(gb: &mut Gb<NoAudio>, frames: usize) {
+    (0..frames).for_each(|_| {
+        gb.run_frame();
+    });
+}
+
+#[test]
+fn loading_replays_the_same_frames() {
+    let mut gb = gb(b"COUNTER");
+    let mut states = SaveStateManager::new(3);
+
+    run(&mut gb, 200);
+    states.save(1, &gb, 1234).unwrap();
+    let frames = gb.stats().frames;
+    run(&mut gb, 30);
+    let expected = (gb.registers(), gb.peek(COUNTER));
+
+    run(&mut gb, 50);
+    assert_ne!((gb.registers(), gb.peek(COUNTER)), expected);
+
+    states.load(1, &mut gb).unwrap();
+    run(&mut gb, 30);
+    assert_eq!((gb.registers(), gb.peek(COUNTER)), expected);
+
+    let info = states.info(1).unwrap();
+    assert_eq!(info.timestamp, 1234);
+    assert_eq!(info.title, "COUNTER");
+    assert_eq!(info.frames, frames);
+}
+
+#[test]
+fn quick_slot_and_errors() {
+    let mut other = gb(b"OTHER");
+    let mut gb = gb(b"COUNTER");
+    let mut states = SaveStateManager::new(2);
+
+    assert_eq!(states.quick_load(&mut gb), Err(SaveStateError::EmptySlot));
+    assert_eq!(states.load(0, &mut gb), Err(SaveStateError::EmptySlot));
+    assert_eq!(states.save(2, &gb, 0), Err(SaveStateError::NoSuchSlot));
+
+    run(&mut gb, 10);
+    states.quick_save(&gb, 0);
+    let saved = gb.registers();
+    run(&mut gb, 10);
+    states.quick_load(&mut gb).unwrap();
+    assert_eq!(gb.registers(), saved);
+
+    assert_eq!(
+        states.quick_load(&mut other),
+        Err(SaveStateError::DifferentCart)
+    );
+
+    states.clear_all();
+    assert!(states.quick_info().is_none());
+}
+
+#[test]
+fn diff_finds_where_consoles_diverge() {
+    let mut gb = gb(b"COUNTER");
+    run(&mut gb, 20);
+
+    let mut other = gb.clone();
+    assert!(gb.diff_state(&other).is_empty());
+
+    run(&mut other, 1);
+    assert!(!gb.diff_state(&other).is_empty());
+
+    other = gb.clone();
+    other.poke(COUNTER + 1, 0x42);
+    let diff = gb.diff_state(&other);
+    assert_eq!(diff.mismatches().len(), 1);
+    assert_eq!(diff.first().unwrap().field, StateField::Wram(1));
+    assert_eq!(diff.first().unwrap().theirs, 0x42);
+}
+
+#[test]
+fn soft_reset_boots_as_another_model() {
+    let mut gb = gb(b"COUNTER");
+    run(&mut gb, 50);
+
+    gb.change_model_and_soft_reset(Model::Cgb);
+    let mut fresh = gb_as(Model::Cgb, b"COUNTER");
+    run(&mut gb, 100);
+    run(&mut fresh, 100);
+
+    assert_eq!(gb.registers(), fresh.registers());
+    assert_eq!(gb.peek(COUNTER), fresh.peek(COUNTER));
+}
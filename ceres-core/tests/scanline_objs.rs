@@ -0,0 +1,70 @@
+// OAM scan keeps the first ten objects on a line, which the DMG then draws
+// by X and the CGB by OAM position
+
+mod common;
+
+use ceres_core::{Gb, Model, NoAudio, MAX_OBJS_PER_LINE};
+
+const LCDC: u16 = 0xFF40;
+
+// X of twelve objects on the top eight lines, the last two don't fit
+const XS: [u8; 12] = [80, 40, 120, 40, 20, 100, 60, 140, 30, 90, 10, 10];
+
+fn gb(model: Model, cgb_flag: u8) -> Gb<NoAudio> {
+    // jr @
+    let mut rom = common::rom(&[0x18, 0xFE]);
+    rom[0x143] = cgb_flag;
+
+    let mut gb = common::gb_with_rom(model, rom);
+    // past the bootrom
+    for _ in 0..300 {
+        gb.run_frame();
+    }
+
+    gb.poke(LCDC, 0x00);
+    for (i, &x) in XS.iter().enumerate() {
+        let addr = 0xFE00 + 4 * i as u16;
+        gb.poke(addr, 16);
+        gb.poke(addr + 1, x + 8);
+    }
+    gb.poke(LCDC, 0x93);
+    for _ in 0..2 {
+        gb.run_frame();
+    }
+
+    gb
+}
+
+#[test]
+fn dmg_orders_by_x() {
+    let gb = gb(Model::Dmg, 0x00);
+    let objs = gb.scanline_objs();
+
+    assert_eq!(objs.len(), 144);
+    assert!(objs[..8]
+        .iter()
+        .all(|line| line.entries().len() == MAX_OBJS_PER_LINE));
+    // the leftmost first, the lower entry first on equal X
+    assert_eq!(objs[0].entries(), [4, 8, 1, 3, 6, 0, 9, 5, 2, 7]);
+    assert!(objs[8..].iter().all(|line| line.entries().is_empty()));
+}
+
+#[test]
+fn cgb_orders_by_oam_position() {
+    let gb = gb(Model::Cgb, 0x80);
+
+    assert_eq!(
+        gb.scanline_objs()[0].entries(),
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
+    );
+}
+
+#[test]
+fn hidden_objects_are_still_selected() {
+    let mut gb = gb(Model::Dmg, 0x00);
+    gb.poke(LCDC, 0x91);
+    gb.run_frame();
+    gb.run_frame();
+
+    assert_eq!(gb.scanline_objs()[0].entries().len(), MAX_OBJS_PER_LINE);
+}
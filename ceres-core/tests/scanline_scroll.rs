@@ -0,0 +1,60 @@
+// A raster split halfway down the screen shows in the per line scroll
+
+mod common;
+
+use ceres_core::{Model, ScanlineScroll};
+
+const SPLIT: usize = 72;
+
+// Scrolls the bottom half right of the top half every frame
+const SPLIT_SCREEN: [u8; 29] = [
+    0x3E,
+    0x12, // ld a,$12
+    0xE0,
+    0x42, // ldh (SCY),a
+    0x3E,
+    0x91, // ld a,$91
+    0xE0,
+    0x40, // ldh (LCDC),a
+    0xF0,
+    0x44, // .top: ldh a,(LY)
+    0xFE,
+    SPLIT as u8, // cp SPLIT
+    0x20,
+    0xFA, // jr nz,.top
+    0x3E,
+    0x56, // ld a,$56
+    0xE0,
+    0x43, // ldh (SCX),a
+    0xF0,
+    0x44, // .bottom: ldh a,(LY)
+    0xA7, // and a
+    0x20,
+    0xFB, // jr nz,.bottom
+    0x3E,
+    0x34, // ld a,$34
+    0xE0,
+    0x43, // ldh (SCX),a
+    0x18,
+    0xEB, // jr .top
+];
+
+#[test]
+fn follows_raster_splits() {
+    let mut gb = common::gb(Model::Dmg, &SPLIT_SCREEN);
+    for _ in 0..3 {
+        gb.run_frame();
+    }
+
+    let scroll = gb.scanline_scroll();
+    assert_eq!(scroll.len(), 144);
+
+    // the lines next to the writes depend on their timing
+    let top = ScanlineScroll {
+        scx: 0x34,
+        scy: 0x12,
+    };
+    let bottom = ScanlineScroll { scx: 0x56, ..top };
+    assert!(scroll[2..SPLIT - 1].iter().all(|&s| s == top));
+    assert!(scroll[SPLIT + 1..].iter().all(|&s| s == bottom));
+}
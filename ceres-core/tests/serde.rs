@@ -0,0 +1,74 @@
+// The whole console goes through serde and comes back the same, with the
+// frontend's settings left out like save states do
+#![cfg(feature = "serde")]
+
+mod common;
+
+use ceres_core::{Gb, Model, NoAudio, SaveStateManager};
+
+// Counts up in BC forever, storing the high byte
+const COUNT: [u8; 9] = [
+    0x03, // .loop: inc bc
+    0x78, // ld a,b
+    0xEA, 0x00, 0xC0, // ld (COUNTER),a
+    0x18, 0xF9, // jr .loop
+    0x00, 0x00,
+];
+
+fn gb(model: Model) -> Gb<NoAudio> {
+    let mut rom = common::rom(&COUNT);
+    rom[0x134..0x13B].copy_from_slice(b"COUNTER");
+
+    common::gb_with_rom(model, rom)
+}
+
+fn run(gb: &mut Gb<NoAudio>, frames: usize) {
+    (0..frames).for_each(|_| {
+        gb.run_frame();
+    });
+}
+
+#[test]
+fn json_round_trip_keeps_the_state() {
+    for model in [Model::Dmg, Model::Cgb, Model::Sgb] {
+        let mut gb = gb(model);
+        run(&mut gb, 100);
+
+        let json = serde_json::to_string(&gb).unwrap();
+        let mut restored: Gb<NoAudio> = serde_json::from_str(&json).unwrap();
+        assert!(gb.diff_state(&restored).is_empty());
+
+        run(&mut gb, 30);
+        run(&mut restored, 30);
+        assert!(gb.diff_state(&restored).is_empty());
+        assert_eq!(gb.registers(), restored.registers());
+        assert_eq!(gb.pixel_data_rgb(), restored.pixel_data_rgb());
+    }
+}
+
+#[test]
+fn save_states_load_after_a_round_trip() {
+    let mut gb = gb(Model::Dmg);
+    let mut states = SaveStateManager::new(1);
+
+    run(&mut gb, 100);
+    states.quick_save(&gb, 0);
+    let json = serde_json::to_string(&gb.save_state(42)).unwrap();
+    run(&mut gb, 50);
+
+    let mut loaded = gb.clone();
+    loaded
+        .load_state(&serde_json::from_str(&json).unwrap())
+        .unwrap();
+    states.quick_load(&mut gb).unwrap();
+    assert!(gb.diff_state(&loaded).is_empty());
+}
+
+#[test]
+fn wrong_array_lengths_are_rejected() {
+    let gb = gb(Model::Dmg);
+    let mut value = serde_json::to_value(&gb).unwrap();
+    value["hram"].as_array_mut().unwrap().pop();
+
+    assert!(serde_json::from_value::<Gb<NoAudio>>(value).is_err());
+}
@@ -0,0 +1,212 @@
+// Super Game Boy commands, driven through P1 like a game would
+
+mod common;
+
+use ceres_core::{Button, Gb, Model, NoAudio, SGB_PX_WIDTH};
+
+const P1: u16 = 0xFF00;
+// MLT_REQ asking for two players
+const MLT_REQ_2P: [u8; 2] = [0x11 << 3 | 1, 0x01];
+
+fn gb(model: Model) -> Gb<NoAudio> {
+    let mut rom = vec![0; 0x8000];
+    // jr @, so only the test touches the hardware
+    rom[0x100..0x102].copy_from_slice(&[0x18, 0xFE]);
+
+    common::gb_with_rom(model, rom)
+}
+
+fn send_packet(gb: &mut Gb<NoAudio>, data: &[u8]) {
+    let mut packet = [0; 16];
+    packet[..data.len()].copy_from_slice(data);
+
+    gb.poke(P1, 0x00);
+    gb.poke(P1, 0x30);
+    for i in 0..128 {
+        let one = packet[i / 8] >> (i % 8) & 1 != 0;
+        gb.poke(P1, if one { 0x10 } else { 0x20 });
+        gb.poke(P1, 0x30);
+    }
+    // stop bit
+    gb.poke(P1, 0x20);
+    gb.poke(P1, 0x30);
+}
+
+// Reads the ID, then the action buttons of that player, then moves on
+fn read_player(gb: &mut Gb<NoAudio>) -> (u8, u8) {
+    gb.poke(P1, 0x30);
+    let id = gb.peek(P1) & 0xF;
+    gb.poke(P1, 0x10);
+    let buttons = gb.peek(P1) & 0xF;
+    gb.poke(P1, 0x30);
+    (id, buttons)
+}
+
+#[test]
+fn mlt_req_multiplexes_controllers() {
+    let mut gb = gb(Model::Sgb);
+    gb.press(Button::A);
+    gb.press_player(1, Button::Start);
+
+    assert_eq!(read_player(&mut gb), (0xF, 0xE));
+
+    send_packet(&mut gb, &MLT_REQ_2P);
+    assert_eq!(read_player(&mut gb), (0xF, 0xE));
+    assert_eq!(read_player(&mut gb), (0xE, 0x7));
+    assert_eq!(read_player(&mut gb), (0xF, 0xE));
+}
+
+#[test]
+fn only_sgb_listens() {
+    let mut gb = gb(Model::Dmg);
+    gb.press_player(1, Button::Start);
+
+    send_packet(&mut gb, &MLT_REQ_2P);
+    assert_eq!(read_player(&mut gb), (0xF, 0xF));
+    assert_eq!(read_player(&mut gb), (0xF, 0xF));
+}
+
+const LCDC: u16 = 0xFF40;
+const STAT: u16 = 0xFF41;
+const SCY: u16 = 0xFF42;
+const BGP: u16 = 0xFF47;
+const RED: u16 = 0x001F;
+const GREEN: u16 = 0x03E0;
+const BLUE: u16 = 0x7C00;
+const WHITE: u16 = 0x7FFF;
+
+fn rgb(color: u16) -> [u8; 3] {
+    let channel = |c: u16| {
+        let c = (c & 0x1F) as u8;
+        c << 3 | c >> 2
+    };
+    [channel(color), channel(color >> 5), channel(color >> 10)]
+}
+
+fn sgb_px(gb: &Gb<NoAudio>, x: usize, y: usize) -> [u8; 3] {
+    let frame = gb.sgb_pixel_data_rgb().unwrap();
+    let i = (y * usize::from(SGB_PX_WIDTH) + x) * 3;
+    [frame[i], frame[i + 1], frame[i + 2]]
+}
+
+// Splits a command into as many packets as it needs
+fn send_command(gb: &mut Gb<NoAudio>, command: &[u8]) {
+    for packet in command.chunks(16) {
+        send_packet(gb, packet);
+    }
+}
+
+fn colors(colors: [u16; 7]) -> Vec<u8> {
+    colors.iter().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+// Shows the bytes as 2 bit per pixel tiles, in the order the SNES reads
+// them back
+fn show(gb: &mut Gb<NoAudio>, data: &[u8]) {
+    // the LCD goes off in VBlank, like games do
+    while gb.peek(LCDC) & 0x80 != 0 && gb.peek(STAT) & 3 != 1 {
+        gb.run_dots(4);
+    }
+    gb.poke(LCDC, 0x00);
+    for (i, &byte) in data.iter().enumerate() {
+        gb.poke(0x8000 + i as u16, byte);
+    }
+    for tile in 0..256_u16 {
+        gb.poke(0x9800 + tile / 20 * 32 + tile % 20, tile as u8);
+    }
+    gb.poke(SCY, 0);
+    gb.poke(BGP, 0xE4);
+    gb.poke(LCDC, 0x91);
+}
+
+fn run_frames(gb: &mut Gb<NoAudio>, frames: usize) {
+    for _ in 0..frames {
+        gb.run_frame();
+    }
+}
+
+// Past the bootrom scrolling the logo, it stops on the idle cartridge
+fn booted(model: Model) -> Gb<NoAudio> {
+    let mut gb = gb(model);
+    run_frames(&mut gb, 200);
+    gb
+}
+
+#[test]
+fn pal01_colors_the_screen() {
+    let mut gb = booted(Model::Sgb);
+    // a blank screen has every pixel in shade 0
+    show(&mut gb, &[]);
+
+    // PAL01, one packet
+    let mut command = vec![1];
+    command.extend(colors([RED, GREEN, BLUE, 0, WHITE, WHITE, WHITE]));
+    send_command(&mut gb, &command);
+    run_frames(&mut gb, 2);
+
+    assert_eq!(sgb_px(&gb, 48, 40), rgb(RED));
+    assert_eq!(sgb_px(&gb, 48 + 159, 40 + 143), rgb(RED));
+    // no border yet, the backdrop shows around the screen
+    assert_eq!(sgb_px(&gb, 0, 0), rgb(RED));
+}
+
+#[test]
+fn attr_div_splits_the_palettes() {
+    let mut gb = booted(Model::Sgb2);
+    // shade 3 everywhere
+    show(&mut gb, &[0xFF; 16]);
+    for tile in 0..0x400 {
+        gb.poke(0x9800 + tile, 0);
+    }
+
+    // PAL01, one packet
+    let mut command = vec![1];
+    command.extend(colors([WHITE, 0, 0, RED, 0, 0, BLUE]));
+    send_command(&mut gb, &command);
+    // horizontal split at row 9: palette 0 above, 1 on and below it
+    send_command(&mut gb, &[0x06 << 3 | 1, 0x40 | 0x10 | 0x01, 9]);
+    run_frames(&mut gb, 2);
+
+    assert_eq!(sgb_px(&gb, 48, 40), rgb(RED));
+    assert_eq!(sgb_px(&gb, 48, 40 + 9 * 8 - 1), rgb(RED));
+    assert_eq!(sgb_px(&gb, 48, 40 + 9 * 8), rgb(BLUE));
+    assert_eq!(sgb_px(&gb, 48 + 159, 40 + 143), rgb(BLUE));
+}
+
+#[test]
+fn border_is_transferred_through_vram() {
+    let mut gb = booted(Model::Sgb);
+
+    // tile 1 is color 1 all over, the rest transparent
+    let mut tiles = vec![0; 0x1000];
+    for row in 0..8 {
+        tiles[32 + row * 2] = 0xFF;
+    }
+    show(&mut gb, &tiles);
+    send_command(&mut gb, &[0x13 << 3 | 1, 0x00]);
+    run_frames(&mut gb, 3);
+
+    // tile 1 with palette 4 on the left half, tile 0 on the right
+    let mut border = vec![0; 0x1000];
+    for row in 0..28 {
+        for col in 0..16 {
+            let i = (row * 32 + col) * 2;
+            border[i..i + 2].copy_from_slice(&(1_u16 | 4 << 10).to_le_bytes());
+        }
+    }
+    border[0x800 + 2..0x800 + 4].copy_from_slice(&GREEN.to_le_bytes());
+    show(&mut gb, &border);
+    send_command(&mut gb, &[0x14 << 3 | 1]);
+    run_frames(&mut gb, 3);
+
+    assert_eq!(sgb_px(&gb, 0, 0), rgb(GREEN));
+    assert_eq!(sgb_px(&gb, 127, 223), rgb(GREEN));
+    // covers the screen too
+    assert_eq!(sgb_px(&gb, 60, 60), rgb(GREEN));
+    assert_ne!(sgb_px(&gb, 255, 0), rgb(GREEN));
+}
+
+#[test]
+fn other_models_have_no_sgb_frame() {
+    assert!(gb(Model::Dmg).sgb_pixel_data_rgb().is_none());
+}
@@ -0,0 +1,45 @@
+// Gb::try_new rejects bad sample rates and warns about odd model choices
+
+use ceres_core::{BuildError, BuildWarning, Cart, Gb, Model, NoAudio, TC_SEC};
+
+fn cart(cgb_flag: u8) -> Cart {
+    let mut rom = vec![0; 0x8000];
+    rom[0x143] = cgb_flag;
+    Cart::new(rom.into_boxed_slice()).unwrap()
+}
+
+fn warnings(model: Model, cgb_flag: u8) -> Vec<BuildWarning> {
+    Gb::try_new(model, 48000, cart(cgb_flag), NoAudio)
+        .unwrap()
+        .1
+}
+
+#[test]
+fn sample_rates_are_checked() {
+    for rate in [0, -48000, TC_SEC + 1] {
+        let error = Gb::try_new(Model::Dmg, rate, cart(0), NoAudio).err();
+        assert_eq!(error, Some(BuildError::SampleRate(rate)));
+    }
+
+    let (_gb, warnings) = Gb::try_new(Model::Dmg, TC_SEC, cart(0), NoAudio).unwrap();
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn cgb_only_games_warn_on_a_dmg() {
+    assert_eq!(warnings(Model::Dmg, 0xC0), [BuildWarning::CgbOnlyOnDmg]);
+    assert_eq!(warnings(Model::Sgb, 0xC0), [BuildWarning::CgbOnlyOnDmg]);
+    assert!(warnings(Model::Dmg, 0x80).is_empty());
+    assert!(warnings(Model::Cgb, 0xC0).is_empty());
+}
+
+#[test]
+fn unusual_cgb_flags_warn_on_a_cgb() {
+    assert_eq!(
+        warnings(Model::Cgb, 0x84),
+        [BuildWarning::UnusualCgbFlag(0x84)]
+    );
+    assert!(warnings(Model::Cgb, 0x80).is_empty());
+    // a DMG game's title may run into the flag
+    assert!(warnings(Model::Cgb, b'E').is_empty());
+}
@@ -0,0 +1,41 @@
+// Tile and sprite viewers follow VRAM and OAM through the video log
+
+mod common;
+
+use ceres_core::{Model, VideoWrite};
+
+// Writes a tile byte twice and a sprite Y, then spins
+const DRAW: [u8; 13] = [
+    0x3E, 0x7E, // ld a,$7E
+    0xEA, 0x10, 0x80, // ld ($8010),a
+    0xEA, 0x10, 0x80, // ld ($8010),a
+    0xEA, 0x04, 0xFE, // ld ($FE04),a
+    0x18, 0xFE, // jr @
+];
+
+#[test]
+fn only_changes_are_logged() {
+    let mut gb = common::gb(Model::Dmg, &DRAW);
+    gb.set_video_logging(true);
+    gb.run_frame();
+
+    let writes = gb.drain_video_writes().collect::<Vec<_>>();
+    assert_eq!(
+        writes,
+        [
+            VideoWrite::Vram {
+                bank: 0,
+                addr: 0x8010,
+                val: 0x7E
+            },
+            VideoWrite::Oam {
+                addr: 0xFE04,
+                val: 0x7E
+            },
+        ]
+    );
+
+    gb.set_video_logging(false);
+    gb.run_frame();
+    assert_eq!(gb.drain_video_writes().count(), 0);
+}
@@ -0,0 +1,88 @@
+// Audio recorded as WAV files
+
+use {
+    ceres_core::{AudioCallback, Cart, Gb, Model},
+    std::sync::mpsc::{channel, Receiver, Sender},
+};
+
+struct Recorder(Sender<(f32, f32)>);
+
+impl AudioCallback for Recorder {
+    type Sample = f32;
+
+    fn audio_sample(&self, l: f32, r: f32) {
+        self.0.send((l, r)).unwrap();
+    }
+}
+
+fn gb() -> (Gb<Recorder>, Receiver<(f32, f32)>) {
+    let mut rom = vec![0; 0x8000];
+    // nop, jp $0150
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    // jr @
+    rom[0x150..0x152].copy_from_slice(&[0x18, 0xFE]);
+
+    let cart = Cart::new(rom.into_boxed_slice()).unwrap();
+    let (tx, rx) = channel();
+    (Gb::new(Model::Dmg, 48000, cart, Recorder(tx)), rx)
+}
+
+fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(data[at..at + 2].try_into().unwrap())
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn records_what_the_callback_gets() {
+    let (mut gb, rx) = gb();
+    assert_eq!(gb.finish_audio_recording(), None);
+
+    gb.run_frame();
+    rx.try_iter().for_each(drop);
+    gb.start_audio_recording(false);
+    assert!(gb.is_recording_audio());
+    // pulse 1 at full volume
+    gb.poke(0xFF26, 0x80);
+    gb.poke(0xFF24, 0x77);
+    gb.poke(0xFF25, 0xFF);
+    gb.poke(0xFF12, 0xF0);
+    gb.poke(0xFF14, 0x86);
+    for _ in 0..10 {
+        gb.run_frame();
+    }
+    let wav = gb.finish_audio_recording().unwrap();
+    assert!(!gb.is_recording_audio());
+    let samples = rx.try_iter().collect::<Vec<_>>();
+
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(u32_at(&wav, 4) as usize, wav.len() - 8);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    // PCM, stereo, 48 kHz, 16 bits
+    assert_eq!(u16_at(&wav, 20), 1);
+    assert_eq!(u16_at(&wav, 22), 2);
+    assert_eq!(u32_at(&wav, 24), 48000);
+    assert_eq!(u16_at(&wav, 32), 4);
+    assert_eq!(u16_at(&wav, 34), 16);
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(u32_at(&wav, 40) as usize, samples.len() * 4);
+
+    for (i, (l, _)) in samples.iter().enumerate() {
+        let recorded = i16::from_le_bytes(wav[44 + i * 4..46 + i * 4].try_into().unwrap());
+        assert!((f32::from(recorded) / f32::from(i16::MAX) - l).abs() < 0.001);
+    }
+}
+
+#[test]
+fn raw_channels_follow_the_mix() {
+    let (mut gb, rx) = gb();
+    gb.start_audio_recording(true);
+    gb.run_frame();
+    let wav = gb.finish_audio_recording().unwrap();
+
+    assert_eq!(u16_at(&wav, 22), 6);
+    assert_eq!(u16_at(&wav, 32), 12);
+    assert_eq!(u32_at(&wav, 40) as usize, rx.try_iter().count() * 12);
+}
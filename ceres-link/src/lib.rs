@@ -0,0 +1,466 @@
+use ceres_core::{Cart, SerialDevice};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 5738;
+// Bumped whenever messages change, both ends must match
+const PROTOCOL_VERSION: u8 = 1;
+const MAGIC: [u8; 4] = *b"CRLK";
+const PINGS: u8 = 4;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// How often a host waiting for a player checks if it was cancelled
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+// The peer only answers while its emulation thread runs, which sleeps
+// between frames, so waits are a few frames longer than the round trip
+const TRANSFER_SLACK: Duration = Duration::from_millis(50);
+
+const HELLO: u8 = 0;
+const PING: u8 = 1;
+const PONG: u8 = 2;
+const TRANSFER: u8 = 3;
+const REPLY: u8 = 4;
+
+// Tells apart the ROMs two players run, the global checksum covers the
+// whole ROM so different revisions and hacks don't match
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomId {
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl RomId {
+    #[must_use]
+    pub const fn of(cart: &Cart) -> Self {
+        Self {
+            header_checksum: cart.header_checksum(),
+            global_checksum: cart.global_checksum(),
+        }
+    }
+}
+
+// Not an error, trading between Pokemon Red and Blue or Gold and Silver
+// always links two different ROMs, but a mismatch may also mean the wrong
+// game was loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomMismatch {
+    pub ours: RomId,
+    pub theirs: RomId,
+}
+
+impl std::fmt::Display for RomMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "the peer runs a different ROM, global checksum ${:04X} instead of ${:04X}",
+            self.theirs.global_checksum, self.ours.global_checksum
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    NotCeres,
+    VersionMismatch { ours: u8, theirs: u8 },
+    Timeout,
+    Cancelled,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::NotCeres => write!(f, "the peer isn't a Ceres link"),
+            Error::VersionMismatch { ours, theirs } => write!(
+                f,
+                "link protocol version {theirs} doesn't match ours, {ours}, update both emulators"
+            ),
+            Error::Timeout => write!(f, "the peer stopped answering during the handshake"),
+            Error::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+// Waits for the other player to join
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub fn bind(port: u16) -> Result<Self, Error> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        // polled so waiting can be cancelled, see accept_unless
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener })
+    }
+
+    // The bound port, useful after binding port 0
+    pub fn port(&self) -> Result<u16, Error> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    // Blocks until a player joins
+    pub fn accept(self, rom: RomId) -> Result<Link, Error> {
+        self.accept_unless(rom, &AtomicBool::new(false))
+    }
+
+    // Blocks until a player joins or cancel is set, the port is free again
+    // once this returns
+    pub fn accept_unless(self, rom: RomId, cancel: &AtomicBool) -> Result<Link, Error> {
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(Error::Cancelled);
+            }
+
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    // some platforms hand out sockets as non-blocking as the
+                    // listener
+                    stream.set_nonblocking(false)?;
+                    return Link::handshake(stream, rom);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(ACCEPT_POLL);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+enum Message {
+    Hello {
+        magic: [u8; 4],
+        version: u8,
+        rom: RomId,
+    },
+    Ping(u8),
+    Pong(u8),
+    // the peer clocked a transfer, numbered so late replies can be told apart
+    Transfer {
+        seq: u16,
+        byte: u8,
+    },
+    Reply {
+        seq: u16,
+        byte: u8,
+    },
+}
+
+impl Message {
+    fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
+        let mut buf = Vec::with_capacity(9);
+        match *self {
+            Message::Hello {
+                magic,
+                version,
+                rom,
+            } => {
+                buf.push(HELLO);
+                buf.extend_from_slice(&magic);
+                buf.push(version);
+                buf.push(rom.header_checksum);
+                buf.extend_from_slice(&rom.global_checksum.to_le_bytes());
+            }
+            Message::Ping(n) => buf.extend_from_slice(&[PING, n]),
+            Message::Pong(n) => buf.extend_from_slice(&[PONG, n]),
+            Message::Transfer { seq, byte } => {
+                buf.push(TRANSFER);
+                buf.extend_from_slice(&seq.to_le_bytes());
+                buf.push(byte);
+            }
+            Message::Reply { seq, byte } => {
+                buf.push(REPLY);
+                buf.extend_from_slice(&seq.to_le_bytes());
+                buf.push(byte);
+            }
+        }
+
+        w.write_all(&buf)
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self, Error> {
+        let mut tag = [0];
+        r.read_exact(&mut tag)?;
+
+        Ok(match tag[0] {
+            HELLO => {
+                let mut buf = [0; 8];
+                r.read_exact(&mut buf)?;
+                let [m0, m1, m2, m3, version, header_checksum, lo, hi] = buf;
+                Message::Hello {
+                    magic: [m0, m1, m2, m3],
+                    version,
+                    rom: RomId {
+                        header_checksum,
+                        global_checksum: u16::from_le_bytes([lo, hi]),
+                    },
+                }
+            }
+            PING | PONG => {
+                let mut n = [0];
+                r.read_exact(&mut n)?;
+                if tag[0] == PING {
+                    Message::Ping(n[0])
+                } else {
+                    Message::Pong(n[0])
+                }
+            }
+            TRANSFER | REPLY => {
+                let mut buf = [0; 3];
+                r.read_exact(&mut buf)?;
+                let seq = u16::from_le_bytes([buf[0], buf[1]]);
+                if tag[0] == TRANSFER {
+                    Message::Transfer { seq, byte: buf[2] }
+                } else {
+                    Message::Reply { seq, byte: buf[2] }
+                }
+            }
+            _ => return Err(Error::NotCeres),
+        })
+    }
+}
+
+// A link cable to another Ceres over TCP, plugged in with
+// LinkPort::Device.
+//
+// Transfers run in lockstep: the side clocking a transfer sends its byte and
+// blocks until the peer answers with the byte in its SB. A peer that isn't
+// waiting for a transfer doesn't answer in time and the byte reads as 0xFF,
+// like with the cable unplugged.
+pub struct Link {
+    writer: Arc<Mutex<TcpStream>>,
+    messages: Receiver<Message>,
+    peer: SocketAddr,
+    round_trip: Duration,
+    rom_mismatch: Option<RomMismatch>,
+    seq: u16,
+    // clocked by the peer, answered by the next exchange
+    pending: Option<u16>,
+    received: u8,
+}
+
+impl Link {
+    // Blocks until connected
+    pub fn join(addr: impl ToSocketAddrs, rom: RomId) -> Result<Self, Error> {
+        let stream = TcpStream::connect(addr)?;
+        Self::handshake(stream, rom)
+    }
+
+    #[must_use]
+    pub const fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    // Measured while connecting
+    #[must_use]
+    pub const fn round_trip(&self) -> Duration {
+        self.round_trip
+    }
+
+    // Set when the peer runs another ROM, the link works all the same
+    #[must_use]
+    pub const fn rom_mismatch(&self) -> Option<RomMismatch> {
+        self.rom_mismatch
+    }
+
+    fn handshake(stream: TcpStream, rom: RomId) -> Result<Self, Error> {
+        // transfers are single bytes, don't wait to fill a packet
+        stream.set_nodelay(true)?;
+        let peer = stream.peer_addr()?;
+
+        let mut reader = stream.try_clone()?;
+        reader.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let writer = Arc::new(Mutex::new(stream));
+
+        Message::Hello {
+            magic: MAGIC,
+            version: PROTOCOL_VERSION,
+            rom,
+        }
+        .write(&mut *lock(&writer))?;
+
+        let rom_mismatch = match Message::read(&mut reader).map_err(handshake_error)? {
+            Message::Hello { magic, .. } if magic != MAGIC => return Err(Error::NotCeres),
+            Message::Hello { version, .. } if version != PROTOCOL_VERSION => {
+                return Err(Error::VersionMismatch {
+                    ours: PROTOCOL_VERSION,
+                    theirs: version,
+                })
+            }
+            Message::Hello { rom: theirs, .. } => {
+                (theirs != rom).then_some(RomMismatch { ours: rom, theirs })
+            }
+            _ => return Err(Error::NotCeres),
+        };
+
+        reader.set_read_timeout(None)?;
+        let (sender, messages) = mpsc::channel();
+        {
+            let writer = Arc::clone(&writer);
+            std::thread::Builder::new()
+                .name("link".to_owned())
+                .spawn(move || {
+                    // pings are answered here so the peer can measure latency
+                    // while emulation is paused
+                    while let Ok(message) = Message::read(&mut reader) {
+                        let res = match message {
+                            Message::Ping(n) => Message::Pong(n).write(&mut *lock(&writer)),
+                            message => sender.send(message).map_err(std::io::Error::other),
+                        };
+
+                        if res.is_err() {
+                            break;
+                        }
+                    }
+                })?;
+        }
+
+        let mut link = Self {
+            writer,
+            messages,
+            peer,
+            round_trip: Duration::ZERO,
+            rom_mismatch,
+            seq: 0,
+            pending: None,
+            received: 0xFF,
+        };
+        link.round_trip = link.measure_round_trip()?;
+
+        Ok(link)
+    }
+
+    // Average of a few pings, the peer may finish first and start a transfer
+    fn measure_round_trip(&mut self) -> Result<Duration, Error> {
+        let mut total = Duration::ZERO;
+
+        for n in 0..PINGS {
+            let start = Instant::now();
+            self.send(&Message::Ping(n))?;
+
+            loop {
+                match self.messages.recv_timeout(HANDSHAKE_TIMEOUT) {
+                    Ok(Message::Pong(pong)) if pong == n => break,
+                    Ok(Message::Transfer { seq, byte }) => {
+                        self.pending = Some(seq);
+                        self.received = byte;
+                    }
+                    Ok(_) => (),
+                    Err(_) => return Err(Error::Timeout),
+                }
+            }
+
+            total += start.elapsed();
+        }
+
+        Ok(total / u32::from(PINGS))
+    }
+
+    fn send(&self, message: &Message) -> std::io::Result<()> {
+        message.write(&mut *lock(&self.writer))
+    }
+}
+
+impl SerialDevice for Link {
+    // Never clocked bit by bit, see shifts_bits
+    fn exchange_bit(&mut self, _sent: bool) -> bool {
+        true
+    }
+
+    fn exchange_byte(&mut self, sent: u8) -> u8 {
+        if let Some(seq) = self.pending.take() {
+            // a lost connection reads like an unplugged cable, there's no one
+            // to report it to from here
+            if self.send(&Message::Reply { seq, byte: sent }).is_err() {
+                return 0xFF;
+            }
+            return self.received;
+        }
+
+        self.seq = self.seq.wrapping_add(1);
+        if self
+            .send(&Message::Transfer {
+                seq: self.seq,
+                byte: sent,
+            })
+            .is_err()
+        {
+            return 0xFF;
+        }
+
+        let deadline = Instant::now() + self.round_trip + TRANSFER_SLACK;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.messages.recv_timeout(timeout) {
+                Ok(Message::Reply { seq, byte }) if seq == self.seq => return byte,
+                // both sides clocking shift in each other's byte
+                Ok(Message::Transfer { seq, .. }) => {
+                    if self.send(&Message::Reply { seq, byte: sent }).is_err() {
+                        return 0xFF;
+                    }
+                }
+                // late replies to transfers that already timed out
+                Ok(_) => (),
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return 0xFF,
+            }
+        }
+    }
+
+    fn clocks_transfer(&mut self) -> bool {
+        if self.pending.is_some() {
+            return true;
+        }
+
+        loop {
+            match self.messages.try_recv() {
+                Ok(Message::Transfer { seq, byte }) => {
+                    self.pending = Some(seq);
+                    self.received = byte;
+                    return true;
+                }
+                Ok(_) => (),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    fn shifts_bits(&self) -> bool {
+        false
+    }
+}
+
+// A panic while holding the lock can't leave a half written message, writes
+// are single calls
+fn lock(writer: &Mutex<TcpStream>) -> std::sync::MutexGuard<'_, TcpStream> {
+    writer
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn handshake_error(e: Error) -> Error {
+    match e {
+        Error::Io(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+            ) =>
+        {
+            Error::Timeout
+        }
+        Error::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Error::NotCeres,
+        e => e,
+    }
+}
@@ -0,0 +1,126 @@
+// Two consoles swapping a byte over a loopback link, one clocking the
+// transfer and the other waiting for it
+
+use ceres_core::{Cart, Gb, LinkPort, Model, NoAudio, StopReason};
+use ceres_link::{Error, Host, Link, RomId, RomMismatch};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const SB: u8 = 0x01;
+const SC: u8 = 0x02;
+
+// Starts a transfer with sc, stops at ld b,b with the received byte in A
+const fn swap(sent: u8, sc: u8) -> [u8; 16] {
+    [
+        0x3E, sent, // ld a,sent
+        0xE0, SB, // ldh (SB),a
+        0x3E, sc, // ld a,sc
+        0xE0, SC, // ldh (SC),a
+        0xF0, SC,   // .wait: ldh a,(SC)
+        0x87, // add a
+        0x38, 0xFB, // jr c,.wait
+        0xF0, SB,   // ldh a,(SB)
+        0x40, // ld b,b
+    ]
+}
+
+fn gb(program: &[u8]) -> Gb<NoAudio> {
+    gb_with_checksum(program, 0)
+}
+
+// Linking two games, like Pokemon Red and Blue, whose ROMs differ
+fn gb_with_checksum(program: &[u8], global_checksum: u16) -> Gb<NoAudio> {
+    let mut rom = vec![0; 0x8000];
+
+    // nop, jp $0150
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    rom[0x14E..0x150].copy_from_slice(&global_checksum.to_be_bytes());
+    rom[0x150..0x150 + program.len()].copy_from_slice(program);
+
+    let cart = Cart::new(rom.into_boxed_slice()).unwrap();
+    let mut gb = Gb::new(Model::Dmg, 48000, cart, NoAudio);
+    gb.set_ld_b_b_breakpoint(true);
+    gb
+}
+
+fn received(mut gb: Gb<NoAudio>, link: Link) -> u8 {
+    gb.set_link_port(LinkPort::Device(Box::new(link)));
+    assert!(matches!(
+        gb.run_until_break(),
+        StopReason::Breakpoint { .. }
+    ));
+    (gb.registers().af >> 8) as u8
+}
+
+#[test]
+fn swaps_bytes() {
+    let host = Host::bind(0).unwrap();
+    let port = host.port().unwrap();
+
+    let slave = gb(&swap(0x55, 0x80));
+    let rom = RomId::of(slave.cartridge());
+    let slave = std::thread::spawn(move || received(slave, host.accept(rom).unwrap()));
+
+    let master = gb(&swap(0x29, 0x81));
+    let link = Link::join(("127.0.0.1", port), rom).unwrap();
+    assert_eq!(link.rom_mismatch(), None);
+
+    assert_eq!(received(master, link), 0x55);
+    assert_eq!(slave.join().unwrap(), 0x29);
+}
+
+#[test]
+fn links_different_roms() {
+    let host = Host::bind(0).unwrap();
+    let port = host.port().unwrap();
+
+    let slave = gb_with_checksum(&swap(0x55, 0x80), 0x1234);
+    let slave_rom = RomId::of(slave.cartridge());
+    let slave = std::thread::spawn(move || {
+        let link = host.accept(slave_rom).unwrap();
+        let mismatch = link.rom_mismatch();
+        (received(slave, link), mismatch)
+    });
+
+    let master = gb_with_checksum(&swap(0x29, 0x81), 0x4321);
+    let master_rom = RomId::of(master.cartridge());
+    let link = Link::join(("127.0.0.1", port), master_rom).unwrap();
+    assert_eq!(
+        link.rom_mismatch(),
+        Some(RomMismatch {
+            ours: master_rom,
+            theirs: slave_rom,
+        })
+    );
+
+    assert_eq!(received(master, link), 0x55);
+    let (byte, mismatch) = slave.join().unwrap();
+    assert_eq!(byte, 0x29);
+    assert_eq!(
+        mismatch,
+        Some(RomMismatch {
+            ours: slave_rom,
+            theirs: master_rom,
+        })
+    );
+}
+
+#[test]
+fn cancelling_frees_the_port() {
+    let host = Host::bind(0).unwrap();
+    let port = host.port().unwrap();
+
+    let rom = RomId {
+        header_checksum: 0,
+        global_checksum: 0,
+    };
+    let cancel = Arc::new(AtomicBool::new(false));
+    let waiting = {
+        let cancel = Arc::clone(&cancel);
+        std::thread::spawn(move || host.accept_unless(rom, &cancel).map(|_| ()))
+    };
+
+    cancel.store(true, Ordering::Relaxed);
+    assert!(matches!(waiting.join().unwrap(), Err(Error::Cancelled)));
+    Host::bind(port).unwrap();
+}
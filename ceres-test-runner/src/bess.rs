@@ -0,0 +1,84 @@
+// Round trips of BESS states made by other emulators. Each state is named
+// <rom>.<emulator>.<model>.bess and sits next to <rom>.gb. It's loaded,
+// exported by us and loaded again, then both consoles run side by side and
+// have to end up the same.
+//
+// The states in ceres-test-runner/bess are built by hand in the layouts
+// SameBoy and mGBA write, not captured from them: the SameBoy ones keep
+// their buffers inside a native state before the blocks, the mGBA ones after
+// the blocks. States saved by the emulators themselves can be dropped in
+// with the same naming.
+
+use crate::{Model, SAMPLE_RATE};
+use ceres_core::{Cart, Gb, NoAudio};
+use std::path::{Path, PathBuf};
+
+// States in dir, sorted so runs are comparable
+pub fn find_states(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut states = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("bess"))
+        {
+            states.push(path);
+        }
+    }
+    states.sort();
+
+    Ok(states)
+}
+
+// The ROM and the model a state was made with, from its name
+fn rom_and_model(state: &Path) -> anyhow::Result<(PathBuf, Model)> {
+    let name = state
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    let mut parts = name.split('.');
+    let (Some(rom), Some(_emulator), Some(model), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        anyhow::bail!("state isn't named <rom>.<emulator>.<model>.bess");
+    };
+    let model = <Model as clap::ValueEnum>::from_str(model, true)
+        .map_err(|e| anyhow::anyhow!("unknown model: {e}"))?;
+
+    Ok((state.with_file_name(rom).with_extension("gb"), model))
+}
+
+fn load(rom: &[u8], model: Model, state: &[u8]) -> anyhow::Result<Gb<NoAudio>> {
+    let cart = Cart::new(rom.into())?;
+    let mut gb = Gb::new(model.into(), SAMPLE_RATE, cart, NoAudio);
+    gb.load_bess(state, 0)?;
+
+    Ok(gb)
+}
+
+// What differs between the console loaded from the reference state and the
+// one loaded from our export of it after running for a while, None if
+// nothing does
+pub fn check(state: &Path, frames: u32) -> anyhow::Result<Option<String>> {
+    let (rom_path, model) = rom_and_model(state)?;
+    let rom = std::fs::read(rom_path)?;
+
+    let mut reference = load(&rom, model, &std::fs::read(state)?)?;
+    let mut exported = load(&rom, model, &reference.save_bess(0))?;
+
+    for _ in 0..frames {
+        reference.run_frame();
+        exported.run_frame();
+    }
+
+    let diff = reference.diff_state(&exported);
+    let difference = if !diff.is_empty() {
+        Some(diff.to_string())
+    } else if reference.pixel_data_rgb() != exported.pixel_data_rgb() {
+        Some("screen".to_owned())
+    } else {
+        None
+    };
+
+    Ok(difference)
+}
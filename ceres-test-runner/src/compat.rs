@@ -0,0 +1,205 @@
+use crate::{Model, SAMPLE_RATE};
+use ceres_core::{Cart, Error, Gb, InvalidOpcodePolicy, NoAudio, StopReason};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Running,
+    // Every pixel is the same colour, usually white
+    BlankScreen,
+    InvalidOpcode,
+    UnsupportedMapper,
+    // The ROM couldn't be loaded for another reason
+    LoadError,
+    // The process running the ROM died, from a panic or otherwise
+    Crash,
+}
+
+impl Status {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Status::Running => "running",
+            Status::BlankScreen => "blank_screen",
+            Status::InvalidOpcode => "invalid_opcode",
+            Status::UnsupportedMapper => "unsupported_mapper",
+            Status::LoadError => "load_error",
+            Status::Crash => "crash",
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+    pub rom: PathBuf,
+    pub title: String,
+    pub status: Status,
+    pub frames: u32,
+    pub detail: String,
+}
+
+// ROMs in dir and its subdirectories, sorted so reports are comparable
+pub fn find_roms(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(current)? {
+            let path = entry?.path();
+
+            let is_rom = path.extension().is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("gb") || ext.eq_ignore_ascii_case("gbc")
+            });
+
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+
+            if is_rom {
+                roms.push(path);
+            }
+        }
+    }
+
+    roms.sort();
+    Ok(roms)
+}
+
+// Runs the ROM in a child process, so a crash only takes that ROM down,
+// even in builds that abort on panic
+pub fn run(rom: &Path, model: Model, frames: u32) -> Entry {
+    let mut entry = Entry {
+        rom: rom.to_path_buf(),
+        title: String::new(),
+        status: Status::Crash,
+        frames: 0,
+        detail: String::new(),
+    };
+
+    let output = std::env::current_exe().and_then(|runner| {
+        Command::new(runner)
+            .arg("compat")
+            .arg("--model")
+            .arg(model.to_string())
+            .arg("--frames")
+            .arg(frames.to_string())
+            .arg("--rom")
+            .arg(rom)
+            .output()
+    });
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            entry.detail = format!("couldn't start the runner: {e}");
+            return entry;
+        }
+    };
+
+    if !output.status.success() {
+        // the panic message follows the line with its location
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr
+            .lines()
+            .skip_while(|line| !line.contains("panicked at"))
+            .nth(1)
+            .unwrap_or_default();
+        entry.detail = format!("{message} ({})", output.status)
+            .trim_start()
+            .to_owned();
+        return entry;
+    }
+
+    match serde_json::from_slice(&output.stdout) {
+        Ok(child) => child,
+        Err(e) => {
+            entry.detail = format!("couldn't read the result: {e}");
+            entry
+        }
+    }
+}
+
+// What the child process started by run does, the result goes to stdout
+pub fn run_here(rom: &Path, model: Model, frames: u32) -> anyhow::Result<()> {
+    let entry = run_rom(rom, model, frames);
+    serde_json::to_writer(std::io::stdout().lock(), &entry)?;
+    Ok(())
+}
+
+fn run_rom(rom: &Path, model: Model, frames: u32) -> Entry {
+    let mut entry = Entry {
+        rom: rom.to_path_buf(),
+        title: String::new(),
+        status: Status::LoadError,
+        frames: 0,
+        detail: String::new(),
+    };
+
+    let cart = match std::fs::read(rom)
+        .map_err(anyhow::Error::from)
+        .and_then(|rom| Ok(Cart::new(rom.into_boxed_slice())?))
+    {
+        Ok(cart) => cart,
+        Err(e) => {
+            if let Some(Error::UnsupportedMBC(_)) = e.downcast_ref::<Error>() {
+                entry.status = Status::UnsupportedMapper;
+            }
+            entry.detail = e.to_string();
+            return entry;
+        }
+    };
+
+    entry.title = String::from_utf8_lossy(cart.ascii_title()).into_owned();
+
+    let mut gb = Gb::new(model.into(), SAMPLE_RATE, cart, NoAudio);
+    gb.set_invalid_opcode_policy(InvalidOpcodePolicy::Trap);
+
+    for frame in 1..=frames {
+        if let StopReason::InvalidOpcode { pc, op } = gb.run_frame() {
+            entry.status = Status::InvalidOpcode;
+            entry.frames = frame;
+            entry.detail = format!("opcode {op:#04x} at {pc:#06x}");
+            return entry;
+        }
+    }
+
+    let screen = gb.pixel_data_rgb();
+    let blank = screen.chunks_exact(3).all(|px| px == &screen[..3]);
+    entry.status = if blank {
+        Status::BlankScreen
+    } else {
+        Status::Running
+    };
+    entry.frames = frames;
+
+    entry
+}
+
+pub fn write_csv(path: &Path, entries: &[Entry]) -> anyhow::Result<()> {
+    let mut csv = String::from("rom,title,status,frames,detail\n");
+
+    for entry in entries {
+        writeln!(
+            csv,
+            "{},{},{},{},{}",
+            quote(&entry.rom.display().to_string()),
+            quote(&entry.title),
+            entry.status.as_str(),
+            entry.frames,
+            quote(&entry.detail)
+        )?;
+    }
+
+    std::fs::write(path, csv)?;
+    Ok(())
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
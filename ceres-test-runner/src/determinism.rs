@@ -0,0 +1,96 @@
+use crate::Model;
+use ceres_core::{Cart, Gb, NoAudio, TC_PER_FRAME};
+use std::path::Path;
+
+// Dots per run_dots call, cycled through. Primes, so calls don't line up
+// with frames, scanlines or each other.
+const CHUNKS: [i32; 6] = [1, 7, 113, 4_567, 70_001, 17_569];
+// The largest of CHUNKS, bounds how far past the target instruction a
+// chunk can run
+const LARGEST_CHUNK: i32 = 70_001;
+// The fastest instructions take four dots
+const MIN_DOTS_PER_INSTRUCTION: i32 = 4;
+
+fn console(rom: &[u8], model: Model, sample_rate: i32) -> anyhow::Result<Gb<NoAudio>> {
+    let cart = Cart::new(rom.into())?;
+    Ok(Gb::new(model.into(), sample_rate, cart, NoAudio))
+}
+
+// Steps until the instruction count reaches target. False if the CPU stops
+// running instructions, like after halt with interrupts off.
+fn step_to(gb: &mut Gb<NoAudio>, target: u64) -> bool {
+    let mut steps = 0;
+    while gb.stats().instructions < target {
+        if steps > TC_PER_FRAME {
+            return false;
+        }
+        gb.step_instruction();
+        steps += 1;
+    }
+
+    true
+}
+
+// Runs the console for frames and then to the end of the next instruction,
+// returns how many instructions that took
+fn run_frames(gb: &mut Gb<NoAudio>, frames: u32) -> anyhow::Result<u64> {
+    for _ in 0..frames {
+        gb.run_frame();
+    }
+
+    // the frame can end in the middle of a halt
+    let target = gb.stats().instructions + 1;
+    if !step_to(gb, target) {
+        anyhow::bail!("the CPU stopped running instructions, the runs can't be lined up");
+    }
+
+    Ok(target)
+}
+
+// Runs the console in chunks to the end of instruction target
+fn run_dots(gb: &mut Gb<NoAudio>, target: u64) -> anyhow::Result<()> {
+    let margin = u64::from((LARGEST_CHUNK / MIN_DOTS_PER_INSTRUCTION).unsigned_abs()) + 1;
+    for dots in CHUNKS.into_iter().cycle() {
+        if gb.stats().instructions + margin >= target {
+            break;
+        }
+        gb.run_dots(dots);
+    }
+
+    if !step_to(gb, target) {
+        anyhow::bail!("the CPU stopped running instructions before the other run did");
+    }
+
+    Ok(())
+}
+
+// Runs the ROM like a frontend pacing by frames would at the first sample
+// rate, and in uneven run_dots chunks at the second, like one pacing by
+// audio. Both stop right after the same instruction. Describes what differs
+// between them, None when nothing does.
+pub fn check(
+    path: &Path,
+    model: Model,
+    frames: u32,
+    sample_rates: [i32; 2],
+) -> anyhow::Result<Option<String>> {
+    let rom = std::fs::read(path)?;
+    let mut first = console(&rom, model, sample_rates[0])?;
+    let mut second = console(&rom, model, sample_rates[1])?;
+
+    let target = run_frames(&mut first, frames)?;
+    run_dots(&mut second, target)?;
+
+    let diff = first.diff_state(&second);
+    let difference = if !diff.is_empty() {
+        Some(diff.to_string())
+    } else if first.drain_serial_output().ne(second.drain_serial_output()) {
+        Some("serial output".to_owned())
+    } else if first.pixel_data_rgb() != second.pixel_data_rgb() {
+        Some("screen".to_owned())
+    } else {
+        None
+    };
+
+    Ok(difference)
+}
@@ -0,0 +1,355 @@
+use crate::{Model, SAMPLE_RATE};
+use ceres_core::{
+    Access, Breakpoint, BreakpointId, Cart, Condition, Gb, NoAudio, Registers, StopReason,
+    Watchpoint,
+};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+
+// GDB has no SM83 target, registers are sent as six little endian 16 bit
+// values in this order
+const REGISTER_COUNT: usize = 6;
+// Dots run between checks for an interrupt from the debugger
+const POLL_DOTS: i32 = 0x4000;
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+// Serves one debugger connection over the GDB remote serial protocol,
+// emulation only advances when the debugger asks for it
+pub fn serve(rom: &Path, model: Model, port: u16) -> anyhow::Result<()> {
+    let cart = Cart::new(std::fs::read(rom)?.into_boxed_slice())?;
+    let mut gb = Gb::new(model.into(), SAMPLE_RATE, cart, NoAudio);
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("waiting for gdb on port {port}, connect with 'target remote :{port}'");
+
+    let (stream, addr) = listener.accept()?;
+    println!("debugger connected from {addr}");
+
+    Session {
+        stream,
+        points: BTreeMap::new(),
+    }
+    .run(&mut gb)
+}
+
+struct Session {
+    stream: TcpStream,
+    // by Z packet type and address
+    points: BTreeMap<(u8, u16), BreakpointId>,
+}
+
+impl Session {
+    fn run(&mut self, gb: &mut Gb<NoAudio>) -> anyhow::Result<()> {
+        while let Some(packet) = self.receive()? {
+            let reply = match packet.as_bytes() {
+                b"?" => signal_reply(SIGTRAP),
+                b"g" => registers_to_hex(gb.registers()),
+                [b'G', hex @ ..] => reply_ok(parse_registers(hex).map(|r| gb.set_registers(r))),
+                [b'p', n @ ..] => read_register(gb, n).unwrap_or_else(|| "E01".to_owned()),
+                [b'P', args @ ..] => reply_ok(write_register(gb, args)),
+                [b'm', args @ ..] => read_memory(gb, args).unwrap_or_else(|| "E01".to_owned()),
+                [b'M', args @ ..] => reply_ok(write_memory(gb, args)),
+                [b'Z', kind @ b'0'..=b'4', b',', args @ ..] => {
+                    reply_ok(self.insert_point(gb, *kind, args))
+                }
+                [b'z', kind @ b'0'..=b'4', b',', args @ ..] => {
+                    reply_ok(self.remove_point(gb, *kind, args))
+                }
+                b"s" => gb
+                    .step_instruction()
+                    .map_or_else(|| signal_reply(SIGTRAP), |r| stop_reply(gb, r)),
+                b"c" => self.resume(gb)?,
+                [b'H', ..] => "OK".to_owned(),
+                b"qAttached" => "1".to_owned(),
+                b"qC" => "QC1".to_owned(),
+                b"qfThreadInfo" => "m1".to_owned(),
+                b"qsThreadInfo" => "l".to_owned(),
+                [b'q', b'S', b'u', b'p', b'p', b'o', b'r', b't', b'e', b'd', ..] => {
+                    "PacketSize=1000".to_owned()
+                }
+                b"D" => {
+                    self.send("OK")?;
+                    break;
+                }
+                b"k" => break,
+                // empty means unsupported, vCont included
+                _ => String::new(),
+            };
+
+            self.send(&reply)?;
+        }
+
+        Ok(())
+    }
+
+    // Runs until a breakpoint or an interrupt from the debugger, returns the
+    // stop reply
+    fn resume(&mut self, gb: &mut Gb<NoAudio>) -> anyhow::Result<String> {
+        loop {
+            gb.run_dots(POLL_DOTS);
+
+            // ld b,b and invalid opcodes are reported like breakpoints
+            if let Some(reason) = gb.take_stop_reason() {
+                return Ok(stop_reply(gb, reason));
+            }
+
+            if self.interrupted()? {
+                return Ok(signal_reply(SIGINT));
+            }
+        }
+    }
+
+    // "addr,kind", kind is the watched length for watchpoints
+    fn insert_point(&mut self, gb: &mut Gb<NoAudio>, kind: u8, args: &[u8]) -> Option<()> {
+        let (addr, len) = parse_addr_len(args)?;
+        if self.points.contains_key(&(kind, addr)) {
+            return Some(());
+        }
+
+        let access = match kind {
+            b'2' => Access::Write,
+            b'3' => Access::Read,
+            b'4' => Access::ReadWrite,
+            _ => {
+                let id = gb.add_breakpoint(Breakpoint {
+                    pc: addr,
+                    condition: Condition::Always,
+                });
+                self.points.insert((kind, addr), id);
+                return Some(());
+            }
+        };
+
+        let id = gb.add_watchpoint(Watchpoint {
+            start: addr,
+            end: addr.wrapping_add(len.max(1) - 1),
+            access,
+        });
+        self.points.insert((kind, addr), id);
+
+        Some(())
+    }
+
+    fn remove_point(&mut self, gb: &mut Gb<NoAudio>, kind: u8, args: &[u8]) -> Option<()> {
+        let (addr, _) = parse_addr_len(args)?;
+        if let Some(id) = self.points.remove(&(kind, addr)) {
+            gb.remove_breakpoint(id);
+        }
+
+        Some(())
+    }
+
+    fn interrupted(&mut self) -> anyhow::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut byte = [0];
+        let res = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false)?;
+
+        match res {
+            Ok(0) => anyhow::bail!("debugger disconnected"),
+            Ok(_) => Ok(byte[0] == 0x03),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // None when the debugger disconnects
+    fn receive(&mut self) -> anyhow::Result<Option<String>> {
+        loop {
+            // acks and interrupts outside of resume are skipped
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => break,
+                Some(_) => (),
+            }
+        }
+
+        let mut packet = Vec::new();
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'#') => break,
+                Some(byte) => packet.push(byte),
+            }
+        }
+
+        let checksum = [self.read_byte()?, self.read_byte()?];
+        let valid = match checksum {
+            [Some(hi), Some(lo)] => {
+                parse_hex_u8(&[hi, lo])
+                    == Some(packet.iter().fold(0, |sum, &b| sum.wrapping_add(b)))
+            }
+            _ => return Ok(None),
+        };
+
+        if valid {
+            self.stream.write_all(b"+")?;
+            Ok(Some(String::from_utf8_lossy(&packet).into_owned()))
+        } else {
+            // ask for it again
+            self.stream.write_all(b"-")?;
+            self.receive()
+        }
+    }
+
+    // None when the debugger disconnects, packets are short so reading a byte
+    // at a time is fine
+    fn read_byte(&mut self) -> anyhow::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.stream.read_exact(&mut byte) {
+            Ok(()) => Ok(Some(byte[0])),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn send(&mut self, reply: &str) -> anyhow::Result<()> {
+        let checksum = reply.bytes().fold(0_u8, u8::wrapping_add);
+        write!(self.stream, "${reply}#{checksum:02x}")?;
+        self.stream.flush()?;
+
+        Ok(())
+    }
+}
+
+fn signal_reply(signal: u8) -> String {
+    format!("S{signal:02x}")
+}
+
+fn stop_reply(gb: &Gb<NoAudio>, reason: StopReason) -> String {
+    match reason {
+        StopReason::Watchpoint { id, addr, .. } => {
+            let kind = match gb.watchpoints().find(|(i, _)| *i == id) {
+                Some((_, wp)) if wp.access == Access::Read => "rwatch",
+                Some((_, wp)) if wp.access == Access::ReadWrite => "awatch",
+                _ => "watch",
+            };
+            format!("T{SIGTRAP:02x}{kind}:{addr:x};")
+        }
+        _ => signal_reply(SIGTRAP),
+    }
+}
+
+fn reply_ok(res: Option<()>) -> String {
+    if res.is_some() {
+        "OK".to_owned()
+    } else {
+        "E01".to_owned()
+    }
+}
+
+fn registers_to_array(r: Registers) -> [u16; REGISTER_COUNT] {
+    [r.af, r.bc, r.de, r.hl, r.sp, r.pc]
+}
+
+fn registers_to_hex(r: Registers) -> String {
+    registers_to_array(r)
+        .iter()
+        .map(|r| hex(&r.to_le_bytes()))
+        .collect()
+}
+
+fn parse_registers(hex: &[u8]) -> Option<Registers> {
+    if hex.len() != REGISTER_COUNT * 4 {
+        return None;
+    }
+
+    let mut values = hex.chunks(4).map(|chunk| {
+        Some(u16::from_le_bytes([
+            parse_hex_u8(&chunk[..2])?,
+            parse_hex_u8(&chunk[2..])?,
+        ]))
+    });
+
+    Some(Registers {
+        af: values.next()??,
+        bc: values.next()??,
+        de: values.next()??,
+        hl: values.next()??,
+        sp: values.next()??,
+        pc: values.next()??,
+    })
+}
+
+fn read_register(gb: &Gb<NoAudio>, n: &[u8]) -> Option<String> {
+    let n = usize::from_str_radix(std::str::from_utf8(n).ok()?, 16).ok()?;
+    let value = registers_to_array(gb.registers()).get(n).copied()?;
+
+    Some(hex(&value.to_le_bytes()))
+}
+
+fn write_register(gb: &mut Gb<NoAudio>, args: &[u8]) -> Option<()> {
+    let (n, value) = std::str::from_utf8(args).ok()?.split_once('=')?;
+    let n = usize::from_str_radix(n, 16).ok()?;
+    let value = value.as_bytes();
+    if value.len() != 4 {
+        return None;
+    }
+    let value = u16::from_le_bytes([parse_hex_u8(&value[..2])?, parse_hex_u8(&value[2..])?]);
+
+    let mut array = registers_to_array(gb.registers());
+    *array.get_mut(n)? = value;
+    let [af, bc, de, hl, sp, pc] = array;
+    gb.set_registers(Registers {
+        af,
+        bc,
+        de,
+        hl,
+        sp,
+        pc,
+    });
+
+    Some(())
+}
+
+fn read_memory(gb: &Gb<NoAudio>, args: &[u8]) -> Option<String> {
+    let (addr, len) = parse_addr_len(args)?;
+
+    let bytes = (0..len)
+        .map(|i| gb.peek_mem(addr.wrapping_add(i)))
+        .collect::<Vec<_>>();
+
+    Some(hex(&bytes))
+}
+
+fn write_memory(gb: &mut Gb<NoAudio>, args: &[u8]) -> Option<()> {
+    let colon = args.iter().position(|&b| b == b':')?;
+    let (addr, len) = parse_addr_len(&args[..colon])?;
+    let data = &args[colon + 1..];
+    if data.len() != usize::from(len) * 2 {
+        return None;
+    }
+
+    for (i, byte) in (0..len).zip(data.chunks(2)) {
+        gb.poke(addr.wrapping_add(i), parse_hex_u8(byte)?);
+    }
+
+    Some(())
+}
+
+// "addr,len" with both in hex
+fn parse_addr_len(args: &[u8]) -> Option<(u16, u16)> {
+    let (addr, len) = std::str::from_utf8(args).ok()?.split_once(',')?;
+
+    Some((
+        u16::from_str_radix(addr, 16).ok()?,
+        u16::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+fn parse_hex_u8(hex: &[u8]) -> Option<u8> {
+    u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    bytes
+        .iter()
+        .flat_map(|b| [DIGITS[usize::from(b >> 4)], DIGITS[usize::from(b & 0xF)]])
+        .map(char::from)
+        .collect()
+}
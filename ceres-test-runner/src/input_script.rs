@@ -0,0 +1,101 @@
+use ceres_core::{Button, Gb, NoAudio};
+
+// Plain text input for reproducing bugs headless, one command per line or
+// separated by semicolons:
+//
+//     # skip the title screen
+//     frame 120: press start
+//     frame 130: release start
+//
+// Frames count from 0 and commands apply right before that frame runs.
+pub struct InputScript {
+    // sorted by frame, keeping the script order within a frame
+    commands: Vec<Command>,
+}
+
+struct Command {
+    frame: u32,
+    press: bool,
+    button: Button,
+}
+
+impl InputScript {
+    pub fn parse(script: &str) -> anyhow::Result<Self> {
+        let mut commands = script
+            .lines()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                let line = line.split('#').next().unwrap_or_default();
+                line.split(';').map(move |command| (i + 1, command.trim()))
+            })
+            .filter(|(_, command)| !command.is_empty())
+            .map(|(line, command)| {
+                Self::parse_command(command).map_err(|e| anyhow::anyhow!("line {line}: {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        commands.sort_by_key(|command| command.frame);
+
+        Ok(Self { commands })
+    }
+
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let script = std::fs::read_to_string(path)?;
+        Self::parse(&script).map_err(|e| anyhow::anyhow!("{}, {e}", path.display()))
+    }
+
+    fn parse_command(command: &str) -> anyhow::Result<Command> {
+        let Some((frame, action)) = command.split_once(':') else {
+            anyhow::bail!("expected 'frame <number>: <press|release> <button>' in '{command}'");
+        };
+
+        let frame = match frame.split_whitespace().collect::<Vec<_>>()[..] {
+            ["frame", number] => number
+                .parse()
+                .map_err(|_err| anyhow::anyhow!("'{number}' isn't a frame number"))?,
+            _ => anyhow::bail!("expected 'frame <number>' before ':' in '{command}'"),
+        };
+
+        let (press, button) = match action.split_whitespace().collect::<Vec<_>>()[..] {
+            ["press", button] => (true, button),
+            ["release", button] => (false, button),
+            _ => anyhow::bail!("expected '<press|release> <button>' after ':' in '{command}'"),
+        };
+
+        let button = match button.to_ascii_lowercase().as_str() {
+            "a" => Button::A,
+            "b" => Button::B,
+            "select" => Button::Select,
+            "start" => Button::Start,
+            "up" => Button::Up,
+            "down" => Button::Down,
+            "left" => Button::Left,
+            "right" => Button::Right,
+            _ => anyhow::bail!("unknown button '{button}'"),
+        };
+
+        Ok(Command {
+            frame,
+            press,
+            button,
+        })
+    }
+
+    // Call before running each frame
+    pub fn apply(&self, frame: u32, gb: &mut Gb<NoAudio>) {
+        let start = self
+            .commands
+            .partition_point(|command| command.frame < frame);
+
+        for command in self.commands[start..]
+            .iter()
+            .take_while(|command| command.frame == frame)
+        {
+            if command.press {
+                gb.press(command.button);
+            } else {
+                gb.release(command.button);
+            }
+        }
+    }
+}
@@ -0,0 +1,718 @@
+use ceres_core::{Cart, Gb, NoAudio, StopReason, TraceEntry, VgmWriter, PX_HEIGHT, PX_WIDTH};
+use input_script::InputScript;
+use matrix::Run;
+use report::TestResult;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+mod bess;
+mod compat;
+mod determinism;
+mod gdb;
+mod input_script;
+mod matrix;
+mod report;
+mod scoreboard;
+mod trace;
+
+// Irrelevant since samples are discarded, but the APU needs one
+const SAMPLE_RATE: i32 = 48000;
+// More than the instructions that fit in a double speed frame
+const TRACE_CAPACITY: usize = 0x1_0000;
+const ABOUT: &str = "Runs Game Boy test ROMs headless and reports their results.";
+const AFTER_HELP: &str = "Completion is detected by:
+
+    | Suite   | Signal                                     |
+    | ------- | ------------------------------------------ |
+    | Mooneye | ld b,b with Fibonacci numbers in registers |
+    | Blargg  | \"Passed\" or \"Failed\" sent through serial   |
+    | Others  | ld b,b, the screen has to be checked       |
+";
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Model {
+    Dmg0,
+    Dmg,
+    Mgb,
+    Sgb,
+    Sgb2,
+    Cgb0,
+    Cgb,
+    CgbE,
+    Agb,
+}
+
+impl std::fmt::Display for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Model::Dmg0 => write!(f, "dmg0"),
+            Model::Dmg => write!(f, "dmg"),
+            Model::Mgb => write!(f, "mgb"),
+            Model::Sgb => write!(f, "sgb"),
+            Model::Sgb2 => write!(f, "sgb2"),
+            Model::Cgb0 => write!(f, "cgb0"),
+            Model::Cgb => write!(f, "cgb"),
+            Model::CgbE => write!(f, "cgb-e"),
+            Model::Agb => write!(f, "agb"),
+        }
+    }
+}
+
+impl From<Model> for ceres_core::Model {
+    fn from(model: Model) -> ceres_core::Model {
+        match model {
+            Model::Dmg0 => ceres_core::Model::Dmg0,
+            Model::Dmg => ceres_core::Model::Dmg,
+            Model::Mgb => ceres_core::Model::Mgb,
+            Model::Sgb => ceres_core::Model::Sgb,
+            Model::Sgb2 => ceres_core::Model::Sgb2,
+            Model::Cgb0 => ceres_core::Model::Cgb0,
+            Model::Cgb => ceres_core::Model::Cgb,
+            Model::CgbE => ceres_core::Model::CgbE,
+            Model::Agb => ceres_core::Model::Agb,
+        }
+    }
+}
+
+#[derive(clap::Parser)]
+#[command(
+    name = "ceres-test-runner",
+    about = ABOUT,
+    after_help = AFTER_HELP,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    #[command(
+        about = "Run a ROM driven by frames and by uneven chunks of dots and check both end up the same"
+    )]
+    Determinism(DeterminismArgs),
+    #[command(about = "Run every ROM in a directory and write a CSV compatibility report")]
+    Compat(CompatArgs),
+    #[command(
+        about = "Load BESS states from other emulators and check exporting them loses nothing"
+    )]
+    Bess(BessArgs),
+    #[command(about = "Record the music a ROM plays without input to a VGM file")]
+    Vgm(VgmArgs),
+    #[command(about = "Debug a ROM with GDB through the remote serial protocol")]
+    Gdb(GdbArgs),
+}
+
+#[derive(clap::Args)]
+struct GdbArgs {
+    #[arg(help = "ROM to debug")]
+    rom: PathBuf,
+    #[arg(
+        short,
+        long,
+        help = "Game Boy model to emulate",
+        default_value = "cgb",
+        value_enum
+    )]
+    model: Model,
+    #[arg(
+        short,
+        long,
+        help = "Local TCP port to wait for the debugger on",
+        default_value_t = 2345
+    )]
+    port: u16,
+}
+
+#[derive(clap::Args)]
+struct VgmArgs {
+    #[arg(help = "ROM to run")]
+    rom: PathBuf,
+    #[arg(
+        short,
+        long,
+        help = "Game Boy model to emulate",
+        default_value = "cgb",
+        value_enum
+    )]
+    model: Model,
+    #[arg(
+        long,
+        help = "Emulated frames to run before recording, skips the bootrom sound",
+        default_value_t = 0
+    )]
+    skip: u32,
+    #[arg(
+        short = 'f',
+        long,
+        help = "Emulated frames to record",
+        default_value_t = 60 * 60
+    )]
+    frames: u32,
+    #[arg(short, long, help = "VGM file to write")]
+    output: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct CompatArgs {
+    #[arg(
+        help = "Directory searched recursively for .gb and .gbc files",
+        required_unless_present = "rom"
+    )]
+    dir: Option<PathBuf>,
+    // Set on the child process running each ROM
+    #[arg(long, hide = true, conflicts_with = "dir")]
+    rom: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help = "Game Boy model to emulate",
+        default_value = "cgb",
+        value_enum
+    )]
+    model: Model,
+    #[arg(
+        short = 'f',
+        long,
+        help = "Emulated frames to run each ROM for",
+        default_value_t = 60 * 10
+    )]
+    frames: u32,
+    #[arg(
+        short,
+        long,
+        help = "CSV file to write the report to",
+        default_value = "compat.csv"
+    )]
+    output: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct BessArgs {
+    #[arg(
+        help = "Directory with <rom>.<emulator>.<model>.bess states next to their <rom>.gb",
+        default_value = "ceres-test-runner/bess"
+    )]
+    dir: PathBuf,
+    #[arg(
+        short = 'f',
+        long,
+        help = "Emulated frames to run each state for before comparing",
+        default_value_t = 60
+    )]
+    frames: u32,
+}
+
+#[derive(clap::Args)]
+struct DeterminismArgs {
+    #[arg(help = "ROM to run")]
+    rom: PathBuf,
+    #[arg(
+        short,
+        long,
+        help = "Game Boy model to emulate",
+        default_value = "cgb",
+        value_enum
+    )]
+    model: Model,
+    #[arg(
+        short = 'f',
+        long,
+        help = "Emulated frames to compare",
+        default_value_t = 60 * 60
+    )]
+    frames: u32,
+    #[arg(
+        long,
+        help = "Audio sample rate of the run driven frame by frame",
+        default_value_t = 48000
+    )]
+    sample_rate: i32,
+    #[arg(
+        long,
+        help = "Audio sample rate of the run driven in uneven chunks of dots",
+        default_value_t = 22050
+    )]
+    other_sample_rate: i32,
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    #[arg(help = "Test ROMs to run", required = true)]
+    roms: Vec<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help = "Game Boy model to emulate",
+        long_help = "Game Boy model to emulate. By default it's chosen from the CGB flag \
+           of each ROM: ROMs without it run on a DMG, CGB enhanced ones on a DMG and \
+           a CGB, and CGB only ones on a CGB and on a DMG where they are expected to \
+           fail.",
+        value_enum
+    )]
+    model: Option<Model>,
+    #[arg(
+        short = 'f',
+        long,
+        help = "Emulated frames after which a test times out",
+        default_value_t = 60 * 60
+    )]
+    max_frames: u32,
+    #[arg(long, help = "Write results as JSON to this file")]
+    json: Option<PathBuf>,
+    #[arg(long, help = "Write results as JUnit XML to this file")]
+    junit: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Save the last frame of failing tests as PPM images in this directory"
+    )]
+    artifacts: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Write a per suite and category accuracy table as Markdown to this file"
+    )]
+    scoreboard: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Re-run failing tests tracing the instructions of their last frames, \
+           saved with the other artifacts, 0 disables it",
+        default_value_t = 2
+    )]
+    trace_frames: usize,
+    #[arg(
+        long,
+        help = "Press and release buttons as the script says",
+        long_help = "Press and release buttons as the script says. It holds commands \
+           like 'frame 120: press start' separated by lines or semicolons, frames \
+           count from 0 and # starts a comment. Buttons are a, b, select, start, up, \
+           down, left and right."
+    )]
+    input_script: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Verdict {
+    Passed,
+    Failed,
+    // Stopped at a breakpoint without reporting a result, the screen has to
+    // be checked. Not a pass.
+    Unverified,
+    TimedOut,
+    // The ROM couldn't be loaded
+    Error,
+    // Failed or timed out on a model the ROM doesn't support
+    ExpectedFailure,
+    // Passed on a model the ROM doesn't support
+    UnexpectedPass,
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Passed => write!(f, "PASS"),
+            Verdict::Failed => write!(f, "FAIL"),
+            Verdict::Unverified => write!(f, "CHECK"),
+            Verdict::TimedOut => write!(f, "TIME"),
+            Verdict::Error => write!(f, "ERR "),
+            Verdict::ExpectedFailure => write!(f, "XFAIL"),
+            Verdict::UnexpectedPass => write!(f, "XPASS"),
+        }
+    }
+}
+
+struct Outcome {
+    verdict: Verdict,
+    frames: u32,
+    // RGB pixels of the last frame
+    screen: Vec<u8>,
+    // Executed instructions of the last frames, if traced
+    trace: VecDeque<Vec<TraceEntry>>,
+}
+
+fn mooneye_verdict(gb: &Gb<NoAudio>) -> Verdict {
+    let regs = gb.registers();
+
+    match (regs.bc, regs.de, regs.hl) {
+        (0x0305, 0x080D, 0x1522) => Verdict::Passed,
+        (0x4242, 0x4242, 0x4242) => Verdict::Failed,
+        _ => Verdict::Unverified,
+    }
+}
+
+fn blargg_verdict(serial: &[u8]) -> Option<Verdict> {
+    let contains = |needle: &[u8]| serial.windows(needle.len()).any(|w| w == needle);
+
+    if contains(b"Passed") {
+        Some(Verdict::Passed)
+    } else if contains(b"Failed") {
+        Some(Verdict::Failed)
+    } else {
+        None
+    }
+}
+
+fn run_test(
+    path: &Path,
+    model: Model,
+    max_frames: u32,
+    trace_frames: usize,
+    script: Option<&InputScript>,
+) -> anyhow::Result<Outcome> {
+    let rom = std::fs::read(path)?.into_boxed_slice();
+    let cart = Cart::new(rom)?;
+
+    let mut gb = Gb::new(model.into(), SAMPLE_RATE, cart, NoAudio);
+    gb.set_ld_b_b_breakpoint(true);
+    if trace_frames > 0 {
+        gb.set_trace_capacity(TRACE_CAPACITY);
+    }
+
+    let mut serial = Vec::new();
+    let mut trace = VecDeque::with_capacity(trace_frames);
+
+    for frames in 1..=max_frames {
+        if let Some(script) = script {
+            script.apply(frames - 1, &mut gb);
+        }
+
+        let reason = gb.run_frame();
+        serial.extend(gb.drain_serial_output());
+
+        if trace_frames > 0 {
+            if trace.len() == trace_frames {
+                trace.pop_front();
+            }
+            trace.push_back(gb.drain_trace().collect());
+        }
+
+        let verdict = if let StopReason::Breakpoint { .. } = reason {
+            Some(mooneye_verdict(&gb))
+        } else {
+            blargg_verdict(&serial)
+        };
+
+        if let Some(verdict) = verdict {
+            return Ok(Outcome {
+                verdict,
+                frames,
+                screen: gb.pixel_data_rgb().to_vec(),
+                trace,
+            });
+        }
+    }
+
+    Ok(Outcome {
+        verdict: Verdict::TimedOut,
+        frames: max_frames,
+        screen: gb.pixel_data_rgb().to_vec(),
+        trace,
+    })
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+fn save_screen(dir: &Path, rom: &Path, screen: &[u8]) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+
+    let name = rom.file_stem().unwrap_or_default();
+    let path = dir.join(name).with_extension("ppm");
+
+    let mut ppm = format!("P6\n{PX_WIDTH} {PX_HEIGHT}\n255\n").into_bytes();
+    ppm.extend_from_slice(screen);
+    std::fs::write(&path, ppm)?;
+
+    Ok(path)
+}
+
+fn test_result(rom: &Path, run: Run, args: &RunArgs, script: Option<&InputScript>) -> TestResult {
+    let mut result = TestResult {
+        name: rom.display().to_string(),
+        model: run.model.to_string(),
+        verdict: Verdict::Error,
+        frames: 0,
+        hash: None,
+        message: None,
+        artifacts: Vec::new(),
+    };
+
+    match run_test(rom, run.model, args.max_frames, 0, script) {
+        Ok(mut outcome) => {
+            if !run.compatible {
+                outcome.verdict = match outcome.verdict {
+                    Verdict::Failed | Verdict::TimedOut => Verdict::ExpectedFailure,
+                    Verdict::Passed => Verdict::UnexpectedPass,
+                    verdict => verdict,
+                };
+            }
+
+            result.verdict = outcome.verdict;
+            result.frames = outcome.frames;
+            result.hash = Some(format!("{:016x}", fnv1a(&outcome.screen)));
+
+            match outcome.verdict {
+                Verdict::Failed => result.message = Some("test reported a failure".to_owned()),
+                Verdict::TimedOut => {
+                    result.message = Some(format!("timed out after {} frames", outcome.frames));
+                }
+                Verdict::UnexpectedPass => {
+                    result.message = Some(format!("passed on unsupported model {}", run.model));
+                }
+                Verdict::Unverified => {
+                    result.message = Some("stopped without a result, check the screen".to_owned());
+                }
+                Verdict::Passed | Verdict::Error | Verdict::ExpectedFailure => {}
+            }
+
+            if let (Some(dir), Verdict::Failed | Verdict::TimedOut) =
+                (&args.artifacts, outcome.verdict)
+            {
+                // a ROM can fail on more than one model
+                let dir = &dir.join(run.model.to_string());
+
+                match save_screen(dir, rom, &outcome.screen) {
+                    Ok(path) => result.artifacts.push(path),
+                    Err(e) => eprintln!("couldn't save screen of {}: {e}", rom.display()),
+                }
+
+                // Runs are deterministic, so tracing only failures keeps passing tests fast
+                if args.trace_frames > 0 {
+                    let traced =
+                        run_test(rom, run.model, args.max_frames, args.trace_frames, script)
+                            .and_then(|traced| trace::save(dir, rom, &traced.trace, traced.frames));
+                    match traced {
+                        Ok(path) => result.artifacts.push(path),
+                        Err(e) => eprintln!("couldn't trace {}: {e}", rom.display()),
+                    }
+                }
+            }
+        }
+        Err(e) => result.message = Some(e.to_string()),
+    }
+
+    result
+}
+
+fn check_determinism(args: &DeterminismArgs) -> ExitCode {
+    let sample_rates = [args.sample_rate, args.other_sample_rate];
+
+    match determinism::check(&args.rom, args.model, args.frames, sample_rates) {
+        Ok(None) => {
+            println!("PASS {} ({} frames)", args.rom.display(), args.frames);
+            ExitCode::SUCCESS
+        }
+        Ok(Some(difference)) => {
+            println!(
+                "FAIL {}: differs after {} frames: {difference}",
+                args.rom.display(),
+                args.frames
+            );
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            println!("ERR  {}: {e}", args.rom.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn compat_report(args: &CompatArgs) -> ExitCode {
+    if let Some(rom) = &args.rom {
+        return match compat::run_here(rom, args.model, args.frames) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("couldn't report on {}: {e}", rom.display());
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let Some(dir) = &args.dir else {
+        return ExitCode::FAILURE;
+    };
+    let roms = match compat::find_roms(dir) {
+        Ok(roms) => roms,
+        Err(e) => {
+            eprintln!("couldn't read {}: {e}", dir.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let entries = roms
+        .iter()
+        .map(|rom| {
+            let entry = compat::run(rom, args.model, args.frames);
+            println!("{} {}", entry.status.as_str(), entry.rom.display());
+            entry
+        })
+        .collect::<Vec<_>>();
+
+    if let Err(e) = compat::write_csv(&args.output, &entries) {
+        eprintln!("couldn't write compatibility report: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn check_bess(args: &BessArgs) -> ExitCode {
+    let states = match bess::find_states(&args.dir) {
+        Ok(states) => states,
+        Err(e) => {
+            eprintln!("couldn't read {}: {e}", args.dir.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut all_passed = !states.is_empty();
+    for state in &states {
+        match bess::check(state, args.frames) {
+            Ok(None) => println!("PASS {} ({} frames)", state.display(), args.frames),
+            Ok(Some(difference)) => {
+                println!("FAIL {}: {difference}", state.display());
+                all_passed = false;
+            }
+            Err(e) => {
+                println!("ERR  {}: {e}", state.display());
+                all_passed = false;
+            }
+        }
+    }
+
+    if all_passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn record_vgm(args: &VgmArgs) -> anyhow::Result<()> {
+    let rom = std::fs::read(&args.rom)?;
+    let cart = Cart::new(rom.into_boxed_slice())?;
+    let mut gb = Gb::new(args.model.into(), SAMPLE_RATE, cart, NoAudio);
+
+    for _ in 0..args.skip {
+        gb.run_frame();
+    }
+
+    gb.set_apu_logging(true);
+    let mut vgm = VgmWriter::default();
+    // whatever was set up during the skipped frames
+    gb.apu_state_writes()
+        .into_iter()
+        .for_each(|write| vgm.push(write));
+
+    for _ in 0..args.frames {
+        gb.run_frame();
+        gb.drain_apu_writes().for_each(|write| vgm.push(write));
+    }
+
+    std::fs::write(&args.output, vgm.finish(gb.apu_log_dots()))?;
+
+    Ok(())
+}
+
+fn run_tests(args: &RunArgs) -> ExitCode {
+    let script = match args.input_script.as_deref().map(InputScript::load) {
+        Some(Ok(script)) => Some(script),
+        Some(Err(e)) => {
+            eprintln!("couldn't load input script: {e}");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    let results = args
+        .roms
+        .iter()
+        .flat_map(|rom| {
+            matrix::runs(rom, args.model)
+                .into_iter()
+                .map(move |run| (rom, run))
+        })
+        .map(|(rom, run)| {
+            let result = test_result(rom, run, args, script.as_ref());
+            match &result.message {
+                Some(message) => println!(
+                    "{} {} on {}: {message}",
+                    result.verdict, result.name, result.model
+                ),
+                None => println!(
+                    "{} {} on {} ({} frames)",
+                    result.verdict, result.name, result.model, result.frames
+                ),
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+
+    let count = |passed: fn(Verdict) -> bool| results.iter().filter(|r| passed(r.verdict)).count();
+    let passed = count(|v| matches!(v, Verdict::Passed | Verdict::ExpectedFailure));
+    let unverified = count(|v| v == Verdict::Unverified);
+    println!(
+        "{passed} passed, {unverified} unverified, {} failed",
+        results.len() - passed - unverified
+    );
+
+    let mut all_passed = passed == results.len();
+
+    if let Some(path) = &args.json {
+        if let Err(e) = report::write_json(path, &results) {
+            eprintln!("couldn't write JSON report: {e}");
+            all_passed = false;
+        }
+    }
+
+    if let Some(path) = &args.junit {
+        if let Err(e) = report::write_junit(path, &results) {
+            eprintln!("couldn't write JUnit report: {e}");
+            all_passed = false;
+        }
+    }
+
+    if let Some(path) = &args.scoreboard {
+        if let Err(e) = scoreboard::write_markdown(path, &results) {
+            eprintln!("couldn't write scoreboard: {e}");
+            all_passed = false;
+        }
+    }
+
+    if all_passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = <Cli as clap::Parser>::parse();
+
+    match &cli.command {
+        Some(Command::Determinism(args)) => check_determinism(args),
+        Some(Command::Compat(args)) => compat_report(args),
+        Some(Command::Bess(args)) => check_bess(args),
+        Some(Command::Vgm(args)) => match record_vgm(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("couldn't record {}: {e}", args.rom.display());
+                ExitCode::FAILURE
+            }
+        },
+        Some(Command::Gdb(args)) => match gdb::serve(&args.rom, args.model, args.port) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("debugging {} failed: {e}", args.rom.display());
+                ExitCode::FAILURE
+            }
+        },
+        None => run_tests(&cli.run),
+    }
+}
@@ -0,0 +1,61 @@
+use crate::Model;
+use std::path::Path;
+
+const CGB_FLAG: usize = 0x143;
+
+// What the CGB flag in the header says the ROM runs on
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Support {
+    // No flag, written for the original models
+    Dmg,
+    Both,
+    CgbOnly,
+}
+
+impl Support {
+    const fn of(flag: u8) -> Self {
+        match flag & 0xC0 {
+            0xC0 => Self::CgbOnly,
+            0x80 => Self::Both,
+            _ => Self::Dmg,
+        }
+    }
+}
+
+// A model to run a test under and whether the test is expected to pass on it
+#[derive(Clone, Copy)]
+pub struct Run {
+    pub model: Model,
+    pub compatible: bool,
+}
+
+// An explicit model is the only one run, otherwise they're chosen from the
+// header. CGB only ROMs also run on a DMG, where they should refuse to work.
+pub fn runs(rom: &Path, model: Option<Model>) -> Vec<Run> {
+    let run = |on| Run {
+        model: on,
+        compatible: true,
+    };
+
+    if let Some(model) = model {
+        return vec![run(model)];
+    }
+
+    // An unreadable ROM is reported by the run itself
+    let flag = std::fs::read(rom)
+        .ok()
+        .and_then(|rom| rom.get(CGB_FLAG).copied())
+        .unwrap_or_default();
+
+    match Support::of(flag) {
+        Support::Dmg => vec![run(Model::Dmg)],
+        Support::Both => vec![run(Model::Dmg), run(Model::Cgb)],
+        Support::CgbOnly => vec![
+            run(Model::Cgb),
+            Run {
+                model: Model::Dmg,
+                compatible: false,
+            },
+        ],
+    }
+}
@@ -0,0 +1,91 @@
+use crate::Verdict;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(serde::Serialize)]
+pub struct TestResult {
+    pub name: String,
+    pub model: String,
+    pub verdict: Verdict,
+    pub frames: u32,
+    // FNV-1a of the last frame in hex, None if the ROM couldn't be loaded
+    pub hash: Option<String>,
+    pub message: Option<String>,
+    pub artifacts: Vec<PathBuf>,
+}
+
+pub fn write_json(path: &Path, results: &[TestResult]) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), results)?;
+    Ok(())
+}
+
+pub fn write_junit(path: &Path, results: &[TestResult]) -> anyhow::Result<()> {
+    let count = |verdict: Verdict| results.iter().filter(|r| r.verdict == verdict).count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        xml,
+        "<testsuite name=\"ceres\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\">",
+        results.len(),
+        count(Verdict::Failed) + count(Verdict::TimedOut) + count(Verdict::UnexpectedPass),
+        count(Verdict::Error),
+        count(Verdict::Unverified)
+    )?;
+
+    for result in results {
+        writeln!(
+            xml,
+            "  <testcase name=\"{}\" classname=\"{}\">",
+            escape(&result.name),
+            escape(&result.model)
+        )?;
+
+        let message = result.message.as_deref().unwrap_or_default();
+        match result.verdict {
+            Verdict::Failed | Verdict::TimedOut | Verdict::UnexpectedPass => {
+                writeln!(xml, "    <failure message=\"{}\"/>", escape(message))?;
+            }
+            Verdict::Error => writeln!(xml, "    <error message=\"{}\"/>", escape(message))?,
+            // the closest JUnit has to a result nobody checked yet
+            Verdict::Unverified => {
+                writeln!(xml, "    <skipped message=\"{}\"/>", escape(message))?;
+            }
+            Verdict::Passed | Verdict::ExpectedFailure => (),
+        }
+
+        write!(xml, "    <system-out>frames: {}", result.frames)?;
+        if let Some(hash) = &result.hash {
+            write!(xml, "\nhash: {hash}")?;
+        }
+        for artifact in &result.artifacts {
+            write!(
+                xml,
+                "\n[[ATTACHMENT|{}]]",
+                escape(&artifact.display().to_string())
+            )?;
+        }
+        writeln!(xml, "</system-out>\n  </testcase>")?;
+    }
+
+    xml.push_str("</testsuite>\n");
+    std::fs::write(path, xml)?;
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            c => res.push(c),
+        }
+    }
+
+    res
+}
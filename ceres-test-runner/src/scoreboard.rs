@@ -0,0 +1,137 @@
+use crate::{report::TestResult, Verdict};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Component, Path};
+
+// Matched against path components, case insensitively
+const SUITES: [(&str, &str); 5] = [
+    ("blargg", "Blargg"),
+    ("mooneye", "Mooneye"),
+    ("acid", "Acid"),
+    ("mealybug", "Mealybug Tearoom"),
+    ("samesuite", "SameSuite"),
+];
+
+#[derive(Default)]
+struct Tally {
+    passed: usize,
+    // stopped without a verdict, the screen has to be checked by hand
+    unverified: usize,
+    total: usize,
+}
+
+impl Tally {
+    fn add(&mut self, verdict: Verdict) {
+        self.total += 1;
+        match verdict {
+            // refusing to run on the wrong model is what the hardware does
+            Verdict::Passed | Verdict::ExpectedFailure => self.passed += 1,
+            Verdict::Unverified => self.unverified += 1,
+            Verdict::Failed | Verdict::TimedOut | Verdict::Error | Verdict::UnexpectedPass => (),
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.passed as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+// Suite and category of a test ROM, the category is the directory path
+// below the suite directory
+fn classify(rom: &Path) -> (&'static str, String) {
+    let dirs = rom
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (i, dir) in dirs.iter().enumerate() {
+        if let Some((_, suite)) = SUITES.iter().find(|(key, _)| dir.contains(key)) {
+            let category = dirs[i + 1..].join("/");
+            return (
+                suite,
+                if category.is_empty() {
+                    "-".to_owned()
+                } else {
+                    category
+                },
+            );
+        }
+    }
+
+    ("Other", "-".to_owned())
+}
+
+pub fn write_markdown(path: &Path, results: &[TestResult]) -> anyhow::Result<()> {
+    let mut suites = BTreeMap::<&str, BTreeMap<String, Tally>>::new();
+
+    for result in results {
+        let (suite, category) = classify(Path::new(&result.name));
+        suites
+            .entry(suite)
+            .or_default()
+            .entry(category)
+            .or_default()
+            .add(result.verdict);
+    }
+
+    let mut md = String::from("# Accuracy\n");
+    let mut overall = Tally::default();
+
+    for (suite, categories) in &suites {
+        let mut suite_tally = Tally::default();
+
+        writeln!(md, "\n## {suite}\n")?;
+        md.push_str("| Category | Passed | Unverified | Total | Accuracy |\n");
+        md.push_str("| -------- | ------ | ---------- | ----- | -------- |\n");
+
+        for (category, tally) in categories {
+            writeln!(
+                md,
+                "| {category} | {} | {} | {} | {:.1}% |",
+                tally.passed,
+                tally.unverified,
+                tally.total,
+                tally.percentage()
+            )?;
+
+            suite_tally.passed += tally.passed;
+            suite_tally.unverified += tally.unverified;
+            suite_tally.total += tally.total;
+        }
+
+        writeln!(
+            md,
+            "| **Total** | {} | {} | {} | {:.1}% |",
+            suite_tally.passed,
+            suite_tally.unverified,
+            suite_tally.total,
+            suite_tally.percentage()
+        )?;
+
+        overall.passed += suite_tally.passed;
+        overall.unverified += suite_tally.unverified;
+        overall.total += suite_tally.total;
+    }
+
+    writeln!(
+        md,
+        "\nOverall: {} of {} tests passed ({:.1}%), {} need manual checking.",
+        overall.passed,
+        overall.total,
+        overall.percentage(),
+        overall.unverified
+    )?;
+
+    std::fs::write(path, md)?;
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use ceres_core::TraceEntry;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// One instruction per line with the registers before it ran, under a line
+// marking the frame it belongs to
+pub fn save(
+    dir: &Path,
+    rom: &Path,
+    frames: &VecDeque<Vec<TraceEntry>>,
+    last_frame: u32,
+) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+
+    let name = rom.file_stem().unwrap_or_default();
+    let path = dir.join(name).with_extension("trace.gz");
+
+    let file = std::fs::File::create(&path)?;
+    let mut gz = flate2::write::GzEncoder::new(
+        std::io::BufWriter::new(file),
+        flate2::Compression::default(),
+    );
+
+    let traced = u32::try_from(frames.len()).unwrap_or(u32::MAX);
+    let first_frame = (last_frame + 1).saturating_sub(traced);
+    for (frame, entries) in (first_frame..).zip(frames) {
+        writeln!(gz, "-- frame {frame}")?;
+
+        for TraceEntry { op, registers: r } in entries {
+            writeln!(
+                gz,
+                "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} \
+                 SP:{:04X} PC:{:04X} OP:{op:02X}",
+                r.af >> 8,
+                r.af & 0xFF,
+                r.bc >> 8,
+                r.bc & 0xFF,
+                r.de >> 8,
+                r.de & 0xFF,
+                r.hl >> 8,
+                r.hl & 0xFF,
+                r.sp,
+                r.pc
+            )?;
+        }
+    }
+
+    gz.finish()?.flush()?;
+
+    Ok(path)
+}
@@ -9,6 +9,11 @@ var smpl: sampler;
 var<uniform> dims: vec2<f32>;
 @group(1) @binding(1)
 var<uniform> scale_type: u32;
+// bits 0-1: clockwise rotation in quarter turns, bit 2: horizontal mirror
+@group(1) @binding(2)
+var<uniform> transform: u32;
+@group(1) @binding(3)
+var<uniform> color_filter: u32;
 
 struct Vertexinput {
     @builtin(vertex_index) vert_idx: u32,
@@ -37,11 +42,36 @@ fn vs_main(in: Vertexinput) -> VertexOutput {
 
     var out: VertexOutput;
     out.clip_position = vec4(vert_coord * dims, 0.0, 1.0);
+    let src_coord = vs_transform(vert_coord);
     // FIXME: ugly hack
-    out.tex_coords = saturate(vec2(vert_coord.x, -vert_coord.y));
+    out.tex_coords = saturate(vec2(src_coord.x, -src_coord.y));
     return out;
 }
 
+// Map a screen corner to the texture corner that should be displayed there
+fn vs_transform(coord: vec2<f32>) -> vec2<f32> {
+    var c = coord;
+
+    if (transform & 4u) != 0u {
+        c.x = -c.x;
+    }
+
+    switch transform & 3u {
+        default: {}
+        case 1u: {
+            c = vec2(-c.y, c.x);
+        }
+        case 2u: {
+            c = -c;
+        }
+        case 3u: {
+            c = vec2(c.y, -c.x);
+        }
+    };
+
+    return c;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     var ret: vec4<f32>;
@@ -61,6 +91,70 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         }
     };
 
+    return vec4(fs_color_filter(ret.xyz), 1.0);
+}
+
+// Daltonization as described by Fidaner, Lin and Ozguven, simulate the
+// deficiency in LMS space and shift the lost information to visible channels
+fn fs_daltonize(c: vec3<f32>, sim: mat3x3<f32>) -> vec3<f32> {
+    // rows of the matrices, since vector * matrix dots with columns
+    let rgb_to_lms = mat3x3(
+        vec3(17.8824, 43.5161, 4.11935),
+        vec3(3.45565, 27.1554, 3.86714),
+        vec3(0.0299566, 0.184309, 1.46709)
+    );
+    let lms_to_rgb = mat3x3(
+        vec3(0.0809444479, -0.130504409, 0.116721066),
+        vec3(-0.0102485335, 0.0540193266, -0.113614708),
+        vec3(-0.000365296938, -0.00412161469, 0.693511405)
+    );
+
+    let lms = c * rgb_to_lms;
+    let simulated = (lms * sim) * lms_to_rgb;
+    let err = c - simulated;
+
+    let shift = vec3(0.0, 0.7 * err.r + err.g, 0.7 * err.r + err.b);
+
+    return saturate(c + shift);
+}
+
+fn fs_color_filter(c: vec3<f32>) -> vec3<f32> {
+    var ret: vec3<f32>;
+
+    switch color_filter {
+        default: {
+            ret = c;
+        }
+        case 1u: {
+            // protanopia
+            ret = fs_daltonize(c, mat3x3(
+                vec3(0.0, 2.02344, -2.52581),
+                vec3(0.0, 1.0, 0.0),
+                vec3(0.0, 0.0, 1.0)
+            ));
+        }
+        case 2u: {
+            // deuteranopia
+            ret = fs_daltonize(c, mat3x3(
+                vec3(1.0, 0.0, 0.0),
+                vec3(0.494207, 0.0, 1.24827),
+                vec3(0.0, 0.0, 1.0)
+            ));
+        }
+        case 3u: {
+            // tritanopia
+            ret = fs_daltonize(c, mat3x3(
+                vec3(1.0, 0.0, 0.0),
+                vec3(0.0, 1.0, 0.0),
+                vec3(-0.395913, 0.801109, 0.0)
+            ));
+        }
+        case 4u: {
+            // high contrast, stretch around mid gray
+            ret = saturate((c - 0.5) * 1.6 + 0.5);
+        }
+    };
+
     return ret;
 }
 
@@ -1,13 +1,42 @@
-use crate::{gb_area, Scaling};
+use crate::{
+    bindings, console, gb_area, hex_editor, input_display, music_view, sandbox, save_file,
+    save_storage, visualizer, ColorFilter, DmgPalette, Model, OutputFilter, Rotation, Scaling,
+};
 use iced::advanced::graphics::futures::event;
-use iced::widget::{button, column, container, pick_list, shader, text};
-use iced::{window, Alignment, Element, Length, Subscription, Theme};
+use iced::widget::{
+    button, checkbox, column, container, pick_list, row, scrollable, shader, stack, text,
+    text_input,
+};
+use iced::{Alignment, Color, Element, Length, Subscription, Theme};
+
+// How long notices stay over the game
+const OSD_DURATION: std::time::Duration = std::time::Duration::from_secs(3);
 
 #[derive(Debug, Clone)]
 pub enum Message {
+    ModelChanged(Model),
     ScalingChanged(Scaling),
+    RotationChanged(Rotation),
+    MirrorToggled(bool),
+    ColorFilterChanged(ColorFilter),
+    OutputFilterChanged(OutputFilter),
+    DmgPaletteChanged(DmgPalette),
+    LoadLutButtonPressed,
+    BarcodeChanged(String),
+    SwipeButtonPressed,
+    LinkAddressChanged(String),
+    HostLinkButtonPressed,
+    JoinLinkButtonPressed,
+    CancelLinkButtonPressed,
+    HexEditor(hex_editor::Message),
+    ChannelToggled(ceres_core::ApuChannel, bool),
     OpenButtonPressed,
+    ResumeButtonPressed,
     Tick,
+    VisualizerTick,
+    ConsoleTick,
+    OsdTick,
+    AutosaveTick,
     EventOcurred(iced::Event),
 }
 
@@ -15,17 +44,105 @@ pub struct App {
     gb_area: gb_area::GbArea,
     _audio: ceres_audio::State,
     show_menu: bool,
-    model: ceres_core::Model,
+    show_help: bool,
+    // shown under the game while set
+    visualizer: Option<ceres_core::AudioVisualizerFrame>,
+    // drawn over the game while set, refreshed every presented frame
+    input_display: Option<input_display::Held>,
+    // beside the game while set
+    hex_editor: Option<hex_editor::HexEditor>,
+    // under the game while set, with the APU writes being logged
+    music_view: Option<music_view::MusicView>,
+    // under the game while the debug console is plugged in
+    console: Option<console::Console>,
+    // typed in the menu while a Barcode Boy is plugged in
+    barcode: String,
+    // host to join, with an optional port
+    link_address: String,
+    model: Model,
+    // offered in the menu when launched without a ROM
+    last_session: Option<std::path::PathBuf>,
+    // short notice over the game and when it was shown
+    osd: Option<(&'static str, std::time::Instant)>,
 }
 
 impl App {
     pub fn new(args: &crate::Cli) -> anyhow::Result<Self> {
-        let audio = ceres_audio::State::new()?;
+        let passphrase = match &args.save_key_file {
+            Some(path) => {
+                let mut key = std::fs::read(path)?;
+                // editors like to end files with a newline
+                while key.last().is_some_and(u8::is_ascii_whitespace) {
+                    key.pop();
+                }
+                Some(key)
+            }
+            None => None,
+        };
+        let save_codec = save_file::SaveCodec::new(args.save_format, passphrase)?;
+
+        let mut audio = ceres_audio::State::new()?;
+        audio.set_thread_priority(!args.no_thread_priority);
+        let bootrom = match &args.bootrom {
+            Some(path) => Some(std::fs::read(path)?.into_boxed_slice()),
+            None => None,
+        };
+
+        let save_storage: Box<dyn save_storage::SaveStorage> = if args.no_save_files {
+            Box::<save_storage::Memory>::default()
+        } else {
+            Box::new(save_storage::LocalDir::new(
+                args.save_dir
+                    .clone()
+                    .unwrap_or_else(gb_area::GbArea::data_dir),
+            ))
+        };
+
+        let mut gb_area = gb_area::GbArea::new(
+            args.model,
+            args.file.as_deref(),
+            &audio,
+            !args.no_thread_priority,
+            save_codec,
+            save_storage,
+            bootrom,
+        )?;
+        gb_area.set_rotation(args.rotation);
+        gb_area.set_mirror(args.mirror);
+        gb_area.set_color_filter(args.color_filter);
+        if let Some(lut) = &args.lut {
+            gb_area.load_lut(lut)?;
+        }
+        gb_area.set_output_filter(args.output_filter);
+        gb_area.set_dmg_palette(args.dmg_palette);
+        if args.barcode_boy {
+            gb_area.plug_barcode_boy();
+        }
+        if args.debug_console {
+            gb_area.plug_debug_console();
+        }
+
+        let last_session = if args.file.is_none() {
+            gb_area.last_session()
+        } else {
+            None
+        };
+
         Ok(App {
-            gb_area: gb_area::GbArea::new(args.model.into(), args.file.as_deref(), &audio)?,
+            gb_area,
             _audio: audio,
-            show_menu: false,
-            model: args.model.into(),
+            show_menu: last_session.is_some(),
+            show_help: false,
+            visualizer: None,
+            input_display: None,
+            hex_editor: None,
+            music_view: None,
+            console: args.debug_console.then(console::Console::default),
+            barcode: String::new(),
+            link_address: String::new(),
+            model: args.model,
+            last_session,
+            osd: None,
         })
     }
 
@@ -35,35 +152,271 @@ impl App {
 
     pub fn update(&mut self, message: Message) {
         match message {
+            Message::ModelChanged(model) => {
+                self.model = model;
+                self.gb_area.change_model(model);
+            }
             Message::ScalingChanged(scaling) => {
                 self.gb_area.set_scaling(scaling);
             }
-            Message::OpenButtonPressed => {
-                let file = rfd::FileDialog::new()
-                    .add_filter("gb", &["gb", "gbc"])
-                    .pick_file();
-
-                if let Some(file) = file {
-                    match self.gb_area.change_rom(&file, self.model) {
-                        Ok(_) => {
-                            self.show_menu = false;
-                        }
-                        Err(e) => eprintln!("Error changing ROM: {e}"),
+            Message::RotationChanged(rotation) => {
+                self.gb_area.set_rotation(rotation);
+            }
+            Message::MirrorToggled(mirror) => {
+                self.gb_area.set_mirror(mirror);
+            }
+            Message::ColorFilterChanged(color_filter) => {
+                self.gb_area.set_color_filter(color_filter);
+            }
+            Message::OutputFilterChanged(output_filter) => {
+                self.gb_area.set_output_filter(output_filter);
+            }
+            Message::DmgPaletteChanged(dmg_palette) => {
+                self.gb_area.set_dmg_palette(dmg_palette);
+            }
+            Message::LoadLutButtonPressed => self.pick_lut(),
+            Message::BarcodeChanged(barcode) => {
+                self.barcode = barcode;
+            }
+            Message::SwipeButtonPressed => {
+                if self.gb_area.swipe_barcode(&self.barcode) {
+                    self.show_menu = false;
+                } else {
+                    eprintln!("Barcodes are 13 digits long");
+                }
+            }
+            Message::LinkAddressChanged(link_address) => {
+                self.link_address = link_address;
+            }
+            Message::HostLinkButtonPressed => {
+                self.gb_area.host_link(ceres_link::DEFAULT_PORT);
+            }
+            Message::JoinLinkButtonPressed => {
+                self.gb_area.join_link(&self.link_address);
+            }
+            Message::CancelLinkButtonPressed => {
+                self.gb_area.cancel_link();
+            }
+            Message::ChannelToggled(channel, enabled) => {
+                self.gb_area.set_channel_enabled(channel, enabled);
+            }
+            Message::HexEditor(message) => {
+                if let Some(hex_editor) = &mut self.hex_editor {
+                    hex_editor.update(message, &mut self.gb_area);
+                }
+            }
+            Message::OpenButtonPressed => self.pick_rom(),
+            Message::ResumeButtonPressed => {
+                if let Some(file) = self.last_session.take() {
+                    self.open_rom(&file);
+                }
+            }
+            Message::Tick => self.tick(),
+            Message::VisualizerTick => {
+                if self.visualizer.is_some() {
+                    self.visualizer = Some(self.gb_area.audio_visualizer_frame());
+                }
+            }
+            Message::ConsoleTick => {
+                if let Some(console) = &mut self.console {
+                    console.push(&self.gb_area.take_console_text());
+                }
+            }
+            Message::OsdTick => {
+                if self
+                    .osd
+                    .is_some_and(|(_, shown)| shown.elapsed() >= OSD_DURATION)
+                {
+                    self.osd = None;
+                }
+            }
+            Message::AutosaveTick => self.gb_area.save_data(),
+            Message::EventOcurred(event) => self.event_occurred(event),
+        }
+    }
+
+    fn event_occurred(&mut self, event: iced::Event) {
+        match event {
+            iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                key: iced::keyboard::Key::Named(named),
+                ..
+            }) => self.key_pressed(named),
+            // ROMs dragged from Finder or any other file manager
+            iced::Event::Window(iced::window::Event::FileDropped(file)) => {
+                if gb_area::GbArea::is_state_file(&file) {
+                    if let Err(e) = self.gb_area.load_state_file(&file) {
+                        eprintln!("couldn't load state: {e}");
                     }
+                } else {
+                    self.open_rom(&file);
                 }
             }
-            Message::Tick => {
-                // TODO: Why don't we need to do anything here?
+            _ => {}
+        }
+    }
+
+    fn pick_lut(&mut self) {
+        let file = rfd::FileDialog::new().pick_file();
+
+        if let Some(file) = file {
+            match self.gb_area.load_lut(&file) {
+                Ok(()) => self.gb_area.set_output_filter(OutputFilter::Lut),
+                Err(e) => eprintln!("Error loading LUT: {e}"),
+            }
+        }
+    }
+
+    fn pick_rom(&mut self) {
+        let file = rfd::FileDialog::new()
+            .add_filter("gb", &["gb", "gbc"])
+            .pick_file();
+
+        if let Some(file) = file {
+            self.open_rom(&file);
+        }
+    }
+
+    // A new frame was presented, updating is enough to trigger a redraw
+    fn tick(&mut self) {
+        if self.gb_area.take_resynced() {
+            self.osd = Some(("Resynced after sleep", std::time::Instant::now()));
+        }
+        if self.input_display.is_some() {
+            self.input_display = Some(self.gb_area.held_buttons());
+        }
+        if let Some(hex_editor) = &mut self.hex_editor {
+            hex_editor.refresh(&self.gb_area);
+        }
+        if let Some(music_view) = &mut self.music_view {
+            let (writes, frame) = self.gb_area.drain_apu_writes();
+            music_view.push(writes, frame);
+        }
+    }
+
+    fn key_pressed(&mut self, key: iced::keyboard::key::Named) {
+        match key {
+            iced::keyboard::key::Named::Escape => {
+                self.show_menu = !self.show_menu;
+            }
+            iced::keyboard::key::Named::F1 => {
+                self.show_help = !self.show_help;
+            }
+            iced::keyboard::key::Named::F2 => {
+                self.gb_area.toggle_layer(ceres_core::Layer::Bg);
+            }
+            iced::keyboard::key::Named::F3 => {
+                self.gb_area.toggle_layer(ceres_core::Layer::Window);
+            }
+            iced::keyboard::key::Named::F4 => {
+                self.gb_area.toggle_layer(ceres_core::Layer::Obj);
+            }
+            iced::keyboard::key::Named::F5 => {
+                self.gb_area.quick_save();
+            }
+            iced::keyboard::key::Named::F8 => {
+                self.gb_area.quick_load();
+            }
+            iced::keyboard::key::Named::F6 => {
+                self.visualizer = if self.visualizer.is_some() {
+                    None
+                } else {
+                    Some(ceres_core::AudioVisualizerFrame::default())
+                };
+                self.gb_area.set_audio_visualizer(self.visualizer.is_some());
+            }
+            iced::keyboard::key::Named::F7 => {
+                self.input_display = if self.input_display.is_some() {
+                    None
+                } else {
+                    Some(self.gb_area.held_buttons())
+                };
             }
-            Message::EventOcurred(event) => {
-                if let iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
-                    key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape),
-                    ..
-                }) = event
+            iced::keyboard::key::Named::F9 => {
+                self.hex_editor = if self.hex_editor.is_some() {
+                    None
+                } else {
+                    Some(hex_editor::HexEditor::new(&self.gb_area))
+                };
+            }
+            iced::keyboard::key::Named::F10 => {
+                self.music_view = if self.music_view.is_some() {
+                    None
+                } else {
+                    Some(music_view::MusicView::default())
+                };
+                self.gb_area.set_apu_logging(self.music_view.is_some());
+            }
+            iced::keyboard::key::Named::F11 => {
+                self.gb_area.frame_advance();
+            }
+            iced::keyboard::key::Named::F12 => {
+                if self.gb_area.is_recording() {
+                    self.gb_area.stop_recording();
+                } else if let Some(dir) =
+                    sandbox::output_dir("Record frames to", gb_area::GbArea::recordings_dir())
                 {
-                    self.show_menu = !self.show_menu;
+                    if let Err(e) = self.gb_area.start_recording(&dir) {
+                        eprintln!("couldn't start recording: {e}");
+                    }
+                } else {
+                    // the folder picker was cancelled
+                }
+            }
+            iced::keyboard::key::Named::Insert => self.toggle_audio_recording(),
+            iced::keyboard::key::Named::Tab => {
+                let speed = if self.gb_area.speed() == gb_area::Speed::NORMAL {
+                    gb_area::TURBO_SPEED
+                } else {
+                    gb_area::Speed::NORMAL
+                };
+                self.gb_area.set_speed(speed);
+            }
+            iced::keyboard::key::Named::PageDown => {
+                self.gb_area.set_speed(self.gb_area.speed().slower());
+            }
+            iced::keyboard::key::Named::PageUp => {
+                self.gb_area.set_speed(self.gb_area.speed().faster());
+            }
+            iced::keyboard::key::Named::Space => {
+                if self.gb_area.is_paused() {
+                    self.gb_area.resume();
+                } else {
+                    self.gb_area.pause();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn toggle_audio_recording(&mut self) {
+        if self.gb_area.is_recording_audio() {
+            if let Err(e) = self.gb_area.stop_audio_recording() {
+                eprintln!("couldn't save audio recording: {e}");
+            }
+        } else if let Some(dir) =
+            sandbox::output_dir("Record audio to", gb_area::GbArea::recordings_dir())
+        {
+            self.gb_area.start_audio_recording(&dir);
+        } else {
+            // the folder picker was cancelled
+        }
+    }
+
+    fn open_rom(&mut self, file: &std::path::Path) {
+        match self.gb_area.change_rom(file, self.model) {
+            Ok(()) => {
+                self.show_menu = false;
+                // banks differ between cartridges
+                if self.hex_editor.is_some() {
+                    self.hex_editor = Some(hex_editor::HexEditor::new(&self.gb_area));
+                }
+                self.gb_area.set_audio_visualizer(self.visualizer.is_some());
+                if self.music_view.is_some() {
+                    self.music_view = Some(music_view::MusicView::default());
+                    self.gb_area.set_apu_logging(true);
                 }
             }
+            Err(e) => eprintln!("Error changing ROM: {e}"),
         }
     }
 
@@ -74,6 +427,15 @@ impl App {
                 button("Open ROM")
                     .on_press(Message::OpenButtonPressed)
                     .padding(5),
+                button("Resume last session")
+                    .on_press_maybe(
+                        self.last_session
+                            .as_ref()
+                            .map(|_| Message::ResumeButtonPressed)
+                    )
+                    .padding(5),
+                text("Model"),
+                pick_list(Model::ALL, Some(self.model), Message::ModelChanged).padding(5),
                 text("Scaling mode"),
                 pick_list(
                     Scaling::ALL,
@@ -81,7 +443,65 @@ impl App {
                     Message::ScalingChanged
                 )
                 .padding(5),
+                text("Rotation"),
+                pick_list(
+                    Rotation::ALL,
+                    Some(self.gb_area.rotation()),
+                    Message::RotationChanged
+                )
+                .padding(5),
+                checkbox("Mirror", self.gb_area.mirror()).on_toggle(Message::MirrorToggled),
+                text("Color filter"),
+                pick_list(
+                    ColorFilter::ALL,
+                    Some(self.gb_area.color_filter()),
+                    Message::ColorFilterChanged
+                )
+                .padding(5),
+                text("Output filter"),
+                pick_list(
+                    OutputFilter::ALL,
+                    Some(self.gb_area.output_filter()),
+                    Message::OutputFilterChanged
+                )
+                .padding(5),
+                button("Load LUT")
+                    .on_press(Message::LoadLutButtonPressed)
+                    .padding(5),
+                text("DMG palette"),
+                pick_list(
+                    DmgPalette::ALL,
+                    Some(self.gb_area.dmg_palette()),
+                    Message::DmgPaletteChanged
+                )
+                .padding(5),
             ]
+            .push_maybe(self.gb_area.has_barcode_boy().then(|| {
+                row![
+                    text_input("13 digit barcode", &self.barcode)
+                        .on_input(Message::BarcodeChanged)
+                        .on_submit(Message::SwipeButtonPressed)
+                        .padding(5),
+                    button("Swipe")
+                        .on_press(Message::SwipeButtonPressed)
+                        .padding(5),
+                ]
+                .spacing(10)
+            }))
+            .push(self.link_cable())
+            .push(text("Warnings"))
+            .push(
+                scrollable(
+                    column(
+                        self.gb_area
+                            .warnings()
+                            .iter()
+                            .map(|warning| text(warning.to_string()).size(12).into()),
+                    )
+                    .width(Length::Fill),
+                )
+                .height(100),
+            )
             .spacing(10);
 
             container(content)
@@ -91,28 +511,155 @@ impl App {
                 .align_y(Alignment::Center)
                 .into()
         } else {
-            let shader = shader(self.gb_area.scene())
-                .height(Length::Fill)
-                .width(Length::Fill);
-
-            container(shader)
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .align_x(Alignment::Center)
-                .align_y(Alignment::Center)
-                .into()
+            self.game()
         }
     }
 
+    // The screen with the overlays toggled from the keyboard
+    fn game(&self) -> Element<'_, Message> {
+        let shader = shader(self.gb_area.scene())
+            .height(Length::Fill)
+            .width(Length::Fill);
+
+        let game = container(shader)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_x(Alignment::Center)
+            .align_y(Alignment::Center);
+
+        let game: Element<'_, Message> = stack![game]
+            .push_maybe(self.input_display.map(|held| {
+                container(input_display::view(held))
+                    .padding(10)
+                    .height(Length::Fill)
+                    .align_y(Alignment::End)
+            }))
+            .push_maybe(self.osd.map(|(notice, _)| Self::osd(notice)))
+            .push_maybe(self.show_help.then(Self::help_overlay))
+            .into();
+
+        let game = match &self.hex_editor {
+            Some(hex_editor) => row![game, hex_editor.view().map(Message::HexEditor)].into(),
+            None => game,
+        };
+
+        column![game]
+            .push_maybe(self.visualizer.as_ref().map(visualizer::view))
+            .push_maybe(self.music_view.as_ref().map(|music_view| {
+                music_view.view(
+                    |channel| self.gb_area.channel_enabled(channel),
+                    Message::ChannelToggled,
+                )
+            }))
+            .push_maybe(self.console.as_ref().map(console::Console::view))
+            .into()
+    }
+
+    fn link_cable(&self) -> Element<'_, Message> {
+        column![
+            text("Link cable"),
+            row![
+                button("Host link")
+                    .on_press(Message::HostLinkButtonPressed)
+                    .padding(5),
+                text_input("host[:port]", &self.link_address)
+                    .on_input(Message::LinkAddressChanged)
+                    .on_submit(Message::JoinLinkButtonPressed)
+                    .padding(5),
+                button("Join link")
+                    .on_press(Message::JoinLinkButtonPressed)
+                    .padding(5),
+            ]
+            .push_maybe(self.gb_area.is_connecting_link().then(|| {
+                button("Cancel")
+                    .on_press(Message::CancelLinkButtonPressed)
+                    .padding(5)
+            }))
+            .spacing(10),
+            text(self.gb_area.link_status().to_string()).size(12),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn osd(notice: &str) -> Element<'_, Message> {
+        container(text(notice))
+            .padding(10)
+            .style(|_: &Theme| container::Style {
+                background: Some(Color::from_rgba(0.0, 0.0, 0.0, 0.75).into()),
+                text_color: Some(Color::WHITE),
+                ..container::Style::default()
+            })
+            .into()
+    }
+
+    fn help_overlay<'a>() -> Element<'a, Message> {
+        let binding = |key: &'a str, action: &'a str| {
+            row![text(key).width(80), text(action)].spacing(10).into()
+        };
+
+        let content = column![text("Game Boy").size(18)]
+            .extend(
+                bindings::BUTTONS
+                    .iter()
+                    .map(|(key, _, label)| binding(key, label)),
+            )
+            .push(text("Player 2 (Super Game Boy)").size(18))
+            .extend(
+                bindings::PLAYER_2_BUTTONS
+                    .iter()
+                    .map(|(key, _, label)| binding(key, label)),
+            )
+            .push(text("Emulator").size(18))
+            .extend(
+                bindings::SYSTEM
+                    .iter()
+                    .map(|(key, action)| binding(key, action)),
+            )
+            .spacing(5);
+
+        let panel = container(content)
+            .padding(15)
+            .style(|_: &Theme| container::Style {
+                background: Some(Color::from_rgba(0.0, 0.0, 0.0, 0.75).into()),
+                text_color: Some(Color::WHITE),
+                ..container::Style::default()
+            });
+
+        container(panel)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_x(Alignment::Center)
+            .align_y(Alignment::Center)
+            .into()
+    }
+
     pub fn theme(&self) -> Theme {
         Theme::GruvboxLight
     }
 
     pub fn subscription(&self) -> Subscription<Message> {
-        // window::frames().map(Message::Tick)
-        iced::Subscription::batch(vec![
-            window::frames().map(|_| Message::Tick),
+        // Only redraw when the emulator presents a new frame
+        let mut subscriptions = vec![
+            Subscription::run_with_id("frames", self.gb_area.frames()).map(|()| Message::Tick),
             event::listen().map(Message::EventOcurred),
-        ])
+            // paused or static games don't tick but still have RAM to save
+            iced::time::every(gb_area::AUTOSAVE_PERIOD).map(|_| Message::AutosaveTick),
+        ];
+
+        // Music keeps playing on static screens, so follow the display instead
+        if self.visualizer.is_some() {
+            subscriptions.push(iced::window::frames().map(|_| Message::VisualizerTick));
+        }
+        // Homebrew often prints and then stops updating the screen
+        if self.console.is_some() {
+            subscriptions.push(iced::window::frames().map(|_| Message::ConsoleTick));
+        }
+        // Static screens don't tick, the notice would never go away
+        if self.osd.is_some() {
+            subscriptions.push(iced::window::frames().map(|_| Message::OsdTick));
+        }
+
+        iced::Subscription::batch(subscriptions)
     }
 }
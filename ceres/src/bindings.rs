@@ -0,0 +1,60 @@
+use ceres_core::Button;
+
+// Keys as iced reports them for character keys, with the label shown in
+// the help overlay
+pub const BUTTONS: [(&str, Button, &str); 8] = [
+    ("w", Button::Up, "Up"),
+    ("a", Button::Left, "Left"),
+    ("s", Button::Down, "Down"),
+    ("d", Button::Right, "Right"),
+    ("l", Button::A, "A"),
+    ("k", Button::B, "B"),
+    ("n", Button::Select, "Select"),
+    ("m", Button::Start, "Start"),
+];
+
+// The second controller, only read by Super Game Boy multiplayer games
+pub const PLAYER_2_BUTTONS: [(&str, Button, &str); 8] = [
+    ("t", Button::Up, "Up"),
+    ("f", Button::Left, "Left"),
+    ("g", Button::Down, "Down"),
+    ("h", Button::Right, "Right"),
+    ("u", Button::A, "A"),
+    ("y", Button::B, "B"),
+    ("v", Button::Select, "Select"),
+    ("b", Button::Start, "Start"),
+];
+
+pub const SYSTEM: [(&str, &str); 18] = [
+    ("Escape", "Menu"),
+    ("Space", "Pause"),
+    ("F11", "Advance one frame while paused"),
+    ("Tab", "Fast-forward on or off"),
+    ("PageDown", "Slow motion, down to a quarter of the speed"),
+    ("PageUp", "Back towards normal speed"),
+    ("F5", "Quick save"),
+    ("F8", "Quick load"),
+    ("F2", "Show or hide the background"),
+    ("F3", "Show or hide the window"),
+    ("F4", "Show or hide sprites"),
+    ("F6", "Show or hide the audio visualizer"),
+    ("F7", "Show or hide the input display"),
+    ("F9", "Show or hide the memory editor"),
+    ("F10", "Show or hide the notes being played"),
+    ("F12", "Start or stop recording frames as PNGs"),
+    ("Insert", "Start or stop recording audio as WAV"),
+    ("F1", "Show or hide this help"),
+];
+
+// The player counting from 0 and their button
+pub fn button(key: &str) -> Option<(usize, Button)> {
+    [BUTTONS, PLAYER_2_BUTTONS]
+        .iter()
+        .enumerate()
+        .find_map(|(player, buttons)| {
+            buttons
+                .iter()
+                .find(|(k, ..)| *k == key)
+                .map(|(_, button, _)| (player, *button))
+        })
+}
@@ -0,0 +1,72 @@
+use iced::widget::{container, scrollable, text};
+use iced::{Element, Length};
+
+const MAX_LINES: usize = 200;
+const ESC: char = '\x1B';
+
+// Text printed by homebrew through the debug console link port device.
+// It's echoed to stdout untouched, so terminals color it, and shown under
+// the game without the escape sequences.
+#[derive(Default)]
+pub struct Console {
+    lines: Vec<String>,
+    // inside an escape sequence, possibly split between pushes
+    escape: Escape,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    #[default]
+    None,
+    Start,
+    // ESC [, until a byte in @..~
+    Csi,
+}
+
+impl Console {
+    pub fn push(&mut self, printed: &str) {
+        if printed.is_empty() {
+            return;
+        }
+
+        print!("{printed}");
+
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+
+        for c in printed.chars() {
+            self.escape = match (self.escape, c) {
+                (Escape::None, ESC) => Escape::Start,
+                (Escape::Start, '[') => Escape::Csi,
+                (Escape::Csi, '@'..='~') | (Escape::Start, _) | (Escape::None, '\r') => {
+                    Escape::None
+                }
+                (Escape::Csi, _) => Escape::Csi,
+                (Escape::None, '\n') => {
+                    self.lines.push(String::new());
+                    Escape::None
+                }
+                (Escape::None, c) => {
+                    if let Some(line) = self.lines.last_mut() {
+                        line.push(c);
+                    }
+                    Escape::None
+                }
+            };
+        }
+
+        let excess = self.lines.len().saturating_sub(MAX_LINES);
+        self.lines.drain(..excess);
+    }
+
+    pub fn view<'a, Message: 'a>(&self) -> Element<'a, Message> {
+        container(
+            scrollable(text(self.lines.join("\n")).size(12).width(Length::Fill))
+                .anchor_bottom()
+                .height(150),
+        )
+        .padding(10)
+        .into()
+    }
+}
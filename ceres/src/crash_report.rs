@@ -0,0 +1,151 @@
+use ceres_core::Gb;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+// Instructions kept for the report, a short ring is cheap enough to
+// always record
+pub const TRACE_LEN: usize = 64;
+
+// The console as the running frame found it. The hook can't reach the one
+// that panicked, the frame it panicked in still holds it.
+struct Snapshot {
+    report: Vec<u8>,
+    state: Vec<u8>,
+    save_data: Option<Vec<u8>>,
+}
+
+static LAST_FRAME: Mutex<Option<Snapshot>> = Mutex::new(None);
+// Set once the hook is installed
+static CRASH_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// Release builds abort on panic, so the report is written from the hook,
+// before anything unwinds. Reports go to dir.
+pub fn install_hook(dir: PathBuf) {
+    if CRASH_DIR.set(dir).is_err() {
+        return;
+    }
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        write(info);
+    }));
+}
+
+// Called before every frame, what a crash during it writes. Does nothing
+// without the hook.
+pub fn snapshot(gb: &mut Gb<ceres_audio::RingBuffer>) {
+    if CRASH_DIR.get().is_none() {
+        return;
+    }
+
+    let mut report = Vec::new();
+    if let Err(e) = describe(gb, &mut report) {
+        eprintln!("couldn't describe the console: {e}");
+    }
+
+    let snapshot = Snapshot {
+        report,
+        state: gb.save_bess(crate::gb_area::unix_time()),
+        save_data: gb.cartridge().save_data().map(<[u8]>::to_vec),
+    };
+
+    if let Ok(mut last_frame) = LAST_FRAME.lock() {
+        *last_frame = Some(snapshot);
+    }
+}
+
+// The report, a state to load where the crashing frame started and the
+// save RAM, so progress isn't lost with the crash
+fn write(info: &std::panic::PanicHookInfo) {
+    let Some(snapshot) = LAST_FRAME.lock().ok().and_then(|mut last| last.take()) else {
+        return;
+    };
+    let Some(dir) = CRASH_DIR.get() else {
+        return;
+    };
+
+    let stem = dir.join(format!("crash-{}", crate::gb_area::unix_time()));
+    let path = stem.with_extension("txt");
+
+    let mut report = Vec::new();
+    if let Err(e) = header(info, &mut report) {
+        eprintln!("couldn't describe the crash: {e}");
+    }
+    report.extend_from_slice(&snapshot.report);
+
+    let res = std::fs::create_dir_all(dir)
+        .and_then(|()| std::fs::write(&path, &report))
+        .and_then(|()| std::fs::write(stem.with_extension("bess"), &snapshot.state))
+        .and_then(|()| match &snapshot.save_data {
+            Some(ram) => std::fs::write(stem.with_extension("sav"), ram),
+            None => Ok(()),
+        });
+
+    match res {
+        Ok(()) => eprintln!("crash report written to {}", path.display()),
+        Err(e) => eprintln!("couldn't write the crash report: {e}"),
+    }
+}
+
+fn header(info: &std::panic::PanicHookInfo, out: &mut impl Write) -> std::io::Result<()> {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    let thread = std::thread::current();
+
+    writeln!(
+        out,
+        "{} {}",
+        crate::CERES_STYLIZED,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    write!(
+        out,
+        "thread {} panicked",
+        thread.name().unwrap_or("<unnamed>")
+    )?;
+    if let Some(location) = info.location() {
+        write!(out, " at {location}")?;
+    }
+    writeln!(out, ": {message}")?;
+    writeln!(out, "the console as the frame it panicked in started:")?;
+
+    Ok(())
+}
+
+fn describe(gb: &mut Gb<ceres_audio::RingBuffer>, out: &mut impl Write) -> std::io::Result<()> {
+    let cart = gb.cartridge();
+    writeln!(
+        out,
+        "ROM: {} version {}, header checksum {:02X}, global checksum {:04X}",
+        cart.title_str().unwrap_or("<not ASCII>"),
+        cart.version(),
+        cart.header_checksum(),
+        cart.global_checksum()
+    )?;
+    writeln!(out, "quirks: {:?}", gb.quirks())?;
+
+    let stats = gb.stats();
+    writeln!(
+        out,
+        "{} frames, {} instructions",
+        stats.frames, stats.instructions
+    )?;
+
+    writeln!(out, "last instructions, oldest first:")?;
+    for entry in gb.drain_trace() {
+        let regs = entry.registers;
+        writeln!(
+            out,
+            "{:04X}: {:02X}  AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X}",
+            regs.pc, entry.op, regs.af, regs.bc, regs.de, regs.hl, regs.sp
+        )?;
+    }
+
+    Ok(())
+}
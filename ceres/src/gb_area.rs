@@ -1,66 +1,218 @@
-use crate::{scene, Scaling};
+use crate::{
+    crash_report, input_display, save_file,
+    save_storage::SaveStorage,
+    scene, screensaver,
+    video_sink::{PngRecorder, SinkId, VideoSink, VideoSinks},
+    ColorFilter, DmgPalette, Model, OutputFilter, Rotation, Scaling,
+};
 use ceres_core::{Cart, Gb};
+use iced::futures::{task::AtomicWaker, Stream};
 use std::{
-    io::Read,
+    collections::VecDeque,
     path::Path,
+    pin::Pin,
     sync::{
-        atomic::{AtomicBool, Ordering::Relaxed},
+        atomic::{AtomicBool, AtomicU32, Ordering::Relaxed},
         Arc, Mutex,
     },
+    task::{Context, Poll},
 };
-use thread_priority::ThreadBuilderExt;
+use thread_priority::{ThreadBuilderExt, ThreadPriority};
+
+const MAX_WARNINGS: usize = 100;
+// Save RAM is also written periodically so a crash loses little progress
+pub const AUTOSAVE_PERIOD: std::time::Duration = std::time::Duration::from_mins(1);
+const LAST_SESSION_FILE: &str = "last_session";
+// How often the emulation thread runs a frame at normal speed, as long as
+// the console takes for one, about 16.74 ms
+pub const FRAME_INTERVAL: std::time::Duration = std::time::Duration::from_nanos(
+    ceres_core::TC_PER_FRAME.unsigned_abs() as u64 * 1_000_000_000
+        / ceres_core::TC_SEC.unsigned_abs() as u64,
+);
+// Running later than this drops the frames missed instead of running them
+// as fast as possible, it only happens when the thread stopped altogether,
+// like while the system slept
+const RESYNC_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(1);
+// Speed while fast-forwarding
+pub const TURBO_SPEED: Speed = Speed::Uncapped;
+// Stepped through by Speed::slower and Speed::faster
+const SLOW_MOTION: [f32; 3] = [0.25, 0.5, 1.0];
+
+// How fast the emulation runs relative to the console
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    Multiplier(f32),
+    // as fast as the host can
+    Uncapped,
+}
+
+impl Speed {
+    pub const NORMAL: Self = Self::Multiplier(1.0);
+
+    // The next slow-motion step down, fast-forward drops to normal first
+    pub fn slower(self) -> Self {
+        match self {
+            Self::Multiplier(multiplier) => Self::Multiplier(
+                SLOW_MOTION
+                    .into_iter()
+                    .rev()
+                    .find(|&step| step < multiplier)
+                    .unwrap_or(SLOW_MOTION[0]),
+            ),
+            Self::Uncapped => Self::NORMAL,
+        }
+    }
+
+    // The next slow-motion step up, stopping at normal
+    pub fn faster(self) -> Self {
+        match self {
+            Self::Multiplier(multiplier) => Self::Multiplier(
+                SLOW_MOTION
+                    .into_iter()
+                    .find(|&step| step > multiplier)
+                    .unwrap_or(multiplier.max(1.0)),
+            ),
+            Self::Uncapped => Self::Uncapped,
+        }
+    }
+
+    // Shared with the emulation thread as f32 bits, infinity for uncapped
+    fn to_bits(self) -> u32 {
+        match self {
+            Self::Multiplier(multiplier) => multiplier.to_bits(),
+            Self::Uncapped => f32::INFINITY.to_bits(),
+        }
+    }
+
+    fn frame_interval(bits: u32) -> std::time::Duration {
+        let multiplier = f32::from_bits(bits);
+        if multiplier.is_finite() && multiplier > 0.0 {
+            FRAME_INTERVAL.div_f32(multiplier)
+        } else {
+            std::time::Duration::ZERO
+        }
+    }
+}
 
 pub struct GbArea {
     scene: scene::Scene,
     rom_ident: String,
     exiting: Arc<AtomicBool>,
+    pause_thread: Arc<AtomicBool>,
+    // frames left to run while paused
+    frames_to_advance: Arc<AtomicU32>,
+    speed: Speed,
+    // Speed::to_bits of speed
+    thread_speed: Arc<AtomicU32>,
+    frame_notifier: Arc<FrameNotifier>,
+    // set by the emulation thread when it gave up catching up
+    resynced: Arc<AtomicBool>,
+    video_sinks: Arc<Mutex<VideoSinks>>,
+    // the PNG recorder among the sinks while recording
+    recorder: Option<SinkId>,
+    // where the WAV goes once audio recording stops
+    audio_recording: Option<std::path::PathBuf>,
+    warnings: Arc<Mutex<VecDeque<ceres_core::Warning>>>,
+    // held only while the game runs un-paused
+    screensaver: Option<screensaver::Inhibitor>,
     audio_stream: ceres_audio::Stream,
     thread_handle: Option<std::thread::JoinHandle<()>>,
+    output_filter: OutputFilter,
+    dmg_palette: DmgPalette,
+    lut: Option<Box<[u8]>>,
+    barcode_boy: bool,
+    debug_console: bool,
+    // by ApuChannel::index
+    channels_enabled: [bool; 4],
+    // written by the thread connecting the cable
+    link_status: Arc<Mutex<LinkStatus>>,
+    pending_link: Option<PendingLink>,
+    save_codec: save_file::SaveCodec,
+    save_storage: Box<dyn SaveStorage>,
+    save_states: ceres_core::SaveStateManager<ceres_audio::RingBuffer>,
+    // replaces the bundled one when it fits the model
+    bootrom: Option<Box<[u8]>>,
 }
 
 impl GbArea {
     pub fn new(
-        model: ceres_core::Model,
+        model: Model,
         rom_path: Option<&Path>,
         audio_state: &ceres_audio::State,
+        thread_priority: bool,
+        save_codec: save_file::SaveCodec,
+        save_storage: Box<dyn SaveStorage>,
+        bootrom: Option<Box<[u8]>>,
     ) -> anyhow::Result<Self> {
         let (cart, rom_ident) = if let Some(rom_path) = rom_path {
             let mut cart = Self::cart_from_path(rom_path)?;
             let ident = Self::ident_from_cart(&cart)?;
-            if let Ok(ram) = Self::ram_from_dirs_ident(&ident) {
-                cart.set_ram(ram)?;
+            if let Some(ram) = Self::load_ram(&ident, &save_codec, save_storage.as_ref())? {
+                Self::set_save_data(&mut cart, &ram)?;
             } else {
                 println!("No RAM found for cart {ident}");
             }
 
+            Self::store_last_session(save_storage.as_ref(), rom_path);
+
             (cart, ident)
         } else {
             (Cart::default(), String::new())
         };
 
-        let sample_rate = ceres_audio::Stream::sample_rate();
+        let sample_rate = ceres_audio::State::sample_rate();
         let mut audio_stream = ceres_audio::Stream::new(audio_state).unwrap();
         let ring_buffer = audio_stream.get_ring_buffer();
 
-        let gb = Arc::new(Mutex::new(Gb::new(model, sample_rate, cart, ring_buffer)));
+        let (mut gb, build_warnings) =
+            Gb::try_new(model.for_cart(&cart), sample_rate, cart, ring_buffer)?;
+        Self::report_build_warnings(&build_warnings);
+        gb.set_exact_sample_rate(true);
+        gb.set_trace_capacity(crash_report::TRACE_LEN);
+        Self::set_bootrom(&mut gb, bootrom.as_deref());
+        let gb = Arc::new(Mutex::new(gb));
         audio_stream.resume().unwrap();
 
         let pause_thread = Arc::new(AtomicBool::new(false));
+        let frames_to_advance = Arc::new(AtomicU32::new(0));
+        let thread_speed = Arc::new(AtomicU32::new(Speed::NORMAL.to_bits()));
 
         let exiting = Arc::new(AtomicBool::new(false));
 
-        let thread_builder = std::thread::Builder::new().name("gb_loop".to_owned());
+        let frame_notifier = Arc::new(FrameNotifier::default());
+
+        let resynced = Arc::new(AtomicBool::new(false));
+
+        let video_sinks = Arc::new(Mutex::new(VideoSinks::default()));
+
+        let warnings = Arc::new(Mutex::new(VecDeque::new()));
+
         let thread_handle = {
             let gb = Arc::clone(&gb);
             let exit = Arc::clone(&exiting);
             let pause_thread = Arc::clone(&pause_thread);
+            let frames_to_advance = Arc::clone(&frames_to_advance);
+            let thread_speed = Arc::clone(&thread_speed);
+            let frame_notifier = Arc::clone(&frame_notifier);
+            let resynced = Arc::clone(&resynced);
+            let video_sinks = Arc::clone(&video_sinks);
+            let warnings = Arc::clone(&warnings);
 
-            // std::thread::spawn(move || gb_loop(gb, exit, pause_thread))
-            thread_builder
-                .spawn_with_priority(thread_priority::ThreadPriority::Max, move |_| {
-                    Self::gb_loop(gb, exit, pause_thread);
-                })
-                .expect("failed to spawn thread")
+            let gb_loop = move || {
+                Self::gb_loop(
+                    gb,
+                    exit,
+                    pause_thread,
+                    frames_to_advance,
+                    thread_speed,
+                    frame_notifier,
+                    resynced,
+                    video_sinks,
+                    warnings,
+                );
+            };
+
+            Self::spawn_gb_loop(thread_priority, gb_loop)
         };
 
         let scene = scene::Scene::new(gb, Scaling::default());
@@ -69,29 +221,531 @@ impl GbArea {
             scene,
             rom_ident,
             exiting,
+            pause_thread,
+            frames_to_advance,
+            speed: Speed::NORMAL,
+            thread_speed,
+            frame_notifier,
+            resynced,
+            video_sinks,
+            recorder: None,
+            audio_recording: None,
+            warnings,
+            screensaver: Self::inhibit_screensaver(),
             thread_handle: Some(thread_handle),
             audio_stream,
+            output_filter: OutputFilter::default(),
+            dmg_palette: DmgPalette::default(),
+            lut: None,
+            barcode_boy: false,
+            debug_console: false,
+            channels_enabled: [true; 4],
+            link_status: Arc::new(Mutex::new(LinkStatus::Unplugged)),
+            pending_link: None,
+            save_codec,
+            save_storage,
+            save_states: ceres_core::SaveStateManager::new(0),
+            bootrom,
         })
     }
 
-    // pub fn is_paused(&self) -> bool {
-    //     self.pause_thread.load(Relaxed)
-    // }
+    pub fn quick_save(&mut self) {
+        if let Ok(gb) = self.scene.gb().lock() {
+            self.save_states.quick_save(&gb, unix_time());
+        }
+    }
 
-    // pub fn pause(&mut self) {
-    //     self.audio_stream.pause();
-    //     self.pause_thread.store(true, Relaxed);
-    // }
+    pub fn quick_load(&mut self) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            if let Err(e) = self.save_states.quick_load(&mut gb) {
+                eprintln!("couldn't load state: {e}");
+            }
+        }
+    }
 
-    // pub fn resume(&mut self) {
-    //     self.pause_thread.store(false, Relaxed);
-    //     self.audio_stream.resume();
-    // }
+    // BESS states exported by other emulators, checked against the loaded
+    // cartridge
+    pub fn load_state_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let data = std::fs::read(path)?;
+        let mut gb = self.scene.gb().lock().map_err(|e| anyhow::anyhow!("{e}"))?;
+        gb.load_bess(&data, unix_time())?;
+        Ok(())
+    }
+
+    // SameBoy numbers its slots .s0 to .s9
+    pub fn is_state_file(path: &Path) -> bool {
+        path.extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("bess")
+                    || ext.len() == 2
+                        && ext.starts_with(['s', 'S'])
+                        && ext.as_bytes()[1].is_ascii_digit()
+            })
+    }
+
+    // ROM that was running when the emulator was last closed or crashed,
+    // kept next to the save files
+    pub fn last_session(&self) -> Option<std::path::PathBuf> {
+        let data = self.save_storage.load(LAST_SESSION_FILE).ok()??;
+        let rom_path = std::path::PathBuf::from(String::from_utf8(data).ok()?);
+
+        rom_path.is_file().then_some(rom_path)
+    }
+
+    fn store_last_session(save_storage: &dyn SaveStorage, rom_path: &Path) {
+        if let Err(e) =
+            save_storage.store(LAST_SESSION_FILE, rom_path.as_os_str().as_encoded_bytes())
+        {
+            eprintln!("couldn't store last session: {e}");
+        }
+    }
+
+    fn project_dirs() -> directories::ProjectDirs {
+        directories::ProjectDirs::from(crate::QUALIFIER, crate::ORGANIZATION, crate::CERES_STYLIZED)
+            .unwrap()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pause_thread.load(Relaxed)
+    }
+
+    // Stopping the audio stream too lets the audio thread sleep while paused,
+    // frames advanced meanwhile are silent instead of piling up samples
+    pub fn pause(&mut self) {
+        if let Err(e) = self.audio_stream.pause() {
+            eprintln!("couldn't pause audio: {e}");
+        }
+        self.pause_thread.store(true, Relaxed);
+        self.update_audio_enabled();
+        self.screensaver = None;
+    }
+
+    pub fn resume(&mut self) {
+        self.frames_to_advance.store(0, Relaxed);
+        self.pause_thread.store(false, Relaxed);
+        self.update_audio_enabled();
+        if let Err(e) = self.audio_stream.resume() {
+            eprintln!("couldn't resume audio: {e}");
+        }
+        self.screensaver = Self::inhibit_screensaver();
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    // Audio is muted at any speed but the normal one, faster it would pile up
+    // in the ring buffer and slower it would crackle as the buffer runs dry
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+        self.thread_speed.store(speed.to_bits(), Relaxed);
+        self.update_audio_enabled();
+    }
+
+    // Mixing is skipped altogether while nothing would be heard
+    fn audio_enabled(&self) -> bool {
+        !self.is_paused() && self.speed == Speed::NORMAL
+    }
+
+    fn update_audio_enabled(&self) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.set_audio_enabled(self.audio_enabled());
+        }
+    }
+
+    // True once after the emulation thread skipped frames it was too late
+    // for, like after the system woke up from sleep
+    pub fn take_resynced(&self) -> bool {
+        self.resynced.swap(false, Relaxed)
+    }
+
+    // Runs a single frame while paused, does nothing while running
+    pub fn frame_advance(&mut self) {
+        if self.is_paused() {
+            self.frames_to_advance.fetch_add(1, Relaxed);
+        }
+    }
+
+    fn inhibit_screensaver() -> Option<screensaver::Inhibitor> {
+        screensaver::Inhibitor::new()
+            .inspect_err(|e| eprintln!("couldn't inhibit screensaver: {e}"))
+            .ok()
+    }
+
+    // Most recent warnings raised by the emulated program, oldest first
+    pub fn warnings(&self) -> Vec<ceres_core::Warning> {
+        self.warnings
+            .lock()
+            .map(|warnings| warnings.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    // Yields every time the emulator presents a frame with new contents,
+    // nothing is yielded while paused, with the LCD off or on static screens
+    pub fn frames(&self) -> Frames {
+        Frames {
+            notifier: Arc::clone(&self.frame_notifier),
+            last_id: None,
+        }
+    }
+
+    // Called with every frame run from now on, until removed
+    pub fn add_video_sink(&mut self, sink: Box<dyn VideoSink>) -> Option<SinkId> {
+        self.video_sinks
+            .lock()
+            .ok()
+            .map(|mut sinks| sinks.add(sink))
+    }
+
+    pub fn remove_video_sink(&mut self, id: SinkId) {
+        if let Ok(mut sinks) = self.video_sinks.lock() {
+            sinks.remove(id);
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some(id) = self.recorder.take() {
+            self.remove_video_sink(id);
+        }
+    }
+
+    // Frames go to a new directory in base each time
+    pub fn start_recording(&mut self, base: &Path) -> anyhow::Result<()> {
+        self.stop_recording();
+
+        let dir = base.join(unix_time().to_string());
+        println!("Recording frames to {}", dir.display());
+        let recorder = PngRecorder::new(dir)?;
+        self.recorder = self.add_video_sink(Box::new(recorder));
+
+        Ok(())
+    }
+
+    pub fn is_recording_audio(&self) -> bool {
+        self.audio_recording.is_some()
+    }
+
+    // The WAV is written to a new file in dir when recording stops
+    pub fn start_audio_recording(&mut self, dir: &Path) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.start_audio_recording(false);
+            let path = dir.join(format!("{}.wav", unix_time()));
+            println!("Recording audio to {}", path.display());
+            self.audio_recording = Some(path);
+        }
+    }
+
+    pub fn stop_audio_recording(&mut self) -> anyhow::Result<()> {
+        let Some(path) = self.audio_recording.take() else {
+            return Ok(());
+        };
+        let wav = self
+            .scene
+            .gb()
+            .lock()
+            .map_err(|e| anyhow::anyhow!("{e}"))?
+            .finish_audio_recording();
+
+        if let Some(wav) = wav {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, wav)?;
+        }
+
+        Ok(())
+    }
 
     // pub fn rom_ident(&self) -> &str {
     //     &self.rom_ident
     // }
 
+    // Debug aid, the layer stays hidden until toggled again or the ROM changes
+    pub fn toggle_layer(&mut self, layer: ceres_core::Layer) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            let enabled = gb.layer_enabled(layer);
+            gb.set_layer_enabled(layer, !enabled);
+        }
+    }
+
+    // The running game boots again, its progress is saved first in case the
+    // new model can't run it
+    pub fn change_model(&mut self, model: Model) {
+        self.save_data();
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            let model = model.for_cart(gb.cartridge());
+            gb.change_model_and_soft_reset(model);
+            Self::set_bootrom(&mut gb, self.bootrom.as_deref());
+        }
+        if let Ok(mut warnings) = self.warnings.lock() {
+            warnings.clear();
+        }
+    }
+
+    // Stays muted when the ROM changes
+    pub fn set_channel_enabled(&mut self, channel: ceres_core::ApuChannel, enabled: bool) {
+        self.channels_enabled[channel.index()] = enabled;
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.set_channel_enabled(channel, enabled);
+        }
+    }
+
+    pub fn channel_enabled(&self, channel: ceres_core::ApuChannel) -> bool {
+        self.channels_enabled[channel.index()]
+    }
+
+    pub fn set_audio_visualizer(&mut self, enabled: bool) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.set_audio_visualizer(enabled);
+        }
+    }
+
+    pub fn audio_visualizer_frame(&self) -> ceres_core::AudioVisualizerFrame {
+        self.scene
+            .gb()
+            .lock()
+            .map(|mut gb| gb.audio_visualizer_frame())
+            .unwrap_or_default()
+    }
+
+    pub fn held_buttons(&self) -> input_display::Held {
+        self.scene
+            .gb()
+            .lock()
+            .map(|gb| input_display::Held::from_fn(|button| gb.held_in_last_frame(button)))
+            .unwrap_or_default()
+    }
+
+    // Empty if the bank doesn't exist
+    pub fn region_bytes(&self, region: ceres_core::MemoryRegion) -> Vec<u8> {
+        self.scene
+            .gb()
+            .lock()
+            .map(|gb| gb.region_bytes(region).to_vec())
+            .unwrap_or_default()
+    }
+
+    pub fn write_mem_byte(
+        &mut self,
+        region: ceres_core::MemoryRegion,
+        offset: usize,
+        val: u8,
+    ) -> bool {
+        self.scene
+            .gb()
+            .lock()
+            .is_ok_and(|mut gb| gb.write_mem_byte(region, offset, val))
+    }
+
+    // Logging restarts the frame count at 0
+    pub fn set_apu_logging(&mut self, enabled: bool) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.set_apu_logging(enabled);
+        }
+    }
+
+    // APU writes since the last call and the frame being run
+    pub fn drain_apu_writes(&self) -> (Vec<ceres_core::ApuWrite>, u64) {
+        self.scene
+            .gb()
+            .lock()
+            .map(|mut gb| {
+                let writes = gb.drain_apu_writes().collect();
+                (
+                    writes,
+                    gb.apu_log_dots() / u64::from(ceres_core::TC_PER_FRAME.unsigned_abs()),
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn has_barcode_boy(&self) -> bool {
+        self.barcode_boy
+    }
+
+    // Stays plugged in when the ROM changes
+    pub fn plug_barcode_boy(&mut self) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.set_link_port(ceres_core::LinkPort::BarcodeBoy(
+                ceres_core::BarcodeBoy::default(),
+            ));
+        }
+        self.barcode_boy = true;
+    }
+
+    // False if the barcode isn't 13 digits
+    pub fn swipe_barcode(&mut self, barcode: &str) -> bool {
+        let Ok(mut gb) = self.scene.gb().lock() else {
+            return false;
+        };
+
+        match gb.link_port_mut() {
+            ceres_core::LinkPort::BarcodeBoy(bcb) => bcb.scan(barcode),
+            ceres_core::LinkPort::Disconnected
+            | ceres_core::LinkPort::DebugConsole(_)
+            | ceres_core::LinkPort::Workboy(_)
+            | ceres_core::LinkPort::Device(_) => false,
+        }
+    }
+
+    // Stays plugged in when the ROM changes
+    pub fn plug_debug_console(&mut self) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.set_link_port(ceres_core::LinkPort::DebugConsole(
+                ceres_core::DebugConsole::default(),
+            ));
+        }
+        self.debug_console = true;
+    }
+
+    // What the game printed since the last call
+    pub fn take_console_text(&mut self) -> String {
+        let Ok(mut gb) = self.scene.gb().lock() else {
+            return String::new();
+        };
+
+        match gb.link_port_mut() {
+            ceres_core::LinkPort::DebugConsole(console) => console.take_text(),
+            _ => String::new(),
+        }
+    }
+
+    pub fn link_status(&self) -> LinkStatus {
+        self.link_status
+            .lock()
+            .map_or(LinkStatus::Unplugged, |status| status.clone())
+    }
+
+    // Waits for the other player in the background
+    pub fn host_link(&mut self, port: u16) {
+        // the port may still be held by an earlier wait
+        self.cancel_link();
+
+        let host = match ceres_link::Host::bind(port) {
+            Ok(host) => host,
+            Err(e) => {
+                self.set_link_status(LinkStatus::Failed(e.to_string()));
+                return;
+            }
+        };
+
+        self.set_link_status(LinkStatus::Waiting(port));
+        self.connect_link(true, move |rom, cancel| host.accept_unless(rom, cancel));
+    }
+
+    // The port can be left out if the host uses the default one
+    pub fn join_link(&mut self, addr: &str) {
+        self.cancel_link();
+
+        let addr = if addr.contains(':') {
+            addr.to_owned()
+        } else {
+            format!("{addr}:{}", ceres_link::DEFAULT_PORT)
+        };
+
+        self.set_link_status(LinkStatus::Joining(addr.clone()));
+        self.connect_link(false, move |rom, _| {
+            ceres_link::Link::join(addr.as_str(), rom)
+        });
+    }
+
+    pub fn is_connecting_link(&self) -> bool {
+        matches!(
+            self.link_status(),
+            LinkStatus::Waiting(_) | LinkStatus::Joining(_)
+        )
+    }
+
+    // Stops waiting for the other player, an established link stays
+    pub fn cancel_link(&mut self) {
+        let Some(pending) = self.pending_link.take() else {
+            return;
+        };
+
+        pending.cancel.store(true, Relaxed);
+        if let Some(thread) = pending.host_thread {
+            // returns within a poll of the listener, freeing the port
+            if thread.join().is_err() {
+                eprintln!("link thread panicked");
+            }
+        }
+
+        if self.is_connecting_link() {
+            self.set_link_status(LinkStatus::Unplugged);
+        }
+    }
+
+    fn connect_link<F>(&mut self, hosting: bool, connect: F)
+    where
+        F: FnOnce(ceres_link::RomId, &AtomicBool) -> Result<ceres_link::Link, ceres_link::Error>
+            + Send
+            + 'static,
+    {
+        let Ok(rom) = self
+            .scene
+            .gb()
+            .lock()
+            .map(|gb| ceres_link::RomId::of(gb.cartridge()))
+        else {
+            return;
+        };
+
+        let gb = Arc::clone(self.scene.gb());
+        let status = Arc::clone(&self.link_status);
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let res = {
+            let cancel = Arc::clone(&cancel);
+            std::thread::Builder::new()
+                .name("link_connect".to_owned())
+                .spawn(move || {
+                    let new_status = match connect(rom, &cancel) {
+                        // whoever cancelled already set the status
+                        _ if cancel.load(Relaxed) => return,
+                        Ok(link) => match gb.lock() {
+                            // the cart may have been swapped while waiting
+                            Ok(mut gb) if ceres_link::RomId::of(gb.cartridge()) == rom => {
+                                let linked = LinkStatus::Connected {
+                                    peer: link.peer(),
+                                    round_trip: link.round_trip(),
+                                    rom_mismatch: link.rom_mismatch(),
+                                };
+                                gb.set_link_port(ceres_core::LinkPort::Device(Box::new(link)));
+                                linked
+                            }
+                            _ => LinkStatus::Failed("the ROM changed while connecting".to_owned()),
+                        },
+                        Err(e) => LinkStatus::Failed(e.to_string()),
+                    };
+
+                    if let Ok(mut status) = status.lock() {
+                        *status = new_status;
+                    }
+                })
+        };
+
+        match res {
+            Ok(thread) => {
+                self.pending_link = Some(PendingLink {
+                    cancel,
+                    host_thread: hosting.then_some(thread),
+                });
+            }
+            Err(e) => self.set_link_status(LinkStatus::Failed(e.to_string())),
+        }
+    }
+
+    fn set_link_status(&self, new_status: LinkStatus) {
+        if let Ok(mut status) = self.link_status.lock() {
+            *status = new_status;
+        }
+    }
+
     pub fn scaling(&self) -> Scaling {
         self.scene.scaling()
     }
@@ -100,23 +754,146 @@ impl GbArea {
         self.scene.set_scaling(scaling);
     }
 
+    pub fn rotation(&self) -> Rotation {
+        self.scene.rotation()
+    }
+
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.scene.set_rotation(rotation);
+    }
+
+    pub fn mirror(&self) -> bool {
+        self.scene.mirror()
+    }
+
+    pub fn set_mirror(&mut self, mirror: bool) {
+        self.scene.set_mirror(mirror);
+    }
+
+    pub fn color_filter(&self) -> ColorFilter {
+        self.scene.color_filter()
+    }
+
+    pub fn set_color_filter(&mut self, color_filter: ColorFilter) {
+        self.scene.set_color_filter(color_filter);
+    }
+
+    pub fn output_filter(&self) -> OutputFilter {
+        self.output_filter
+    }
+
+    // Selecting the LUT filter does nothing until a LUT is loaded
+    pub fn set_output_filter(&mut self, output_filter: OutputFilter) {
+        let Some(filter) = self.core_output_filter(output_filter) else {
+            eprintln!("no LUT loaded");
+            return;
+        };
+
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.set_output_filter(filter);
+        }
+        self.output_filter = output_filter;
+    }
+
+    pub fn dmg_palette(&self) -> DmgPalette {
+        self.dmg_palette
+    }
+
+    pub fn set_dmg_palette(&mut self, dmg_palette: DmgPalette) {
+        if let Ok(mut gb) = self.scene.gb().lock() {
+            gb.set_dmg_palette(&dmg_palette.colors());
+        }
+        self.dmg_palette = dmg_palette;
+    }
+
+    pub fn load_lut(&mut self, path: &Path) -> anyhow::Result<()> {
+        let data = std::fs::read(path)?;
+
+        if data.len() != ceres_core::LUT_SIZE {
+            anyhow::bail!(
+                "LUT file is {} bytes long, expected {}",
+                data.len(),
+                ceres_core::LUT_SIZE
+            );
+        }
+
+        self.lut = Some(data.into_boxed_slice());
+        if self.output_filter == OutputFilter::Lut {
+            self.set_output_filter(OutputFilter::Lut);
+        }
+
+        Ok(())
+    }
+
+    fn core_output_filter(&self, output_filter: OutputFilter) -> Option<ceres_core::OutputFilter> {
+        match output_filter {
+            OutputFilter::None => Some(ceres_core::OutputFilter::None),
+            OutputFilter::Greyscale => Some(ceres_core::OutputFilter::Greyscale),
+            OutputFilter::Sepia => Some(ceres_core::OutputFilter::Sepia),
+            OutputFilter::Agb => Some(ceres_core::OutputFilter::Agb),
+            OutputFilter::Lut => self.lut.clone().and_then(ceres_core::OutputFilter::lut),
+        }
+    }
+
     pub fn scene(&self) -> &scene::Scene {
         &self.scene
     }
 
-    pub fn change_rom(&mut self, rom_path: &Path, model: ceres_core::Model) -> anyhow::Result<()> {
+    pub fn change_rom(&mut self, rom_path: &Path, model: Model) -> anyhow::Result<()> {
         let mut cart = Self::cart_from_path(rom_path)?;
         let ident = Self::ident_from_cart(&cart)?;
 
-        if let Ok(ram) = Self::ram_from_dirs_ident(&ident) {
-            cart.set_ram(ram).unwrap();
+        // Don't lose progress in the ROM being replaced. Saved before loading
+        // so reopening the same ROM doesn't read back an older save.
+        self.save_data();
+        if let Some(ram) = Self::load_ram(&ident, &self.save_codec, self.save_storage.as_ref())? {
+            Self::set_save_data(&mut cart, &ram)?;
+        }
+
+        if let Err(e) = self.stop_audio_recording() {
+            eprintln!("couldn't save audio recording: {e}");
         }
 
-        let sample_rate = ceres_audio::Stream::sample_rate();
+        let sample_rate = ceres_audio::State::sample_rate();
         let ring_buffer = self.audio_stream.get_ring_buffer();
 
-        let new_gb = Gb::new(model, sample_rate, cart, ring_buffer);
+        let (mut new_gb, build_warnings) =
+            Gb::try_new(model.for_cart(&cart), sample_rate, cart, ring_buffer)?;
+        Self::report_build_warnings(&build_warnings);
+        new_gb.set_exact_sample_rate(true);
+        new_gb.set_trace_capacity(crash_report::TRACE_LEN);
+        Self::set_bootrom(&mut new_gb, self.bootrom.as_deref());
+        if let Some(filter) = self.core_output_filter(self.output_filter) {
+            new_gb.set_output_filter(filter);
+        }
+        new_gb.set_dmg_palette(&self.dmg_palette.colors());
+        new_gb.set_audio_enabled(self.audio_enabled());
+        for channel in ceres_core::ApuChannel::ALL {
+            new_gb.set_channel_enabled(channel, self.channel_enabled(channel));
+        }
+        if self.barcode_boy {
+            new_gb.set_link_port(ceres_core::LinkPort::BarcodeBoy(
+                ceres_core::BarcodeBoy::default(),
+            ));
+        }
+        if self.debug_console {
+            new_gb.set_link_port(ceres_core::LinkPort::DebugConsole(
+                ceres_core::DebugConsole::default(),
+            ));
+        }
+        // a player joining now would link to a ROM that's gone
+        self.cancel_link();
         self.scene.replace_gb(new_gb);
+        // the cable went with the old console
+        self.set_link_status(LinkStatus::Unplugged);
+        self.save_states.clear_all();
+        self.rom_ident = ident;
+
+        Self::store_last_session(self.save_storage.as_ref(), rom_path);
+
+        if let Ok(mut warnings) = self.warnings.lock() {
+            warnings.clear();
+        }
 
         Ok(())
     }
@@ -125,8 +902,7 @@ impl GbArea {
 impl GbArea {
     // In theory can't ever fail because ROM title is always ASCII, in practice I don't know if we check for that on Cart creation
     fn ident_from_cart(cart: &ceres_core::Cart) -> anyhow::Result<String> {
-        let mut ident = String::new();
-        cart.ascii_title().read_to_string(&mut ident)?;
+        let mut ident = cart.title_str()?.to_owned();
         ident.push('-');
         ident.push_str(cart.version().to_string().as_str());
         ident.push('-');
@@ -137,6 +913,20 @@ impl GbArea {
         Ok(ident)
     }
 
+    fn report_build_warnings(warnings: &[ceres_core::BuildWarning]) {
+        for warning in warnings {
+            eprintln!("{warning}");
+        }
+    }
+
+    fn set_bootrom(gb: &mut Gb<ceres_audio::RingBuffer>, bootrom: Option<&[u8]>) {
+        if let Some(bootrom) = bootrom {
+            if let Err(e) = gb.set_bootrom(bootrom.into()) {
+                eprintln!("Using the bundled bootrom: {e}");
+            }
+        }
+    }
+
     fn cart_from_path(path: &Path) -> anyhow::Result<ceres_core::Cart> {
         let rom = std::fs::read(path)
             .map(Vec::into_boxed_slice)
@@ -145,93 +935,278 @@ impl GbArea {
         ceres_core::Cart::new(rom).map_err(std::convert::Into::into)
     }
 
-    fn ram_from_dirs_ident(ident: &str) -> anyhow::Result<Box<[u8]>> {
-        let directories = directories::ProjectDirs::from(
-            crate::QUALIFIER,
-            crate::ORGANIZATION,
-            crate::CERES_STYLIZED,
-        )
-        .unwrap();
+    // A save that exists but doesn't verify is an error, starting with empty
+    // RAM would overwrite it on the next autosave
+    fn load_ram(
+        ident: &str,
+        save_codec: &save_file::SaveCodec,
+        save_storage: &dyn SaveStorage,
+    ) -> anyhow::Result<Option<Box<[u8]>>> {
+        let name = format!("{ident}.sav");
+        let location = save_storage.locate(&name);
 
-        let path = directories.data_dir().join(ident).with_extension("sav");
+        println!("Loading RAM from {location}");
 
-        println!("Loading RAM from {path:?}");
+        let Some(file) = save_storage.load(&name)? else {
+            return Ok(None);
+        };
 
-        std::fs::read(path)
-            .map(Vec::into_boxed_slice)
-            .map_err(|e| anyhow::anyhow!(e))
+        save_codec
+            .decode(&file)
+            .map(|ram| Some(ram.into_boxed_slice()))
+            .map_err(|e| anyhow::anyhow!("{location}: {e}"))
+    }
+
+    // RAM sizes are multiples of 512 bytes, anything past them is the RTC
+    // footer other emulators write too
+    fn set_save_data(cart: &mut Cart, save: &[u8]) -> anyhow::Result<()> {
+        let (ram, footer) = save.split_at(save.len() - save.len() % 0x200);
+        cart.set_ram(ram.into())?;
+
+        if !footer.is_empty() && !cart.load_rtc_footer(footer, unix_time()) {
+            eprintln!(
+                "ignoring {} unknown bytes at the end of the save",
+                footer.len()
+            );
+        }
+
+        Ok(())
     }
 
+    // Where recordings go unless sandboxed
+    pub fn recordings_dir() -> std::path::PathBuf {
+        Self::data_dir().join("recordings")
+    }
+
+    // The default save location
+    pub fn data_dir() -> std::path::PathBuf {
+        Self::project_dirs().data_dir().to_path_buf()
+    }
+
+    fn spawn_gb_loop(
+        thread_priority: bool,
+        gb_loop: impl FnOnce() + Send + 'static,
+    ) -> std::thread::JoinHandle<()> {
+        let thread_builder = std::thread::Builder::new().name("gb_loop".to_owned());
+
+        if thread_priority {
+            // Above normal but below the audio thread, which must never
+            // starve waiting for emulation
+            let priority = thread_priority::ThreadPriorityValue::try_from(70)
+                .map_or(ThreadPriority::Max, ThreadPriority::Crossplatform);
+
+            thread_builder.spawn_with_priority(priority, move |res| {
+                if res.is_err() {
+                    eprintln!("Couldn't raise the emulation thread priority");
+                }
+                gb_loop();
+            })
+        } else {
+            thread_builder.spawn(gb_loop)
+        }
+        .expect("failed to spawn thread")
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn gb_loop(
         gb: Arc<Mutex<Gb<ceres_audio::RingBuffer>>>,
         exiting: Arc<AtomicBool>,
         pause_thread: Arc<AtomicBool>,
+        frames_to_advance: Arc<AtomicU32>,
+        speed: Arc<AtomicU32>,
+        frame_notifier: Arc<FrameNotifier>,
+        resynced: Arc<AtomicBool>,
+        video_sinks: Arc<Mutex<VideoSinks>>,
+        warnings: Arc<Mutex<VecDeque<ceres_core::Warning>>>,
     ) {
-        loop {
-            let begin = std::time::Instant::now();
+        // Frames are paced against deadlines so small delays are made up for
+        let mut deadline = std::time::Instant::now();
 
+        loop {
             if exiting.load(Relaxed) {
                 break;
             }
 
-            let duration = std::time::Duration::from_millis(1000 / 60);
+            let paused = pause_thread.load(Relaxed);
+            let advance = paused
+                && frames_to_advance
+                    .fetch_update(Relaxed, Relaxed, |frames| frames.checked_sub(1))
+                    .is_ok();
 
-            if !pause_thread.load(Relaxed) {
+            if !paused || advance {
                 if let Ok(mut gb) = gb.lock() {
-                    gb.run_frame();
+                    crash_report::snapshot(&mut gb);
+                    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        gb.run_frame();
+                    }));
+                    if let Err(payload) = res {
+                        // the hook wrote the report, unlocked first so the
+                        // console isn't poisoned for the UI
+                        drop(gb);
+                        std::panic::resume_unwind(payload);
+                    }
+                    frame_notifier.notify(gb.frame_id());
+
+                    if let Ok(mut sinks) = video_sinks.lock() {
+                        sinks.frame(gb.pixel_data_rgb());
+                    }
+
+                    if let Ok(mut warnings) = warnings.lock() {
+                        for warning in gb.drain_warnings() {
+                            // Programs tend to repeat the same mistake every frame
+                            if warnings.back() == Some(&warning) {
+                                continue;
+                            }
+
+                            if warnings.len() == MAX_WARNINGS {
+                                warnings.pop_front();
+                            }
+                            warnings.push_back(warning);
+                        }
+                    }
                 }
             }
 
-            let elapsed = begin.elapsed();
+            let frame_interval = Speed::frame_interval(speed.load(Relaxed));
+            deadline += frame_interval;
+            let now = std::time::Instant::now();
 
-            if elapsed < duration {
-                spin_sleep::sleep(duration - elapsed);
+            if frame_interval.is_zero() {
+                deadline = now;
+            } else if now > deadline + RESYNC_THRESHOLD {
+                // Catching up would fast-forward through everything missed
+                deadline = now;
+                resynced.store(true, Relaxed);
+            } else if deadline > now {
+                spin_sleep::sleep(deadline - now);
+            } else {
+                // running late, the next frame starts right away
             }
-            // TODO: we're always running late
-            // else {
-            //     eprintln!("running late: {elapsed:?}");
-            // }
         }
 
         // FIXME: clippy says we have to drop
         drop(gb);
         drop(exiting);
         drop(pause_thread);
+        drop(frames_to_advance);
+        drop(speed);
+        drop(frame_notifier);
+        drop(resynced);
+        drop(video_sinks);
+        drop(warnings);
     }
 
     pub fn save_data(&self) {
         if let Ok(gb) = self.scene.gb().lock() {
             if let Some(save_data) = gb.cartridge().save_data() {
-                // FIXME: don't repeat this everywhere
-                let directories = directories::ProjectDirs::from(
-                    crate::QUALIFIER,
-                    crate::ORGANIZATION,
-                    crate::CERES_STYLIZED,
-                )
-                .unwrap();
+                let mut save_data = save_data.to_vec();
+                if let Some(footer) = gb.cartridge().rtc_footer(unix_time()) {
+                    save_data.extend_from_slice(&footer);
+                }
+
+                let save_data = match self.save_codec.encode(&save_data) {
+                    Ok(save_data) => save_data,
+                    Err(e) => {
+                        eprintln!("couldn't encode save data: {e}");
+                        return;
+                    }
+                };
 
-                std::fs::create_dir_all(directories.data_dir())
-                    .expect("couldn't create data directory");
+                let name = format!("{}.sav", self.rom_ident);
+                if let Err(e) = self.save_storage.store(&name, &save_data) {
+                    eprintln!("couldn't save data in save file: {e}");
+                }
+            }
+        }
+    }
+}
 
-                let path = directories
-                    .data_dir()
-                    .join(&self.rom_ident)
-                    .with_extension("sav");
+pub fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
 
-                println!("Saving RAM to {path:?}");
+// A link cable being connected in the background
+struct PendingLink {
+    cancel: Arc<AtomicBool>,
+    // Joined when cancelled so the port can be bound again. Joining holds no
+    // port and may block until the OS gives up connecting, so it's left to
+    // finish on its own.
+    host_thread: Option<std::thread::JoinHandle<()>>,
+}
 
-                let sav_file = std::fs::File::create(path);
-                match sav_file {
-                    Ok(mut f) => {
-                        if let Err(e) = std::io::Write::write_all(&mut f, save_data) {
-                            eprintln!("couldn't save data in save file: {e}");
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("couldn't open save file: {e}");
-                    }
+#[derive(Clone, Debug)]
+pub enum LinkStatus {
+    Unplugged,
+    Waiting(u16),
+    Joining(String),
+    Connected {
+        peer: std::net::SocketAddr,
+        round_trip: std::time::Duration,
+        rom_mismatch: Option<ceres_link::RomMismatch>,
+    },
+    Failed(String),
+}
+
+impl std::fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LinkStatus::Unplugged => write!(f, "Not linked"),
+            LinkStatus::Waiting(port) => write!(f, "Waiting for a player on port {port}"),
+            LinkStatus::Joining(addr) => write!(f, "Joining {addr}"),
+            LinkStatus::Connected {
+                peer,
+                round_trip,
+                rom_mismatch,
+            } => {
+                write!(
+                    f,
+                    "Linked to {peer}, {} ms round trip",
+                    round_trip.as_millis()
+                )?;
+                if let Some(mismatch) = rom_mismatch {
+                    write!(f, ", {mismatch}")?;
                 }
+                Ok(())
             }
+            LinkStatus::Failed(e) => write!(f, "Link failed: {e}"),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct FrameNotifier {
+    frame_id: AtomicU32,
+    waker: AtomicWaker,
+}
+
+impl FrameNotifier {
+    fn notify(&self, frame_id: u32) {
+        if self.frame_id.swap(frame_id, Relaxed) != frame_id {
+            self.waker.wake();
+        }
+    }
+}
+
+pub struct Frames {
+    notifier: Arc<FrameNotifier>,
+    last_id: Option<u32>,
+}
+
+impl Stream for Frames {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<()>> {
+        // Register before checking so a notification can't be missed
+        self.notifier.waker.register(cx.waker());
+
+        let frame_id = self.notifier.frame_id.load(Relaxed);
+        if self.last_id == Some(frame_id) {
+            Poll::Pending
+        } else {
+            self.last_id = Some(frame_id);
+            Poll::Ready(Some(()))
         }
     }
 }
@@ -239,7 +1214,14 @@ impl GbArea {
 impl Drop for GbArea {
     fn drop(&mut self) {
         self.exiting.store(true, Relaxed);
-        self.thread_handle.take().unwrap().join().unwrap();
+        if let Some(thread) = self.thread_handle.take() {
+            if thread.join().is_err() {
+                eprintln!("emulation thread panicked");
+            }
+        }
         self.save_data();
+        if let Err(e) = self.stop_audio_recording() {
+            eprintln!("couldn't save audio recording: {e}");
+        }
     }
 }
@@ -0,0 +1,189 @@
+use crate::gb_area::GbArea;
+use ceres_core::MemoryRegion;
+use iced::widget::{button, column, pick_list, rich_text, row, span, text_input};
+use iced::{Color, Element, Length};
+
+const BYTES_PER_ROW: usize = 16;
+const PAGE_SIZE: usize = BYTES_PER_ROW * 16;
+// Enough for the largest cartridge RAM, banks missing on the running
+// cartridge or model aren't offered
+const MAX_SRAM_BANKS: u8 = 16;
+const CHANGED: Color = Color::from_rgb(0.8, 0.14, 0.11);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region(MemoryRegion);
+
+impl Region {
+    fn all() -> impl Iterator<Item = Self> {
+        (0..8)
+            .map(MemoryRegion::Wram)
+            .chain([MemoryRegion::Hram])
+            .chain((0..MAX_SRAM_BANKS).map(MemoryRegion::Sram))
+            .chain((0..2).map(MemoryRegion::Vram))
+            .map(Self)
+    }
+
+    // Where the CPU sees the bank when it's mapped
+    fn base(self) -> usize {
+        match self.0 {
+            MemoryRegion::Wram(0) => 0xC000,
+            MemoryRegion::Wram(_) => 0xD000,
+            MemoryRegion::Hram => 0xFF80,
+            MemoryRegion::Sram(_) => 0xA000,
+            MemoryRegion::Vram(_) => 0x8000,
+        }
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            MemoryRegion::Wram(bank) => write!(f, "WRAM {bank}"),
+            MemoryRegion::Hram => write!(f, "HRAM"),
+            MemoryRegion::Sram(bank) => write!(f, "SRAM {bank}"),
+            MemoryRegion::Vram(bank) => write!(f, "VRAM {bank}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    RegionChanged(Region),
+    PreviousPage,
+    NextPage,
+    AddressChanged(String),
+    ValueChanged(String),
+    WritePressed,
+}
+
+// A page of one memory region, bytes that changed since the last presented
+// frame are highlighted
+pub struct HexEditor {
+    regions: Vec<Region>,
+    region: Region,
+    page: usize,
+    bytes: Vec<u8>,
+    previous: Vec<u8>,
+    // typed as the CPU sees them, in hex
+    address: String,
+    value: String,
+}
+
+impl HexEditor {
+    pub fn new(gb_area: &GbArea) -> Self {
+        let regions = Region::all()
+            .filter(|region| !gb_area.region_bytes(region.0).is_empty())
+            .collect::<Vec<_>>();
+        let region = Region(MemoryRegion::Wram(0));
+        let bytes = gb_area.region_bytes(region.0);
+
+        Self {
+            regions,
+            region,
+            page: 0,
+            previous: bytes.clone(),
+            bytes,
+            address: String::new(),
+            value: String::new(),
+        }
+    }
+
+    // Call every presented frame
+    pub fn refresh(&mut self, gb_area: &GbArea) {
+        self.previous = std::mem::replace(&mut self.bytes, gb_area.region_bytes(self.region.0));
+    }
+
+    pub fn update(&mut self, message: Message, gb_area: &mut GbArea) {
+        match message {
+            Message::RegionChanged(region) => {
+                self.region = region;
+                self.page = 0;
+                self.bytes = gb_area.region_bytes(region.0);
+                self.previous.clone_from(&self.bytes);
+            }
+            Message::PreviousPage => {
+                self.page = self.page.saturating_sub(1);
+            }
+            Message::NextPage => {
+                if (self.page + 1) * PAGE_SIZE < self.bytes.len() {
+                    self.page += 1;
+                }
+            }
+            Message::AddressChanged(address) => {
+                self.address = address;
+            }
+            Message::ValueChanged(value) => {
+                self.value = value;
+            }
+            Message::WritePressed => {
+                let address = usize::from_str_radix(self.address.trim(), 16);
+                let value = u8::from_str_radix(self.value.trim(), 16);
+
+                let offset = address.ok().and_then(|a| a.checked_sub(self.region.base()));
+                let written = match (offset, value) {
+                    (Some(offset), Ok(value)) => {
+                        gb_area.write_mem_byte(self.region.0, offset, value)
+                    }
+                    _ => false,
+                };
+
+                if written {
+                    self.refresh(gb_area);
+                } else {
+                    eprintln!("{} isn't an address in {}", self.address, self.region);
+                }
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let start = self.page * PAGE_SIZE;
+        let end = (start + PAGE_SIZE).min(self.bytes.len());
+
+        let byte = |i: usize| {
+            let changed = self.previous.get(i) != self.bytes.get(i);
+            span(format!(" {:02X}", self.bytes[i])).color_maybe(changed.then_some(CHANGED))
+        };
+
+        let rows = (start..end).step_by(BYTES_PER_ROW).map(|row_start| {
+            let row_end = (row_start + BYTES_PER_ROW).min(end);
+            let address = span(format!("{:04X} ", self.region.base() + row_start));
+            let spans = std::iter::once(address)
+                .chain((row_start..row_end).map(byte))
+                .collect::<Vec<_>>();
+
+            rich_text(spans).size(12).into()
+        });
+
+        column![
+            row![
+                pick_list(
+                    self.regions.as_slice(),
+                    Some(self.region),
+                    Message::RegionChanged
+                )
+                .padding(5),
+                button("<").on_press(Message::PreviousPage).padding(5),
+                button(">").on_press(Message::NextPage).padding(5),
+            ]
+            .spacing(5),
+            column(rows),
+            row![
+                text_input("address", &self.address)
+                    .on_input(Message::AddressChanged)
+                    .width(Length::Fixed(80.0))
+                    .padding(5),
+                text_input("value", &self.value)
+                    .on_input(Message::ValueChanged)
+                    .on_submit(Message::WritePressed)
+                    .width(Length::Fixed(60.0))
+                    .padding(5),
+                button("Write").on_press(Message::WritePressed).padding(5),
+            ]
+            .spacing(5),
+        ]
+        .spacing(10)
+        .padding(10)
+        .into()
+    }
+}
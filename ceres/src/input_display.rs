@@ -0,0 +1,112 @@
+use ceres_core::Button;
+use iced::widget::{column, container, row, text, Space};
+use iced::{border, Alignment, Color, Element, Length, Theme};
+
+const KEY: f32 = 20.0;
+const FACE_BUTTON: f32 = 28.0;
+
+const BUTTONS: [Button; 8] = [
+    Button::Up,
+    Button::Down,
+    Button::Left,
+    Button::Right,
+    Button::A,
+    Button::B,
+    Button::Select,
+    Button::Start,
+];
+
+// Buttons of the first controller held during the last frame
+#[derive(Clone, Copy, Default)]
+pub struct Held(u8);
+
+impl Held {
+    pub fn from_fn(held: impl Fn(Button) -> bool) -> Self {
+        Self(
+            BUTTONS
+                .into_iter()
+                .filter(|button| held(*button))
+                .fold(0, |mask, button| mask | button as u8),
+        )
+    }
+
+    fn is_held(self, button: Button) -> bool {
+        self.0 & button as u8 != 0
+    }
+}
+
+// A Game Boy pad with the held buttons lit, for speedrun recordings
+pub fn view<'a, Message: 'a>(held: Held) -> Element<'a, Message> {
+    // lit is None for the gaps around the cross
+    let key = |lit: Option<bool>, width: f32, height: f32, radius: f32, label: &'a str| {
+        container(text(label).size(10))
+            .width(width)
+            .height(height)
+            .align_x(Alignment::Center)
+            .align_y(Alignment::Center)
+            .style(move |theme: &Theme| container::Style {
+                background: lit.map(|lit| {
+                    if lit {
+                        theme.palette().primary.into()
+                    } else {
+                        Color::from_rgba(1.0, 1.0, 1.0, 0.25).into()
+                    }
+                }),
+                text_color: Some(Color::WHITE),
+                border: border::rounded(radius),
+                ..container::Style::default()
+            })
+    };
+    let dpad = |lit: Option<bool>| key(lit, KEY, KEY, 2.0, "");
+
+    let cross = column![
+        row![dpad(None), dpad(Some(held.is_held(Button::Up))), dpad(None)],
+        row![
+            dpad(Some(held.is_held(Button::Left))),
+            dpad(Some(false)),
+            dpad(Some(held.is_held(Button::Right)))
+        ],
+        row![
+            dpad(None),
+            dpad(Some(held.is_held(Button::Down))),
+            dpad(None)
+        ],
+    ];
+
+    let face_button = |button: Button, label: &'a str| {
+        key(
+            Some(held.is_held(button)),
+            FACE_BUTTON,
+            FACE_BUTTON,
+            FACE_BUTTON,
+            label,
+        )
+    };
+    let face = row![face_button(Button::B, "B"), face_button(Button::A, "A")].spacing(8);
+
+    let option = |button: Button, label: &'a str| {
+        key(Some(held.is_held(button)), 2.0 * KEY, KEY / 2.0, KEY, label)
+    };
+    let options = row![
+        option(Button::Select, "SELECT"),
+        option(Button::Start, "START")
+    ]
+    .spacing(8);
+
+    let pad = column![
+        row![cross, Space::with_width(24), face].align_y(Alignment::Center),
+        options
+    ]
+    .spacing(10)
+    .align_x(Alignment::Center);
+
+    container(pad)
+        .padding(10)
+        .style(|_: &Theme| container::Style {
+            background: Some(Color::from_rgba(0.0, 0.0, 0.0, 0.6).into()),
+            border: border::rounded(8),
+            ..container::Style::default()
+        })
+        .width(Length::Shrink)
+        .into()
+}
@@ -0,0 +1,172 @@
+use crate::verify::SAMPLE_RATE;
+use crate::Model;
+use ceres_core::{Button, Cart, Gb, NoAudio};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+// Frames to wait for a reaction before giving up on a press
+const TIMEOUT_FRAMES: u32 = 60;
+// Frames between presses, so the screen settles
+const SETTLE_FRAMES: u32 = 10;
+
+#[derive(clap::Args)]
+pub struct LatencyArgs {
+    #[arg(
+        help = "ROM to measure, by default a built-in one that turns the screen black while A is held",
+        required = false
+    )]
+    rom: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help = "Presses to measure, spread over the time between two frames",
+        default_value_t = 20
+    )]
+    presses: u32,
+    #[arg(
+        short,
+        long,
+        help = "Game Boy model to emulate, by default the one the cartridge header asks for",
+        default_value = "auto",
+        value_enum
+    )]
+    model: Model,
+}
+
+// Presses A at known times, paced like the emulation thread, and reports
+// how long it takes for a frame showing it. That's up to the frame being
+// handed to the renderer, the compositor and the display add their own.
+pub fn run(args: &LatencyArgs) -> ExitCode {
+    match measure(args) {
+        Ok(latencies) => {
+            report(&latencies);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("couldn't measure input latency: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn measure(args: &LatencyArgs) -> anyhow::Result<Vec<(Duration, u32)>> {
+    let rom = match &args.rom {
+        Some(path) => std::fs::read(path)?,
+        None => rom(),
+    };
+    let cart = Cart::new(rom.into_boxed_slice())?;
+    let mut gb = Gb::new(args.model.for_cart(&cart), SAMPLE_RATE, cart, NoAudio);
+    let interval = crate::gb_area::FRAME_INTERVAL;
+
+    (0..args.presses)
+        .map(|i| {
+            gb.release(Button::A);
+            for _ in 0..SETTLE_FRAMES {
+                paced_frame(&mut gb, interval);
+            }
+            let before = gb.pixel_data_rgb().to_vec();
+
+            // Input is applied between frames, a key pressed while one is
+            // running waits for it to end
+            let begin = Instant::now();
+            gb.run_frame();
+            let pressed = begin + interval * i / args.presses;
+            spin_sleep::sleep(pressed.saturating_duration_since(Instant::now()));
+            gb.press(Button::A);
+            spin_sleep::sleep((begin + interval).saturating_duration_since(Instant::now()));
+
+            for frames in 1..=TIMEOUT_FRAMES {
+                let frame_start = Instant::now();
+                gb.run_frame();
+                if gb.pixel_data_rgb() != before {
+                    let latency = pressed.elapsed();
+                    spin_sleep::sleep(
+                        (frame_start + interval).saturating_duration_since(Instant::now()),
+                    );
+                    return Ok((latency, frames));
+                }
+                spin_sleep::sleep(
+                    (frame_start + interval).saturating_duration_since(Instant::now()),
+                );
+            }
+
+            anyhow::bail!("the screen didn't change within {TIMEOUT_FRAMES} frames of pressing A")
+        })
+        .collect()
+}
+
+fn paced_frame(gb: &mut Gb<NoAudio>, interval: Duration) {
+    let begin = Instant::now();
+    gb.run_frame();
+    spin_sleep::sleep((begin + interval).saturating_duration_since(Instant::now()));
+}
+
+fn report(latencies: &[(Duration, u32)]) {
+    let Some(min) = latencies.iter().map(|&(latency, _)| latency).min() else {
+        return;
+    };
+    let max = latencies
+        .iter()
+        .map(|&(latency, _)| latency)
+        .max()
+        .unwrap_or(min);
+    let mean = latencies
+        .iter()
+        .map(|&(latency, _)| latency)
+        .sum::<Duration>()
+        / u32::try_from(latencies.len()).unwrap_or(u32::MAX);
+    let min_frames = latencies
+        .iter()
+        .map(|&(_, frames)| frames)
+        .min()
+        .unwrap_or(0);
+    let max_frames = latencies
+        .iter()
+        .map(|&(_, frames)| frames)
+        .max()
+        .unwrap_or(0);
+
+    println!(
+        "{} {}: {} presses",
+        crate::CERES_STYLIZED,
+        env!("CARGO_PKG_VERSION"),
+        latencies.len()
+    );
+    println!(
+        "latency min {:.1} ms, mean {:.1} ms, max {:.1} ms",
+        min.as_secs_f64() * 1000.0,
+        mean.as_secs_f64() * 1000.0,
+        max.as_secs_f64() * 1000.0
+    );
+    println!("{min_frames} to {max_frames} frames after the press");
+}
+
+// Polls the buttons in a loop and sets BGP to black while A is held,
+// white otherwise, so the very next frame drawn shows the press
+fn rom() -> Vec<u8> {
+    const TITLE: &[u8] = b"LATENCY";
+    const PROGRAM: [u8; 24] = [
+        0x3E, 0x91, // ld a,$91
+        0xE0, 0x40, // ldh (LCDC),a
+        // .loop
+        0x3E, 0x10, // ld a,$10
+        0xE0, 0x00, // ldh (P1),a
+        0xF0, 0x00, // ldh a,(P1)
+        0xF0, 0x00, // ldh a,(P1)
+        0xE6, 0x01, // and $01
+        0x3E, 0x00, // ld a,$00
+        0x20, 0x02, // jr nz,.set
+        0x3E, 0xFF, // ld a,$FF
+        // .set
+        0xE0, 0x47, // ldh (BGP),a
+        0x18, 0xEC, // jr .loop
+    ];
+
+    let mut rom = vec![0; 0x8000];
+    // nop, jp $0150
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    rom[0x134..0x134 + TITLE.len()].copy_from_slice(TITLE);
+    rom[0x150..0x150 + PROGRAM.len()].copy_from_slice(&PROGRAM);
+    rom
+}
@@ -0,0 +1,103 @@
+use crate::verify::SAMPLE_RATE;
+use crate::{Model, PX_HEIGHT, PX_WIDTH};
+use ceres_core::{Cart, Gb, Layer, NoAudio};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const LAYERS: [Layer; 3] = [Layer::Bg, Layer::Window, Layer::Obj];
+
+#[derive(clap::Args)]
+pub struct LayersArgs {
+    #[arg(help = "ROM to run")]
+    rom: PathBuf,
+    #[arg(
+        short = 'f',
+        long,
+        help = "Frame to save, counting from 1, without input or save data",
+        default_value_t = 60 * 10
+    )]
+    frames: u32,
+    #[arg(
+        short,
+        long,
+        help = "Game Boy model to emulate, by default the one the cartridge header asks for",
+        default_value = "auto",
+        value_enum
+    )]
+    model: Model,
+    #[arg(
+        short,
+        long,
+        help = "Directory for the PNGs, named after the ROM",
+        default_value = "."
+    )]
+    out: PathBuf,
+}
+
+// Saves the composited frame and one PNG per layer, for documentation and
+// for telling which layer a glitch is in
+pub fn run(args: &LayersArgs) -> ExitCode {
+    match save_layers(args) {
+        Ok(paths) => {
+            for path in paths {
+                println!("saved {}", path.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("couldn't save the layers of {}: {e}", args.rom.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn save_layers(args: &LayersArgs) -> anyhow::Result<Vec<PathBuf>> {
+    let cart = Cart::new(std::fs::read(&args.rom)?.into_boxed_slice())?;
+    let mut gb = Gb::new(args.model.for_cart(&cart), SAMPLE_RATE, cart, NoAudio);
+    for _ in 1..args.frames {
+        gb.run_frame();
+    }
+
+    // Every layer is drawn from the same state, hiding the others
+    let before = gb.save_state(0);
+    let stem = args
+        .rom
+        .file_stem()
+        .map_or_else(|| "frame".into(), |stem| stem.to_string_lossy());
+    std::fs::create_dir_all(&args.out)?;
+
+    let shown: [(&str, &[Layer]); 4] = [
+        ("composite", &LAYERS),
+        ("bg", &[Layer::Bg]),
+        ("window", &[Layer::Window]),
+        ("obj", &[Layer::Obj]),
+    ];
+
+    shown
+        .into_iter()
+        .map(|(name, shown)| {
+            gb.load_state(&before)?;
+            for layer in LAYERS {
+                gb.set_layer_enabled(layer, shown.contains(&layer));
+            }
+            gb.run_frame();
+
+            let path = args.out.join(format!("{stem}-{name}.png"));
+            save_png(&path, gb.pixel_data_rgb())?;
+            Ok(path)
+        })
+        .collect()
+}
+
+pub fn save_png(path: &Path, rgb: &[u8]) -> anyhow::Result<()> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, PX_WIDTH, PX_HEIGHT);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgb)?;
+    writer.finish()?;
+
+    Ok(())
+}
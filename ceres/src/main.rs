@@ -1,6 +1,21 @@
 mod app;
+mod bindings;
+mod console;
+mod crash_report;
 mod gb_area;
+mod hex_editor;
+mod input_display;
+mod latency;
+mod layers;
+mod music_view;
+mod sandbox;
+mod save_file;
+mod save_storage;
 mod scene;
+mod screensaver;
+mod verify;
+mod video_sink;
+mod visualizer;
 
 const SCREEN_MUL: u32 = 1;
 const PX_WIDTH: u32 = ceres_core::PX_WIDTH as u32;
@@ -13,38 +28,69 @@ const ORGANIZATION: &str = "remind-me-later";
 const CERES_BIN: &str = "ceres";
 const CERES_STYLIZED: &str = "Ceres";
 const ABOUT: &str = "A (very experimental) Game Boy/Color emulator.";
-const AFTER_HELP: &str = "GB bindings:
-
-    | Gameboy | Emulator  |
-    | ------- | --------- |
-    | Dpad    | WASD      |
-    | A       | K         |
-    | B       | L         |
-    | Start   | M         |
-    | Select  | N         |
-
-Other binsings:
-
-    | System       | Emulator |
-    | ------------ | -------- |
-    | Fullscreen   | F        |
-    | Scale filter | Z        |
-";
-
-#[derive(Default, Clone, Copy, clap::ValueEnum)]
-enum Model {
+const AFTER_HELP: &str = "Press F1 in the emulator window to show the key bindings.";
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Model {
+    // Picked from the cartridge header
+    #[default]
+    Auto,
+    Dmg0,
     Dmg,
     Mgb,
-    #[default]
+    Sgb,
+    Sgb2,
+    Cgb0,
     Cgb,
+    CgbE,
+    Agb,
 }
 
-impl From<Model> for ceres_core::Model {
-    fn from(model: Model) -> ceres_core::Model {
-        match model {
+impl Model {
+    pub const ALL: [Model; 10] = [
+        Model::Auto,
+        Model::Dmg0,
+        Model::Dmg,
+        Model::Mgb,
+        Model::Sgb,
+        Model::Sgb2,
+        Model::Cgb0,
+        Model::Cgb,
+        Model::CgbE,
+        Model::Agb,
+    ];
+
+    // Monochrome games boot on a DMG or SGB so they get their palettes
+    #[must_use]
+    pub fn for_cart(self, cart: &ceres_core::Cart) -> ceres_core::Model {
+        match self {
+            Model::Auto => ceres_core::Model::from_cart(cart),
+            Model::Dmg0 => ceres_core::Model::Dmg0,
             Model::Dmg => ceres_core::Model::Dmg,
             Model::Mgb => ceres_core::Model::Mgb,
+            Model::Sgb => ceres_core::Model::Sgb,
+            Model::Sgb2 => ceres_core::Model::Sgb2,
+            Model::Cgb0 => ceres_core::Model::Cgb0,
             Model::Cgb => ceres_core::Model::Cgb,
+            Model::CgbE => ceres_core::Model::CgbE,
+            Model::Agb => ceres_core::Model::Agb,
+        }
+    }
+}
+
+impl std::fmt::Display for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Model::Auto => write!(f, "Automatic"),
+            Model::Dmg0 => write!(f, "Game Boy (first revision)"),
+            Model::Dmg => write!(f, "Game Boy"),
+            Model::Mgb => write!(f, "Game Boy Pocket"),
+            Model::Sgb => write!(f, "Super Game Boy"),
+            Model::Sgb2 => write!(f, "Super Game Boy 2"),
+            Model::Cgb0 => write!(f, "Game Boy Color (first revision)"),
+            Model::Cgb => write!(f, "Game Boy Color"),
+            Model::CgbE => write!(f, "Game Boy Color (revision E)"),
+            Model::Agb => write!(f, "Game Boy Advance"),
         }
     }
 }
@@ -80,9 +126,166 @@ impl std::fmt::Display for Scaling {
     }
 }
 
+// Accessibility filters, applied after scaling in the fragment stage
+#[derive(Default, Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ColorFilter {
+    #[default]
+    None = 0,
+    Protanopia = 1,
+    Deuteranopia = 2,
+    Tritanopia = 3,
+    HighContrast = 4,
+}
+
+impl ColorFilter {
+    pub const ALL: [ColorFilter; 5] = [
+        ColorFilter::None,
+        ColorFilter::Protanopia,
+        ColorFilter::Deuteranopia,
+        ColorFilter::Tritanopia,
+        ColorFilter::HighContrast,
+    ];
+}
+
+impl std::fmt::Display for ColorFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorFilter::None => write!(f, "None"),
+            ColorFilter::Protanopia => write!(f, "Protanopia"),
+            ColorFilter::Deuteranopia => write!(f, "Deuteranopia"),
+            ColorFilter::Tritanopia => write!(f, "Tritanopia"),
+            ColorFilter::HighContrast => write!(f, "High contrast"),
+        }
+    }
+}
+
+// Applied by the core as pixels are drawn, before the color filter
+#[derive(Default, Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFilter {
+    #[default]
+    None,
+    Greyscale,
+    Sepia,
+    // GBA screen colors for any model
+    Agb,
+    // the file loaded with --lut or from the menu
+    Lut,
+}
+
+impl OutputFilter {
+    pub const ALL: [OutputFilter; 5] = [
+        OutputFilter::None,
+        OutputFilter::Greyscale,
+        OutputFilter::Sepia,
+        OutputFilter::Agb,
+        OutputFilter::Lut,
+    ];
+}
+
+impl std::fmt::Display for OutputFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFilter::None => write!(f, "None"),
+            OutputFilter::Greyscale => write!(f, "Greyscale"),
+            OutputFilter::Sepia => write!(f, "Sepia"),
+            OutputFilter::Agb => write!(f, "GBA colors"),
+            OutputFilter::Lut => write!(f, "Custom LUT"),
+        }
+    }
+}
+
+// Colors of the four shades on DMG models
+#[derive(Default, Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum DmgPalette {
+    #[default]
+    Grayscale,
+    Green,
+    Pocket,
+    HighContrast,
+}
+
+impl DmgPalette {
+    pub const ALL: [DmgPalette; 4] = [
+        DmgPalette::Grayscale,
+        DmgPalette::Green,
+        DmgPalette::Pocket,
+        DmgPalette::HighContrast,
+    ];
+
+    #[must_use]
+    pub fn colors(self) -> [(u8, u8, u8); 4] {
+        match self {
+            DmgPalette::Grayscale => ceres_core::DmgPalette::Grayscale,
+            DmgPalette::Green => ceres_core::DmgPalette::Green,
+            DmgPalette::Pocket => ceres_core::DmgPalette::Pocket,
+            DmgPalette::HighContrast => ceres_core::DmgPalette::HighContrast,
+        }
+        .colors()
+    }
+}
+
+impl std::fmt::Display for DmgPalette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DmgPalette::Grayscale => write!(f, "Grayscale"),
+            DmgPalette::Green => write!(f, "Classic green"),
+            DmgPalette::Pocket => write!(f, "Pocket"),
+            DmgPalette::HighContrast => write!(f, "High contrast"),
+        }
+    }
+}
+
+// Clockwise rotation of the screen, applied in the vertex stage
+#[derive(Default, Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Rotation {
+    #[default]
+    #[value(name = "0")]
+    Rotate0 = 0,
+    #[value(name = "90")]
+    Rotate90 = 1,
+    #[value(name = "180")]
+    Rotate180 = 2,
+    #[value(name = "270")]
+    Rotate270 = 3,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::Rotate0,
+        Rotation::Rotate90,
+        Rotation::Rotate180,
+        Rotation::Rotate270,
+    ];
+
+    // Width and height of the screen are swapped
+    #[must_use]
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Rotation::Rotate90 | Rotation::Rotate270)
+    }
+}
+
+impl std::fmt::Display for Rotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rotation::Rotate0 => write!(f, "0\u{b0}"),
+            Rotation::Rotate90 => write!(f, "90\u{b0}"),
+            Rotation::Rotate180 => write!(f, "180\u{b0}"),
+            Rotation::Rotate270 => write!(f, "270\u{b0}"),
+        }
+    }
+}
+
 #[derive(clap::Parser)]
-#[command(name = CERES_BIN, about = ABOUT, after_help = AFTER_HELP)]
+#[command(
+    name = CERES_BIN,
+    about = ABOUT,
+    after_help = AFTER_HELP,
+    args_conflicts_with_subcommands = true
+)]
+#[allow(clippy::struct_excessive_bools)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(
         help = "Game Boy/Color ROM file to emulate.",
         long_help = "Game Boy/Color ROM file to emulate. Extension doesn't matter, the \
@@ -94,8 +297,8 @@ struct Cli {
     #[arg(
         short,
         long,
-        help = "Game Boy model to emulate",
-        default_value = "cgb",
+        help = "Game Boy model to emulate, by default the one the cartridge header asks for",
+        default_value = "auto",
         value_enum,
         required = false
     )]
@@ -109,12 +312,147 @@ struct Cli {
         required = false
     )]
     scaling: Scaling,
+    #[arg(
+        short,
+        long,
+        help = "Clockwise screen rotation in degrees",
+        default_value = "0",
+        value_enum,
+        required = false
+    )]
+    rotation: Rotation,
+    #[arg(long, help = "Mirror the screen horizontally", required = false)]
+    mirror: bool,
+    #[arg(
+        short,
+        long,
+        help = "Color filter for color vision deficiencies",
+        default_value = "none",
+        value_enum,
+        required = false
+    )]
+    color_filter: ColorFilter,
+    #[arg(
+        long,
+        help = "Greyscale, sepia or custom LUT output",
+        default_value = "none",
+        value_enum,
+        required = false
+    )]
+    output_filter: OutputFilter,
+    #[arg(
+        long,
+        help = "Colors of the four shades when emulating a DMG, MGB or SGB",
+        default_value = "grayscale",
+        value_enum,
+        required = false
+    )]
+    dmg_palette: DmgPalette,
+    #[arg(
+        long,
+        help = "LUT file for the lut output filter",
+        long_help = "LUT file for the lut output filter. It holds the RGB output for \
+           every color with its channels cut to 5 bits, 3 bytes each, in the order \
+           red * 1024 + green * 32 + blue (98304 bytes in total).",
+        required = false
+    )]
+    lut: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        help = "Bootrom to start with instead of the bundled one",
+        long_help = "Bootrom to start with instead of the bundled one, like a dump of \
+           the original. DMG, MGB and SGB bootroms are 256 bytes long, CGB and AGB \
+           ones 2304. The bundled one is used for models it doesn't fit.",
+        required = false
+    )]
+    bootrom: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        help = "Plug a Barcode Boy into the link port, cards are swiped from the menu",
+        required = false
+    )]
+    barcode_boy: bool,
+    #[arg(
+        long,
+        help = "Plug a debug console into the link port, bytes sent are printed as text",
+        long_help = "Plug a debug console into the link port. Every byte a game sends \
+           is UTF-8 text, shown under the game and echoed to stdout with escape \
+           sequences intact, a printf for homebrew.",
+        conflicts_with = "barcode_boy",
+        required = false
+    )]
+    debug_console: bool,
+    #[arg(
+        long,
+        help = "Run the emulation and audio threads at normal priority",
+        long_help = "Run the emulation and audio threads at normal priority. By default \
+           audio runs with realtime priority where the OS permits it and emulation \
+           above normal, which avoids crackling under load but may be unwanted on \
+           shared machines.",
+        required = false
+    )]
+    no_thread_priority: bool,
+    #[arg(
+        long,
+        help = "How save files are written",
+        long_help = "How save files are written: raw cartridge RAM as other emulators \
+           expect it, behind a checksum that is verified when loading, or encrypted \
+           with the key from --save-key-file. Any of them is read back regardless.",
+        default_value = "raw",
+        value_enum,
+        required = false
+    )]
+    save_format: save_file::SaveFormat,
+    #[arg(
+        long,
+        help = "File holding the passphrase for encrypted save files",
+        required = false
+    )]
+    save_key_file: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        help = "Directory for save files, like a folder synced to the cloud",
+        required = false
+    )]
+    save_dir: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        help = "Keep save files in memory, nothing is written to disk",
+        long_help = "Keep save files in memory, nothing is written to disk and \
+           progress is lost on exit. For sandboxes without a writable file \
+           system, demos and kiosks.",
+        conflicts_with = "save_dir",
+        required = false
+    )]
+    no_save_files: bool,
 }
 
-pub fn main() -> iced::Result {
+#[derive(clap::Subcommand)]
+enum Command {
+    #[command(about = "Run a ROM headless and compare its screen to a screenshot")]
+    Verify(verify::VerifyArgs),
+    #[command(about = "Run a ROM headless and save a frame split into its layers as PNGs")]
+    Layers(layers::LayersArgs),
+    #[command(about = "Measure how long a button press takes to show on screen")]
+    Latency(latency::LatencyArgs),
+}
+
+pub fn main() -> std::process::ExitCode {
     let args = <crate::Cli as clap::Parser>::parse();
 
-    iced::application(app::App::title, app::App::update, app::App::view)
+    match &args.command {
+        Some(Command::Verify(verify)) => return verify::run(verify),
+        Some(Command::Layers(layers)) => return layers::run(layers),
+        Some(Command::Latency(latency)) => return latency::run(latency),
+        None => {}
+    }
+
+    // nothing goes to disk without save files, panics are still printed
+    if !args.no_save_files {
+        crash_report::install_hook(gb_area::GbArea::data_dir().join("crashes"));
+    }
+
+    let res = iced::application(app::App::title, app::App::update, app::App::view)
         .subscription(app::App::subscription)
         .default_font(iced::Font {
             family: iced::font::Family::Monospace,
@@ -128,5 +466,13 @@ pub fn main() -> iced::Result {
         .scale_factor(|_| 0.8)
         .theme(app::App::theme)
         .exit_on_close_request(true)
-        .run_with(move || (app::App::new(&args).unwrap(), iced::Task::none()))
+        .run_with(move || (app::App::new(&args).unwrap(), iced::Task::none()));
+
+    match res {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::ExitCode::FAILURE
+        }
+    }
 }
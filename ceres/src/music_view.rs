@@ -0,0 +1,105 @@
+use ceres_core::{ApuChannel, ApuWrite, NoteEvent, NoteTracker};
+use iced::widget::{checkbox, column, container, row, Space};
+use iced::{Color, Element, Length, Theme};
+use std::collections::VecDeque;
+
+// Two seconds
+const FRAMES: u64 = 120;
+const SLOT_WIDTH: f32 = 4.0;
+const LANE_HEIGHT: f32 = 40.0;
+const NOTE_HEIGHT: f32 = 3.0;
+// MIDI notes from C1 to C8
+const LOWEST_NOTE: f32 = 24.0;
+const HIGHEST_NOTE: f32 = 108.0;
+const CHANNELS: [(ApuChannel, &str); 4] = [
+    (ApuChannel::Pulse1, "Pulse 1"),
+    (ApuChannel::Pulse2, "Pulse 2"),
+    (ApuChannel::Wave, "Wave"),
+    (ApuChannel::Noise, "Noise"),
+];
+
+// Piano roll of the notes triggered in the last two seconds, pitch going up
+// and time to the right
+#[derive(Default)]
+pub struct MusicView {
+    tracker: NoteTracker,
+    notes: VecDeque<NoteEvent>,
+    frame: u64,
+}
+
+impl MusicView {
+    // frame is the one running now, writes are oldest first
+    pub fn push(&mut self, writes: Vec<ApuWrite>, frame: u64) {
+        self.notes.extend(
+            writes
+                .into_iter()
+                .filter_map(|write| self.tracker.push(write)),
+        );
+        self.frame = frame;
+
+        while self
+            .notes
+            .front()
+            .is_some_and(|note| note.frame + FRAMES <= self.frame)
+        {
+            self.notes.pop_front();
+        }
+    }
+
+    // Each lane has a checkbox to hear its channel or not
+    pub fn view<'a, Message: 'a>(
+        &self,
+        enabled: impl Fn(ApuChannel) -> bool,
+        on_toggle: impl Fn(ApuChannel, bool) -> Message + Copy + 'a,
+    ) -> Element<'a, Message> {
+        let first = (self.frame + 1).saturating_sub(FRAMES);
+
+        let lanes = CHANNELS.iter().map(|&(channel, name)| {
+            let slots = (first..=self.frame).map(|frame| {
+                self.notes
+                    .iter()
+                    .rev()
+                    .find(|note| note.channel == channel && note.frame == frame)
+                    .map_or_else(
+                        || Space::new(SLOT_WIDTH, LANE_HEIGHT).into(),
+                        |note| Self::note(note),
+                    )
+            });
+
+            let toggle = checkbox(name, enabled(channel))
+                .on_toggle(move |enabled| on_toggle(channel, enabled))
+                .text_size(12)
+                .width(80);
+
+            row![toggle, row(slots)].into()
+        });
+
+        column(lanes).spacing(2).padding(5).into()
+    }
+
+    fn note<'a, Message: 'a>(note: &NoteEvent) -> Element<'a, Message> {
+        let midi = 69.0 + 12.0 * (note.frequency() / 440.0).log2();
+        let height = ((midi - LOWEST_NOTE) / (HIGHEST_NOTE - LOWEST_NOTE)).clamp(0.0, 1.0);
+        let alpha = 0.25 + 0.75 * f32::from(note.volume) / 15.0;
+
+        let block = container(Space::new(SLOT_WIDTH, NOTE_HEIGHT)).style(move |theme: &Theme| {
+            container::Style {
+                background: Some(
+                    Color {
+                        a: alpha,
+                        ..theme.palette().primary
+                    }
+                    .into(),
+                ),
+                ..container::Style::default()
+            }
+        });
+
+        column![
+            Space::new(SLOT_WIDTH, (1.0 - height) * (LANE_HEIGHT - NOTE_HEIGHT)),
+            block
+        ]
+        .height(Length::Fixed(LANE_HEIGHT))
+        .into()
+    }
+}
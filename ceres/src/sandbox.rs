@@ -0,0 +1,21 @@
+use std::path::{Path, PathBuf};
+
+// Flatpak and Snap only let the app see its own data directory and what the
+// user picks through the XDG desktop portal. rfd already goes through the
+// portal for its dialogs.
+pub fn is_sandboxed() -> bool {
+    std::env::var_os("FLATPAK_ID").is_some()
+        || std::env::var_os("SNAP").is_some()
+        || Path::new("/.flatpak-info").exists()
+}
+
+// Where to write files the user wants to find afterwards, like recordings.
+// The data directory is tucked away inside the sandbox, so there a folder is
+// picked through the portal instead. None if the dialog was cancelled.
+pub fn output_dir(title: &str, default: PathBuf) -> Option<PathBuf> {
+    if is_sandboxed() {
+        rfd::FileDialog::new().set_title(title).pick_folder()
+    } else {
+        Some(default)
+    }
+}
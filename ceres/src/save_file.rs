@@ -0,0 +1,156 @@
+use chacha20poly1305::aead::{Aead, Generate, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+// Files without the header are raw cartridge RAM, as written by older
+// versions and most other emulators, and load as they are
+const MAGIC: &[u8; 8] = b"CERESSAV";
+const VERSION: u8 = 1;
+const KIND_CHECKSUM: u8 = 0;
+const KIND_ENCRYPTED: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+const CRC_LEN: usize = 4;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SaveFormat {
+    // Plain RAM, readable by other emulators
+    #[default]
+    Raw,
+    // Plain RAM behind a CRC32 that is checked when loading
+    Checksum,
+    // XChaCha20-Poly1305 with a key derived from --save-key-file
+    Encrypted,
+}
+
+// How save files are written, reading accepts every format as long as the
+// key is there for encrypted ones
+pub struct SaveCodec {
+    format: SaveFormat,
+    passphrase: Option<Vec<u8>>,
+    // deriving is slow on purpose, so keep the key for the salt new files use
+    key: Option<([u8; SALT_LEN], Key)>,
+}
+
+impl SaveCodec {
+    pub fn new(format: SaveFormat, passphrase: Option<Vec<u8>>) -> anyhow::Result<Self> {
+        let key = match (format, &passphrase) {
+            (SaveFormat::Encrypted, Some(passphrase)) => {
+                let salt = <[u8; SALT_LEN]>::try_generate()
+                    .map_err(|e| anyhow::anyhow!("couldn't generate a salt: {e}"))?;
+                Some((salt, derive_key(passphrase, &salt)?))
+            }
+            (SaveFormat::Encrypted, None) => {
+                anyhow::bail!("encrypted saves need a key, pass one with --save-key-file")
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            format,
+            passphrase,
+            key,
+        })
+    }
+
+    pub fn encode(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(HEADER_LEN + SALT_LEN + NONCE_LEN + data.len() + TAG_LEN);
+
+        match (self.format, &self.key) {
+            (SaveFormat::Raw, _) => out.extend_from_slice(data),
+            (SaveFormat::Checksum, _) => {
+                out.extend_from_slice(MAGIC);
+                out.extend_from_slice(&[VERSION, KIND_CHECKSUM]);
+                out.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+                out.extend_from_slice(data);
+            }
+            (SaveFormat::Encrypted, Some((salt, key))) => {
+                let nonce = XNonce::try_generate()
+                    .map_err(|e| anyhow::anyhow!("couldn't generate a nonce: {e}"))?;
+                let ciphertext = XChaCha20Poly1305::new(key)
+                    .encrypt(&nonce, data)
+                    .map_err(|e| anyhow::anyhow!("couldn't encrypt save data: {e}"))?;
+
+                out.extend_from_slice(MAGIC);
+                out.extend_from_slice(&[VERSION, KIND_ENCRYPTED]);
+                out.extend_from_slice(salt);
+                out.extend_from_slice(&nonce);
+                out.extend_from_slice(&ciphertext);
+            }
+            (SaveFormat::Encrypted, None) => unreachable!("checked in SaveCodec::new"),
+        }
+
+        Ok(out)
+    }
+
+    pub fn decode(&self, file: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let Some(body) = file.strip_prefix(MAGIC) else {
+            return Ok(file.to_vec());
+        };
+
+        let [version, kind, body @ ..] = body else {
+            anyhow::bail!("save file is truncated");
+        };
+
+        if *version != VERSION {
+            anyhow::bail!("save file version {version} isn't supported");
+        }
+
+        match *kind {
+            KIND_CHECKSUM => {
+                let Some((crc, data)) = body.split_first_chunk::<CRC_LEN>() else {
+                    anyhow::bail!("save file is truncated");
+                };
+
+                let expected = u32::from_le_bytes(*crc);
+                let found = crc32fast::hash(data);
+                if expected != found {
+                    anyhow::bail!(
+                        "save file is corrupted, checksum is {found:08x} instead of {expected:08x}"
+                    );
+                }
+
+                Ok(data.to_vec())
+            }
+            KIND_ENCRYPTED => {
+                let Some(passphrase) = &self.passphrase else {
+                    anyhow::bail!("save file is encrypted, pass its key with --save-key-file");
+                };
+
+                if body.len() < SALT_LEN + NONCE_LEN + TAG_LEN {
+                    anyhow::bail!("save file is truncated");
+                }
+                let (salt, body) = body.split_at(SALT_LEN);
+                let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+
+                let derived;
+                let key = match &self.key {
+                    Some((own_salt, key)) if own_salt.as_slice() == salt => key,
+                    _ => {
+                        derived = derive_key(passphrase, salt)?;
+                        &derived
+                    }
+                };
+
+                let nonce = XNonce::try_from(nonce)
+                    .map_err(|_err| anyhow::anyhow!("save file is truncated"))?;
+                XChaCha20Poly1305::new(key)
+                    .decrypt(&nonce, ciphertext)
+                    .map_err(|_err| {
+                        anyhow::anyhow!("save file is corrupted or was encrypted with another key")
+                    })
+            }
+            _ => anyhow::bail!("save file has an unknown format"),
+        }
+    }
+}
+
+fn derive_key(passphrase: &[u8], salt: &[u8]) -> anyhow::Result<Key> {
+    let mut key = Key::default();
+    argon2::Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("couldn't derive the save key: {e}"))?;
+
+    Ok(key)
+}
@@ -0,0 +1,73 @@
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+
+// Where save files live, implement it to keep them somewhere other than a
+// local directory. Names are plain file names like "TETRIS-1-10-4660.sav".
+pub trait SaveStorage {
+    // None if nothing was stored under the name yet
+    fn load(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>>;
+    fn store(&self, name: &str, data: &[u8]) -> anyhow::Result<()>;
+    // Where the name is stored, for messages
+    fn locate(&self, name: &str) -> String;
+}
+
+// A directory on disk, the data directory by default or a folder synced by
+// a cloud client
+pub struct LocalDir {
+    dir: PathBuf,
+}
+
+impl LocalDir {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl SaveStorage for LocalDir {
+    fn load(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match std::fs::read(self.dir.join(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store(&self, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        // Sync clients would upload a half written file, so replace it whole
+        let path = self.dir.join(name);
+        let tmp = self.dir.join(format!(".{name}.tmp"));
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(tmp, path)?;
+
+        Ok(())
+    }
+
+    fn locate(&self, name: &str) -> String {
+        self.dir.join(name).display().to_string()
+    }
+}
+
+// Nothing touches the disk and everything is gone on exit, for sandboxes
+// without a writable file system, demos and kiosks
+#[derive(Default)]
+pub struct Memory {
+    files: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl SaveStorage for Memory {
+    fn load(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let files = self.files.lock().map_err(|e| anyhow::anyhow!("{e}"))?;
+        Ok(files.get(name).cloned())
+    }
+
+    fn store(&self, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        let mut files = self.files.lock().map_err(|e| anyhow::anyhow!("{e}"))?;
+        files.insert(name.to_owned(), data.to_vec());
+        Ok(())
+    }
+
+    fn locate(&self, name: &str) -> String {
+        format!("memory ({name})")
+    }
+}
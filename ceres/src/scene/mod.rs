@@ -1,18 +1,28 @@
 mod pipeline;
 mod texture;
 
-use std::sync::{atomic::AtomicBool, Arc, Mutex};
+use std::sync::{Arc, Mutex};
 
 use ceres_core::{Button, Gb};
 use iced::{event, keyboard::Key, mouse, widget::shader, Rectangle};
 use pipeline::Pipeline;
 
-use crate::{Scaling, PX_HEIGHT, PX_WIDTH};
+use crate::{ColorFilter, Rotation, Scaling, PX_HEIGHT, PX_WIDTH};
+
+fn key_button(key: &Key) -> Option<(usize, Button)> {
+    if let Key::Character(c) = key {
+        crate::bindings::button(c.as_ref())
+    } else {
+        None
+    }
+}
 
 pub struct Scene {
     gb: Arc<Mutex<Gb<ceres_audio::RingBuffer>>>,
     scaling: Scaling,
-    pause_thread: Arc<AtomicBool>,
+    rotation: Rotation,
+    mirror: bool,
+    color_filter: ColorFilter,
 }
 
 impl Scene {
@@ -20,7 +30,9 @@ impl Scene {
         Self {
             gb,
             scaling,
-            pause_thread: Arc::new(AtomicBool::new(false)),
+            rotation: Rotation::default(),
+            mirror: false,
+            color_filter: ColorFilter::default(),
         }
     }
 
@@ -32,6 +44,30 @@ impl Scene {
         self.scaling
     }
 
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    pub fn set_mirror(&mut self, mirror: bool) {
+        self.mirror = mirror;
+    }
+
+    pub fn mirror(&self) -> bool {
+        self.mirror
+    }
+
+    pub fn set_color_filter(&mut self, color_filter: ColorFilter) {
+        self.color_filter = color_filter;
+    }
+
+    pub fn color_filter(&self) -> ColorFilter {
+        self.color_filter
+    }
+
     pub fn replace_gb(&mut self, gb: Gb<ceres_audio::RingBuffer>) {
         *self.gb.lock().unwrap() = gb;
     }
@@ -53,7 +89,13 @@ impl<Message> shader::Program<Message> for Scene {
     ) -> Self::Primitive {
         let gb = self.gb.lock().unwrap();
 
-        Primitive::new(&gb, self.scaling)
+        Primitive::new(
+            &gb,
+            self.scaling,
+            self.rotation,
+            self.mirror,
+            self.color_filter,
+        )
     }
 
     fn update(
@@ -69,50 +111,16 @@ impl<Message> shader::Program<Message> for Scene {
                 iced::keyboard::Event::KeyPressed { key, .. } => {
                     let mut gb = self.gb.lock().unwrap();
 
-                    match key {
-                        Key::Character(c) => {
-                            // gb.press(Button::Up);
-                            match c.as_ref() {
-                                "w" => gb.press(Button::Up),
-                                "a" => gb.press(Button::Left),
-                                "s" => gb.press(Button::Down),
-                                "d" => gb.press(Button::Right),
-                                "l" => gb.press(Button::A),
-                                "k" => gb.press(Button::B),
-                                "n" => gb.press(Button::Select),
-                                "m" => gb.press(Button::Start),
-                                _ => return (event::Status::Ignored, None),
-                            }
-
-                            return (event::Status::Captured, None);
-                        }
-                        Key::Named(iced::keyboard::key::Named::Space) => {
-                            self.pause_thread.store(
-                                !self.pause_thread.load(std::sync::atomic::Ordering::Relaxed),
-                                std::sync::atomic::Ordering::Relaxed,
-                            );
-                            return (event::Status::Captured, None);
-                        }
-                        _ => {}
+                    if let Some((player, button)) = key_button(&key) {
+                        gb.press_player(player, button);
+                        return (event::Status::Captured, None);
                     }
                 }
                 iced::keyboard::Event::KeyReleased { key, .. } => {
                     let mut gb = self.gb.lock().unwrap();
 
-                    if let Key::Character(c) = key {
-                        // gb.press(Button::Up);
-                        match c.as_ref() {
-                            "w" => gb.release(Button::Up),
-                            "a" => gb.release(Button::Left),
-                            "s" => gb.release(Button::Down),
-                            "d" => gb.release(Button::Right),
-                            "l" => gb.release(Button::A),
-                            "k" => gb.release(Button::B),
-                            "n" => gb.release(Button::Select),
-                            "m" => gb.release(Button::Start),
-                            _ => return (event::Status::Ignored, None),
-                        }
-
+                    if let Some((player, button)) = key_button(&key) {
+                        gb.release_player(player, button);
                         return (event::Status::Captured, None);
                     }
                 }
@@ -128,15 +136,30 @@ impl<Message> shader::Program<Message> for Scene {
 pub struct Primitive {
     rgb: [u8; PX_HEIGHT as usize * PX_WIDTH as usize * 3],
     scaling: Scaling,
+    rotation: Rotation,
+    mirror: bool,
+    color_filter: ColorFilter,
 }
 
 impl Primitive {
-    pub fn new(gb: &Gb<ceres_audio::RingBuffer>, scaling: Scaling) -> Self {
+    pub fn new(
+        gb: &Gb<ceres_audio::RingBuffer>,
+        scaling: Scaling,
+        rotation: Rotation,
+        mirror: bool,
+        color_filter: ColorFilter,
+    ) -> Self {
         let mut rgb = [0; PX_HEIGHT as usize * PX_WIDTH as usize * 3];
 
         rgb.copy_from_slice(gb.pixel_data_rgb());
 
-        Self { rgb, scaling }
+        Self {
+            rgb,
+            scaling,
+            rotation,
+            mirror,
+            color_filter,
+        }
     }
 }
 
@@ -157,6 +180,9 @@ impl shader::Primitive for Primitive {
                 format,
                 viewport.physical_size(),
                 self.scaling,
+                self.rotation,
+                self.mirror,
+                self.color_filter,
             ));
         }
 
@@ -168,6 +194,9 @@ impl shader::Primitive for Primitive {
             queue,
             viewport.physical_size(),
             self.scaling,
+            self.rotation,
+            self.mirror,
+            self.color_filter,
             &self.rgb,
         );
     }
@@ -1,5 +1,5 @@
 use super::texture::Texture;
-use crate::{Scaling, PX_HEIGHT, PX_WIDTH};
+use crate::{ColorFilter, Rotation, Scaling, PX_HEIGHT, PX_WIDTH};
 use iced::{widget::shader::wgpu, Rectangle, Size};
 use wgpu::util::DeviceExt;
 
@@ -9,6 +9,8 @@ pub(super) struct Pipeline {
     // Shader config binds
     dimensions_uniform: wgpu::Buffer,
     scale_uniform: wgpu::Buffer,
+    transform_uniform: wgpu::Buffer,
+    color_filter_uniform: wgpu::Buffer,
     uniform_bind_group: wgpu::BindGroup,
 
     // Texture binds
@@ -18,16 +20,22 @@ pub(super) struct Pipeline {
     // Size of the screen
     size: Size<u32>,
     scaling: Scaling,
+    rotation: Rotation,
+    mirror: bool,
+    color_filter: ColorFilter,
 }
 
 impl Pipeline {
-    #[allow(clippy::too_many_lines)]
+    #[allow(clippy::too_many_lines, clippy::too_many_arguments)]
     pub fn new(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         format: wgpu::TextureFormat,
         target_size: Size<u32>,
         scaling: Scaling,
+        rotation: Rotation,
+        mirror: bool,
+        color_filter: ColorFilter,
     ) -> Self {
         let texture = Texture::new(device, PX_WIDTH, PX_HEIGHT, None);
 
@@ -94,6 +102,26 @@ impl Pipeline {
                         },
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
                 label: None,
             });
@@ -110,6 +138,18 @@ impl Pipeline {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let transform_uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[Self::transform_bits(rotation, mirror)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let color_filter_uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[color_filter as u32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
         let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &uniform_bind_group_layout,
             entries: &[
@@ -121,6 +161,14 @@ impl Pipeline {
                     binding: 1,
                     resource: scale_uniform.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: transform_uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: color_filter_uniform.as_entire_binding(),
+                },
             ],
             label: None,
         });
@@ -168,11 +216,16 @@ impl Pipeline {
             render_pipeline,
             dimensions_uniform,
             scale_uniform,
+            transform_uniform,
+            color_filter_uniform,
             uniform_bind_group,
             texture,
             diffuse_bind_group,
             size: target_size,
             scaling,
+            rotation,
+            mirror,
+            color_filter,
         };
 
         res.resize(queue, target_size);
@@ -209,30 +262,79 @@ impl Pipeline {
         );
     }
 
+    fn filter(&mut self, queue: &wgpu::Queue, color_filter: ColorFilter) {
+        queue.write_buffer(
+            &self.color_filter_uniform,
+            0,
+            bytemuck::cast_slice(&[color_filter as u32]),
+        );
+    }
+
+    // Bits 0-1 hold the rotation, bit 2 the horizontal mirror
+    fn transform_bits(rotation: Rotation, mirror: bool) -> u32 {
+        rotation as u32 | u32::from(mirror) << 2
+    }
+
+    fn transform(&mut self, queue: &wgpu::Queue, rotation: Rotation, mirror: bool) {
+        queue.write_buffer(
+            &self.transform_uniform,
+            0,
+            bytemuck::cast_slice(&[Self::transform_bits(rotation, mirror)]),
+        );
+    }
+
     fn resize(&mut self, queue: &wgpu::Queue, new_size: Size<u32>) {
         let width = new_size.width;
         let height = new_size.height;
 
+        let (px_width, px_height) = if self.rotation.is_sideways() {
+            (PX_HEIGHT, PX_WIDTH)
+        } else {
+            (PX_WIDTH, PX_HEIGHT)
+        };
+
         let (x, y) = {
-            let mul = (width / PX_WIDTH).min(height / PX_HEIGHT);
+            let mul = (width / px_width).min(height / px_height);
             #[allow(clippy::cast_precision_loss)]
-            let x = (PX_WIDTH * mul) as f32 / width as f32;
+            let x = (px_width * mul) as f32 / width as f32;
             #[allow(clippy::cast_precision_loss)]
-            let y = (PX_HEIGHT * mul) as f32 / height as f32;
+            let y = (px_height * mul) as f32 / height as f32;
             (x, y)
         };
 
         queue.write_buffer(&self.dimensions_uniform, 0, bytemuck::cast_slice(&[x, y]));
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
         _device: &wgpu::Device,
         queue: &wgpu::Queue,
         target_size: Size<u32>,
         scaling: Scaling,
+        rotation: Rotation,
+        mirror: bool,
+        color_filter: ColorFilter,
         rgb: &[u8],
     ) {
+        if rotation != self.rotation || mirror != self.mirror {
+            // Sideways screens fit the target differently
+            let refit = rotation.is_sideways() != self.rotation.is_sideways();
+
+            self.transform(queue, rotation, mirror);
+            self.rotation = rotation;
+            self.mirror = mirror;
+
+            if refit {
+                self.resize(queue, target_size);
+            }
+        }
+
+        if color_filter != self.color_filter {
+            self.filter(queue, color_filter);
+            self.color_filter = color_filter;
+        }
+
         if target_size != self.size {
             self.resize(queue, target_size);
             self.size = target_size;
@@ -0,0 +1,92 @@
+// Keeps the screen from blanking while a game is running. X11 and Wayland
+// desktops both implement the org.freedesktop.ScreenSaver interface, the
+// inhibition lasts until it's dropped or the connection is closed. Feral's
+// GameMode daemon, when it runs, is told about the game too so it can switch
+// the CPU governor and the like.
+#[cfg(target_os = "linux")]
+pub struct Inhibitor {
+    conn: zbus::blocking::Connection,
+    cookie: u32,
+    game_mode: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl Inhibitor {
+    const DESTINATION: &'static str = "org.freedesktop.ScreenSaver";
+    const PATH: &'static str = "/org/freedesktop/ScreenSaver";
+    const GAME_MODE_DESTINATION: &'static str = "com.feralinteractive.GameMode";
+    const GAME_MODE_PATH: &'static str = "/com/feralinteractive/GameMode";
+
+    pub fn new() -> anyhow::Result<Self> {
+        let conn = zbus::blocking::Connection::session()?;
+        let reply = conn.call_method(
+            Some(Self::DESTINATION),
+            Self::PATH,
+            Some(Self::DESTINATION),
+            "Inhibit",
+            &(crate::CERES_STYLIZED, "Playing a game"),
+        )?;
+        let cookie = reply.body().deserialize::<u32>()?;
+
+        // most systems don't run GameMode, the screensaver matters more
+        let game_mode = Self::call_game_mode(&conn, "RegisterGame")
+            .inspect_err(|e| eprintln!("couldn't register with GameMode: {e}"))
+            .is_ok();
+
+        Ok(Self {
+            conn,
+            cookie,
+            game_mode,
+        })
+    }
+
+    // Registers or unregisters this process, GameMode replies -1 when it
+    // refuses
+    fn call_game_mode(conn: &zbus::blocking::Connection, method: &str) -> anyhow::Result<()> {
+        let pid = i32::try_from(std::process::id())?;
+        let reply = conn.call_method(
+            Some(Self::GAME_MODE_DESTINATION),
+            Self::GAME_MODE_PATH,
+            Some(Self::GAME_MODE_DESTINATION),
+            method,
+            &pid,
+        )?;
+
+        match reply.body().deserialize::<i32>()? {
+            0 => Ok(()),
+            status => anyhow::bail!("{method} returned {status}"),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        if let Err(e) = self.conn.call_method(
+            Some(Self::DESTINATION),
+            Self::PATH,
+            Some(Self::DESTINATION),
+            "UnInhibit",
+            &self.cookie,
+        ) {
+            eprintln!("couldn't uninhibit screensaver: {e}");
+        }
+
+        if self.game_mode {
+            if let Err(e) = Self::call_game_mode(&self.conn, "UnregisterGame") {
+                eprintln!("couldn't unregister from GameMode: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub struct Inhibitor;
+
+#[cfg(not(target_os = "linux"))]
+impl Inhibitor {
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{Model, PX_HEIGHT, PX_WIDTH};
+use ceres_core::{Cart, Gb, NoAudio};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+// Irrelevant since samples are discarded, but the APU needs one
+pub const SAMPLE_RATE: i32 = 48000;
+const PIXELS: usize = PX_WIDTH as usize * PX_HEIGHT as usize;
+
+#[derive(clap::Args)]
+pub struct VerifyArgs {
+    #[arg(help = "ROM to run")]
+    rom: PathBuf,
+    #[arg(
+        long,
+        help = "PNG screenshot to compare the last frame to",
+        long_help = "PNG screenshot to compare the last frame to, from another emulator \
+           or a capture of the hardware. It has to be 160x144 or an integer multiple \
+           of it."
+    )]
+    against: PathBuf,
+    #[arg(
+        short = 'f',
+        long,
+        help = "Emulated frames to run before comparing, without input or save data",
+        default_value_t = 60 * 10
+    )]
+    frames: u32,
+    #[arg(
+        short,
+        long,
+        help = "Game Boy model to emulate, by default the one the cartridge header asks for",
+        default_value = "auto",
+        value_enum
+    )]
+    model: Model,
+    #[arg(
+        long,
+        help = "Largest difference in any channel for a pixel to still match",
+        long_help = "Largest difference in any channel for a pixel to still match. \
+           Emulators correct colors differently, a tolerance keeps that from \
+           counting as a mismatch.",
+        default_value_t = 0
+    )]
+    tolerance: u8,
+}
+
+// Prints how much of the screen matches the reference, for bug reports
+pub fn run(args: &VerifyArgs) -> ExitCode {
+    match matching_pixels(args) {
+        Ok(matching) => {
+            #[allow(clippy::cast_precision_loss)]
+            let percentage = matching as f64 * 100.0 / PIXELS as f64;
+            println!(
+                "{percentage:.1}% match against {} ({} of {PIXELS} pixels differ)",
+                args.against.display(),
+                PIXELS - matching
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("couldn't verify {}: {e}", args.rom.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn matching_pixels(args: &VerifyArgs) -> anyhow::Result<usize> {
+    let reference = load_png(&args.against)?;
+
+    let cart = Cart::new(std::fs::read(&args.rom)?.into_boxed_slice())?;
+    let mut gb = Gb::new(args.model.for_cart(&cart), SAMPLE_RATE, cart, NoAudio);
+    for _ in 0..args.frames {
+        gb.run_frame();
+    }
+
+    let matching = gb
+        .pixel_data_rgb()
+        .chunks_exact(3)
+        .zip(reference.chunks_exact(3))
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(*b)
+                .all(|(a, b)| a.abs_diff(*b) <= args.tolerance)
+        })
+        .count();
+
+    Ok(matching)
+}
+
+// RGB pixels at the Game Boy resolution, scaled screenshots are sampled
+// every scale pixels
+fn load_png(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(std::fs::File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+
+    let scale = info.width / PX_WIDTH;
+    if scale == 0 || info.width != PX_WIDTH * scale || info.height != PX_HEIGHT * scale {
+        anyhow::bail!(
+            "{}x{} isn't a multiple of {PX_WIDTH}x{PX_HEIGHT}",
+            info.width,
+            info.height
+        );
+    }
+
+    let channels = info.color_type.samples();
+    let (scale, width) = (scale as usize, info.width as usize);
+
+    let rgb = (0..PX_HEIGHT as usize)
+        .flat_map(|y| {
+            (0..PX_WIDTH as usize).map(move |x| (y * scale * width + x * scale) * channels)
+        })
+        .flat_map(|i| match info.color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => [buf[i]; 3],
+            _ => [buf[i], buf[i + 1], buf[i + 2]],
+        })
+        .collect();
+
+    Ok(rgb)
+}
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+// Anything that wants every frame the emulation thread runs, like a
+// recorder or a stream, without a hook of its own in GbArea. Sinks are called
+// on the emulation thread, so slow ones delay the next frame.
+pub trait VideoSink: Send {
+    // RGB pixels, PX_WIDTH by PX_HEIGHT, and how many frames ran before this
+    // one since the sink was added
+    fn frame(&mut self, rgb: &[u8], index: u64);
+}
+
+// Returned when adding a sink to remove it later
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SinkId(u32);
+
+#[derive(Default)]
+pub struct VideoSinks {
+    sinks: Vec<(SinkId, u64, Box<dyn VideoSink>)>,
+    next_id: u32,
+}
+
+impl VideoSinks {
+    pub fn add(&mut self, sink: Box<dyn VideoSink>) -> SinkId {
+        let id = SinkId(self.next_id);
+        self.next_id += 1;
+        self.sinks.push((id, 0, sink));
+        id
+    }
+
+    pub fn remove(&mut self, id: SinkId) {
+        self.sinks.retain(|(sink_id, ..)| *sink_id != id);
+    }
+
+    pub fn frame(&mut self, rgb: &[u8]) {
+        for (_, index, sink) in &mut self.sinks {
+            sink.frame(rgb, *index);
+            *index += 1;
+        }
+    }
+}
+
+// Every frame as a numbered PNG, to be assembled into a video with ffmpeg
+// or similar
+pub struct PngRecorder {
+    dir: PathBuf,
+}
+
+impl PngRecorder {
+    pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+}
+
+impl VideoSink for PngRecorder {
+    fn frame(&mut self, rgb: &[u8], index: u64) {
+        let path = self.dir.join(format!("{index:06}.png"));
+        if let Err(e) = crate::layers::save_png(&path, rgb) {
+            eprintln!("couldn't record {}: {e}", path.display());
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use ceres_core::AudioVisualizerFrame;
+use iced::widget::{column, container, progress_bar, row, text, Space};
+use iced::{Alignment, Element, Length, Theme};
+
+const SCOPE_HEIGHT: f32 = 64.0;
+const CHANNELS: [&str; 4] = ["Pulse 1", "Pulse 2", "Wave", "Noise"];
+
+// Oscilloscope of the mixed output over per channel level meters
+pub fn view<'a, Message: 'a>(frame: &AudioVisualizerFrame) -> Element<'a, Message> {
+    let scope = row(frame.scope.iter().map(|&sample| {
+        let bar = container(Space::new(Length::Fill, Length::Fill))
+            .width(Length::Fill)
+            .height((sample.abs() * SCOPE_HEIGHT).max(1.0))
+            .style(|theme: &Theme| container::Style {
+                background: Some(theme.palette().primary.into()),
+                ..container::Style::default()
+            });
+
+        container(bar)
+            .height(SCOPE_HEIGHT)
+            .align_y(Alignment::Center)
+            .into()
+    }))
+    .width(Length::Fill);
+
+    let meters = row(CHANNELS.iter().zip(frame.channels).map(|(name, level)| {
+        column![
+            text(*name).size(12),
+            progress_bar(0.0..=1.0, level).height(8)
+        ]
+        .spacing(2)
+        .width(Length::Fill)
+        .into()
+    }))
+    .spacing(10);
+
+    column![scope, meters].spacing(5).padding(5).into()
+}
@@ -0,0 +1,27 @@
+#![no_main]
+
+use ceres_core::{bess_thumbnail, Cart, Gb, Model, NoAudio};
+use libfuzzer_sys::fuzz_target;
+
+// A blank ROM, its title and checksum are zeros so INFO blocks are easy to
+// match
+fn gb(model: Model) -> Gb<NoAudio> {
+    let mut rom = vec![0; 0x8000];
+    // jr -2
+    rom[0x100..0x102].copy_from_slice(&[0x18, 0xFE]);
+    let cart = Cart::new(rom.into()).unwrap();
+
+    Gb::new(model, 48000, cart, NoAudio)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = bess_thumbnail(data);
+
+    // CGB states are only loaded on a CGB, try both families
+    for model in [Model::Dmg, Model::Cgb] {
+        let mut gb = gb(model);
+        if gb.load_bess(data, 0).is_ok() {
+            gb.run_frame();
+        }
+    }
+});
@@ -0,0 +1,22 @@
+#![no_main]
+
+use ceres_core::Cart;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut cart) = Cart::new(data.into()) else {
+        return;
+    };
+
+    let _ = cart.title_str();
+    let _ = cart.is_old_licensee_code();
+    let _ = cart.header_checksum();
+    let _ = cart.global_checksum();
+    let _ = cart.version();
+    let _ = cart.clock();
+
+    // Saves are loaded from user files too, reuse the ROM bytes as one
+    if let Some(len) = cart.save_data().map(<[u8]>::len) {
+        let _ = cart.set_ram(data[..len.min(data.len())].into());
+    }
+});